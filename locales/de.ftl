@@ -0,0 +1,98 @@
+## Notifications
+
+started-title = Bluetooth inaktiv
+started-body = { $effect ->
+        [disconnect] Die Bluetooth-Geräte von { $adapter } werden in { $remaining } getrennt, sofern sich kein Gerät erneut verbindet.
+        [discoverable] Bluetooth von { $adapter } ist in { $remaining } nicht mehr sichtbar, sofern sich kein Gerät erneut verbindet.
+        [command] Die Bluetooth-Leerlaufaktion von { $adapter } wird in { $remaining } ausgeführt, sofern sich kein Gerät erneut verbindet.
+       *[off] Bluetooth von { $adapter } wird in { $remaining } ausgeschaltet, sofern sich kein Gerät erneut verbindet.
+    }
+started-body-device = { $effect ->
+        [disconnect] { $device } getrennt — die Bluetooth-Geräte von { $adapter } werden in { $remaining } getrennt, sofern sich kein Gerät erneut verbindet.
+        [discoverable] { $device } getrennt — Bluetooth von { $adapter } ist in { $remaining } nicht mehr sichtbar, sofern sich kein Gerät erneut verbindet.
+        [command] { $device } getrennt — die Bluetooth-Leerlaufaktion von { $adapter } wird in { $remaining } ausgeführt, sofern sich kein Gerät erneut verbindet.
+       *[off] { $device } getrennt — Bluetooth von { $adapter } wird in { $remaining } ausgeschaltet, sofern sich kein Gerät erneut verbindet.
+    }
+cancelled-title = Bluetooth-Timer abgebrochen
+cancelled-body = Ein Gerät hat sich verbunden, Bluetooth von { $adapter } bleibt eingeschaltet.
+cancelled-body-device = { $device } verbunden, Bluetooth von { $adapter } bleibt eingeschaltet.
+warning-title = Bluetooth-Timeout-Warnung
+warning-body = { $effect ->
+        [disconnect] Die Bluetooth-Geräte von { $adapter } werden in { $remaining } wegen Inaktivität getrennt.
+        [discoverable] Bluetooth von { $adapter } ist in { $remaining } wegen Inaktivität nicht mehr sichtbar.
+        [command] Die Bluetooth-Leerlaufaktion von { $adapter } wird in { $remaining } wegen Inaktivität ausgeführt.
+       *[off] Bluetooth von { $adapter } wird in { $remaining } wegen Inaktivität ausgeschaltet.
+    }
+countdown-title = Bluetooth-Timeout
+turned-off-title = Bluetooth-Adapter ausgeschaltet
+turned-off-body = Bluetooth von { $adapter } wurde wegen Inaktivität ausgeschaltet.
+expired-title = Bluetooth-Leerlauf
+disconnected-body = Die Bluetooth-Geräte von { $adapter } wurden wegen Inaktivität getrennt.
+discoverable-off-title = Bluetooth-Sichtbarkeit
+discoverable-off-body = Bluetooth von { $adapter } ist nicht mehr sichtbar.
+pairable-off-title = Bluetooth-Kopplung
+pairable-off-body = Bluetooth von { $adapter } nimmt keine Kopplungsanfragen mehr an.
+action-ran-body = Die Bluetooth-Leerlaufaktion von { $adapter } wurde ausgeführt.
+blackout-title = Bluetooth-Sperrzeit
+blackout-body = Bluetooth von { $adapter } wurde ausgeschaltet; es bleibt bis { $until } Uhr aus.
+power-off-blocked-title = Bluetooth schaltet sich wieder ein
+power-off-blocked-body = Bluetooth von { $adapter } wurde nach dem Timeout wieder eingeschaltet; es wurde stattdessen per rfkill blockiert.
+power-off-failed-body = Bluetooth von { $adapter } wurde nach dem Timeout wieder eingeschaltet und konnte nicht blockiert werden; es bleibt an.
+weekly-summary-title = Bluetooth-Wochenübersicht
+weekly-summary-body = { $count ->
+        [one] Bluetooth von { $adapter } wurde letzte Woche einmal automatisch ausgeschaltet und war so etwa { $saved } weniger an.
+       *[other] Bluetooth von { $adapter } wurde letzte Woche { $count }-mal automatisch ausgeschaltet und war so etwa { $saved } weniger an.
+    }
+
+## Notification actions
+
+action-keep-on = Eingeschaltet lassen
+action-snooze = { $duration } aufschieben
+action-turn-off-now = Jetzt ausschalten
+
+## Command line output
+
+cli-notify-test-warning-sent = Warnung gesendet (ID: { $id }).
+cli-notify-test-final-sent = Abschlussbenachrichtigung gesendet (ID: { $id }).
+cli-snoozed = Timeout um { $duration } aufgeschoben.
+cli-kept-on = Adapter bleibt { $duration } lang eingeschaltet.
+cli-keep-on-ended = Adapter wird nicht mehr eingeschaltet gehalten.
+cli-powered-off = Adapter ausgeschaltet.
+cli-profile-switched = Zu Profil { $profile } gewechselt.
+cli-profile-reset = Zurück zur Hauptkonfiguration gewechselt.
+cli-timeout-set = Timeout auf { $duration } gesetzt.
+cli-timeout-reset = Timeout auf die Konfiguration zurückgesetzt.
+cli-healthy = Der Dienst läuft fehlerfrei.
+cli-setup-adapter-found = Adapter { $adapter } gefunden.
+cli-setup-adapters = Bluetooth-Adapter:
+cli-setup-all-adapters = Alle Adapter, auch später hinzugefügte
+cli-setup-adapter = Zu verwaltender Adapter
+cli-setup-timeout = Bluetooth ausschalten nach einer Leerlaufzeit von
+cli-setup-notifications = Vor dem Ausschalten von Bluetooth benachrichtigen?
+cli-setup-on-start = Auch beim Start des Countdowns benachrichtigen?
+cli-setup-written = { $path } geschrieben.
+cli-setup-service = Benutzerdienst installieren und starten?
+cli-setup-invalid-choice = Ungültige Auswahl, bitte erneut versuchen.
+cli-service-installed = { $path } installiert und gestartet.
+cli-service-uninstalled = { $path } entfernt.
+cli-autostart-installed = { $path } installiert; der Dienst startet bei der nächsten Anmeldung.
+cli-autostart-uninstalled = { $path } entfernt.
+cli-status = Zustand des Bluetooth-Dienstes: { $state }
+cli-status-profile = Aktives Profil: { $profile }
+cli-status-remaining = Timeout läuft in { $remaining } ab.
+cli-status-capabilities = Controller: { $capabilities }
+cli-status-devices = Verbundene Geräte:
+cli-status-device-battery = { $name } (Akku: { $percent } %)
+cli-status-events = Letzte Ereignisse:
+cli-status-no-events = Keine Ereignisse.
+cli-status-stats = Einschaltzeit pro Tag (UTC):
+cli-status-stats-day = { $date }: an { $powered }, aus { $off } (davon { $saved } durch den Timeout), { $count ->
+        [one] eine automatische Abschaltung
+       *[other] { $count } automatische Abschaltungen
+    }
+cli-status-latency = Ereignislatenzen (Signal bis Verarbeitung):
+cli-status-no-latency = Keine Ereignislatenzen gemessen.
+cli-status-latency-kind = { $kind }: { $count ->
+        [one] ein Ereignis
+       *[other] { $count } Ereignisse
+    }, Mittel { $mean }, Maximum { $max }
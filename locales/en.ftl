@@ -0,0 +1,98 @@
+## Notifications
+
+started-title = Bluetooth Idle
+started-body = { $effect ->
+        [disconnect] { $adapter } Bluetooth devices will be disconnected in { $remaining } unless a device reconnects.
+        [discoverable] { $adapter } Bluetooth will stop being discoverable in { $remaining } unless a device reconnects.
+        [command] The { $adapter } Bluetooth idle action will run in { $remaining } unless a device reconnects.
+       *[off] { $adapter } Bluetooth will turn off in { $remaining } unless a device reconnects.
+    }
+started-body-device = { $effect ->
+        [disconnect] { $device } disconnected — { $adapter } Bluetooth devices will be disconnected in { $remaining } unless a device reconnects.
+        [discoverable] { $device } disconnected — { $adapter } Bluetooth will stop being discoverable in { $remaining } unless a device reconnects.
+        [command] { $device } disconnected — the { $adapter } Bluetooth idle action will run in { $remaining } unless a device reconnects.
+       *[off] { $device } disconnected — { $adapter } Bluetooth will turn off in { $remaining } unless a device reconnects.
+    }
+cancelled-title = Bluetooth Timer Cancelled
+cancelled-body = A device connected, { $adapter } Bluetooth stays on.
+cancelled-body-device = { $device } connected, { $adapter } Bluetooth stays on.
+warning-title = Bluetooth Timeout Warning
+warning-body = { $effect ->
+        [disconnect] { $adapter } Bluetooth devices will be disconnected in { $remaining } due to inactivity.
+        [discoverable] { $adapter } Bluetooth will stop being discoverable in { $remaining } due to inactivity.
+        [command] The { $adapter } Bluetooth idle action will run in { $remaining } due to inactivity.
+       *[off] { $adapter } Bluetooth will turn off in { $remaining } due to inactivity.
+    }
+countdown-title = Bluetooth Timeout
+turned-off-title = Bluetooth Adapter Turned Off
+turned-off-body = { $adapter } Bluetooth has been turned off due to inactivity.
+expired-title = Bluetooth Idle Timeout
+disconnected-body = { $adapter } Bluetooth devices have been disconnected due to inactivity.
+discoverable-off-title = Bluetooth Visibility
+discoverable-off-body = { $adapter } Bluetooth is no longer discoverable.
+pairable-off-title = Bluetooth Pairing
+pairable-off-body = { $adapter } Bluetooth no longer accepts pairing requests.
+action-ran-body = The { $adapter } Bluetooth idle action has been run.
+blackout-title = Bluetooth Blackout
+blackout-body = { $adapter } Bluetooth has been turned off; it stays off until { $until }.
+power-off-blocked-title = Bluetooth Kept Turning On
+power-off-blocked-body = { $adapter } Bluetooth was turned back on after the timeout; it has been blocked via rfkill instead.
+power-off-failed-body = { $adapter } Bluetooth was turned back on after the timeout and could not be blocked; it stays on.
+weekly-summary-title = Bluetooth Weekly Summary
+weekly-summary-body = { $count ->
+        [one] { $adapter } Bluetooth was turned off automatically once last week, saving about { $saved } of radio-on time.
+       *[other] { $adapter } Bluetooth was turned off automatically { $count } times last week, saving about { $saved } of radio-on time.
+    }
+
+## Notification actions
+
+action-keep-on = Keep on
+action-snooze = Snooze { $duration }
+action-turn-off-now = Turn off now
+
+## Command line output
+
+cli-notify-test-warning-sent = Sent warning notification (id: { $id }).
+cli-notify-test-final-sent = Sent final notification (id: { $id }).
+cli-snoozed = Snoozed timeout by { $duration }.
+cli-kept-on = Keeping the adapter on for { $duration }.
+cli-keep-on-ended = No longer keeping the adapter on.
+cli-powered-off = Turned the adapter off.
+cli-profile-switched = Switched to profile { $profile }.
+cli-profile-reset = Switched back to the main configuration.
+cli-timeout-set = Timeout set to { $duration }.
+cli-timeout-reset = Timeout reset to the configuration.
+cli-healthy = The daemon is healthy.
+cli-setup-adapter-found = Found adapter { $adapter }.
+cli-setup-adapters = Bluetooth adapters:
+cli-setup-all-adapters = All adapters, including ones added later
+cli-setup-adapter = Adapter to manage
+cli-setup-timeout = Turn Bluetooth off after being idle for
+cli-setup-notifications = Show notifications before turning Bluetooth off?
+cli-setup-on-start = Also notify when the countdown starts?
+cli-setup-written = Wrote { $path }.
+cli-setup-service = Install and start the user service?
+cli-setup-invalid-choice = Invalid choice, please try again.
+cli-service-installed = Installed and started { $path }.
+cli-service-uninstalled = Removed { $path }.
+cli-autostart-installed = Installed { $path }; the daemon starts on your next login.
+cli-autostart-uninstalled = Removed { $path }.
+cli-status = Bluetooth service state: { $state }
+cli-status-profile = Active profile: { $profile }
+cli-status-remaining = Timeout expires in { $remaining }.
+cli-status-capabilities = Controller: { $capabilities }
+cli-status-devices = Connected devices:
+cli-status-device-battery = { $name } (battery: { $percent }%)
+cli-status-events = Recent events:
+cli-status-no-events = No recent events.
+cli-status-stats = Powered time per day (UTC):
+cli-status-stats-day = { $date }: on { $powered }, off { $off } ({ $saved } of it by the timeout), { $count ->
+        [one] one automatic power-off
+       *[other] { $count } automatic power-offs
+    }
+cli-status-latency = Event latencies (signal to handling):
+cli-status-no-latency = No event latencies measured.
+cli-status-latency-kind = { $kind }: { $count ->
+        [one] one event
+       *[other] { $count } events
+    }, mean { $mean }, max { $max }
@@ -0,0 +1,185 @@
+// -- std imports
+use std::time::Duration;
+
+// -- crate imports
+use anyhow::{Context, Result};
+use tokio::process::Command;
+use tracing::{error, info, warn};
+
+// -- module imports (conditional)
+#[cfg(feature = "notifications")]
+use crate::notification::Notification;
+
+// -- module imports
+use crate::{
+    bluetooth::{backend::BluetoothBackend, flag::AdapterFlag},
+    configuration::{Conf, TimeoutAction},
+};
+
+/// Runs the configured timeout `actions` in order against the adapter managed by `backend`.
+///
+/// A failing action is logged and does not prevent the remaining actions from running.
+pub async fn run(actions: &[TimeoutAction], backend: &dyn BluetoothBackend) {
+    for action in actions {
+        match run_one(*action, backend).await {
+            Ok(()) => info!("Timeout action '{:?}' completed.", action),
+            Err(e) => warn!("Timeout action '{:?}' failed: {}", action, e),
+        }
+    }
+}
+
+/// Runs a single timeout `action`.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the action cannot be carried out.
+async fn run_one(action: TimeoutAction, backend: &dyn BluetoothBackend) -> Result<()> {
+    match action {
+        TimeoutAction::PowerOff => power_off(backend).await,
+        TimeoutAction::Rfkill => backend.soft_block().await,
+        TimeoutAction::Disconnect => {
+            for device in backend.connected_devices().await? {
+                backend.disconnect_device(&device).await?;
+                info!("Disconnected device {}.", device.display_name());
+            }
+            Ok(())
+        }
+        TimeoutAction::DiscoverableOff => discoverable_off(backend).await,
+        TimeoutAction::Command => match &Conf::instance().action_command {
+            Some(command) => run_command(command, backend).await,
+            None => Err(anyhow::anyhow!(
+                "Timeout action 'command' requires 'action_command'"
+            )),
+        },
+    }
+}
+
+/// Makes the adapter managed by `backend` invisible, unless its controller only supports LE, for
+/// which `Discoverable` means nothing (see [`AdapterCapabilities::manages`]).
+///
+/// [`AdapterCapabilities::manages`]: crate::bluetooth::capabilities::AdapterCapabilities::manages
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the flag cannot be cleared.
+async fn discoverable_off(backend: &dyn BluetoothBackend) -> Result<()> {
+    if let Ok(capabilities) = backend.capabilities().await
+        && !capabilities.manages(AdapterFlag::Discoverable)
+    {
+        info!(
+            "Not turning off Discoverable of {}, as its controller only supports LE.",
+            backend.adapter_name()
+        );
+        return Ok(());
+    }
+
+    backend.set_flag(AdapterFlag::Discoverable, false).await
+}
+
+/// Turns off the adapter managed by `backend` and checks `power_off_verify` later that it stayed
+/// off. If it was turned back on (e.g., by the desktop environment), it is turned off once more
+/// and then soft-blocked, which is logged as an error and notified.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the adapter cannot be turned off or queried, or it kept being turned
+///   back on and cannot be soft-blocked.
+async fn power_off(backend: &dyn BluetoothBackend) -> Result<()> {
+    let verify = Conf::instance().power_off_verify;
+    let name = backend.adapter_name();
+
+    backend.power_off().await?;
+    if stays_off(backend, verify).await? {
+        return Ok(());
+    }
+    warn!("{} was turned back on; turning it off again.", name);
+    backend.power_off().await?;
+    if stays_off(backend, verify).await? {
+        return Ok(());
+    }
+
+    error!("{} keeps being turned back on; soft-blocking it.", name);
+    let blocked = backend.soft_block().await;
+    #[cfg(feature = "notifications")]
+    if Conf::instance().notifications().enabled
+        && let Err(e) = Notification::power_off_escalated(name, blocked.is_ok())
+            .send()
+            .await
+    {
+        warn!("Could not send the power-off notification: {}", e);
+    }
+
+    blocked.with_context(|| format!("{} stays on, as it could not be soft-blocked", name))
+}
+
+/// Returns whether the adapter managed by `backend` is still off after `verify`. A zero `verify`
+/// skips the check.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the adapter cannot be queried.
+async fn stays_off(backend: &dyn BluetoothBackend, verify: Duration) -> Result<bool> {
+    if verify.is_zero() {
+        return Ok(true);
+    }
+
+    tokio::time::sleep(verify).await;
+    Ok(!backend.is_powered().await?)
+}
+
+/// Runs the user-configured timeout command through `sh -c`.
+///
+/// The adapter's identifier (for BlueZ, its object path) is passed via the `BLUETOOTH_ADAPTER` environment variable.
+async fn run_command(command: &str, backend: &dyn BluetoothBackend) -> Result<()> {
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("BLUETOOTH_ADAPTER", backend.adapter())
+        .status()
+        .await
+        .with_context(|| format!("Could not run timeout command '{command}'"))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("'{}' exited with {}", command, status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+    use crate::bluetooth::{backend::mock::MockBackend, capabilities::AdapterCapabilities};
+
+    #[tokio::test(start_paused = true)]
+    async fn soft_blocks_an_adapter_that_is_turned_back_on() {
+        Conf::for_tests();
+        let backend = MockBackend::new(true);
+        backend.set_sticky(true);
+
+        run(&[TimeoutAction::PowerOff], backend.as_ref()).await;
+
+        assert_eq!(backend.power_offs(), 2);
+        assert!(backend.soft_blocked());
+        assert!(!backend.powered());
+    }
+
+    #[tokio::test]
+    async fn discoverable_is_left_alone_on_le_only_controllers() {
+        Conf::for_tests();
+        let backend = MockBackend::new(true);
+        backend
+            .set_flag(AdapterFlag::Discoverable, true)
+            .await
+            .unwrap();
+        backend.set_capabilities(AdapterCapabilities::new(
+            false,
+            vec!["peripheral".to_string()],
+        ));
+
+        run(&[TimeoutAction::DiscoverableOff], backend.as_ref()).await;
+
+        assert!(backend.get_flag(AdapterFlag::Discoverable).await.unwrap());
+    }
+}
@@ -0,0 +1,176 @@
+// -- std imports
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+// -- crate imports
+use anyhow::Result;
+use async_trait::async_trait;
+use tracing::error;
+
+// -- module imports
+use crate::{
+    bluetooth::{
+        backend::BluetoothBackend,
+        cache::DeviceCache,
+        capabilities::AdapterCapabilities,
+        device::BluetoothDevice,
+        event_bus::EventBus,
+        flag::AdapterFlag,
+        latency::EventLatency,
+        observer::{BluetoothEvent, BluetoothEventObserver},
+        rfkill,
+        service_proxy::BluetoothServiceProxy,
+    },
+    configuration::Conf,
+    error::Error,
+};
+
+/// [`BluetoothBackend`] on top of BlueZ's D-Bus API.
+///
+/// Operations are carried out by a [`BluetoothServiceProxy`], events are produced by a
+/// [`BluetoothEventObserver`] listening for D-Bus signals, once it is run by
+/// [`BlueZBackend::observe`].
+#[derive(Debug)]
+pub struct BlueZBackend {
+    /// Proxy to the adapter's D-Bus objects.
+    proxy: BluetoothServiceProxy,
+    /// Alias of the adapter, read once at startup.
+    name: String,
+    /// Bus the observer publishes its events on.
+    events: EventBus<BluetoothEvent>,
+    /// The observer producing the events.
+    observer: BluetoothEventObserver,
+    /// Whether the observer is running.
+    observing: Arc<AtomicBool>,
+    /// Latencies of the observer's events.
+    latency: EventLatency,
+}
+
+impl BlueZBackend {
+    /// Creates a new BlueZ backend for the adapter at `adapter_path` (e.g., "/org/bluez/hci0").
+    /// Its events are not observed until [`BlueZBackend::observe`] runs.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::DbusUnavailable`] if the connection to the system D-Bus cannot be established.
+    /// - [`Error::AdapterNotFound`] if BlueZ does not know the adapter (or is not running).
+    pub async fn new(adapter_path: String) -> Result<Self, Error> {
+        let cache = DeviceCache::new(Conf::instance().dbus.device_cache_ttl);
+        let latency = EventLatency::new();
+        let observer =
+            BluetoothEventObserver::new(adapter_path.clone(), cache.clone(), latency.clone())
+                .await
+                .map_err(Error::DbusUnavailable)?;
+        let proxy = BluetoothServiceProxy::new(adapter_path.clone(), cache)
+            .await
+            .map_err(Error::DbusUnavailable)?;
+        proxy
+            .is_powered()
+            .await
+            .map_err(|reason| Error::AdapterNotFound {
+                path: adapter_path,
+                reason,
+            })?;
+
+        let name = proxy
+            .alias()
+            .await
+            .unwrap_or_else(|_| proxy.hci().to_string());
+
+        let events = observer.events.clone();
+
+        Ok(Self {
+            proxy,
+            name,
+            events,
+            observer,
+            observing: Arc::new(AtomicBool::new(false)),
+            latency,
+        })
+    }
+
+    /// Returns a future running the observer of the adapter's events until it fails, e.g. for a
+    /// [`Supervisor`](crate::supervisor::Supervisor) to spawn and restart.
+    pub fn observe(&self) -> impl Future<Output = ()> + Send + use<> {
+        let observer = self.observer.clone();
+        let observing = self.observing.clone();
+
+        async move {
+            observing.store(true, Ordering::Relaxed);
+            let result = observer.run().await;
+            observing.store(false, Ordering::Relaxed);
+            if let Err(e) = result {
+                error!("Bluetooth observer failed: {}", e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl BluetoothBackend for BlueZBackend {
+    fn adapter(&self) -> &str {
+        &self.proxy.iface
+    }
+
+    fn adapter_name(&self) -> &str {
+        &self.name
+    }
+
+    fn events(&self) -> &EventBus<BluetoothEvent> {
+        &self.events
+    }
+
+    fn is_observing(&self) -> bool {
+        self.observing.load(Ordering::Relaxed)
+    }
+
+    fn latency(&self) -> Option<&EventLatency> {
+        Some(&self.latency)
+    }
+
+    async fn is_powered(&self) -> Result<bool> {
+        self.proxy.is_powered().await
+    }
+
+    async fn power_on(&self) -> Result<()> {
+        self.proxy.turn_on_adapter().await
+    }
+
+    async fn power_off(&self) -> Result<()> {
+        self.proxy.turn_off_adapter().await
+    }
+
+    async fn soft_block(&self) -> Result<()> {
+        rfkill::soft_block(self.proxy.hci(), true)
+    }
+
+    async fn capabilities(&self) -> Result<AdapterCapabilities> {
+        self.proxy.capabilities().await
+    }
+
+    async fn devices(&self) -> Result<Vec<BluetoothDevice>> {
+        self.proxy.get_devices().await
+    }
+
+    async fn connect_device(&self, device: &BluetoothDevice) -> Result<()> {
+        self.proxy.connect_device(device).await
+    }
+
+    async fn disconnect_device(&self, device: &BluetoothDevice) -> Result<()> {
+        self.proxy.disconnect_device(device).await
+    }
+
+    async fn is_paired(&self, device: &BluetoothDevice) -> Result<bool> {
+        self.proxy.is_paired(device).await
+    }
+
+    async fn get_flag(&self, flag: AdapterFlag) -> Result<bool> {
+        self.proxy.get_flag(flag).await
+    }
+
+    async fn set_flag(&self, flag: AdapterFlag, value: bool) -> Result<()> {
+        self.proxy.set_flag(flag, value).await
+    }
+}
@@ -0,0 +1,252 @@
+// -- std imports
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+// -- crate imports
+use anyhow::Result;
+use async_trait::async_trait;
+
+// -- module imports
+use crate::bluetooth::{
+    backend::BluetoothBackend, capabilities::AdapterCapabilities, device::BluetoothDevice,
+    event_bus::EventBus, flag::AdapterFlag, observer::BluetoothEvent, service_proxy::DbusTimeout,
+};
+
+/// Object path of the adapter simulated by [`MockBackend`].
+pub const MOCK_ADAPTER: &str = "/org/bluez/hci0";
+
+/// State of the adapter simulated by [`MockBackend`].
+#[derive(Debug, Default)]
+struct MockState {
+    /// Whether the adapter is powered on.
+    powered: bool,
+    /// Devices known to the adapter.
+    devices: Vec<BluetoothDevice>,
+    /// Adapter flags that have been set.
+    flags: HashMap<AdapterFlag, bool>,
+    /// Number of `power_off` calls.
+    power_offs: usize,
+    /// Whether listing the devices times out, as with a hanging `bluetoothd`.
+    hanging: bool,
+    /// Whether the adapter is turned back on right after `power_off`, as by some desktop
+    /// environments.
+    sticky: bool,
+    /// Whether the adapter has been soft-blocked.
+    soft_blocked: bool,
+    /// What the controller supports.
+    capabilities: AdapterCapabilities,
+}
+
+/// In-memory [`BluetoothBackend`] for tests.
+///
+/// Operations change the simulated adapter and broadcast the same events BlueZ would (e.g.,
+/// `AdapterOff` on `power_off`), so the service reacts as it would to a real adapter.
+#[derive(Debug)]
+pub struct MockBackend {
+    state: Mutex<MockState>,
+    events: EventBus<BluetoothEvent>,
+}
+
+impl MockBackend {
+    /// Creates a mock adapter that is `powered` and knows no devices.
+    pub fn new(powered: bool) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(MockState {
+                powered,
+                ..Default::default()
+            }),
+            events: EventBus::new(),
+        })
+    }
+
+    /// Adds a device with the given `name` to the adapter and returns it.
+    ///
+    /// Unlike [`MockBackend::connect`], this does not broadcast an event, so it can be used to set
+    /// up the adapter before the service is created.
+    pub fn add_device(&self, name: &str, connected: bool) -> BluetoothDevice {
+        let device = BluetoothDevice {
+            common_name: Some(name.to_string()),
+            connected,
+            paired: true,
+            ..BluetoothDevice::new(format!("{}/dev_{}", MOCK_ADAPTER, name))
+        };
+        self.state.lock().unwrap().devices.push(device.clone());
+        device
+    }
+
+    /// Connects the known `device` and broadcasts `DeviceConnected`.
+    pub fn connect(&self, device: &BluetoothDevice) -> BluetoothEvent {
+        self.set_connected(device, true);
+        self.emit(BluetoothEvent::DeviceConnected(device.clone()))
+    }
+
+    /// Disconnects the known `device` and broadcasts `DeviceDisconnected`.
+    pub fn disconnect(&self, device: &BluetoothDevice) -> BluetoothEvent {
+        self.set_connected(device, false);
+        self.emit(BluetoothEvent::DeviceDisconnected(device.clone()))
+    }
+
+    /// Sets whether the known `device` is tethering and broadcasts `TetheringChanged`.
+    pub fn set_tethering(&self, device: &BluetoothDevice, tethering: bool) -> BluetoothEvent {
+        let mut state = self.state.lock().unwrap();
+        for known in state
+            .devices
+            .iter_mut()
+            .filter(|known| known.object_path == device.object_path)
+        {
+            known.tethering = tethering;
+        }
+        drop(state);
+
+        self.emit(BluetoothEvent::TetheringChanged(device.clone(), tethering))
+    }
+
+    /// Returns whether the adapter is powered on.
+    pub fn powered(&self) -> bool {
+        self.state.lock().unwrap().powered
+    }
+
+    /// Returns how often the adapter has been powered off.
+    pub fn power_offs(&self) -> usize {
+        self.state.lock().unwrap().power_offs
+    }
+
+    /// Makes listing the devices fail with a [`DbusTimeout`] while `hanging`.
+    pub fn set_hanging(&self, hanging: bool) {
+        self.state.lock().unwrap().hanging = hanging;
+    }
+
+    /// Makes `power_off` not stick while `sticky`, as if the adapter was turned back on at once.
+    pub fn set_sticky(&self, sticky: bool) {
+        self.state.lock().unwrap().sticky = sticky;
+    }
+
+    /// Sets what the controller supports.
+    pub fn set_capabilities(&self, capabilities: AdapterCapabilities) {
+        self.state.lock().unwrap().capabilities = capabilities;
+    }
+
+    /// Returns whether the adapter has been soft-blocked.
+    pub fn soft_blocked(&self) -> bool {
+        self.state.lock().unwrap().soft_blocked
+    }
+
+    /// Sets the `Connected` state of the known `device`.
+    fn set_connected(&self, device: &BluetoothDevice, connected: bool) {
+        let mut state = self.state.lock().unwrap();
+        for known in state
+            .devices
+            .iter_mut()
+            .filter(|known| known.object_path == device.object_path)
+        {
+            known.connected = connected;
+        }
+    }
+
+    /// Broadcasts `event` (ignoring the absence of subscribers) and returns it.
+    fn emit(&self, event: BluetoothEvent) -> BluetoothEvent {
+        self.events.publish(event.clone());
+        event
+    }
+}
+
+#[async_trait]
+impl BluetoothBackend for MockBackend {
+    fn adapter(&self) -> &str {
+        MOCK_ADAPTER
+    }
+
+    fn events(&self) -> &EventBus<BluetoothEvent> {
+        &self.events
+    }
+
+    async fn is_powered(&self) -> Result<bool> {
+        Ok(self.powered())
+    }
+
+    async fn power_on(&self) -> Result<()> {
+        self.state.lock().unwrap().powered = true;
+        self.emit(BluetoothEvent::AdapterOn);
+        Ok(())
+    }
+
+    async fn power_off(&self) -> Result<()> {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.power_offs += 1;
+            if state.sticky {
+                return Ok(());
+            }
+            state.powered = false;
+            state.devices.iter_mut().for_each(|d| d.connected = false);
+        }
+        self.emit(BluetoothEvent::AdapterOff);
+        Ok(())
+    }
+
+    async fn soft_block(&self) -> Result<()> {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.powered = false;
+            state.soft_blocked = true;
+            state.devices.iter_mut().for_each(|d| d.connected = false);
+        }
+        self.emit(BluetoothEvent::AdapterOff);
+        Ok(())
+    }
+
+    async fn capabilities(&self) -> Result<AdapterCapabilities> {
+        Ok(self.state.lock().unwrap().capabilities.clone())
+    }
+
+    async fn devices(&self) -> Result<Vec<BluetoothDevice>> {
+        let state = self.state.lock().unwrap();
+        if state.hanging {
+            return Err(DbusTimeout {
+                call: "GetManagedObjects".to_string(),
+                after: Duration::from_secs(5),
+            }
+            .into());
+        }
+
+        Ok(state.devices.clone())
+    }
+
+    async fn connect_device(&self, device: &BluetoothDevice) -> Result<()> {
+        self.connect(device);
+        Ok(())
+    }
+
+    async fn disconnect_device(&self, device: &BluetoothDevice) -> Result<()> {
+        self.disconnect(device);
+        Ok(())
+    }
+
+    async fn is_paired(&self, device: &BluetoothDevice) -> Result<bool> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .devices
+            .iter()
+            .any(|known| known.object_path == device.object_path))
+    }
+
+    async fn get_flag(&self, flag: AdapterFlag) -> Result<bool> {
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .flags
+            .get(&flag)
+            .copied()
+            .unwrap_or(false))
+    }
+
+    async fn set_flag(&self, flag: AdapterFlag, value: bool) -> Result<()> {
+        self.state.lock().unwrap().flags.insert(flag, value);
+        self.emit(BluetoothEvent::FlagChanged(flag, value));
+        Ok(())
+    }
+}
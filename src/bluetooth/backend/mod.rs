@@ -0,0 +1,107 @@
+// -- std imports
+use std::{fmt::Debug, sync::Arc};
+
+// -- crate imports
+use anyhow::Result;
+use async_trait::async_trait;
+
+// -- module definitions
+pub mod bluez;
+#[cfg(test)]
+pub mod mock;
+
+// -- module imports
+use crate::bluetooth::{
+    capabilities::AdapterCapabilities, device::BluetoothDevice, event_bus::EventBus,
+    flag::AdapterFlag, latency::EventLatency, observer::BluetoothEvent,
+};
+
+/// A [`BluetoothBackend`] shared between the service, its timeout tasks and helpers.
+pub type SharedBackend = Arc<dyn BluetoothBackend>;
+
+/// Operations on a single Bluetooth adapter and its devices.
+///
+/// The service state machine only talks to the adapter through this trait, so alternative
+/// backends (e.g., a mock for tests) can be swapped in. [`bluez::BlueZBackend`] implements it on
+/// top of BlueZ's D-Bus API.
+///
+/// A backend on top of the `bluer` crate (BlueZ's official Rust bindings) would implement this
+/// trait as well. It is deferred until `bluer` can be vendored, as it cannot be resolved from the
+/// registry the project is built against.
+#[async_trait]
+pub trait BluetoothBackend: Debug + Send + Sync {
+    /// Returns the identifier of the managed adapter (for BlueZ, its object path).
+    fn adapter(&self) -> &str;
+
+    /// Returns the human-readable name of the managed adapter (for BlueZ, its `Alias`), used in
+    /// notifications and logs. Defaults to [`BluetoothBackend::adapter`].
+    fn adapter_name(&self) -> &str {
+        self.adapter()
+    }
+
+    /// Returns the bus the backend publishes its [`BluetoothEvent`]s on, which helpers (e.g., the
+    /// timeout task) publish their own events on as well.
+    fn events(&self) -> &EventBus<BluetoothEvent>;
+
+    /// Checks if the backend still produces [`BluetoothEvent`]s (e.g., that the task observing
+    /// the adapter has not stopped). Defaults to `true`.
+    fn is_observing(&self) -> bool {
+        true
+    }
+
+    /// Returns the measurement the backend stamps its [`BluetoothEvent`]s in, if it measures
+    /// their latency. Defaults to `None`.
+    fn latency(&self) -> Option<&EventLatency> {
+        None
+    }
+
+    /// Checks if the adapter is currently powered on.
+    async fn is_powered(&self) -> Result<bool>;
+
+    /// Powers the adapter on.
+    async fn power_on(&self) -> Result<()>;
+
+    /// Powers the adapter off.
+    async fn power_off(&self) -> Result<()>;
+
+    /// Soft-blocks the adapter (e.g., via rfkill), if the backend supports it.
+    async fn soft_block(&self) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Soft-blocking is not supported by this backend"
+        ))
+    }
+
+    /// Returns what the adapter's controller supports. Defaults to [`AdapterCapabilities`]'s
+    /// default, with which everything is managed.
+    async fn capabilities(&self) -> Result<AdapterCapabilities> {
+        Ok(AdapterCapabilities::default())
+    }
+
+    /// Returns all devices known to the adapter.
+    async fn devices(&self) -> Result<Vec<BluetoothDevice>>;
+
+    /// Returns the devices that are currently connected.
+    async fn connected_devices(&self) -> Result<Vec<BluetoothDevice>> {
+        Ok(self
+            .devices()
+            .await?
+            .into_iter()
+            .filter(|device| device.connected)
+            .collect())
+    }
+
+    /// Connects the given `device`.
+    async fn connect_device(&self, device: &BluetoothDevice) -> Result<()>;
+
+    /// Disconnects the given `device`.
+    async fn disconnect_device(&self, device: &BluetoothDevice) -> Result<()>;
+
+    /// Checks if the given `device` is paired with the adapter.
+    async fn is_paired(&self, device: &BluetoothDevice) -> Result<bool>;
+
+    /// Checks if the given adapter `flag` is currently set.
+    async fn get_flag(&self, flag: AdapterFlag) -> Result<bool>;
+
+    /// Sets the given adapter `flag` to `value`.
+    async fn set_flag(&self, flag: AdapterFlag, value: bool) -> Result<()>;
+}
@@ -0,0 +1,61 @@
+// -- std imports
+use std::time::Duration;
+
+// -- crate imports
+use tracing::{info, warn};
+
+// -- module imports
+use crate::bluetooth::{backend::SharedBackend, device::BluetoothDevice};
+
+/// Logs the battery level of the connected devices that report one every `interval` (see
+/// `log.battery_interval`), so a low battery shows up next to the disconnect it may explain.
+/// Nothing is logged while no connected device reports its battery level.
+pub async fn log(backend: SharedBackend, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+
+        match backend.connected_devices().await {
+            Ok(devices) => {
+                if let Some(levels) = levels(&devices) {
+                    info!("Battery of {}: {}", backend.adapter_name(), levels);
+                }
+            }
+            Err(e) => warn!("Could not query battery levels: {}", e),
+        }
+    }
+}
+
+/// Formats the battery levels of `devices` (e.g., "Headset 80%, Mouse 8%"), or returns `None` if
+/// none of them reports one.
+fn levels(devices: &[BluetoothDevice]) -> Option<String> {
+    let levels: Vec<String> = devices
+        .iter()
+        .filter_map(|device| {
+            let battery = device.battery?;
+            Some(format!("{} {}%", device.display_name(), battery))
+        })
+        .collect();
+
+    (!levels.is_empty()).then(|| levels.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    #[test]
+    fn lists_only_devices_reporting_their_battery() {
+        let device = |name: &str, battery| BluetoothDevice {
+            common_name: Some(name.to_string()),
+            battery,
+            ..BluetoothDevice::new(format!("/org/bluez/hci0/dev_{}", name))
+        };
+
+        assert_eq!(
+            levels(&[device("Headset", Some(80)), device("Keyboard", None)]),
+            Some("Headset 80%".to_string())
+        );
+        assert_eq!(levels(&[device("Keyboard", None)]), None);
+    }
+}
@@ -0,0 +1,97 @@
+// -- std imports
+use std::time::Duration;
+
+// -- crate imports
+use chrono::{Local, NaiveTime};
+
+// -- module imports
+use crate::configuration::BlackoutConf;
+
+/// Length of a day, after which the boundaries of the periods repeat.
+const DAY: Duration = Duration::from_hours(24);
+
+/// Longest time between two checks of the local time, so a boundary passed while the system was
+/// suspended (or the clock changed) is noticed soon.
+const MAX_WAIT: Duration = Duration::from_mins(1);
+
+/// Returns the period of `periods` that `time` lies within, if any.
+pub fn active(periods: &[BlackoutConf], time: NaiveTime) -> Option<&BlackoutConf> {
+    periods.iter().find(|period| period.contains(time))
+}
+
+/// Returns `true` if the current local time lies within one of `periods`.
+pub fn active_now(periods: &[BlackoutConf]) -> bool {
+    active(periods, Local::now().time()).is_some()
+}
+
+/// Resolves with the new value once whether a period of `periods` is active differs from
+/// `active`. Never resolves if there are no periods.
+pub async fn changed(periods: &[BlackoutConf], active: bool) -> Option<bool> {
+    loop {
+        let wait = until_change(periods, Local::now().time())?;
+        tokio::time::sleep(wait.min(MAX_WAIT)).await;
+
+        let now = active_now(periods);
+        if now != active {
+            return Some(now);
+        }
+    }
+}
+
+/// Returns the time from `time` until the next start or end of one of `periods`, or `None` if
+/// there are no periods.
+fn until_change(periods: &[BlackoutConf], time: NaiveTime) -> Option<Duration> {
+    periods
+        .iter()
+        .flat_map(|period| [period.from, period.to])
+        .map(|boundary| {
+            let day = DAY.as_millis() as i64;
+            let until = boundary
+                .signed_duration_since(time)
+                .num_milliseconds()
+                .rem_euclid(day);
+            Duration::from_millis(if until == 0 { day } else { until } as u64)
+        })
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    /// Returns the time of day `hour:minute`.
+    fn at(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    /// Returns a period from `from` to `to`.
+    fn period(from: NaiveTime, to: NaiveTime) -> BlackoutConf {
+        BlackoutConf { from, to }
+    }
+
+    #[test]
+    fn periods_may_span_midnight() {
+        let night = [period(at(22, 0), at(6, 0))];
+
+        assert!(active(&night, at(23, 30)).is_some());
+        assert!(active(&night, at(0, 0)).is_some());
+        assert!(active(&night, at(6, 0)).is_none());
+        assert!(active(&night, at(12, 0)).is_none());
+    }
+
+    #[test]
+    fn waits_for_the_next_boundary() {
+        let periods = [period(at(0, 0), at(6, 0)), period(at(12, 0), at(13, 0))];
+
+        assert_eq!(
+            until_change(&periods, at(5, 30)),
+            Some(Duration::from_mins(30))
+        );
+        assert_eq!(
+            until_change(&periods, at(13, 0)),
+            Some(Duration::from_hours(11))
+        );
+        assert_eq!(until_change(&[], at(13, 0)), None);
+    }
+}
@@ -0,0 +1,125 @@
+// -- std imports
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+// -- crate imports
+use tokio::time::Instant;
+
+// -- module imports
+use crate::bluetooth::device::BluetoothDevice;
+
+/// The contents of a [`DeviceCache`].
+#[derive(Debug, Default)]
+struct CacheState {
+    /// The cached devices and when they were fetched, if any.
+    devices: Option<(Instant, Vec<BluetoothDevice>)>,
+    /// Incremented on every invalidation, so a fetch that raced with one is not stored.
+    generation: u64,
+}
+
+/// Short-lived cache of the adapter's devices and their properties.
+///
+/// A burst of events (e.g., several devices disconnecting at once) makes the service query the
+/// devices once per event. The cache answers all but the first of these queries without a D-Bus
+/// round trip. It is invalidated by the [`BluetoothEventObserver`] whenever BlueZ reports a change,
+/// and expires after `ttl` for changes that are not signalled. Clones share the same cache.
+///
+/// [`BluetoothEventObserver`]: crate::bluetooth::observer::BluetoothEventObserver
+#[derive(Debug, Clone)]
+pub struct DeviceCache {
+    /// How long cached devices are used.
+    ttl: Duration,
+    /// The cached devices.
+    state: Arc<Mutex<CacheState>>,
+}
+
+impl DeviceCache {
+    /// Creates an empty cache keeping devices for `ttl`. A `ttl` of zero disables caching.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            state: Arc::default(),
+        }
+    }
+
+    /// Returns the cached devices, or `None` if there are none or they expired.
+    pub fn get(&self) -> Option<Vec<BluetoothDevice>> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state
+            .devices
+            .as_ref()
+            .filter(|(fetched, _)| fetched.elapsed() < self.ttl)
+            .map(|(_, devices)| devices.clone())
+    }
+
+    /// Returns the current generation, to be passed to [`DeviceCache::set`] after fetching.
+    pub fn generation(&self) -> u64 {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .generation
+    }
+
+    /// Stores `devices` fetched at `generation`. They are dropped if the cache was invalidated
+    /// since, as they may predate the change.
+    pub fn set(&self, generation: u64, devices: Vec<BluetoothDevice>) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.generation == generation && !self.ttl.is_zero() {
+            state.devices = Some((Instant::now(), devices));
+        }
+    }
+
+    /// Drops the cached devices.
+    pub fn invalidate(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.devices = None;
+        state.generation += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    /// Returns a cache with a TTL of one second holding a single device.
+    fn filled_cache() -> DeviceCache {
+        let cache = DeviceCache::new(Duration::from_secs(1));
+        cache.set(
+            cache.generation(),
+            vec![BluetoothDevice::new(
+                "/org/bluez/hci0/dev_00_11_22_33_44_55",
+            )],
+        );
+        cache
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn expires_after_ttl() {
+        let cache = filled_cache();
+        assert_eq!(cache.get().map(|d| d.len()), Some(1));
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(cache.get(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn invalidate_drops_devices() {
+        let cache = filled_cache();
+        cache.clone().invalidate();
+
+        assert_eq!(cache.get(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ignores_fetch_that_raced_with_invalidation() {
+        let cache = DeviceCache::new(Duration::from_secs(1));
+        let generation = cache.generation();
+        cache.invalidate();
+        cache.set(generation, vec![]);
+
+        assert_eq!(cache.get(), None);
+    }
+}
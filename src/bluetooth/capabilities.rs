@@ -0,0 +1,68 @@
+// -- module imports
+use crate::bluetooth::flag::AdapterFlag;
+
+/// What the controller of an adapter supports (see
+/// [`BluetoothServiceProxy::capabilities`](crate::bluetooth::service_proxy::BluetoothServiceProxy::capabilities)).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AdapterCapabilities {
+    /// Whether the controller supports BR/EDR.
+    pub bredr: bool,
+    /// LE roles the controller supports (the adapter's `Roles`, e.g., "central" and
+    /// "peripheral"); empty if it does not support LE.
+    pub roles: Vec<String>,
+}
+
+impl AdapterCapabilities {
+    /// Returns the capabilities of a controller that supports BR/EDR if `bredr` is set, and the LE
+    /// `roles`.
+    pub fn new(bredr: bool, roles: Vec<String>) -> Self {
+        Self { bredr, roles }
+    }
+
+    /// Checks if the controller only supports LE.
+    pub fn le_only(&self) -> bool {
+        !self.bredr && !self.roles.is_empty()
+    }
+
+    /// Checks if `flag` means anything for the controller and should be managed. `Discoverable`
+    /// only controls BR/EDR inquiry scans, so it is left alone on LE-only controllers.
+    pub fn manages(&self, flag: AdapterFlag) -> bool {
+        !(flag == AdapterFlag::Discoverable && self.le_only())
+    }
+
+    /// Returns a short description for logs and `status` (e.g., "BR/EDR, LE (central,
+    /// peripheral)").
+    pub fn describe(&self) -> String {
+        let le = match self.roles.as_slice() {
+            [] => None,
+            roles => Some(format!("LE ({})", roles.join(", "))),
+        };
+
+        match (self.bredr, le) {
+            (true, Some(le)) => format!("BR/EDR, {}", le),
+            (true, None) => "BR/EDR".to_string(),
+            (false, Some(le)) => le,
+            (false, None) => "unknown".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    #[test]
+    fn discoverable_is_left_alone_on_le_only_controllers() {
+        let roles = vec!["central".to_string(), "peripheral".to_string()];
+        let dual = AdapterCapabilities::new(true, roles.clone());
+        let le_only = AdapterCapabilities::new(false, roles);
+
+        assert!(dual.manages(AdapterFlag::Discoverable));
+        assert!(!le_only.manages(AdapterFlag::Discoverable));
+        assert!(le_only.manages(AdapterFlag::Pairable));
+        assert!(!AdapterCapabilities::default().le_only());
+        assert_eq!(dual.describe(), "BR/EDR, LE (central, peripheral)");
+        assert_eq!(le_only.describe(), "LE (central, peripheral)");
+    }
+}
@@ -2,12 +2,75 @@
 use serde::{Deserialize, Serialize};
 
 /// Represents a Bluetooth device with its relevant properties.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BluetoothDevice {
     /// The D-Bus object path of the Bluetooth device.
     pub object_path: String,
-    /// The name of the Bluetooth device.
+    /// The MAC address of the device (e.g., "AA:BB:CC:DD:EE:FF").
+    #[serde(default)]
+    pub address: Option<String>,
+    /// The name of the Bluetooth device (its `Alias`, which defaults to its `Name`).
     pub common_name: Option<String>,
     /// Whether the device is currently connected.
     pub connected: bool,
+    /// Whether the device is paired with the adapter.
+    #[serde(default)]
+    pub paired: bool,
+    /// Whether the device is trusted, i.e. may connect without confirmation.
+    #[serde(default)]
+    pub trusted: bool,
+    /// The signal strength of the device in dBm, if reported by the adapter.
+    #[serde(default)]
+    pub rssi: Option<i16>,
+    /// The freedesktop icon name of the device type (e.g., "audio-headset"), if known.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// The battery level of the device in percent, if it reports one (`org.bluez.Battery1`).
+    #[serde(default)]
+    pub battery: Option<u8>,
+    /// Whether a network (PAN) connection to the device is up, i.e. the device is tethering.
+    #[serde(default)]
+    pub tethering: bool,
+}
+
+impl BluetoothDevice {
+    /// Creates a device at `object_path` with its address derived from the path and all other
+    /// properties unknown.
+    pub fn new(object_path: impl Into<String>) -> Self {
+        let object_path = object_path.into();
+        Self {
+            address: address_from_path(&object_path),
+            object_path,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the human-readable name of the device, or its object path if it has none.
+    pub fn display_name(&self) -> &str {
+        self.common_name.as_deref().unwrap_or(&self.object_path)
+    }
+
+    /// Returns `true` if the device, while connected, keeps the adapter on: any device does, unless
+    /// `trusted_only` restricts this to trusted devices.
+    pub fn keeps_adapter_on(&self, trusted_only: bool) -> bool {
+        self.trusted || !trusted_only
+    }
+
+    /// Returns `true` if `pattern` is the MAC address or the name of the device (ignoring case).
+    pub fn matches(&self, pattern: &str) -> bool {
+        self.address
+            .as_deref()
+            .is_some_and(|address| address.eq_ignore_ascii_case(pattern))
+            || self
+                .common_name
+                .as_deref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(pattern))
+    }
+}
+
+/// Derives the MAC address of a device from its object path (e.g.,
+/// "/org/bluez/hci0/dev_AA_BB_CC_DD_EE_FF").
+fn address_from_path(object_path: &str) -> Option<String> {
+    let (_, dev) = object_path.rsplit_once("/dev_")?;
+    Some(dev.replace('_', ":"))
 }
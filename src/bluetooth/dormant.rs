@@ -0,0 +1,149 @@
+// -- std imports
+use std::collections::HashMap;
+
+// -- crate imports
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+// -- module imports
+use crate::{
+    bluetooth::{backend::SharedBackend, device::BluetoothDevice, observer::BluetoothEvent},
+    configuration::DormantConf,
+};
+
+/// Publishes (on the backend's event bus) [`BluetoothEvent::DeviceDormant`] for devices
+/// matching one of the `rules` once they have been connected for the rule's `after`.
+///
+/// This keeps devices that hold a connection around the clock (e.g., smart watches or beacons)
+/// from blocking the timeout forever. Devices that are already connected when this starts are
+/// counted from then on, as their connection time is unknown. Returns once the event stream is
+/// closed.
+pub async fn run(backend: SharedBackend, rules: &'static [DormantConf]) {
+    // Subscribed before listing the connected devices, so no connection is missed in between
+    let mut events = backend.events().subscribe("dormant");
+
+    info!(
+        "Tracking the connection time of {} dormant device rule(s).",
+        rules.len()
+    );
+    // Object path -> (instant the device becomes dormant, the device)
+    let mut dormant_at: HashMap<String, (Instant, BluetoothDevice)> = HashMap::new();
+
+    match backend.connected_devices().await {
+        Ok(devices) => {
+            for device in devices {
+                track(&mut dormant_at, rules, device);
+            }
+        }
+        Err(e) => warn!("Could not list connected devices: {}", e),
+    }
+
+    loop {
+        let next = dormant_at.values().map(|(at, _)| *at).min();
+
+        tokio::select! {
+            _ = tokio::time::sleep_until(next.unwrap_or_else(Instant::now)), if next.is_some() => {
+                let now = Instant::now();
+                for (_, (_, device)) in dormant_at.extract_if(|_, (at, _)| *at <= now) {
+                    if !backend.events().publish(BluetoothEvent::DeviceDormant(device)) {
+                        return;
+                    }
+                }
+            }
+            event = events.recv() => match event {
+                Some(BluetoothEvent::DeviceConnected(device)) => {
+                    // The event may lack the name of the device, which rules can refer to
+                    let device = backend
+                        .devices()
+                        .await
+                        .unwrap_or_default()
+                        .into_iter()
+                        .find(|known| known.object_path == device.object_path)
+                        .unwrap_or(device);
+                    track(&mut dormant_at, rules, device);
+                }
+                Some(BluetoothEvent::DeviceDisconnected(device)) => {
+                    dormant_at.remove(&device.object_path);
+                }
+                Some(_) => {}
+                None => return,
+            },
+        }
+    }
+}
+
+/// Starts counting the connection time of `device` if it matches one of the `rules`.
+fn track(
+    dormant_at: &mut HashMap<String, (Instant, BluetoothDevice)>,
+    rules: &[DormantConf],
+    device: BluetoothDevice,
+) {
+    let Some(rule) = rules.iter().find(|rule| device.matches(&rule.device)) else {
+        return;
+    };
+
+    debug!(
+        "{} will be dormant after {} of connection.",
+        device.display_name(),
+        humantime::format_duration(rule.after)
+    );
+    let at = Instant::now() + rule.after;
+    dormant_at.insert(device.object_path.clone(), (at, device));
+}
+
+#[cfg(test)]
+mod tests {
+    // -- std imports
+    use std::time::Duration;
+
+    // -- module imports
+    use super::*;
+    use crate::bluetooth::backend::{BluetoothBackend, mock::MockBackend};
+
+    const AFTER: Duration = Duration::from_secs(60);
+
+    fn rules() -> &'static [DormantConf] {
+        Box::leak(Box::new([DormantConf {
+            device: "watch".to_string(),
+            after: AFTER,
+        }]))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reports_matching_devices_connected_for_long() {
+        let backend = MockBackend::new(true);
+        let watch = backend.add_device("watch", true);
+        backend.add_device("headset", true);
+        let mut events = backend.events().subscribe("test");
+        let _task = tokio::spawn(run(backend.clone(), rules()));
+
+        tokio::time::sleep(AFTER / 2).await;
+        assert_eq!(events.try_recv(), None);
+
+        tokio::time::sleep(AFTER).await;
+        assert_eq!(
+            events.try_recv(),
+            Some(BluetoothEvent::DeviceDormant(watch))
+        );
+        assert_eq!(events.try_recv(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn forgets_devices_that_disconnect() {
+        let backend = MockBackend::new(true);
+        let watch = backend.add_device("watch", false);
+        let mut events = backend.events().subscribe("test");
+        let _task = tokio::spawn(run(backend.clone(), rules()));
+        tokio::task::yield_now().await;
+
+        backend.connect(&watch);
+        tokio::time::sleep(AFTER / 2).await;
+        backend.disconnect(&watch);
+        tokio::time::sleep(2 * AFTER).await;
+
+        let dormant = std::iter::from_fn(|| events.try_recv())
+            .filter(|event| matches!(event, BluetoothEvent::DeviceDormant(_)))
+            .count();
+        assert_eq!(dormant, 0);
+    }
+}
@@ -0,0 +1,129 @@
+// -- crate imports
+use tokio::sync::broadcast::{
+    self,
+    error::{RecvError, TryRecvError},
+};
+use tracing::warn;
+
+/// Number of events a subscriber may fall behind before it misses the oldest ones.
+const CAPACITY: usize = 16;
+
+/// Publish/subscribe bus of the events of type `E` of an adapter (e.g., [`BluetoothEvent`]s).
+///
+/// Every event is delivered to all subscriptions that exist when it is published, so subscribe
+/// before looking at the state the events change. Clones publish on the same bus. Subscribers
+/// that fall behind by more than [`CAPACITY`] events miss the oldest ones, which is logged.
+///
+/// [`BluetoothEvent`]: crate::bluetooth::observer::BluetoothEvent
+#[derive(Debug, Clone)]
+pub struct EventBus<E> {
+    /// Sender of the underlying channel.
+    tx: broadcast::Sender<E>,
+}
+
+impl<E: Clone> EventBus<E> {
+    /// Creates a bus without subscribers.
+    pub fn new() -> Self {
+        Self {
+            tx: broadcast::Sender::new(CAPACITY),
+        }
+    }
+
+    /// Publishes `event` to the current subscribers.
+    ///
+    /// Returns `false` if there are none, e.g. as the service has stopped.
+    pub fn publish(&self, event: E) -> bool {
+        self.tx.send(event).is_ok()
+    }
+
+    /// Returns `true` if anyone is subscribed.
+    pub fn has_subscribers(&self) -> bool {
+        self.tx.receiver_count() > 0
+    }
+
+    /// Subscribes to the events published from now on; `subscriber` names it in the logs (e.g.,
+    /// "service").
+    pub fn subscribe(&self, subscriber: &'static str) -> Subscription<E> {
+        Subscription {
+            subscriber,
+            rx: self.tx.subscribe(),
+        }
+    }
+}
+
+impl<E: Clone> Default for EventBus<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A subscription to an [`EventBus`], receiving its events in the order they were published.
+#[derive(Debug)]
+pub struct Subscription<E> {
+    /// Name of the subscriber for the logs.
+    subscriber: &'static str,
+    /// Receiver of the underlying channel.
+    rx: broadcast::Receiver<E>,
+}
+
+impl<E: Clone> Subscription<E> {
+    /// Waits for the next event, skipping those missed by falling behind.
+    ///
+    /// Returns `None` once the bus is gone.
+    pub async fn recv(&mut self) -> Option<E> {
+        loop {
+            match self.rx.recv().await {
+                Ok(event) => return Some(event),
+                Err(RecvError::Lagged(missed)) => self.lagged(missed),
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Returns the next event that was already published, skipping those missed by falling
+    /// behind, or `None` if there is none (yet).
+    pub fn try_recv(&mut self) -> Option<E> {
+        loop {
+            match self.rx.try_recv() {
+                Ok(event) => return Some(event),
+                Err(TryRecvError::Lagged(missed)) => self.lagged(missed),
+                Err(TryRecvError::Empty | TryRecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Logs that `missed` events were skipped.
+    fn lagged(&self, missed: u64) {
+        warn!("{} fell behind, missed {} events.", self.subscriber, missed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    #[tokio::test]
+    async fn delivers_events_to_every_subscriber() {
+        let bus = EventBus::new();
+        assert!(!bus.publish(0));
+
+        let (mut first, mut second) = (bus.subscribe("first"), bus.subscribe("second"));
+        assert!(bus.publish(1));
+
+        assert_eq!(first.recv().await, Some(1));
+        assert_eq!(second.try_recv(), Some(1));
+        assert_eq!(second.try_recv(), None);
+    }
+
+    #[test]
+    fn lagging_subscribers_skip_the_oldest_events() {
+        let bus = EventBus::new();
+        let mut subscription = bus.subscribe("slow");
+        for event in 0..CAPACITY + 2 {
+            bus.publish(event);
+        }
+
+        assert_eq!(subscription.try_recv(), Some(2));
+    }
+}
@@ -0,0 +1,111 @@
+// -- crate imports (conditional)
+#[cfg(feature = "notifications")]
+use tracing::error;
+
+// -- crate imports
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+// -- module imports (conditional)
+#[cfg(feature = "notifications")]
+use crate::notification::Notification;
+
+// -- module imports
+use crate::{
+    bluetooth::backend::SharedBackend,
+    configuration::{Conf, FlagTimeoutConf},
+};
+
+/// Adapter properties that are turned off after a timeout of their own, independently of the
+/// idle timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AdapterFlag {
+    /// Whether the adapter is visible to other devices.
+    Discoverable,
+    /// Whether the adapter accepts pairing requests.
+    Pairable,
+}
+
+impl AdapterFlag {
+    /// All flags, e.g. to query their initial state.
+    pub const ALL: [AdapterFlag; 2] = [AdapterFlag::Discoverable, AdapterFlag::Pairable];
+
+    /// Returns the name of the adapter property.
+    pub fn property(self) -> &'static str {
+        match self {
+            Self::Discoverable => "Discoverable",
+            Self::Pairable => "Pairable",
+        }
+    }
+
+    /// Returns the flag for the adapter property `name`, if it is managed.
+    pub fn from_property(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|flag| flag.property() == name)
+    }
+
+    /// Returns the timeout configuration of the flag.
+    pub fn conf(self) -> &'static FlagTimeoutConf {
+        match self {
+            Self::Discoverable => &Conf::instance().discoverable,
+            Self::Pairable => &Conf::instance().pairable,
+        }
+    }
+}
+
+/// Spawns a task that turns `flag` off after its configured timeout and sends a notification (if
+/// enabled). Flags that the adapter's controller does not use (see
+/// [`AdapterCapabilities::manages`]) are left alone.
+///
+/// Returns `None` if the timeout of the flag is disabled (`0s`).
+///
+/// [`AdapterCapabilities::manages`]: crate::bluetooth::capabilities::AdapterCapabilities::manages
+pub fn spawn_timer(flag: AdapterFlag, backend: SharedBackend) -> Option<JoinHandle<()>> {
+    let conf = flag.conf();
+    if conf.timeout.is_zero() {
+        return None;
+    }
+
+    Some(tokio::spawn(async move {
+        if let Ok(capabilities) = backend.capabilities().await
+            && !capabilities.manages(flag)
+        {
+            info!(
+                "Leaving {} of {} alone, as its controller only supports LE.",
+                flag.property(),
+                backend.adapter_name()
+            );
+            return;
+        }
+
+        info!(
+            "{} of {} will be turned off in {}.",
+            flag.property(),
+            backend.adapter_name(),
+            humantime::format_duration(conf.timeout)
+        );
+        tokio::time::sleep(conf.timeout).await;
+
+        if let Err(e) = backend.set_flag(flag, false).await {
+            warn!(
+                "Failed to turn off {} of {}: {}",
+                flag.property(),
+                backend.adapter_name(),
+                e
+            );
+            return;
+        }
+        info!(
+            "{} of {} turned off after timeout.",
+            flag.property(),
+            backend.adapter_name()
+        );
+
+        #[cfg(feature = "notifications")]
+        if conf.notify && Conf::instance().notifications().enabled {
+            let _ = Notification::flag_turned_off(backend.adapter_name(), flag)
+                .send()
+                .await
+                .inspect_err(|e| error!("Failed to show notification: {}", e));
+        }
+    }))
+}
@@ -0,0 +1,972 @@
+// -- std imports
+use std::time::Duration;
+
+// -- crate imports
+use tracing::{debug, info, warn};
+
+// -- module imports
+use crate::{
+    bluetooth::service::{BluetoothServiceCommand, BluetoothServiceState},
+    configuration::{Conf, LockAction, LockConf, TimeoutConf, TimeoutContext},
+};
+
+/// Inputs of the [`Fsm`], derived from Bluetooth events and service commands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    /// The service started in its initial state.
+    Started,
+    /// The adapter was powered on; `connected` devices are connected.
+    AdapterOn { connected: usize },
+    /// The adapter was powered off, `automatically` by the timeout or otherwise.
+    AdapterOff { automatically: bool },
+    /// The set of connected devices changed; `connected` devices are connected now.
+    ///
+    /// `device` names the device that caused the change, if known.
+    DevicesChanged {
+        connected: usize,
+        device: Option<String>,
+    },
+    /// A command was sent to the service.
+    Command(BluetoothServiceCommand),
+    /// The settings changed (e.g., another profile was activated), now with `timeouts` (`None`
+    /// if off) and, with `announce`, a notification when the last device disconnects.
+    SettingsChanged {
+        timeouts: Option<TimeoutConf>,
+        announce: bool,
+    },
+    /// The window the adapter was kept on for (see [`BluetoothServiceCommand::KeepOnFor`])
+    /// ended.
+    KeepOnExpired,
+    /// A blackout period (see `blackout`) started (`true`) or ended (`false`).
+    BlackoutChanged(bool),
+    /// Airplane mode (an rfkill block of the adapter) was turned on (`true`) or off (`false`).
+    AirplaneModeChanged(bool),
+    /// The adapter disappeared (e.g., the dongle was unplugged).
+    AdapterRemoved,
+    /// The adapter reappeared, `powered` on or not, with `connected` devices connected.
+    AdapterAdded { powered: bool, connected: usize },
+    /// The wake timer (see `power_on.after_timeout`) expired.
+    WakeTimerExpired,
+}
+
+/// Side effects requested by the [`Fsm`], applied by the [`BluetoothService`] in order.
+///
+/// [`BluetoothService`]: crate::bluetooth::service::BluetoothService
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    /// Start a timeout timer expiring after `timeout`.
+    ///
+    /// With `announce`, a notification (naming `device`, if known) is sent when it starts.
+    StartTimer {
+        timeout: Duration,
+        announce: bool,
+        device: Option<String>,
+    },
+    /// Abort the timeout timer.
+    CancelTimer,
+    /// Abort the timeout timer because `device` connected, withdrawing its notifications.
+    CancelTimerOnConnect { device: Option<String> },
+    /// Abort the timeout timer after a grace period, so it can finish its final notification.
+    ReleaseTimer,
+    /// Postpone the deadline of the timeout timer.
+    SnoozeTimer(Duration),
+    /// Bring the deadline of the timeout timer forward.
+    AccelerateTimer(Duration),
+    /// Pause the countdown of the timeout timer, keeping its remaining time.
+    PauseTimer,
+    /// Resume the countdown of the timeout timer paused by [`Effect::PauseTimer`].
+    ResumeTimer,
+    /// Power off the adapter.
+    PowerOff,
+    /// Power on the adapter.
+    PowerOn,
+    /// Keep the adapter on for the given duration, replacing an active window; ends with
+    /// [`Input::KeepOnExpired`].
+    StartKeepOn(Duration),
+    /// End the window the adapter is kept on for early.
+    CancelKeepOn,
+    /// Connect the devices listed in `auto_connect`.
+    AutoConnect,
+    /// Abort the timers of the adapter flags.
+    CancelFlagTimers,
+    /// Notify the user that the adapter was turned off because of a blackout period.
+    NotifyBlackout,
+    /// Start the wake timer expiring after the given duration, replacing a running one; ends
+    /// with [`Input::WakeTimerExpired`].
+    StartWakeTimer(Duration),
+    /// Stop the wake timer.
+    CancelWakeTimer,
+}
+
+/// The state machine of the [`BluetoothService`].
+///
+/// Maps the current state and an [`Input`] to the next state and the [`Effect`]s to apply. It
+/// does no I/O, so the transitions can be tested in isolation.
+///
+/// [`BluetoothService`]: crate::bluetooth::service::BluetoothService
+#[derive(Debug, Clone)]
+pub struct Fsm {
+    /// Current state of the adapter.
+    state: BluetoothServiceState,
+    /// Whether a timeout timer is running.
+    timer: bool,
+    /// Whether the user asked to keep the adapter on until the next state change.
+    keep_on: bool,
+    /// Whether the user asked to keep the adapter on for a while, regardless of state changes.
+    keep_on_for: bool,
+    /// Durations before an idle adapter is turned off, depending on what made it idle, or `None`
+    /// if it is never turned off.
+    timeouts: Option<TimeoutConf>,
+    /// Reaction to the screen being locked.
+    lock: LockConf,
+    /// Whether a notification is sent when the last device disconnects.
+    announce: bool,
+    /// Whether a blackout period is active, during which the adapter is kept off.
+    blackout: bool,
+    /// Whether airplane mode is on, during which the adapter cannot be powered on.
+    airplane_mode: bool,
+    /// Duration after which an adapter turned off by the timeout is powered on again, if at all.
+    wake_after: Option<Duration>,
+    /// Whether the wake timer is running.
+    wake_timer: bool,
+}
+
+impl Fsm {
+    /// Creates a state machine in `state`, turning off an idle adapter after `timeouts` (never if
+    /// `None`).
+    ///
+    /// The reaction to the screen being locked, the start notification and the wake timer are
+    /// taken from the global configuration.
+    pub fn new(state: BluetoothServiceState, timeouts: Option<TimeoutConf>) -> Self {
+        let conf = Conf::instance();
+        Self {
+            state,
+            timer: false,
+            keep_on: false,
+            keep_on_for: false,
+            timeouts,
+            lock: conf.lock.clone(),
+            announce: conf.notifications().on_start,
+            blackout: false,
+            airplane_mode: false,
+            wake_after: conf.power_on.after_timeout,
+            wake_timer: false,
+        }
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> BluetoothServiceState {
+        self.state
+    }
+
+    /// Returns `true` if a blackout period is active.
+    pub fn blackout(&self) -> bool {
+        self.blackout
+    }
+
+    /// Returns `true` if airplane mode is on.
+    pub fn airplane_mode(&self) -> bool {
+        self.airplane_mode
+    }
+
+    /// Returns `true` if a timeout timer is running.
+    #[cfg(test)]
+    pub fn timer(&self) -> bool {
+        self.timer
+    }
+
+    /// Records that the timeout timer has finished on its own (e.g., after its last stage).
+    pub fn timer_finished(&mut self) {
+        self.timer = false;
+    }
+
+    /// Processes `input` and returns the effects to apply, in order.
+    pub fn step(&mut self, input: Input) -> Vec<Effect> {
+        let mut effects = vec![];
+
+        match input {
+            Input::Started => {
+                if self.state == BluetoothServiceState::Idle
+                    && !self.blackout
+                    && let Some(timeout) = self.timeout(Some(TimeoutContext::Boot))
+                {
+                    info!(
+                        "Starting timeout timer for idle adapter with timeout of {:?}",
+                        timeout
+                    );
+                    self.start_timer(&mut effects, timeout, false, None);
+                }
+            }
+            Input::AdapterOn { .. } | Input::AdapterOff { .. } | Input::DevicesChanged { .. }
+                if self.state == BluetoothServiceState::Detached =>
+            {
+                debug!("Ignoring {:?}; the adapter is detached.", input);
+            }
+            Input::AdapterOn { connected } if self.blackout => {
+                info!("Turning the adapter off again; it is kept off during the blackout period.");
+                self.power_off_for_blackout(&mut effects);
+                self.state = if connected > 0 {
+                    BluetoothServiceState::Running
+                } else {
+                    BluetoothServiceState::Idle
+                };
+            }
+            Input::AdapterOn { connected } => {
+                self.cancel_wake_timer(&mut effects);
+                if self.state == BluetoothServiceState::Off {
+                    effects.push(Effect::AutoConnect);
+                }
+
+                if connected > 0 {
+                    self.cancel_timer(&mut effects, Effect::CancelTimer);
+                    self.keep_on = false;
+                    self.state = BluetoothServiceState::Running;
+                } else {
+                    if !self.timer
+                        && !self.kept_on()
+                        && let Some(timeout) = self.timeout(Some(TimeoutContext::ManualOn))
+                    {
+                        self.start_timer(&mut effects, timeout, false, None);
+                    }
+                    self.state = BluetoothServiceState::Idle;
+                }
+            }
+            Input::AdapterOff { automatically } => {
+                self.cancel_timer(&mut effects, Effect::ReleaseTimer);
+                effects.push(Effect::CancelFlagTimers);
+                if self.keep_on_for && !self.airplane_mode {
+                    info!("Powering the adapter back on; it is kept on for now.");
+                    effects.push(Effect::PowerOn);
+                } else if automatically && let Some(after) = self.wake_after {
+                    self.wake_timer = true;
+                    effects.push(Effect::StartWakeTimer(after));
+                }
+                self.keep_on = false;
+                self.state = BluetoothServiceState::Off;
+            }
+            Input::DevicesChanged { connected, device } => {
+                debug!("Connected devices count: {}", connected);
+
+                if connected > 0 {
+                    self.cancel_timer(&mut effects, Effect::CancelTimerOnConnect { device });
+                    self.keep_on = false;
+                    self.state = BluetoothServiceState::Running;
+                } else {
+                    // Announce the countdown only when the last device just disconnected
+                    let disconnected = self.state == BluetoothServiceState::Running;
+                    let context = disconnected.then_some(TimeoutContext::Disconnect);
+                    if !self.timer
+                        && !self.kept_on()
+                        && let Some(timeout) = self.timeout(context)
+                    {
+                        debug!(
+                            "No connected devices and no active timer. Starting timeout timer..."
+                        );
+                        self.start_timer(
+                            &mut effects,
+                            timeout,
+                            disconnected && self.announce,
+                            device,
+                        );
+                    }
+                    self.state = BluetoothServiceState::Idle;
+                }
+            }
+            Input::Command(command) => self.on_command(&mut effects, command),
+            Input::SettingsChanged { timeouts, announce } => {
+                self.timeouts = timeouts;
+                self.announce = announce;
+
+                // Restart the countdown of an idle adapter with the new timeout
+                if self.state == BluetoothServiceState::Idle && !self.kept_on() {
+                    self.cancel_timer(&mut effects, Effect::CancelTimer);
+                    if let Some(timeout) = self.timeout(None) {
+                        self.start_timer(&mut effects, timeout, false, None);
+                    }
+                }
+            }
+            Input::KeepOnExpired => self.end_keep_on(&mut effects),
+            Input::BlackoutChanged(blackout) => {
+                self.blackout = blackout;
+                if !blackout {
+                    info!("The blackout period ended.");
+                } else if self.state != BluetoothServiceState::Off {
+                    info!("The blackout period started; turning the adapter off.");
+                    self.power_off_for_blackout(&mut effects);
+                }
+            }
+            Input::AdapterRemoved => {
+                warn!("The adapter disappeared; waiting for it to return.");
+                self.cancel_timer(&mut effects, Effect::CancelTimer);
+                effects.push(Effect::CancelFlagTimers);
+                self.keep_on = false;
+                self.state = BluetoothServiceState::Detached;
+            }
+            Input::AdapterAdded { powered, connected } => {
+                info!("The adapter returned; picking it up again.");
+                self.state = BluetoothServiceState::Off;
+                if powered {
+                    return self.step(Input::AdapterOn { connected });
+                }
+                if self.keep_on_for && !self.airplane_mode {
+                    info!("Powering the adapter back on; it is kept on for now.");
+                    effects.push(Effect::PowerOn);
+                }
+            }
+            Input::WakeTimerExpired => {
+                self.wake_timer = false;
+                if self.state != BluetoothServiceState::Off {
+                    debug!(
+                        "Wake timer expired in state {:?}; nothing to do.",
+                        self.state
+                    );
+                } else if self.blackout || self.airplane_mode {
+                    info!("Not powering the adapter back on; it is kept off for now.");
+                } else {
+                    info!("Powering the adapter back on after the timeout turned it off.");
+                    effects.push(Effect::PowerOn);
+                }
+            }
+            Input::AirplaneModeChanged(airplane_mode) => {
+                self.airplane_mode = airplane_mode;
+                if airplane_mode {
+                    info!("Airplane mode is on; pausing until it is turned off.");
+                    if self.timer {
+                        effects.push(Effect::PauseTimer);
+                    }
+                } else {
+                    info!("Airplane mode is off; resuming.");
+                    if self.timer {
+                        effects.push(Effect::ResumeTimer);
+                    }
+                    if self.keep_on_for && self.state == BluetoothServiceState::Off {
+                        info!("Powering the adapter back on; it is kept on for now.");
+                        effects.push(Effect::PowerOn);
+                    }
+                }
+            }
+        }
+
+        effects
+    }
+
+    /// Handles a `command`.
+    ///
+    /// Commands only affect an idle adapter; they are ignored while devices are connected or the
+    /// adapter is off. Keeping the adapter on for a while applies in every state; meanwhile, only
+    /// turning it off on request is not ignored, which also ends the window.
+    fn on_command(&mut self, effects: &mut Vec<Effect>, command: BluetoothServiceCommand) {
+        if let BluetoothServiceCommand::KeepOnFor(duration) = command {
+            self.keep_on_for(effects, duration);
+            return;
+        }
+
+        if self.state != BluetoothServiceState::Idle {
+            if command == BluetoothServiceCommand::ScreenLocked {
+                debug!("Screen locked in state {:?}; nothing to do.", self.state);
+            } else {
+                warn!(
+                    "Ignoring command {:?} in state {:?}; it only applies to an idle adapter.",
+                    command, self.state
+                );
+            }
+            return;
+        }
+
+        if self.keep_on_for && command != BluetoothServiceCommand::TurnOffNow {
+            info!(
+                "Ignoring command {:?}; the adapter is kept on for now.",
+                command
+            );
+            return;
+        }
+
+        match command {
+            BluetoothServiceCommand::KeepOn => {
+                self.cancel_timer(effects, Effect::CancelTimer);
+                self.keep_on = true;
+                info!("Keeping adapter on until the next state change.");
+            }
+            BluetoothServiceCommand::Snooze(duration) => {
+                self.keep_on = false;
+                if self.timer {
+                    effects.push(Effect::SnoozeTimer(duration));
+                } else {
+                    info!(
+                        "No active timeout to snooze: adapter will turn off in {}.",
+                        humantime::format_duration(duration)
+                    );
+                    self.start_timer(effects, duration, false, None);
+                }
+            }
+            BluetoothServiceCommand::TurnOffNow => {
+                if self.keep_on_for {
+                    self.keep_on_for = false;
+                    effects.push(Effect::CancelKeepOn);
+                }
+                self.cancel_timer(effects, Effect::CancelTimer);
+                effects.push(Effect::PowerOff);
+                info!("Turning off adapter on request.");
+            }
+            BluetoothServiceCommand::ScreenLocked => match self.lock.action {
+                LockAction::Ignore => {}
+                LockAction::Immediate => {
+                    self.cancel_timer(effects, Effect::CancelTimer);
+                    effects.push(Effect::PowerOff);
+                    info!("Turning off adapter because the screen was locked.");
+                }
+                LockAction::Accelerate => {
+                    self.keep_on = false;
+                    if self.timer {
+                        effects.push(Effect::AccelerateTimer(self.lock.timeout));
+                    } else {
+                        self.start_timer(effects, self.lock.timeout, false, None);
+                    }
+                }
+            },
+            // Turned into `Input::SettingsChanged` by the service, which looks up the new settings
+            BluetoothServiceCommand::SettingsChanged => {}
+            // Handled above, as it applies in every state
+            BluetoothServiceCommand::KeepOnFor(_) => {}
+        }
+    }
+
+    /// Keeps the adapter on for `duration`, powering it on if it is off, or ends an active window
+    /// if `duration` is zero.
+    fn keep_on_for(&mut self, effects: &mut Vec<Effect>, duration: Duration) {
+        if self.blackout && !duration.is_zero() {
+            warn!("Ignoring request to keep the adapter on during the blackout period.");
+            return;
+        }
+        if duration.is_zero() {
+            if self.keep_on_for {
+                effects.push(Effect::CancelKeepOn);
+                self.end_keep_on(effects);
+            }
+            return;
+        }
+
+        self.cancel_timer(effects, Effect::CancelTimer);
+        self.keep_on_for = true;
+        effects.push(Effect::StartKeepOn(duration));
+        if self.state == BluetoothServiceState::Off && !self.airplane_mode {
+            effects.push(Effect::PowerOn);
+        }
+        info!(
+            "Keeping adapter on for {}.",
+            humantime::format_duration(duration)
+        );
+    }
+
+    /// Ends the window the adapter is kept on for, starting the countdown of an idle adapter.
+    fn end_keep_on(&mut self, effects: &mut Vec<Effect>) {
+        self.keep_on_for = false;
+        info!("No longer keeping the adapter on.");
+
+        if self.state == BluetoothServiceState::Idle
+            && !self.timer
+            && !self.keep_on
+            && let Some(timeout) = self.timeout(None)
+        {
+            self.start_timer(effects, timeout, false, None);
+        }
+    }
+
+    /// Turns off the adapter because of a blackout period, ending any request to keep it on.
+    fn power_off_for_blackout(&mut self, effects: &mut Vec<Effect>) {
+        self.cancel_timer(effects, Effect::CancelTimer);
+        self.cancel_wake_timer(effects);
+        if self.keep_on_for {
+            self.keep_on_for = false;
+            effects.push(Effect::CancelKeepOn);
+        }
+        self.keep_on = false;
+        effects.extend([Effect::PowerOff, Effect::NotifyBlackout]);
+    }
+
+    /// Returns `true` if the user asked to keep the adapter on, so no countdown is started.
+    fn kept_on(&self) -> bool {
+        self.keep_on || self.keep_on_for
+    }
+
+    /// Returns the timeout in `context` (the default one if `None`), or `None` if an idle adapter
+    /// is never turned off.
+    fn timeout(&self, context: Option<TimeoutContext>) -> Option<Duration> {
+        let timeouts = self.timeouts.as_ref()?;
+        Some(context.map_or(timeouts.default, |context| timeouts.after(context)))
+    }
+
+    /// Requests a timer expiring after `timeout`.
+    fn start_timer(
+        &mut self,
+        effects: &mut Vec<Effect>,
+        timeout: Duration,
+        announce: bool,
+        device: Option<String>,
+    ) {
+        self.timer = true;
+        effects.push(Effect::StartTimer {
+            timeout,
+            announce,
+            device,
+        });
+    }
+
+    /// Requests the wake timer (if running) to be stopped.
+    fn cancel_wake_timer(&mut self, effects: &mut Vec<Effect>) {
+        if self.wake_timer {
+            self.wake_timer = false;
+            effects.push(Effect::CancelWakeTimer);
+        }
+    }
+
+    /// Requests the running timer (if any) to be aborted with `cancel`.
+    fn cancel_timer(&mut self, effects: &mut Vec<Effect>, cancel: Effect) {
+        if self.timer {
+            self.timer = false;
+            effects.push(cancel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Returns a state machine in `state`.
+    fn fsm(state: BluetoothServiceState) -> Fsm {
+        Conf::for_tests();
+        Fsm::new(state, Some(TIMEOUT.into()))
+    }
+
+    fn start_timer(timeout: Duration) -> Effect {
+        Effect::StartTimer {
+            timeout,
+            announce: false,
+            device: None,
+        }
+    }
+
+    fn devices_changed(connected: usize) -> Input {
+        Input::DevicesChanged {
+            connected,
+            device: Some("headset".to_string()),
+        }
+    }
+
+    #[test]
+    fn idle_start_arms_the_timer() {
+        let mut fsm = fsm(BluetoothServiceState::Idle);
+
+        assert_eq!(fsm.step(Input::Started), vec![start_timer(TIMEOUT)]);
+        assert!(fsm.timer());
+    }
+
+    #[test]
+    fn running_and_off_starts_do_nothing() {
+        for state in [BluetoothServiceState::Running, BluetoothServiceState::Off] {
+            let mut fsm = fsm(state);
+
+            assert_eq!(fsm.step(Input::Started), vec![]);
+            assert!(!fsm.timer());
+        }
+    }
+
+    #[test]
+    fn adapter_on_without_devices_auto_connects_and_arms_the_timer() {
+        let mut fsm = fsm(BluetoothServiceState::Off);
+
+        assert_eq!(
+            fsm.step(Input::AdapterOn { connected: 0 }),
+            vec![Effect::AutoConnect, start_timer(TIMEOUT)]
+        );
+        assert_eq!(fsm.state(), BluetoothServiceState::Idle);
+    }
+
+    #[test]
+    fn adapter_on_with_devices_runs_without_timer() {
+        let mut fsm = fsm(BluetoothServiceState::Off);
+
+        assert_eq!(
+            fsm.step(Input::AdapterOn { connected: 1 }),
+            vec![Effect::AutoConnect]
+        );
+        assert_eq!(fsm.state(), BluetoothServiceState::Running);
+        assert!(!fsm.timer());
+    }
+
+    #[test]
+    fn adapter_off_releases_the_timer() {
+        let mut fsm = fsm(BluetoothServiceState::Idle);
+        fsm.step(Input::Started);
+
+        assert_eq!(
+            fsm.step(Input::AdapterOff {
+                automatically: false
+            }),
+            vec![Effect::ReleaseTimer, Effect::CancelFlagTimers]
+        );
+        assert_eq!(fsm.state(), BluetoothServiceState::Off);
+        assert!(!fsm.timer());
+    }
+
+    #[test]
+    fn connecting_cancels_the_timer_naming_the_device() {
+        let mut fsm = fsm(BluetoothServiceState::Idle);
+        fsm.step(Input::Started);
+
+        assert_eq!(
+            fsm.step(devices_changed(1)),
+            vec![Effect::CancelTimerOnConnect {
+                device: Some("headset".to_string())
+            }]
+        );
+        assert_eq!(fsm.state(), BluetoothServiceState::Running);
+    }
+
+    #[test]
+    fn last_disconnect_arms_the_timer_once() {
+        let mut fsm = fsm(BluetoothServiceState::Running);
+
+        assert_eq!(
+            fsm.step(devices_changed(0)),
+            vec![Effect::StartTimer {
+                timeout: TIMEOUT,
+                announce: false,
+                device: Some("headset".to_string()),
+            }]
+        );
+        assert_eq!(fsm.state(), BluetoothServiceState::Idle);
+        assert_eq!(fsm.step(devices_changed(0)), vec![]);
+    }
+
+    #[test]
+    fn finished_timer_is_rearmed() {
+        let mut fsm = fsm(BluetoothServiceState::Idle);
+        fsm.step(Input::Started);
+        fsm.timer_finished();
+
+        assert_eq!(fsm.step(devices_changed(1)), vec![]);
+        assert_eq!(
+            fsm.step(Input::DevicesChanged {
+                connected: 0,
+                device: None
+            }),
+            vec![start_timer(TIMEOUT)]
+        );
+    }
+
+    #[test]
+    fn keep_on_holds_until_the_next_state_change() {
+        let mut fsm = fsm(BluetoothServiceState::Idle);
+        fsm.step(Input::Started);
+
+        assert_eq!(
+            fsm.step(Input::Command(BluetoothServiceCommand::KeepOn)),
+            vec![Effect::CancelTimer]
+        );
+        assert_eq!(fsm.step(Input::AdapterOn { connected: 0 }), vec![]);
+
+        fsm.step(devices_changed(1));
+        assert_eq!(
+            fsm.step(Input::DevicesChanged {
+                connected: 0,
+                device: None
+            }),
+            vec![start_timer(TIMEOUT)]
+        );
+    }
+
+    #[test]
+    fn snooze_postpones_a_running_timer_or_starts_one() {
+        let snooze = Duration::from_secs(900);
+        let mut fsm = fsm(BluetoothServiceState::Idle);
+
+        assert_eq!(
+            fsm.step(Input::Command(BluetoothServiceCommand::Snooze(snooze))),
+            vec![start_timer(snooze)]
+        );
+        assert_eq!(
+            fsm.step(Input::Command(BluetoothServiceCommand::Snooze(snooze))),
+            vec![Effect::SnoozeTimer(snooze)]
+        );
+    }
+
+    #[test]
+    fn turn_off_now_cancels_the_timer_and_powers_off() {
+        let mut fsm = fsm(BluetoothServiceState::Idle);
+        fsm.step(Input::Started);
+
+        assert_eq!(
+            fsm.step(Input::Command(BluetoothServiceCommand::TurnOffNow)),
+            vec![Effect::CancelTimer, Effect::PowerOff]
+        );
+    }
+
+    #[test]
+    fn commands_are_ignored_unless_idle() {
+        for state in [BluetoothServiceState::Running, BluetoothServiceState::Off] {
+            let mut fsm = fsm(state);
+
+            for command in [
+                BluetoothServiceCommand::KeepOn,
+                BluetoothServiceCommand::Snooze(TIMEOUT),
+                BluetoothServiceCommand::TurnOffNow,
+                BluetoothServiceCommand::ScreenLocked,
+            ] {
+                assert_eq!(fsm.step(Input::Command(command)), vec![]);
+            }
+            assert_eq!(fsm.state(), state);
+        }
+    }
+
+    #[test]
+    fn timeout_depends_on_what_made_the_adapter_idle() {
+        Conf::for_tests();
+        let timeouts = TimeoutConf {
+            default: TIMEOUT,
+            after_boot: Some(15 * TIMEOUT),
+            after_disconnect: Some(3 * TIMEOUT),
+            after_manual_on: None,
+        };
+
+        let mut fsm = Fsm::new(BluetoothServiceState::Idle, Some(timeouts.clone()));
+        assert_eq!(fsm.step(Input::Started), vec![start_timer(15 * TIMEOUT)]);
+
+        let mut fsm = Fsm::new(BluetoothServiceState::Off, Some(timeouts.clone()));
+        assert_eq!(
+            fsm.step(Input::AdapterOn { connected: 0 }),
+            vec![Effect::AutoConnect, start_timer(TIMEOUT)]
+        );
+
+        let mut fsm = Fsm::new(BluetoothServiceState::Running, Some(timeouts));
+        assert_eq!(
+            fsm.step(Input::DevicesChanged {
+                connected: 0,
+                device: None,
+            }),
+            vec![start_timer(3 * TIMEOUT)]
+        );
+    }
+
+    #[test]
+    fn no_timer_is_started_without_timeouts() {
+        Conf::for_tests();
+        let mut fsm = Fsm::new(BluetoothServiceState::Running, None);
+
+        assert_eq!(fsm.step(devices_changed(0)), vec![]);
+        assert_eq!(fsm.step(Input::AdapterOn { connected: 0 }), vec![]);
+        assert!(!fsm.timer());
+    }
+
+    #[test]
+    fn settings_change_restarts_the_countdown() {
+        let mut fsm = fsm(BluetoothServiceState::Idle);
+        fsm.step(Input::Started);
+
+        assert_eq!(
+            fsm.step(Input::SettingsChanged {
+                timeouts: Some((2 * TIMEOUT).into()),
+                announce: false,
+            }),
+            vec![Effect::CancelTimer, start_timer(2 * TIMEOUT)]
+        );
+        assert_eq!(
+            fsm.step(Input::SettingsChanged {
+                timeouts: None,
+                announce: false,
+            }),
+            vec![Effect::CancelTimer]
+        );
+        assert!(!fsm.timer());
+    }
+
+    #[test]
+    fn keep_on_for_powers_on_and_survives_state_changes() {
+        let mut fsm = fsm(BluetoothServiceState::Off);
+        let hours = Duration::from_hours(3);
+
+        assert_eq!(
+            fsm.step(Input::Command(BluetoothServiceCommand::KeepOnFor(hours))),
+            vec![Effect::StartKeepOn(hours), Effect::PowerOn]
+        );
+        assert_eq!(
+            fsm.step(Input::AdapterOn { connected: 0 }),
+            vec![Effect::AutoConnect]
+        );
+        fsm.step(devices_changed(1));
+        assert_eq!(fsm.step(devices_changed(0)), vec![]);
+        assert_eq!(
+            fsm.step(Input::Command(BluetoothServiceCommand::Snooze(TIMEOUT))),
+            vec![]
+        );
+        assert_eq!(
+            fsm.step(Input::AdapterOff {
+                automatically: false
+            }),
+            vec![Effect::CancelFlagTimers, Effect::PowerOn]
+        );
+    }
+
+    #[test]
+    fn expired_keep_on_starts_the_countdown() {
+        let mut fsm = fsm(BluetoothServiceState::Idle);
+        fsm.step(Input::Started);
+        fsm.step(Input::Command(BluetoothServiceCommand::KeepOnFor(TIMEOUT)));
+        assert!(!fsm.timer());
+
+        assert_eq!(fsm.step(Input::KeepOnExpired), vec![start_timer(TIMEOUT)]);
+        assert_eq!(
+            fsm.step(Input::Command(BluetoothServiceCommand::KeepOnFor(
+                Duration::ZERO
+            ))),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn detached_adapter_is_picked_up_again_when_it_returns() {
+        let mut fsm = fsm(BluetoothServiceState::Idle);
+        fsm.step(Input::Started);
+
+        assert_eq!(
+            fsm.step(Input::AdapterRemoved),
+            vec![Effect::CancelTimer, Effect::CancelFlagTimers]
+        );
+        assert_eq!(fsm.state(), BluetoothServiceState::Detached);
+        assert_eq!(fsm.step(devices_changed(0)), vec![]);
+        assert_eq!(
+            fsm.step(Input::AdapterOff {
+                automatically: false
+            }),
+            vec![]
+        );
+        assert_eq!(fsm.state(), BluetoothServiceState::Detached);
+
+        assert_eq!(
+            fsm.step(Input::AdapterAdded {
+                powered: true,
+                connected: 0
+            }),
+            vec![Effect::AutoConnect, start_timer(TIMEOUT)]
+        );
+        assert_eq!(fsm.state(), BluetoothServiceState::Idle);
+    }
+
+    #[test]
+    fn blackout_turns_the_adapter_off_and_keeps_it_off() {
+        let mut fsm = fsm(BluetoothServiceState::Idle);
+        fsm.step(Input::Started);
+        fsm.step(Input::Command(BluetoothServiceCommand::KeepOnFor(TIMEOUT)));
+
+        assert_eq!(
+            fsm.step(Input::BlackoutChanged(true)),
+            vec![
+                Effect::CancelKeepOn,
+                Effect::PowerOff,
+                Effect::NotifyBlackout
+            ]
+        );
+        assert_eq!(
+            fsm.step(Input::AdapterOff {
+                automatically: false
+            }),
+            vec![Effect::CancelFlagTimers]
+        );
+        assert_eq!(
+            fsm.step(Input::Command(BluetoothServiceCommand::KeepOnFor(TIMEOUT))),
+            vec![]
+        );
+        assert_eq!(
+            fsm.step(Input::AdapterOn { connected: 0 }),
+            vec![Effect::PowerOff, Effect::NotifyBlackout]
+        );
+        fsm.step(Input::AdapterOff {
+            automatically: false,
+        });
+
+        assert_eq!(fsm.step(Input::BlackoutChanged(false)), vec![]);
+        assert_eq!(
+            fsm.step(Input::AdapterOn { connected: 0 }),
+            vec![Effect::AutoConnect, start_timer(TIMEOUT)]
+        );
+    }
+
+    #[test]
+    fn airplane_mode_pauses_the_timer_and_defers_powering_on() {
+        let mut fsm = fsm(BluetoothServiceState::Idle);
+        fsm.step(Input::Started);
+
+        assert_eq!(
+            fsm.step(Input::AirplaneModeChanged(true)),
+            vec![Effect::PauseTimer]
+        );
+        fsm.step(Input::AdapterOff {
+            automatically: false,
+        });
+        assert_eq!(
+            fsm.step(Input::Command(BluetoothServiceCommand::KeepOnFor(TIMEOUT))),
+            vec![Effect::StartKeepOn(TIMEOUT)]
+        );
+        assert_eq!(
+            fsm.step(Input::AirplaneModeChanged(false)),
+            vec![Effect::PowerOn]
+        );
+    }
+
+    #[test]
+    fn turn_off_now_ends_keep_on() {
+        let mut fsm = fsm(BluetoothServiceState::Idle);
+        fsm.step(Input::Command(BluetoothServiceCommand::KeepOnFor(TIMEOUT)));
+
+        assert_eq!(
+            fsm.step(Input::Command(BluetoothServiceCommand::TurnOffNow)),
+            vec![Effect::CancelKeepOn, Effect::PowerOff]
+        );
+        assert_eq!(
+            fsm.step(Input::AdapterOff {
+                automatically: false
+            }),
+            vec![Effect::CancelFlagTimers]
+        );
+    }
+
+    #[test]
+    fn timed_out_adapter_is_woken_unless_powered_on_meanwhile() {
+        let wake = Duration::from_secs(8 * 3600);
+        let mut fsm = fsm(BluetoothServiceState::Idle);
+        fsm.wake_after = Some(wake);
+        fsm.step(Input::Started);
+
+        assert_eq!(
+            fsm.step(Input::AdapterOff {
+                automatically: true
+            }),
+            vec![
+                Effect::ReleaseTimer,
+                Effect::CancelFlagTimers,
+                Effect::StartWakeTimer(wake)
+            ]
+        );
+        assert_eq!(fsm.step(Input::WakeTimerExpired), vec![Effect::PowerOn]);
+
+        fsm.step(Input::AdapterOn { connected: 0 });
+        fsm.step(Input::AdapterOff {
+            automatically: true,
+        });
+        assert_eq!(
+            fsm.step(Input::AdapterOn { connected: 1 }),
+            vec![Effect::CancelWakeTimer, Effect::AutoConnect]
+        );
+        fsm.step(devices_changed(0));
+        assert_eq!(
+            fsm.step(Input::AdapterOff {
+                automatically: false
+            }),
+            vec![Effect::ReleaseTimer, Effect::CancelFlagTimers]
+        );
+    }
+}
@@ -0,0 +1,120 @@
+// -- std imports
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, TryLockError},
+    time::SystemTime,
+};
+
+/// A single entry of the [`History`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// When the entry was recorded.
+    pub time: SystemTime,
+    /// What happened (e.g., "State: Idle -> Running").
+    pub message: String,
+}
+
+/// Recent state transitions, events and commands of the Bluetooth service, kept in memory.
+///
+/// The history is a ring buffer: once `capacity` entries are recorded, each new entry replaces
+/// the oldest one. Clones share the same buffer, so the service can record entries while the
+/// control interface reads them.
+#[derive(Debug, Clone)]
+pub struct History {
+    /// The recorded entries, oldest first.
+    entries: Arc<Mutex<VecDeque<HistoryEntry>>>,
+    /// Maximum number of entries kept.
+    capacity: usize,
+}
+
+impl History {
+    /// Creates an empty history keeping at most `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// Records `message` with the current time, dropping the oldest entry if the history is full.
+    pub fn record(&self, message: impl Into<String>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(HistoryEntry {
+            time: SystemTime::now(),
+            message: message.into(),
+        });
+    }
+
+    /// Returns the recorded entries, oldest first.
+    #[cfg_attr(not(feature = "cli-control"), allow(dead_code))]
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().cloned().collect()
+    }
+
+    /// Returns the recorded entries like [`History::entries`], or `None` if they are being
+    /// recorded right now, instead of waiting for them (e.g., in a panic hook).
+    pub fn try_entries(&self) -> Option<Vec<HistoryEntry>> {
+        let entries = match self.entries.try_lock() {
+            Ok(entries) => entries,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return None,
+        };
+        Some(entries.iter().cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    /// Returns the messages recorded in `history`, oldest first.
+    fn messages(history: &History) -> Vec<String> {
+        history.entries().into_iter().map(|e| e.message).collect()
+    }
+
+    #[test]
+    fn keeps_the_newest_entries() {
+        let history = History::new(2);
+        history.record("first");
+        history.record("second");
+        history.record("third");
+
+        assert_eq!(messages(&history), ["second", "third"]);
+    }
+
+    #[test]
+    fn try_entries_does_not_wait_for_a_recording() {
+        let history = History::new(2);
+        history.record("first");
+        let recording = history.entries.lock().unwrap();
+
+        assert!(history.try_entries().is_none());
+        drop(recording);
+        assert_eq!(history.try_entries().map(|entries| entries.len()), Some(1));
+    }
+
+    #[test]
+    fn clones_share_the_buffer() {
+        let history = History::new(2);
+        history.clone().record("first");
+
+        assert_eq!(messages(&history), ["first"]);
+    }
+
+    #[test]
+    fn zero_capacity_records_nothing() {
+        let history = History::new(0);
+        history.record("first");
+
+        assert!(history.entries().is_empty());
+    }
+}
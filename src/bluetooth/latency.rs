@@ -0,0 +1,218 @@
+// -- std imports (conditional)
+#[cfg(all(feature = "otlp", feature = "metrics"))]
+use std::sync::LazyLock;
+#[cfg(feature = "metrics")]
+use std::{collections::BTreeMap, time::Duration};
+
+// -- std imports
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+// -- crate imports (conditional)
+#[cfg(all(feature = "otlp", feature = "metrics"))]
+use opentelemetry::{KeyValue, global, metrics};
+
+// -- crate imports
+use tokio::time::Instant;
+use tracing::{Span, debug, info_span};
+
+/// Upper bounds of the histogram buckets in milliseconds; slower events fall into a last bucket.
+#[cfg_attr(
+    not(any(feature = "metrics", feature = "cli-control")),
+    allow(dead_code)
+)]
+pub const BUCKETS_MS: [u64; 8] = [1, 2, 5, 10, 50, 100, 500, 1000];
+
+/// Histogram the latencies are exported to via OTLP, in milliseconds by event kind (see
+/// `log.otlp_metrics_endpoint`), with the buckets of [`BUCKETS_MS`].
+#[cfg(all(feature = "otlp", feature = "metrics"))]
+static EXPORTED: LazyLock<metrics::Histogram<f64>> = LazyLock::new(|| {
+    global::meter(env!("CARGO_PKG_NAME"))
+        .f64_histogram("bluetooth_timeout.event.latency")
+        .with_unit("ms")
+        .with_description("Time from a D-Bus signal until its event was handled")
+        .with_boundaries(BUCKETS_MS.iter().map(|ms| *ms as f64).collect())
+        .build()
+});
+
+/// Number of events that may await handling; older stamps are dropped, e.g. if no service
+/// consumes the events.
+const MAX_PENDING: usize = 32;
+
+/// Latencies of the events of one kind.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram {
+    /// Number of events per bucket of [`BUCKETS_MS`], plus one for slower events.
+    pub buckets: [u64; BUCKETS_MS.len() + 1],
+    /// Number of events.
+    pub count: u64,
+    /// Sum of the latencies.
+    pub sum: Duration,
+    /// Highest latency.
+    pub max: Duration,
+}
+
+#[cfg(feature = "metrics")]
+impl Histogram {
+    /// Adds an event that took `latency`.
+    fn record(&mut self, latency: Duration) {
+        let bucket = BUCKETS_MS
+            .iter()
+            .position(|ms| latency <= Duration::from_millis(*ms))
+            .unwrap_or(BUCKETS_MS.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum += latency;
+        self.max = self.max.max(latency);
+    }
+}
+
+/// Reception of a D-Bus signal.
+#[derive(Debug, Clone)]
+pub struct Stamp {
+    /// When the signal was received.
+    pub at: Instant,
+    /// Span of the signal, the root of the spans of handling its events (e.g., the state
+    /// transition and the D-Bus calls of the resulting actions).
+    pub span: Span,
+}
+
+/// State of the measurement, shared by the clones of [`EventLatency`].
+#[derive(Debug, Default)]
+struct Measurement {
+    /// Reception of the signal that is being processed.
+    received: Option<Stamp>,
+    /// Kinds and stamps of the events broadcast but not handled yet, oldest first.
+    pending: VecDeque<(&'static str, Stamp)>,
+    /// Latencies by event kind.
+    #[cfg(feature = "metrics")]
+    histograms: BTreeMap<&'static str, Histogram>,
+}
+
+/// End-to-end latencies of the events of an adapter, from the reception of the D-Bus signal that
+/// caused an event to the service having handled it (e.g., started or cancelled the timeout).
+///
+/// The observer calls [`EventLatency::received`] for every signal and [`EventLatency::sent`] for
+/// every event it broadcasts; the service [takes](EventLatency::take) the stamp of an event before
+/// handling it and calls [`EventLatency::handled`] once it is done. As events are received in the
+/// order they were broadcast, an event belongs to the oldest pending stamp of its kind. Events
+/// that do not stem from a signal (e.g., ticks) have no stamp and are not measured. Clones share
+/// the same measurement.
+#[derive(Debug, Clone, Default)]
+pub struct EventLatency {
+    /// The shared measurement.
+    inner: Arc<Mutex<Measurement>>,
+}
+
+impl EventLatency {
+    /// Creates an empty measurement.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Notes that the `signal` (e.g., "PropertiesChanged") was received now, returning its stamp.
+    pub fn received(&self, signal: &'static str) -> Stamp {
+        let stamp = Stamp {
+            at: Instant::now(),
+            span: info_span!(parent: None, "dbus_signal", signal),
+        };
+        self.resume(stamp.clone());
+        stamp
+    }
+
+    /// Continues with the signal of `stamp` (e.g., after a debounce).
+    pub fn resume(&self, stamp: Stamp) {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .received = Some(stamp);
+    }
+
+    /// Stamps an event of `kind` that is broadcast for the last received signal.
+    pub fn sent(&self, kind: &'static str) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(stamp) = inner.received.clone() else {
+            return;
+        };
+        if inner.pending.len() == MAX_PENDING {
+            inner.pending.pop_front();
+        }
+        inner.pending.push_back((kind, stamp));
+    }
+
+    /// Returns the stamp of the event of `kind` that is about to be handled, if it was stamped.
+    pub fn take(&self, kind: &'static str) -> Option<Stamp> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let index = inner
+            .pending
+            .iter()
+            .position(|(pending, _)| *pending == kind)?;
+
+        // Stamps before it belong to events the service missed
+        inner
+            .pending
+            .drain(..=index)
+            .next_back()
+            .map(|(_, stamp)| stamp)
+    }
+
+    /// Records the latency of the event of `kind` with `stamp` that was just handled, and exports
+    /// it via OTLP with the `otlp` feature. Without the `metrics` feature, it is only logged.
+    pub fn handled(&self, kind: &'static str, stamp: &Stamp) {
+        let latency = stamp.at.elapsed();
+        #[cfg(feature = "metrics")]
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .histograms
+            .entry(kind)
+            .or_default()
+            .record(latency);
+        #[cfg(all(feature = "otlp", feature = "metrics"))]
+        EXPORTED.record(
+            latency.as_secs_f64() * 1000.0,
+            &[KeyValue::new("kind", kind)],
+        );
+        debug!("Handled {} {:?} after its signal.", kind, latency);
+    }
+
+    /// Returns the latencies recorded so far by event kind.
+    #[cfg(feature = "metrics")]
+    #[cfg_attr(not(feature = "cli-control"), allow(dead_code))]
+    pub fn histograms(&self) -> BTreeMap<&'static str, Histogram> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .histograms
+            .clone()
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn measures_from_the_signal_until_the_event_is_handled() {
+        let latency = EventLatency::new();
+
+        latency.received("PropertiesChanged");
+        latency.sent("DeviceConnected");
+        tokio::time::advance(Duration::from_millis(3)).await;
+        assert!(latency.take("TimerTick").is_none());
+        let stamp = latency.take("DeviceConnected").unwrap();
+        latency.handled("DeviceConnected", &stamp);
+        assert!(latency.take("DeviceConnected").is_none());
+
+        let histograms = latency.histograms();
+        let connected = &histograms["DeviceConnected"];
+        assert_eq!(histograms.len(), 1);
+        assert_eq!(connected.count, 1);
+        assert_eq!(connected.buckets[2], 1);
+        assert_eq!(connected.max, Duration::from_millis(3));
+    }
+}
@@ -0,0 +1,305 @@
+// -- std imports (conditional)
+#[cfg(feature = "mgmt-fallback")]
+use std::fs;
+
+// -- std imports
+use std::{
+    fs::File,
+    io::{Read, Write},
+    mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    time::Duration,
+};
+
+// -- crate imports
+use anyhow::{Context, Result};
+
+/// `BTPROTO_HCI` from `bluetooth/bluetooth.h`.
+const BTPROTO_HCI: libc::c_int = 1;
+
+/// `HCI_DEV_NONE` from `bluetooth/hci.h`, as the control channel is not bound to a controller.
+const HCI_DEV_NONE: u16 = 0xffff;
+
+/// `HCI_CHANNEL_CONTROL` from `bluetooth/hci.h`, the channel of the management API.
+const HCI_CHANNEL_CONTROL: u16 = 3;
+
+/// `MGMT_OP_READ_INFO` from `bluetooth/mgmt.h`, which needs no privileges.
+const MGMT_OP_READ_INFO: u16 = 0x0004;
+
+/// `MGMT_OP_SET_POWERED` from `bluetooth/mgmt.h`.
+#[cfg_attr(not(feature = "mgmt-fallback"), allow(dead_code))]
+const MGMT_OP_SET_POWERED: u16 = 0x0005;
+
+/// `MGMT_EV_CMD_COMPLETE` from `bluetooth/mgmt.h`.
+const MGMT_EV_CMD_COMPLETE: u16 = 0x0001;
+
+/// `MGMT_EV_CMD_STATUS` from `bluetooth/mgmt.h`.
+const MGMT_EV_CMD_STATUS: u16 = 0x0002;
+
+/// `MGMT_SETTING_BREDR` from `bluetooth/mgmt.h`.
+const MGMT_SETTING_BREDR: u32 = 1 << 7;
+
+/// Offset of the supported settings in the reply to [`MGMT_OP_READ_INFO`], after the address
+/// (6 bytes), the Bluetooth version (1) and the manufacturer (2).
+const SUPPORTED_SETTINGS_OFFSET: usize = 9;
+
+/// `CAP_NET_ADMIN` from `linux/capability.h`, which the control channel requires.
+#[cfg(feature = "mgmt-fallback")]
+const CAP_NET_ADMIN: u32 = 12;
+
+/// How long to wait for the kernel to answer a command.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// `struct sockaddr_hci` from `bluetooth/hci.h`.
+#[repr(C)]
+struct SockaddrHci {
+    hci_family: libc::sa_family_t,
+    hci_dev: u16,
+    hci_channel: u16,
+}
+
+/// Powers the Bluetooth controller `hci` (e.g., "hci0") off through the kernel's Bluetooth
+/// management API, bypassing BlueZ and its authorization (e.g., polkit).
+///
+/// BlueZ notices the change and updates the adapter's `Powered` property as usual.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the process lacks `CAP_NET_ADMIN`, the management socket cannot be
+///   opened or the kernel rejects the command.
+#[cfg(feature = "mgmt-fallback")]
+pub async fn power_off(hci: &str) -> Result<()> {
+    let index = index(hci)?;
+    if !has_net_admin()? {
+        anyhow::bail!("The management API requires CAP_NET_ADMIN");
+    }
+
+    tokio::task::spawn_blocking(move || {
+        send(
+            index,
+            MGMT_OP_SET_POWERED,
+            &[0],
+            &mut open().context("Could not open the management socket")?,
+        )
+    })
+    .await??;
+    Ok(())
+}
+
+/// Checks if the Bluetooth controller `hci` (e.g., "hci0") supports BR/EDR ("classic"), from the
+/// settings the kernel reports it to support, whether they are enabled or not.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the management socket cannot be opened (e.g., without Bluetooth support
+///   in the kernel) or the kernel does not know the controller.
+pub async fn supports_bredr(hci: &str) -> Result<bool> {
+    let index = index(hci)?;
+    let info = tokio::task::spawn_blocking(move || {
+        send(
+            index,
+            MGMT_OP_READ_INFO,
+            &[],
+            &mut open().context("Could not open the management socket")?,
+        )
+    })
+    .await??;
+
+    bredr_supported(&info)
+}
+
+/// Checks if the controller information `info` (the reply to [`MGMT_OP_READ_INFO`]) lists BR/EDR
+/// among the supported settings.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if `info` is too short to contain the supported settings.
+fn bredr_supported(info: &[u8]) -> Result<bool> {
+    let settings = info
+        .get(SUPPORTED_SETTINGS_OFFSET..SUPPORTED_SETTINGS_OFFSET + 4)
+        .context("The controller information is too short")?;
+
+    Ok(u32::from_le_bytes(settings.try_into()?) & MGMT_SETTING_BREDR != 0)
+}
+
+/// Returns the index of the controller `hci` (e.g., 0 for "hci0").
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if `hci` is not the name of a controller.
+fn index(hci: &str) -> Result<u16> {
+    hci.strip_prefix("hci")
+        .and_then(|index| index.parse::<u16>().ok())
+        .with_context(|| format!("'{}' is not a controller name", hci))
+}
+
+/// Returns whether the process has `CAP_NET_ADMIN` in its effective capabilities.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if `/proc/self/status` cannot be read or parsed.
+#[cfg(feature = "mgmt-fallback")]
+fn has_net_admin() -> Result<bool> {
+    let status = fs::read_to_string("/proc/self/status")?;
+    let caps = status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .context("No effective capabilities in /proc/self/status")?;
+
+    Ok(u64::from_str_radix(caps.trim(), 16)? & (1 << CAP_NET_ADMIN) != 0)
+}
+
+/// Opens a socket on the management channel, with [`REPLY_TIMEOUT`] for reads.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the socket cannot be created or bound (e.g., without Bluetooth support in
+///   the kernel).
+fn open() -> Result<File> {
+    // SAFETY: `socket` has no preconditions; the descriptor is owned right after it is checked.
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_BLUETOOTH,
+            libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+            BTPROTO_HCI,
+        )
+    };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    // SAFETY: `fd` is a valid descriptor that nothing else owns.
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let addr = SockaddrHci {
+        hci_family: libc::AF_BLUETOOTH as libc::sa_family_t,
+        hci_dev: HCI_DEV_NONE,
+        hci_channel: HCI_CHANNEL_CONTROL,
+    };
+    let timeout = libc::timeval {
+        tv_sec: REPLY_TIMEOUT.as_secs() as libc::time_t,
+        tv_usec: 0,
+    };
+    // SAFETY: both pointers refer to live values of the given sizes.
+    let failed = unsafe {
+        libc::bind(
+            fd.as_raw_fd(),
+            (&raw const addr).cast(),
+            mem::size_of::<SockaddrHci>() as libc::socklen_t,
+        ) < 0
+            || libc::setsockopt(
+                fd.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                (&raw const timeout).cast(),
+                mem::size_of::<libc::timeval>() as libc::socklen_t,
+            ) < 0
+    };
+    if failed {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    Ok(File::from(fd))
+}
+
+/// Sends the command `opcode` with `params` for the controller `index` over `socket` and waits
+/// for its reply, skipping unrelated events. Returns the parameters of the reply.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the socket fails, no reply arrives within [`REPLY_TIMEOUT`] or the
+///   kernel answers with a non-zero status.
+fn send(
+    index: u16,
+    opcode: u16,
+    params: &[u8],
+    socket: &mut (impl Read + Write),
+) -> Result<Vec<u8>> {
+    socket.write_all(&command(index, opcode, params))?;
+
+    let mut buf = [0u8; 512];
+    loop {
+        let len = socket
+            .read(&mut buf)
+            .context("No reply from the management API")?;
+        if len == 0 {
+            anyhow::bail!("The management socket was closed");
+        }
+        match reply(&buf[..len], index, opcode) {
+            Some((0, params)) => return Ok(params.to_vec()),
+            Some((status, _)) => {
+                anyhow::bail!("The management API failed with status {:#04x}", status)
+            }
+            None => continue,
+        }
+    }
+}
+
+/// Returns the packet of the command `opcode` with `params` for the controller `index`.
+fn command(index: u16, opcode: u16, params: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(6 + params.len());
+    packet.extend_from_slice(&opcode.to_le_bytes());
+    packet.extend_from_slice(&index.to_le_bytes());
+    packet.extend_from_slice(&(params.len() as u16).to_le_bytes());
+    packet.extend_from_slice(params);
+    packet
+}
+
+/// Returns the status and the parameters of `event` if it answers the command `opcode` for the
+/// controller `index`.
+fn reply(event: &[u8], index: u16, opcode: u16) -> Option<(u8, &[u8])> {
+    let word = |at: usize| Some(u16::from_le_bytes([*event.get(at)?, *event.get(at + 1)?]));
+
+    let code = word(0)?;
+    if (code != MGMT_EV_CMD_COMPLETE && code != MGMT_EV_CMD_STATUS)
+        || word(2)? != index
+        || word(6)? != opcode
+    {
+        return None;
+    }
+
+    Some((*event.get(8)?, event.get(9..).unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    #[test]
+    fn waits_for_the_reply_to_its_command() {
+        let new_settings = [0x06, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let complete = [
+            0x01, 0x00, 0x00, 0x00, 0x07, 0x00, 0x05, 0x00, 0x00, 0, 0, 0, 0,
+        ];
+        let rejected = [0x02, 0x00, 0x00, 0x00, 0x03, 0x00, 0x05, 0x00, 0x14];
+
+        assert_eq!(
+            command(0, MGMT_OP_SET_POWERED, &[0]),
+            [0x05, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00]
+        );
+        assert_eq!(reply(&new_settings, 0, MGMT_OP_SET_POWERED), None);
+        assert_eq!(
+            reply(&complete, 0, MGMT_OP_SET_POWERED),
+            Some((0, &[0, 0, 0, 0][..]))
+        );
+        assert_eq!(reply(&complete, 1, MGMT_OP_SET_POWERED), None);
+        assert_eq!(
+            reply(&rejected, 0, MGMT_OP_SET_POWERED),
+            Some((0x14, &[][..]))
+        );
+    }
+
+    #[test]
+    fn reads_bredr_support_from_the_supported_settings() {
+        // Address, version and manufacturer, then the supported and the current settings
+        let mut dual = vec![0; SUPPORTED_SETTINGS_OFFSET];
+        dual.extend_from_slice(&0x0000_02ffu32.to_le_bytes());
+        dual.extend_from_slice(&0u32.to_le_bytes());
+        let mut le_only = vec![0; SUPPORTED_SETTINGS_OFFSET];
+        le_only.extend_from_slice(&0x0000_0201u32.to_le_bytes());
+
+        assert!(bredr_supported(&dual).unwrap());
+        assert!(!bredr_supported(&le_only).unwrap());
+        assert!(bredr_supported(&dual[..SUPPORTED_SETTINGS_OFFSET + 2]).is_err());
+    }
+}
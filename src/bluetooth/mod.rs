@@ -1,5 +1,23 @@
 // -- module definitions
+pub mod backend;
+pub mod battery;
+pub mod blackout;
+pub mod cache;
+pub mod capabilities;
 pub mod device;
+pub mod dormant;
+pub mod event_bus;
+pub mod flag;
+pub mod fsm;
+pub mod history;
+pub mod latency;
+pub mod mgmt;
+pub mod monitor;
 pub mod observer;
+pub mod proximity;
+pub mod retry;
+pub mod rfkill;
+pub mod saved_power;
 pub mod service;
 pub mod service_proxy;
+pub mod stats;
@@ -0,0 +1,181 @@
+// -- crate imports
+use anyhow::{Context, Result};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use zbus::{Connection, fdo::ObjectManager, interface, zvariant::OwnedObjectPath};
+
+// -- module imports
+use crate::{
+    bluetooth::backend::SharedBackend,
+    bus,
+    configuration::{AdPatternConf, Conf},
+    power_on,
+};
+
+/// Object path of the monitor application (the root of the registered monitors).
+const MONITOR_APP_PATH: &str = "/io/github/hendrikboeck/BluetoothTimeout/monitor";
+
+/// Object path of the single advertisement monitor below [`MONITOR_APP_PATH`].
+const MONITOR_PATH: &str = "/io/github/hendrikboeck/BluetoothTimeout/monitor/0";
+
+/// An advertisement monitor exported to BlueZ (`org.bluez.AdvertisementMonitor1`).
+///
+/// BlueZ passively scans for advertisements matching any of the patterns and reports matching
+/// devices via `DeviceFound`, which are forwarded to `found`.
+#[derive(Debug)]
+struct AdvertisementMonitor {
+    /// Patterns as `(start position, AD data type, content)`.
+    patterns: Vec<(u8, u8, Vec<u8>)>,
+    /// Sender for the object paths of found devices.
+    found: mpsc::UnboundedSender<OwnedObjectPath>,
+}
+
+#[interface(name = "org.bluez.AdvertisementMonitor1")]
+impl AdvertisementMonitor {
+    /// Called by BlueZ when the monitor is removed (e.g., the adapter went away).
+    fn release(&self) {
+        info!("Advertisement monitor released by BlueZ.");
+    }
+
+    /// Called by BlueZ once the monitor is active.
+    fn activate(&self) {
+        info!("Advertisement monitor activated.");
+    }
+
+    /// Called by BlueZ when a device matching the patterns is advertising.
+    fn device_found(&self, device: OwnedObjectPath) {
+        debug!("Advertisement monitor found {}.", device.as_str());
+        let _ = self.found.send(device);
+    }
+
+    /// Called by BlueZ when a previously found device stopped advertising.
+    fn device_lost(&self, device: OwnedObjectPath) {
+        debug!("Advertisement monitor lost {}.", device.as_str());
+    }
+
+    /// The monitor type; BlueZ only supports `or_patterns`.
+    #[zbus(property, name = "Type")]
+    fn kind(&self) -> &str {
+        "or_patterns"
+    }
+
+    /// The patterns advertisements are matched against.
+    #[zbus(property)]
+    fn patterns(&self) -> Vec<(u8, u8, Vec<u8>)> {
+        self.patterns.clone()
+    }
+}
+
+/// Registers an advertisement monitor with BlueZ for the adapter managed by `backend` and spawns a
+/// task that wakes the adapter when a known device starts advertising.
+///
+/// A device is known if it matches `auto_connect`, or (if `auto_connect` is empty) if it is
+/// paired. When found, the adapter is powered on (if needed) and the device is connected.
+///
+/// Passive scanning requires a powered adapter, so this only has an effect while the adapter is
+/// on, e.g. after a `disconnect` or `discoverable_off` stage. The returned connection serves the
+/// monitor and must be kept alive for as long as it should stay registered.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if a pattern is invalid or no pattern is configured.
+/// - [`anyhow::Error`] if the system bus is unavailable or BlueZ rejects the monitor (e.g., the
+///   adapter does not support advertisement monitoring).
+pub async fn register(backend: SharedBackend, patterns: &[AdPatternConf]) -> Result<Connection> {
+    if patterns.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Waking on advertisements requires at least one pattern"
+        ));
+    }
+    let patterns = patterns
+        .iter()
+        .map(|p| Ok((p.offset, p.ad_type, parse_hex(&p.content)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let (found_tx, found_rx) = mpsc::unbounded_channel();
+    let monitor = AdvertisementMonitor {
+        patterns,
+        found: found_tx,
+    };
+    let conn = bus::system_builder()?
+        .serve_at(MONITOR_APP_PATH, ObjectManager)?
+        .serve_at(MONITOR_PATH, monitor)?
+        .build()
+        .await?;
+
+    let conf = Conf::instance();
+    conn.call_method(
+        Some(conf.dbus.service.as_str()),
+        backend.adapter(),
+        Some("org.bluez.AdvertisementMonitorManager1"),
+        "RegisterMonitor",
+        &(OwnedObjectPath::try_from(MONITOR_APP_PATH)?),
+    )
+    .await
+    .context("Could not register advertisement monitor")?;
+    info!(
+        "Registered advertisement monitor for {}.",
+        backend.adapter()
+    );
+
+    tokio::spawn(wake_on_found(backend, found_rx));
+
+    Ok(conn)
+}
+
+/// Powers on the adapter and connects known devices reported by the monitor.
+async fn wake_on_found(
+    backend: SharedBackend,
+    mut found: mpsc::UnboundedReceiver<OwnedObjectPath>,
+) {
+    let conf = Conf::instance();
+
+    while let Some(path) = found.recv().await {
+        let devices = backend.devices().await.unwrap_or_default();
+        let Some(device) = devices.iter().find(|d| d.object_path == path.as_str()) else {
+            continue;
+        };
+        if device.connected {
+            continue;
+        }
+
+        let known = if conf.auto_connect.is_empty() {
+            backend.is_paired(device).await.unwrap_or(false)
+        } else {
+            conf.auto_connect
+                .iter()
+                .any(|pattern| device.matches(pattern))
+        };
+        if !known {
+            debug!(
+                "Ignoring advertisement of unknown device {}.",
+                device.display_name()
+            );
+            continue;
+        }
+
+        info!("Known device {} is advertising.", device.display_name());
+        let _ = power_on::power_on(backend.as_ref(), "known device advertising")
+            .await
+            .inspect_err(|e| warn!("Failed to power on adapter: {}", e));
+        let _ = backend
+            .connect_device(device)
+            .await
+            .inspect_err(|e| warn!("Failed to connect device {}: {}", device.display_name(), e));
+    }
+}
+
+/// Parses a hex string (e.g., "4c00") into bytes.
+fn parse_hex(hex: &str) -> Result<Vec<u8>> {
+    if hex.is_empty() || !hex.is_ascii() || !hex.len().is_multiple_of(2) {
+        return Err(anyhow::anyhow!("Invalid pattern content '{}'", hex));
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .with_context(|| format!("Invalid pattern content '{}'", hex))
+        })
+        .collect()
+}
@@ -1,19 +1,28 @@
 // -- std imports
-use core::panic;
+use std::{collections::HashMap, time::Duration};
 
 // -- crate imports
 use anyhow::Result;
 use futures_util::stream::StreamExt;
-use tokio::{sync::broadcast, task::JoinHandle};
+use tokio::time::Instant;
 use tracing::{debug, error, info, instrument, warn};
 use zbus::{
-    Connection,
-    fdo::{ObjectManagerProxy, PropertiesProxy},
-    zvariant::Value,
+    Connection, MatchRule, Message, MessageStream,
+    fdo::{InterfacesAdded, InterfacesRemoved, ObjectManagerProxy, PropertiesProxy},
+    message::Type,
+    names::InterfaceName,
+    zvariant::{OwnedValue, Value},
 };
 
 // -- module imports
-use crate::configuration::Conf;
+use crate::{
+    bluetooth::{
+        cache::DeviceCache, device::BluetoothDevice, event_bus::EventBus, flag::AdapterFlag,
+        latency::EventLatency,
+    },
+    bus,
+    configuration::Conf,
+};
 
 /// Defines the Bluetooth events that can be observed.
 ///
@@ -25,21 +34,88 @@ pub enum BluetoothEvent {
     AdapterOn,
     /// Emitted when a Bluetooth adapter is turned off.
     AdapterOff,
+    /// Emitted when the adapter itself disappears (e.g., the dongle was unplugged).
+    AdapterRemoved,
+    /// Emitted when the adapter reappears after [`BluetoothEvent::AdapterRemoved`], with its
+    /// `Powered` state.
+    AdapterAdded { powered: bool },
     /// Emitted when a Bluetooth interface connects to a device.
     InterfaceAdded,
     /// Emitted when a Bluetooth interface disconnects from a device.
     InterfaceRemoved,
+    /// Emitted when a device of the adapter connects.
+    DeviceConnected(BluetoothDevice),
+    /// Emitted when a device of the adapter disconnects.
+    DeviceDisconnected(BluetoothDevice),
+    /// Emitted when a managed adapter flag (e.g., `Discoverable`) changes.
+    FlagChanged(AdapterFlag, bool),
+    /// Emitted when the adapter starts or stops discovering (scanning for devices).
+    DiscoveringChanged(bool),
+    /// Emitted when a connected device has been out of range for a while (see `proximity`).
+    DeviceOutOfRange(BluetoothDevice),
+    /// Emitted when a device reported as out of range is back in range.
+    DeviceInRange(BluetoothDevice),
+    /// Emitted when a network (PAN) connection to a device goes up or down.
+    TetheringChanged(BluetoothDevice, bool),
+    /// Emitted when a connected device matching a `dormant` rule has been connected for a while.
+    DeviceDormant(BluetoothDevice),
+    /// Emitted every `tick_interval` by a running timeout with the time left until it expires.
+    TimerTick { remaining: Duration },
+    /// Emitted `before_power_off` before a running timeout turns off the adapter, with the time
+    /// left until then.
+    BeforePowerOff { remaining: Duration },
 }
 
-/// Observes Bluetooth status changes from D-Bus and broadcasts them.
+impl BluetoothEvent {
+    /// Returns the name of the event kind (e.g., `DeviceConnected`), used as a log field.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BluetoothEvent::AdapterOn => "AdapterOn",
+            BluetoothEvent::AdapterOff => "AdapterOff",
+            BluetoothEvent::AdapterRemoved => "AdapterRemoved",
+            BluetoothEvent::AdapterAdded { .. } => "AdapterAdded",
+            BluetoothEvent::InterfaceAdded => "InterfaceAdded",
+            BluetoothEvent::InterfaceRemoved => "InterfaceRemoved",
+            BluetoothEvent::DeviceConnected(_) => "DeviceConnected",
+            BluetoothEvent::DeviceDisconnected(_) => "DeviceDisconnected",
+            BluetoothEvent::FlagChanged(..) => "FlagChanged",
+            BluetoothEvent::DiscoveringChanged(_) => "DiscoveringChanged",
+            BluetoothEvent::DeviceOutOfRange(_) => "DeviceOutOfRange",
+            BluetoothEvent::DeviceInRange(_) => "DeviceInRange",
+            BluetoothEvent::TetheringChanged(..) => "TetheringChanged",
+            BluetoothEvent::DeviceDormant(_) => "DeviceDormant",
+            BluetoothEvent::TimerTick { .. } => "TimerTick",
+            BluetoothEvent::BeforePowerOff { .. } => "BeforePowerOff",
+        }
+    }
+
+    /// Returns the device the event is about, if any.
+    pub fn device(&self) -> Option<&BluetoothDevice> {
+        match self {
+            BluetoothEvent::DeviceConnected(device)
+            | BluetoothEvent::DeviceDisconnected(device)
+            | BluetoothEvent::DeviceOutOfRange(device)
+            | BluetoothEvent::DeviceInRange(device)
+            | BluetoothEvent::TetheringChanged(device, _)
+            | BluetoothEvent::DeviceDormant(device) => Some(device),
+            _ => None,
+        }
+    }
+}
+
+/// Observes Bluetooth status changes from D-Bus and publishes them on an [`EventBus`].
 #[derive(Debug, Clone)]
 pub struct BluetoothEventObserver {
     /// Interface path for the Bluetooth adapter.
     pub iface: String,
     /// The current connection to the D-Bus.
     conn: Connection,
-    /// The sender for broadcasting events to subscribers.
-    pub tx: broadcast::Sender<BluetoothEvent>,
+    /// The bus the events are published on.
+    pub events: EventBus<BluetoothEvent>,
+    /// Cached devices of the adapter, invalidated whenever one of them changes.
+    cache: DeviceCache,
+    /// Stamps the events with the reception of their signals.
+    latency: EventLatency,
 }
 
 impl BluetoothEventObserver {
@@ -48,7 +124,9 @@ impl BluetoothEventObserver {
     /// # Arguments
     ///
     /// - `iface` - A string slice that holds the D-Bus object path of the Bluetooth adapter (e.g.,
-    ///    "/org/bluez/hci0").
+    ///   "/org/bluez/hci0").
+    /// - `cache` - The device cache to invalidate on changes.
+    /// - `latency` - The measurement to stamp the broadcast events in.
     ///
     /// # Returns
     ///
@@ -58,144 +136,286 @@ impl BluetoothEventObserver {
     /// # Errors
     ///
     /// - [`anyhow::Error`] if the connection to the system D-Bus cannot be established.
-    pub async fn new(iface: String) -> Result<Self> {
-        let conn = Connection::system().await?;
-        let (tx, _rx) = broadcast::channel(10);
-
-        Ok(Self { iface, conn, tx })
-    }
-
-    /// Subscribes to Bluetooth events.
-    pub fn subscribe(&self) -> broadcast::Receiver<BluetoothEvent> {
-        self.tx.subscribe()
-    }
-
-    /// Spawns the observer to run in a background task.
-    #[instrument(skip(self))]
-    pub fn listen(self) -> JoinHandle<()> {
-        info!("Spawning Bluetooth event observer task.");
-        tokio::spawn(async move {
-            if let Err(e) = self.run().await {
-                error!("Bluetooth observer failed: {}", e);
-                panic!("Bluetooth observer encountered a fatal error.");
-            }
+    pub async fn new(iface: String, cache: DeviceCache, latency: EventLatency) -> Result<Self> {
+        let conn = bus::system().await?;
+        Ok(Self {
+            iface,
+            conn,
+            events: EventBus::new(),
+            cache,
+            latency,
         })
     }
 
-    /// The private event loop. Listens for D-Bus signals and processes them.
-    ///
-    /// # Errors
+    /// The event loop, run in a background task (see [`BlueZBackend::observe`]). Subscribes to
+    /// the D-Bus signals and processes them until their streams end.
     ///
-    /// - [`anyhow::Error`] if setting up the observers fails or if there are issues
-    ///     receiving D-Bus signals.
-    #[instrument(skip_all)]
-    async fn run(&self) -> Result<()> {
-        self.dispatch_iface_observer().await?;
-        self.dispatch_adapter_props_observer().await?;
-
-        Ok(())
-    }
-
-    /// Sets up the observer for Bluetooth interface added/removed signals.
+    /// [`BlueZBackend::observe`]: crate::bluetooth::backend::bluez::BlueZBackend::observe
     ///
     /// # Errors
     ///
-    /// - [`anyhow::Error`] if setting up the observer fails.
+    /// - [`anyhow::Error`] if subscribing to the D-Bus signals fails.
     #[instrument(skip_all)]
-    async fn dispatch_iface_observer(&self) -> Result<()> {
-        let proxy = ObjectManagerProxy::builder(&self.conn)
-            .destination(Conf::instance().dbus.service.as_str())?
+    pub async fn run(&self) -> Result<()> {
+        let conf = Conf::instance();
+
+        let objects = ObjectManagerProxy::builder(&self.conn)
+            .destination(conf.dbus.service.as_str())?
             .path("/")? // always root path for ObjectManager
             .build()
             .await?;
-        debug!("Bluetooth interface proxy created.");
+        let mut iface_add_stream = objects.receive_interfaces_added().await?;
+        let mut iface_rm_stream = objects.receive_interfaces_removed().await?;
+        debug!("Bluetooth interface streams created.");
+
+        let adapter = PropertiesProxy::builder(&self.conn)
+            .destination(conf.dbus.service.as_str())?
+            .path(self.iface.as_str())?
+            .build()
+            .await?;
+        let mut adapter_props_stream = adapter.receive_properties_changed().await?;
+        debug!("Bluetooth adapter properties stream created.");
 
-        let mut iface_add_stream = proxy.receive_interfaces_added().await?;
-        let mut iface_rm_stream = proxy.receive_interfaces_removed().await?;
+        // Last `Powered` state that was broadcast, and a change that waits out `power_debounce`
+        let mut powered = adapter
+            .get(
+                InterfaceName::try_from(conf.dbus.adapter_iface.as_str())?,
+                "Powered",
+            )
+            .await
+            .ok()
+            .and_then(|value| bool::try_from(value).ok());
+        let mut pending_power = None;
+        let mut pending_stamp = None;
+        let debounce = tokio::time::sleep(conf.power_debounce);
+        tokio::pin!(debounce);
 
-        tokio::spawn({
-            let tx = self.tx.clone();
-            async move {
-                info!("Listening for InterfacesAdded signals.");
-                while let Some(signal) = iface_add_stream.next().await {
+        let rule = MatchRule::builder()
+            .msg_type(Type::Signal)
+            .sender(conf.dbus.service.as_str())?
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .path_namespace(self.iface.as_str())?
+            .build();
+        let mut device_props_stream = MessageStream::for_match_rule(rule, &self.conn, None).await?;
+        debug!("Bluetooth device properties stream created.");
+
+        info!("Listening for Bluetooth signals.");
+        loop {
+            tokio::select! {
+                Some(signal) = iface_add_stream.next() => {
                     debug!("Received InterfacesAdded signal: {:#?}", signal.args());
-                    if let Err(e) = tx.send(BluetoothEvent::InterfaceAdded) {
-                        error!("Failed to send InterfaceAdded event: {}", e);
+                    self.latency.received("InterfacesAdded");
+                    self.cache.invalidate();
+                    match self.adapter_added(&signal) {
+                        Some(value) => {
+                            info!("Bluetooth adapter {} is back.", self.iface);
+                            powered = value;
+                            pending_power = None;
+                            let powered = value.unwrap_or(false);
+                            self.send(BluetoothEvent::AdapterAdded { powered });
+                        }
+                        None => self.send(BluetoothEvent::InterfaceAdded),
                     }
                 }
-            }
-        });
-
-        tokio::spawn({
-            let tx = self.tx.clone();
-            async move {
-                info!("Listening for InterfacesRemoved signals.");
-                while let Some(signal) = iface_rm_stream.next().await {
+                Some(signal) = iface_rm_stream.next() => {
                     debug!("Received InterfacesRemoved signal: {:#?}", signal.args());
-                    if let Err(e) = tx.send(BluetoothEvent::InterfaceRemoved) {
-                        error!("Failed to send InterfaceRemoved event: {}", e);
+                    self.latency.received("InterfacesRemoved");
+                    self.cache.invalidate();
+                    if self.adapter_removed(&signal) {
+                        warn!("Bluetooth adapter {} disappeared.", self.iface);
+                        powered = None;
+                        pending_power = None;
+                        self.send(BluetoothEvent::AdapterRemoved);
+                    } else {
+                        self.send(BluetoothEvent::InterfaceRemoved);
                     }
                 }
-            }
-        });
+                Some(signal) = adapter_props_stream.next() => {
+                    debug!("Received PropertiesChanged signal: {:#?}", signal.args());
+                    let stamp = self.latency.received("PropertiesChanged");
+                    let Ok(args) = signal.args() else { continue };
+                    let Some(value) = self.on_adapter_props_changed(&args.changed_properties)
+                    else {
+                        continue;
+                    };
 
-        Ok(())
+                    if conf.power_debounce.is_zero() {
+                        self.send_powered(value);
+                    } else {
+                        pending_power = Some(value);
+                        pending_stamp = Some(stamp);
+                        debounce.as_mut().reset(Instant::now() + conf.power_debounce);
+                    }
+                }
+                () = &mut debounce, if pending_power.is_some() => {
+                    let value = pending_power.take().unwrap_or_default();
+                    if let Some(stamp) = pending_stamp.take() {
+                        self.latency.resume(stamp);
+                    }
+                    if powered == Some(value) {
+                        debug!("Adapter flapped back to Powered = {}, ignoring.", value);
+                    } else {
+                        powered = Some(value);
+                        self.send_powered(value);
+                    }
+                }
+                Some(Ok(msg)) = device_props_stream.next() => {
+                    self.latency.received("PropertiesChanged");
+                    self.on_device_props_changed(&msg).await;
+                }
+                else => {
+                    warn!("All Bluetooth signal streams ended.");
+                    return Ok(());
+                }
+            }
+        }
     }
 
-    /// Sets up the observer for Bluetooth adapter property changes.
-    ///
-    /// # Errors
-    ///
-    /// - [`anyhow::Error`] if setting up the observer fails.
-    #[instrument(skip_all)]
-    async fn dispatch_adapter_props_observer(&self) -> Result<()> {
-        let proxy = PropertiesProxy::builder(&self.conn)
-            .destination(Conf::instance().dbus.service.as_str())?
-            .path(self.iface.as_str())?
-            .build()
-            .await?;
-        debug!("Bluetooth adapter properties proxy created.");
+    /// Returns the `Powered` state of the adapter (`None` if unknown) if `signal` announces the
+    /// adapter itself, or `None` if it is about other objects (e.g., a device).
+    fn adapter_added(&self, signal: &InterfacesAdded) -> Option<Option<bool>> {
+        let args = signal.args().ok()?;
+        if *args.object_path != *self.iface {
+            return None;
+        }
+        let props = args
+            .interfaces_and_properties
+            .get(Conf::instance().dbus.adapter_iface.as_str())?;
 
-        let mut props_changed_stream = proxy.receive_properties_changed().await?;
+        Some(props.get("Powered").and_then(|v| bool::try_from(v).ok()))
+    }
 
-        tokio::spawn({
-            let tx = self.tx.clone();
-            async move {
-                info!("Listening for PropertiesChanged signals.");
+    /// Returns `true` if `signal` announces that the adapter itself was removed.
+    fn adapter_removed(&self, signal: &InterfacesRemoved) -> bool {
+        let adapter_iface = Conf::instance().dbus.adapter_iface.as_str();
+        signal.args().is_ok_and(|args| {
+            *args.object_path == *self.iface
+                && args.interfaces.iter().any(|iface| iface == adapter_iface)
+        })
+    }
 
-                while let Some(signal) = props_changed_stream.next().await {
-                    debug!("Received PropertiesChanged signal: {:#?}", signal.args());
-                    let args = &signal.args().unwrap();
-
-                    match args.changed_properties.get("Powered") {
-                        Some(Value::Bool(true)) => {
-                            debug!(
-                                "Bluetooth adapter powered ON on interface: {}",
-                                args.interface_name
-                            );
-                            if let Err(e) = tx.send(BluetoothEvent::AdapterOn) {
-                                error!("Failed to send AdapterOn event: {}", e);
-                            }
-                        }
+    /// Publishes `event` to the subscribers.
+    fn send(&self, event: BluetoothEvent) {
+        let kind = event.kind();
+        if self.events.has_subscribers() {
+            self.latency.sent(kind);
+        }
+        if !self.events.publish(event) {
+            error!("Failed to send {} event: nobody is subscribed.", kind);
+        }
+    }
 
-                        Some(Value::Bool(false)) => {
-                            debug!(
-                                "Bluetooth adapter powered OFF on interface: {}",
-                                args.interface_name
-                            );
-                            if let Err(e) = tx.send(BluetoothEvent::AdapterOff) {
-                                error!("Failed to send AdapterOff event: {}", e);
-                            }
-                        }
+    /// Broadcasts that the adapter was powered on or off.
+    fn send_powered(&self, powered: bool) {
+        if powered {
+            debug!("Bluetooth adapter powered ON on interface: {}", self.iface);
+            self.send(BluetoothEvent::AdapterOn);
+        } else {
+            debug!("Bluetooth adapter powered OFF on interface: {}", self.iface);
+            self.send(BluetoothEvent::AdapterOff);
+        }
+    }
 
-                        _ => {
-                            debug!("Powered property not changed or not a boolean.");
-                        }
-                    }
-                }
+    /// Handles changed properties of the adapter: changes of the [`AdapterFlag`]s (`Discoverable`
+    /// and `Pairable`) and of `Discovering` are broadcast, while a changed `Powered` state is
+    /// returned, so it can be debounced.
+    fn on_adapter_props_changed(&self, changed: &HashMap<&str, Value<'_>>) -> Option<bool> {
+        for (name, value) in changed.iter() {
+            if let (Some(flag), Value::Bool(value)) = (AdapterFlag::from_property(name), value) {
+                self.send(BluetoothEvent::FlagChanged(flag, *value));
             }
-        });
+        }
+        if let Some(Value::Bool(discovering)) = changed.get("Discovering") {
+            debug!("Bluetooth adapter discovering: {}", discovering);
+            self.send(BluetoothEvent::DiscoveringChanged(*discovering));
+        }
+
+        match changed.get("Powered") {
+            Some(Value::Bool(powered)) => Some(*powered),
+            _ => {
+                debug!("Powered property not changed or not a boolean.");
+                None
+            }
+        }
+    }
+
+    /// Handles a `PropertiesChanged` signal of the adapter or one of its devices (on any
+    /// interface, e.g. the battery level), invalidating the device cache and broadcasting changes
+    /// of a device's `Connected` property and of its network connection.
+    async fn on_device_props_changed(&self, msg: &Message) {
+        let Some(path) = msg.header().path().map(|p| p.to_string()) else {
+            return;
+        };
+        if path == self.iface {
+            // handled by the adapter properties stream
+            return;
+        }
+        self.cache.invalidate();
+
+        let Ok((iface, changed, _invalidated)) =
+            msg.body()
+                .deserialize::<(String, HashMap<String, OwnedValue>, Vec<String>)>()
+        else {
+            return;
+        };
+        let conf = Conf::instance();
+        if iface != conf.dbus.device_iface && iface != conf.dbus.network_iface {
+            return;
+        }
 
-        Ok(())
+        let Some(connected) = changed
+            .get("Connected")
+            .and_then(|v| v.downcast_ref::<bool>().ok())
+        else {
+            return;
+        };
+
+        if iface == conf.dbus.network_iface {
+            let device = BluetoothDevice {
+                common_name: fetch_device_name(&self.conn, &path).await,
+                ..BluetoothDevice::new(path)
+            };
+            debug!("Device {} tethering: {}", device.display_name(), connected);
+            self.send(BluetoothEvent::TetheringChanged(device, connected));
+            return;
+        }
+
+        let device = BluetoothDevice {
+            common_name: fetch_device_name(&self.conn, &path).await,
+            connected,
+            ..BluetoothDevice::new(path)
+        };
+        debug!("Device {} connected: {}", device.display_name(), connected);
+
+        self.send(match connected {
+            true => BluetoothEvent::DeviceConnected(device),
+            false => BluetoothEvent::DeviceDisconnected(device),
+        });
     }
 }
+
+/// Fetches the human-readable name (`Alias`, which defaults to `Name`) of the device at `path`.
+///
+/// Returns `None` if the name cannot be retrieved.
+async fn fetch_device_name(conn: &Connection, path: &str) -> Option<String> {
+    let conf = Conf::instance();
+    let proxy = PropertiesProxy::builder(conn)
+        .destination(conf.dbus.service.as_str())
+        .ok()?
+        .path(path)
+        .ok()?
+        .build()
+        .await
+        .ok()?;
+
+    let alias = proxy
+        .get(
+            InterfaceName::try_from(conf.dbus.device_iface.as_str()).ok()?,
+            "Alias",
+        )
+        .await
+        .ok()?;
+
+    String::try_from(alias).ok()
+}
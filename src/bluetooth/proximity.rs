@@ -0,0 +1,69 @@
+// -- std imports
+use std::collections::HashMap;
+
+// -- crate imports
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+// -- module imports
+use crate::{
+    bluetooth::{backend::SharedBackend, observer::BluetoothEvent},
+    configuration::ProximityConf,
+};
+
+/// Polls the RSSI of connected devices and broadcasts (on the backend's event stream)
+/// [`BluetoothEvent::DeviceOutOfRange`] for devices that have been out of range (no RSSI or below
+/// `rssi_threshold`) for `out_of_range_for`, and [`BluetoothEvent::DeviceInRange`] once they are
+/// back in range.
+///
+/// This catches devices that are still connected but not in use, e.g. headphones left in a bag in
+/// another room. Returns once the event stream is closed.
+pub async fn run(backend: SharedBackend, conf: &'static ProximityConf) {
+    info!(
+        "Monitoring proximity of connected devices (threshold: {} dBm).",
+        conf.rssi_threshold
+    );
+    // Object path -> (out of range since, reported as out of range)
+    let mut out_of_range: HashMap<String, (Instant, bool)> = HashMap::new();
+
+    loop {
+        tokio::time::sleep(conf.poll_interval).await;
+
+        let connected = match backend.connected_devices().await {
+            Ok(devices) => devices,
+            Err(e) => {
+                warn!("Could not poll device RSSI: {}", e);
+                continue;
+            }
+        };
+        out_of_range.retain(|path, _| connected.iter().any(|d| &d.object_path == path));
+
+        for device in connected {
+            let in_range = device.rssi.is_some_and(|rssi| rssi >= conf.rssi_threshold);
+            debug!("RSSI of {}: {:?}", device.display_name(), device.rssi);
+
+            let event = if in_range {
+                match out_of_range.remove(&device.object_path) {
+                    Some((_, true)) => Some(BluetoothEvent::DeviceInRange(device)),
+                    _ => None,
+                }
+            } else {
+                let (since, reported) = out_of_range
+                    .entry(device.object_path.clone())
+                    .or_insert((Instant::now(), false));
+                if !*reported && since.elapsed() >= conf.out_of_range_for {
+                    *reported = true;
+                    Some(BluetoothEvent::DeviceOutOfRange(device))
+                } else {
+                    None
+                }
+            };
+
+            if let Some(event) = event
+                && !backend.events().publish(event)
+            {
+                return;
+            }
+        }
+    }
+}
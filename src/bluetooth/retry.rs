@@ -0,0 +1,133 @@
+// -- std imports
+use std::{
+    hash::{BuildHasher, Hasher, RandomState},
+    time::Duration,
+};
+
+// -- crate imports
+use anyhow::Result;
+use tracing::debug;
+use zbus::DBusError;
+
+// -- module imports
+use crate::{bluetooth::service_proxy::DbusTimeout, configuration::Conf};
+
+/// Errors returned by BlueZ (or the bus) that are likely to go away on their own.
+const TRANSIENT_ERRORS: [&str; 6] = [
+    "org.freedesktop.DBus.Error.NoReply",
+    "org.freedesktop.DBus.Error.ServiceUnknown",
+    "org.freedesktop.DBus.Error.Timeout",
+    "org.freedesktop.DBus.Error.LimitsExceeded",
+    "org.bluez.Error.Busy",
+    "org.bluez.Error.NotReady",
+];
+
+/// Runs the D-Bus call `op` (named `call` in the log) up to `dbus.call_attempts` times, waiting
+/// with jittered exponential backoff, starting at `dbus.retry_backoff`, after each transient
+/// failure (see [`is_transient`]).
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] of the last attempt, if it failed or the failure is not transient.
+pub async fn with_backoff<T, F>(call: &str, mut op: impl FnMut() -> F) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    let conf = &Conf::instance().dbus;
+    let mut attempt = 1;
+
+    loop {
+        match op().await {
+            Err(e) if attempt < conf.call_attempts && is_transient(&e) => {
+                let delay = delay(conf.retry_backoff, attempt, jitter());
+                debug!(
+                    "D-Bus call '{}' failed (attempt {} of {}): {:#}; retrying in {}.",
+                    call,
+                    attempt,
+                    conf.call_attempts,
+                    e,
+                    humantime::format_duration(delay)
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Returns whether the failure `e` of a D-Bus call is likely to go away on retrying: the call
+/// timed out, the connection failed or BlueZ answered with one of [`TRANSIENT_ERRORS`].
+pub fn is_transient(e: &anyhow::Error) -> bool {
+    if e.is::<DbusTimeout>() {
+        return true;
+    }
+
+    let error = match e.downcast_ref::<zbus::fdo::Error>() {
+        Some(zbus::fdo::Error::ZBus(error)) => Some(error),
+        Some(error) => return TRANSIENT_ERRORS.contains(&error.name().as_str()),
+        None => e.downcast_ref::<zbus::Error>(),
+    };
+    match error {
+        Some(zbus::Error::InputOutput(_)) => true,
+        Some(zbus::Error::MethodError(name, _, _)) => TRANSIENT_ERRORS.contains(&name.as_str()),
+        _ => false,
+    }
+}
+
+/// Returns the delay after the failed `attempt` (starting at 1): `backoff`, doubled for every
+/// previous attempt, of which `jitter` (in `[0, 1)`) picks between half and all.
+fn delay(backoff: Duration, attempt: u32, jitter: f64) -> Duration {
+    let full = backoff.saturating_mul(1 << attempt.saturating_sub(1).min(16));
+
+    full.mul_f64(0.5 + jitter / 2.0)
+}
+
+/// Returns a random number in `[0, 1)`, so that several daemons retrying at once spread out.
+fn jitter() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_per_attempt_within_the_jitter() {
+        let backoff = Duration::from_millis(200);
+
+        assert_eq!(delay(backoff, 1, 0.0), Duration::from_millis(100));
+        assert_eq!(delay(backoff, 1, 1.0), Duration::from_millis(200));
+        assert_eq!(delay(backoff, 3, 1.0), Duration::from_millis(800));
+        assert!((0.0..1.0).contains(&jitter()));
+    }
+
+    #[test]
+    fn only_transient_failures_are_retried() {
+        let busy = zbus::Error::MethodError(
+            "org.bluez.Error.Busy".try_into().unwrap(),
+            None,
+            zbus::message::Message::method_call("/", "Get")
+                .unwrap()
+                .build(&())
+                .unwrap(),
+        );
+        let timeout = DbusTimeout {
+            call: "Powered".to_string(),
+            after: Duration::from_secs(5),
+        };
+
+        assert!(is_transient(&busy.into()));
+        assert!(is_transient(&timeout.into()));
+        assert!(is_transient(
+            &zbus::fdo::Error::NoReply("bluetoothd".into()).into()
+        ));
+        assert!(!is_transient(
+            &zbus::fdo::Error::UnknownObject("/org/bluez/hci9".into()).into()
+        ));
+        assert!(!is_transient(&anyhow::anyhow!("rfkill failed")));
+    }
+}
@@ -0,0 +1,202 @@
+// -- std imports
+use std::{fs, io::Write, path::Path, time::Duration};
+
+// -- crate imports
+use anyhow::{Context, Result};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// Path of the rfkill control device.
+const RFKILL_DEV: &str = "/dev/rfkill";
+
+/// Directory listing the rfkill switches of the system.
+const RFKILL_SYSFS: &str = "/sys/class/rfkill";
+
+/// `RFKILL_TYPE_WLAN` from `linux/rfkill.h`.
+pub const RFKILL_TYPE_WLAN: u8 = 1;
+
+/// `RFKILL_TYPE_BLUETOOTH` from `linux/rfkill.h`.
+const RFKILL_TYPE_BLUETOOTH: u8 = 2;
+
+/// `RFKILL_TYPE_WWAN` from `linux/rfkill.h`.
+pub const RFKILL_TYPE_WWAN: u8 = 5;
+
+/// `RFKILL_OP_CHANGE` from `linux/rfkill.h`.
+const RFKILL_OP_CHANGE: u8 = 2;
+
+/// `RFKILL_OP_CHANGE_ALL` from `linux/rfkill.h`.
+const RFKILL_OP_CHANGE_ALL: u8 = 3;
+
+/// How often the rfkill switches are checked for airplane mode.
+const AIRPLANE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Soft-blocks (or unblocks) the rfkill switch of the Bluetooth adapter `hci` (e.g., "hci0").
+///
+/// Unlike setting `Powered` to `false`, a soft-block is not undone by desktop environments that
+/// re-enable the adapter, only by unblocking the switch again.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if no rfkill switch exists for the adapter or `/dev/rfkill` cannot be
+///   written (e.g., missing permissions).
+pub fn soft_block(hci: &str, block: bool) -> Result<()> {
+    write_event(
+        switch_index(hci)?,
+        RFKILL_TYPE_BLUETOOTH,
+        RFKILL_OP_CHANGE,
+        block,
+    )
+}
+
+/// Soft-blocks (or unblocks) every rfkill switch of `kind` (e.g., [`RFKILL_TYPE_WLAN`]).
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if `/dev/rfkill` cannot be written (e.g., missing permissions).
+pub fn soft_block_all(kind: u8, block: bool) -> Result<()> {
+    write_event(0, kind, RFKILL_OP_CHANGE_ALL, block)
+}
+
+/// Returns whether any rfkill switch of the type named `kind` in sysfs (e.g., "wlan") is neither
+/// soft- nor hard-blocked. Returns `false` if there is no such switch.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the rfkill switches cannot be listed.
+pub fn any_unblocked(kind: &str) -> Result<bool> {
+    for entry in fs::read_dir(RFKILL_SYSFS)?.flatten() {
+        let read = |attr: &str| fs::read_to_string(entry.path().join(attr)).unwrap_or_default();
+        if read("type").trim() == kind && read("soft").trim() == "0" && read("hard").trim() == "0" {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Returns whether airplane mode is on for the Bluetooth adapter `hci` (e.g., "hci0"): its rfkill
+/// switch is hard-blocked (e.g., by a hardware switch), or every rfkill switch is soft-blocked and
+/// there are switches of other types, as desktop environments do when enabling airplane mode.
+///
+/// Soft-blocking only the Bluetooth switches (e.g., with `power_off: rfkill`) merely turns the
+/// adapter off and does not count as airplane mode.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the rfkill switches cannot be listed.
+pub fn is_airplane_mode(hci: &str) -> Result<bool> {
+    let (mut all_soft_blocked, mut other_types) = (true, false);
+    for entry in fs::read_dir(RFKILL_SYSFS)?.flatten() {
+        let read = |attr: &str| fs::read_to_string(entry.path().join(attr)).unwrap_or_default();
+        let bluetooth = read("type").trim() == "bluetooth";
+        if bluetooth && read("name").trim() == hci && read("hard").trim() == "1" {
+            return Ok(true);
+        }
+
+        all_soft_blocked &= read("soft").trim() == "1";
+        other_types |= !bluetooth;
+    }
+
+    Ok(all_soft_blocked && other_types)
+}
+
+/// Watches whether airplane mode is on for the Bluetooth adapter `hci` (see
+/// [`is_airplane_mode`]), checking every [`AIRPLANE_POLL_INTERVAL`]. Returns `None` if the system
+/// has no rfkill switches.
+///
+/// If the switches cannot be read, airplane mode is assumed to be off.
+pub fn watch_airplane_mode(hci: &str) -> Option<watch::Receiver<bool>> {
+    if !Path::new(RFKILL_SYSFS).is_dir() {
+        return None;
+    }
+
+    let hci = hci.to_string();
+    let (tx, rx) = watch::channel(is_airplane_mode(&hci).unwrap_or(false));
+    tokio::spawn(async move {
+        let mut failing = false;
+        loop {
+            tokio::time::sleep(AIRPLANE_POLL_INTERVAL).await;
+            if tx.is_closed() {
+                break;
+            }
+
+            let airplane_mode = match is_airplane_mode(&hci) {
+                Ok(airplane_mode) => {
+                    failing = false;
+                    airplane_mode
+                }
+                Err(e) => {
+                    if !failing {
+                        warn!(
+                            "Could not check for airplane mode, assuming it is off: {}",
+                            e
+                        );
+                    }
+                    failing = true;
+                    false
+                }
+            };
+            tx.send_if_modified(|current| {
+                let changed = *current != airplane_mode;
+                if changed {
+                    info!(
+                        "Airplane mode turned {} for {}.",
+                        if airplane_mode { "on" } else { "off" },
+                        hci
+                    );
+                }
+                *current = airplane_mode;
+                changed
+            });
+        }
+    });
+
+    Some(rx)
+}
+
+/// Writes an rfkill event changing the soft-block state of the switch `index` of `kind` to
+/// `/dev/rfkill`.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if `/dev/rfkill` cannot be written.
+fn write_event(index: u32, kind: u8, op: u8, block: bool) -> Result<()> {
+    // struct rfkill_event { __u32 idx; __u8 type; __u8 op; __u8 soft; __u8 hard; }
+    let mut event = [0u8; 8];
+    event[..4].copy_from_slice(&index.to_ne_bytes());
+    event[4] = kind;
+    event[5] = op;
+    event[6] = block.into();
+
+    fs::OpenOptions::new()
+        .write(true)
+        .open(RFKILL_DEV)
+        .and_then(|mut dev| dev.write_all(&event))
+        .with_context(|| format!("Could not write to {}", RFKILL_DEV))
+}
+
+/// Returns whether the rfkill switch of the Bluetooth adapter `hci` is soft-blocked.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if no rfkill switch exists for the adapter.
+pub fn is_soft_blocked(hci: &str) -> Result<bool> {
+    let switch = Path::new(RFKILL_SYSFS).join(format!("rfkill{}", switch_index(hci)?));
+
+    Ok(fs::read_to_string(switch.join("soft"))?.trim() == "1")
+}
+
+/// Returns the index of the rfkill switch belonging to the Bluetooth adapter `hci`.
+fn switch_index(hci: &str) -> Result<u32> {
+    for entry in fs::read_dir(RFKILL_SYSFS)?.flatten() {
+        let read = |attr: &str| fs::read_to_string(entry.path().join(attr)).unwrap_or_default();
+        if read("type").trim() == "bluetooth" && read("name").trim() == hci {
+            return Ok(read("index").trim().parse()?);
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "No rfkill switch found for adapter {}",
+        hci
+    ))
+}
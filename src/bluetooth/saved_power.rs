@@ -0,0 +1,177 @@
+// -- std imports
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+// -- crate imports
+use anyhow::Result;
+use futures_util::stream::StreamExt;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use zbus::{MatchRule, MessageStream, message::Type};
+
+// -- module imports
+use crate::{bus, configuration::state_path, logind, supervisor::Supervisor};
+
+/// Set once the system is shutting down, after which the powered state is no longer saved.
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Contents of the file the powered state is kept in.
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    /// Whether the adapter was powered on.
+    powered: bool,
+}
+
+/// The powered state an adapter was left in, kept in a file across restarts of the daemon and
+/// reboots, so it can be restored (see `power_on.restore`).
+///
+/// Once the system is shutting down (see [`listen_shutdown`]), changes are no longer saved, as
+/// BlueZ powers the adapter off when it stops.
+#[derive(Debug)]
+pub struct SavedPower {
+    /// File the state is kept in.
+    path: PathBuf,
+    /// State last written to the file, to skip writing it again.
+    saved: Mutex<Option<bool>>,
+}
+
+impl SavedPower {
+    /// Creates the powered state kept in the file at `path`.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            saved: Mutex::new(None),
+        }
+    }
+
+    /// Returns whether the adapter was left powered on, or `None` if that is unknown (e.g., the
+    /// file does not exist yet or cannot be parsed).
+    pub fn load(&self) -> Option<bool> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!(
+                    "Could not read power state '{}': {}",
+                    self.path.display(),
+                    e
+                );
+                return None;
+            }
+        };
+        let record: Record = serde_yaml::from_str(&contents)
+            .inspect_err(|e| {
+                warn!(
+                    "Ignoring invalid power state '{}': {}",
+                    self.path.display(),
+                    e
+                )
+            })
+            .ok()?;
+
+        *self.saved.lock().unwrap_or_else(|e| e.into_inner()) = Some(record.powered);
+        Some(record.powered)
+    }
+
+    /// Records that the adapter is now `powered` (or off), unless the system is shutting down.
+    /// Failures are logged.
+    pub fn save(&self, powered: bool) {
+        if SHUTTING_DOWN.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut saved = self.saved.lock().unwrap_or_else(|e| e.into_inner());
+        if *saved == Some(powered) {
+            return;
+        }
+
+        let result = serde_yaml::to_string(&Record { powered })
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| Ok(fs::write(&self.path, contents)?));
+        match result {
+            Ok(()) => {
+                *saved = Some(powered);
+                debug!("Saved power state to '{}'.", self.path.display());
+            }
+            Err(e) => warn!(
+                "Could not save power state '{}': {}",
+                self.path.display(),
+                e
+            ),
+        }
+    }
+}
+
+/// Spawns a task in `supervisor` that stops saving the powered state once logind announces that
+/// the system is shutting down (`PrepareForShutdown`), so the state from before the shutdown is
+/// kept.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the system bus is unavailable or the subscription fails.
+pub async fn listen_shutdown(supervisor: &mut Supervisor) -> Result<()> {
+    let conn = bus::system().await?;
+    let rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .interface(logind::LOGIND_MANAGER_IFACE)?
+        .member("PrepareForShutdown")?
+        .build();
+    let mut stream = MessageStream::for_match_rule(rule, &conn, None).await?;
+
+    supervisor.track("shutdown listener", async move {
+        while let Some(Ok(msg)) = stream.next().await {
+            let shutting_down = msg.body().deserialize::<bool>().unwrap_or(false);
+            if shutting_down {
+                info!("System is shutting down; keeping the saved power state.");
+            }
+            SHUTTING_DOWN.store(shutting_down, Ordering::Relaxed);
+        }
+    });
+
+    Ok(())
+}
+
+/// Returns the path of the file the powered state of `adapter` (e.g., "/org/bluez/hci0") is kept
+/// in.
+///
+/// This is `hci0.power.yml` among the state files (see [`state_path`]).
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the state directory cannot be determined or created.
+pub fn path(adapter: &str) -> Result<PathBuf> {
+    let name = adapter.rsplit('/').next().unwrap_or(adapter);
+
+    state_path(&format!("{}.power.yml", name))
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    /// Returns the path of a scratch state file that does not exist yet.
+    fn state_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("bluetooth-timeout-power-{}.yml", name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn keeps_the_powered_state_across_restarts() {
+        let path = state_file("restart");
+        assert_eq!(SavedPower::new(path.clone()).load(), None);
+
+        let saved = SavedPower::new(path.clone());
+        saved.save(true);
+        saved.save(false);
+
+        assert_eq!(SavedPower::new(path.clone()).load(), Some(false));
+        let _ = fs::remove_file(path);
+    }
+}
@@ -1,19 +1,45 @@
 // -- std imports
-use std::time::Duration;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
 // -- crate imports
 use anyhow::Result;
-use tokio::sync::broadcast;
-use tracing::{debug, error, info};
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinHandle,
+    time::Instant,
+};
+use tracing::{Instrument, Span, debug, error, field, info, info_span, instrument, warn};
+
+// -- module imports (conditional)
+#[cfg(feature = "notifications")]
+use crate::notification::Notification;
 
 // -- module imports
 use crate::{
-    bluetooth::{observer::BluetoothEvent, service_proxy::BluetoothServiceProxy},
-    timeout::TimeoutTask,
+    bluetooth::{
+        backend::{BluetoothBackend, SharedBackend},
+        blackout,
+        device::BluetoothDevice,
+        event_bus::Subscription,
+        flag::{self, AdapterFlag},
+        fsm::{Effect, Fsm, Input},
+        history::History,
+        observer::BluetoothEvent,
+        rfkill,
+        saved_power::SavedPower,
+        service_proxy::DbusTimeout,
+        stats::PowerStats,
+    },
+    configuration::{Conf, TimeoutConf},
+    idle, obex,
+    timeout::{TimeoutHandle, TimeoutTask, WakeTimer},
 };
 
 /// Represents the state of the Bluetooth service.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BluetoothServiceState {
     /// The Bluetooth adapter is powered off.
     Off,
@@ -21,36 +47,193 @@ pub enum BluetoothServiceState {
     Idle,
     /// The Bluetooth adapter is on and at least one device is connected.
     Running,
+    /// The Bluetooth adapter has disappeared (e.g., the dongle was unplugged); it is picked up
+    /// again once it returns.
+    Detached,
+}
+
+impl BluetoothServiceState {
+    /// Returns `true` if the adapter is present and powered on.
+    pub fn is_powered(self) -> bool {
+        matches!(
+            self,
+            BluetoothServiceState::Idle | BluetoothServiceState::Running
+        )
+    }
+}
+
+/// Commands that can be sent to a running [`BluetoothService`] (e.g., from notification actions).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(not(feature = "notifications"), allow(dead_code))]
+pub enum BluetoothServiceCommand {
+    /// Cancel the active timeout and keep the adapter on until the next state change.
+    KeepOn,
+    /// Postpone the timeout by the given duration.
+    Snooze(Duration),
+    /// Turn off the adapter immediately.
+    TurnOffNow,
+    /// Keep the adapter on for the given duration, powering it on if necessary, then return to
+    /// the regular timeout; a zero duration ends the window early.
+    #[cfg_attr(not(feature = "cli-control"), allow(dead_code))]
+    KeepOnFor(Duration),
+    /// The screen was locked; handled according to `lock.action`.
+    ScreenLocked,
+    /// The timeout or notification settings changed, e.g. another profile was activated (see
+    /// [`Conf::set_profile`]); they apply from now on.
+    SettingsChanged,
 }
 
 /// Manages the state of a Bluetooth adapter and handles events.
 ///
 /// This service listens for Bluetooth events and manages a timeout to turn off
-/// the adapter when it's idle.
+/// the adapter when it's idle. The transitions are decided by the [`Fsm`]; the service applies
+/// the requested [`Effect`]s.
 #[derive(Debug)]
 pub struct BluetoothService {
     /// The Bluetooth interface name (e.g., "hci0").
     pub iface: String,
-    /// Receiver for Bluetooth events.
+    /// Events of the backend, subscribed to when the service is created.
+    events: Subscription<BluetoothEvent>,
+    /// Sender for commands to the service, handed out via `command_sender`.
+    command_tx: mpsc::UnboundedSender<BluetoothServiceCommand>,
+    /// Receiver for commands to the service.
     ///
-    /// This is an `Option` to allow for late initialization via `subscribe_to`.
-    rx: Option<broadcast::Receiver<BluetoothEvent>>,
-    /// Proxy to interact with the Bluetooth service via D-Bus.
-    service_proxy: BluetoothServiceProxy,
-    /// Current state of the Bluetooth service.
-    pub state: BluetoothServiceState,
+    /// This is an `Option`, as it is moved into the event loop by `start`.
+    command_rx: Option<mpsc::UnboundedReceiver<BluetoothServiceCommand>>,
+    /// Backend to operate on the Bluetooth adapter.
+    backend: SharedBackend,
+    /// State machine deciding the transitions of the service.
+    fsm: Fsm,
     /// Handle to the active timeout timer task, if any.
-    pub active_timer: Option<tokio::task::JoinHandle<()>>,
-    /// Duration before the timeout triggers.
-    timeout: Duration,
+    pub active_timer: Option<TimeoutHandle>,
+    /// Deadline of the active timeout timer, published by the timers themselves.
+    deadline: watch::Sender<Option<Instant>>,
+    /// End of the window the adapter is kept on for, if any.
+    keep_on_until: Option<Instant>,
+    /// Timer powering the adapter back on a while after the timeout turned it off.
+    wake_timer: WakeTimer,
+    /// Idle state of the user session, if the timeout is coupled to it.
+    idle: Option<watch::Receiver<bool>>,
+    /// Whether the timeout is inhibited (an application inhibits idling, a configured process runs
+    /// or a file transfer is in progress), if it is paused meanwhile.
+    inhibited: Option<watch::Receiver<bool>>,
+    /// Whether airplane mode is on, if the system has rfkill switches.
+    airplane_mode: Option<watch::Receiver<bool>>,
+    /// Object paths of connected devices that are out of range and not counted as connected.
+    out_of_range: HashSet<String>,
+    /// Object paths of connected devices that are dormant and not counted as connected.
+    dormant: HashSet<String>,
+    /// Timers turning off adapter flags (e.g., `Discoverable`) that are currently set.
+    flag_timers: HashMap<AdapterFlag, JoinHandle<()>>,
+    /// Publishes the current state, e.g., for the control interface.
+    state_tx: watch::Sender<BluetoothServiceState>,
+    /// Recent state transitions, events and commands, e.g., for `status --events` and crash
+    /// reports.
+    history: History,
+    /// Totals of the powered time, e.g., for `status --stats`.
+    stats: PowerStats,
+    /// Powered state the adapter is left in, if it is remembered for `power_on.restore`.
+    saved_power: Option<SavedPower>,
 }
 
-/// Retrieves the number of connected Bluetooth devices using the service proxy.
-async fn get_connected_devices_count_from_proxy(proxy: &BluetoothServiceProxy) -> usize {
-    let devices = proxy.get_devices().await.unwrap_or(vec![]);
-    let connected_count = devices.iter().filter(|dev| dev.connected).count();
+/// Starts watching the configured sources inhibiting the timeout: idle inhibitors (see
+/// `idle.respect_inhibitors`), processes (see `inhibit.processes`) and OBEX file transfers (see
+/// `pause_on_transfers`).
+async fn inhibition_sources() -> Vec<watch::Receiver<bool>> {
+    let conf = Conf::instance();
+    let mut sources: Vec<_> = idle::watch_inhibitors(&conf.idle)
+        .into_iter()
+        .chain(idle::watch_processes(&conf.inhibit))
+        .collect();
 
-    connected_count
+    if conf.pause_on_transfers {
+        match obex::watch_transfers().await {
+            Ok(transfers) => sources.push(transfers),
+            Err(e) => warn!("Could not watch OBEX file transfers: {}", e),
+        }
+    }
+
+    sources
+}
+
+/// Retrieves the number of connected Bluetooth devices using the backend, counted like
+/// [`BluetoothService::get_connected_devices_count`] before any device went out of range or
+/// dormant.
+async fn get_connected_devices_count_from_backend(backend: &dyn BluetoothBackend) -> usize {
+    let trusted_only = Conf::instance().trusted_only;
+    let devices = backend.devices().await.unwrap_or(vec![]);
+    devices
+        .iter()
+        .filter(|dev| counts_as_connected(dev, trusted_only, |_| false))
+        .count()
+}
+
+/// Whether `device` keeps the adapter on: it is tethering, or it is connected, not untrusted with
+/// `trusted_only` and not `ignored` (e.g., out of range or dormant).
+fn counts_as_connected(
+    device: &BluetoothDevice,
+    trusted_only: bool,
+    ignored: impl Fn(&str) -> bool,
+) -> bool {
+    device.tethering
+        || (device.connected
+            && device.keeps_adapter_on(trusted_only)
+            && !ignored(&device.object_path))
+}
+
+/// Merges the `sources` of inhibition into a single receiver, which is `true` while any of them
+/// is. Returns `None` if there are no sources.
+fn any_inhibited(mut sources: Vec<watch::Receiver<bool>>) -> Option<watch::Receiver<bool>> {
+    if sources.len() <= 1 {
+        return sources.pop();
+    }
+
+    let (tx, rx) = watch::channel(sources.iter().any(|source| *source.borrow()));
+    tokio::spawn(async move {
+        loop {
+            let changes = sources.iter_mut().map(|source| Box::pin(source.changed()));
+            let (changed, _, _) = futures_util::future::select_all(changes).await;
+            if changed.is_err() {
+                break;
+            }
+
+            let inhibited = sources.iter().any(|source| *source.borrow());
+            tx.send_if_modified(|current| std::mem::replace(current, inhibited) != inhibited);
+            if tx.is_closed() {
+                break;
+            }
+        }
+    });
+
+    Some(rx)
+}
+
+/// Resolves with the new value once `watched` changes (e.g., whether the timeout is inhibited).
+/// Never resolves if nothing is watched (e.g., pausing on inhibitors is disabled).
+///
+/// If the watcher has stopped, watching is disabled by resetting `watched` to `None`.
+async fn watched_changed(watched: &mut Option<watch::Receiver<bool>>) -> Option<bool> {
+    let rx = watched.as_mut()?;
+    if rx.changed().await.is_err() {
+        *watched = None;
+        return None;
+    }
+
+    Some(*rx.borrow_and_update())
+}
+
+/// Closes the timeout notification with the given `id`, as it is outdated. Failures are logged.
+#[cfg(feature = "notifications")]
+async fn withdraw_notification(id: u32) {
+    if let Err(e) = Notification::close(id).await {
+        warn!("Could not withdraw timeout notification: {}", e);
+    }
+}
+
+/// Resolves once `until` has passed. Never resolves if the adapter is not kept on for a while.
+async fn keep_on_expired(until: Option<Instant>) -> Option<()> {
+    tokio::time::sleep_until(until?).await;
+    Some(())
 }
 
 impl BluetoothService {
@@ -61,13 +244,18 @@ impl BluetoothService {
     ///
     /// # Arguments
     ///
-    /// - `iface` - The name of the Bluetooth interface to manage.
-    /// - `timeout` - The duration to wait before turning off an idle adapter.
-    pub async fn new(iface: String, timeout: Duration) -> Result<Self> {
-        let service_proxy = BluetoothServiceProxy::new(iface.clone()).await?;
-        let num_connected_devices = get_connected_devices_count_from_proxy(&service_proxy).await;
+    /// - `backend` - The backend of the Bluetooth adapter to manage.
+    /// - `timeouts` - The durations to wait before turning off an idle adapter, or `None` to never
+    ///   turn it off.
+    pub async fn new(backend: SharedBackend, timeouts: Option<TimeoutConf>) -> Result<Self> {
+        // Subscribed before the state is determined, so no change in between is missed
+        let events = backend.events().subscribe("BluetoothService");
+        let iface = backend.adapter().to_string();
+        let hci = iface.rsplit('/').next().unwrap_or(&iface).to_string();
+        let num_connected_devices =
+            get_connected_devices_count_from_backend(backend.as_ref()).await;
         // Assume adapter is off if we cannot determine its powered state (e.g., Adapter not found)
-        let powered = service_proxy.is_powered().await.unwrap_or(false);
+        let powered = backend.is_powered().await.unwrap_or(false);
 
         let state = match (powered, num_connected_devices) {
             (false, 0) => BluetoothServiceState::Off,
@@ -80,78 +268,640 @@ impl BluetoothService {
                 ));
             }
         };
-        info!("Initial BluetoothService state: {:#?}", state);
-
-        let active_timer = if state == BluetoothServiceState::Idle {
+        info!(
+            "Initial BluetoothService state of {}: {:#?}",
+            backend.adapter_name(),
+            state
+        );
+        if let Ok(capabilities) = backend.capabilities().await {
             info!(
-                "Starting timeout timer for idle adapter with timeout of {:?}",
-                timeout
+                "Capabilities of {}: {}",
+                backend.adapter_name(),
+                capabilities.describe()
             );
-            Some(TimeoutTask::new(timeout, service_proxy.clone()).spawn())
-        } else {
-            None
-        };
+        }
 
-        let service = Self {
+        let (command_tx, command_rx) = mpsc::unbounded_channel();
+        let mut service = Self {
             iface,
-            rx: None,
-            service_proxy,
-            state,
-            active_timer,
-            timeout,
+            events,
+            command_tx,
+            command_rx: Some(command_rx),
+            backend,
+            fsm: Fsm::new(state, timeouts),
+            active_timer: None,
+            deadline: watch::Sender::new(None),
+            keep_on_until: None,
+            wake_timer: WakeTimer::default(),
+            idle: idle::watch(&Conf::instance().idle),
+            inhibited: any_inhibited(inhibition_sources().await),
+            airplane_mode: rfkill::watch_airplane_mode(&hci),
+            out_of_range: HashSet::new(),
+            dormant: HashSet::new(),
+            flag_timers: HashMap::new(),
+            state_tx: watch::Sender::new(state),
+            history: History::new(Conf::instance().log.history),
+            stats: PowerStats::new(),
+            saved_power: None,
         };
+        service.stats.set_powered(powered, false);
+
+        if powered {
+            for flag in AdapterFlag::ALL {
+                if service.backend.get_flag(flag).await.unwrap_or(false) {
+                    service.on_flag_changed(flag, true);
+                }
+            }
+        }
+
+        if service
+            .airplane_mode
+            .as_ref()
+            .is_some_and(|rx| *rx.borrow())
+        {
+            service.step(Input::AirplaneModeChanged(true)).await?;
+        }
+        if blackout::active_now(&Conf::instance().blackout) {
+            service.step(Input::BlackoutChanged(true)).await?;
+        }
+        service.step(Input::Started).await?;
         debug!("Created new BluetoothService for iface {:?}", service.iface);
 
         Ok(service)
     }
 
-    /// Subscribes the service to a broadcast channel for `BluetoothEvent`s.
-    pub fn subscribe_to(&mut self, rx: broadcast::Receiver<BluetoothEvent>) -> &mut Self {
-        self.rx = Some(rx);
-        return self;
+    /// Creates a [`TimeoutTask`] for `timeout`, coupled to the idle state of the user session if
+    /// enabled.
+    fn timeout_task(&self, timeout: Duration) -> TimeoutTask {
+        TimeoutTask::new(timeout, self.backend.clone())
+            .idle(self.idle.clone())
+            .deadline(self.deadline.clone())
+    }
+
+    /// Spawns `task`, pausing it right away if the timeout is currently inhibited or airplane mode
+    /// is on.
+    fn spawn_timer(&self, task: TimeoutTask) -> TimeoutHandle {
+        let timer = task.spawn();
+        if self.is_inhibited() || self.fsm.airplane_mode() {
+            timer.pause();
+        }
+
+        timer
+    }
+
+    /// Returns `true` if the timeout is currently inhibited.
+    fn is_inhibited(&self) -> bool {
+        self.inhibited.as_ref().is_some_and(|rx| *rx.borrow())
+    }
+
+    /// Records the powered time in `stats` (e.g., loaded from a file) instead of in memory only.
+    pub fn keep_stats(&mut self, stats: PowerStats) -> &mut Self {
+        stats.set_powered(self.fsm.state().is_powered(), false);
+        self.stats = stats;
+        self
+    }
+
+    /// Remembers the powered state the adapter is left in (see `power_on.restore`) in
+    /// `saved_power`, starting with the current one.
+    pub fn keep_power_state(&mut self, saved_power: SavedPower) -> &mut Self {
+        if self.fsm.state() != BluetoothServiceState::Detached {
+            saved_power.save(self.fsm.state().is_powered());
+        }
+        self.saved_power = Some(saved_power);
+        self
+    }
+
+    /// Returns a sender that can be used to send [`BluetoothServiceCommand`]s to the service.
+    pub fn command_sender(&self) -> mpsc::UnboundedSender<BluetoothServiceCommand> {
+        self.command_tx.clone()
+    }
+
+    /// Returns a receiver that is updated with every state transition of the service.
+    pub fn state_receiver(&self) -> watch::Receiver<BluetoothServiceState> {
+        self.state_tx.subscribe()
+    }
+
+    /// Returns a receiver of the deadline of the active timeout timer (`None` if there is none or
+    /// it is paused).
+    #[cfg(feature = "cli-control")]
+    pub fn deadline_receiver(&self) -> watch::Receiver<Option<Instant>> {
+        self.deadline.subscribe()
+    }
+
+    /// Returns the history of recent state transitions, events and commands of the service.
+    pub fn history(&self) -> History {
+        self.history.clone()
+    }
+
+    /// Returns the totals of the powered time of the adapter.
+    #[cfg(feature = "cli-control")]
+    pub fn stats(&self) -> PowerStats {
+        self.stats.clone()
     }
 
     /// Starts the main event loop for the service.
     ///
-    /// This method will run indefinitely, waiting for and processing `BluetoothEvent`s.
-    /// It requires a receiver to have been subscribed via `subscribe_to`.
+    /// This method will run indefinitely, waiting for and processing `BluetoothEvent`s (published
+    /// since the service was created) and `BluetoothServiceCommand`s.
     pub async fn start(&mut self) -> Result<()> {
-        if self.rx.is_none() {
-            return Err(anyhow::anyhow!(
-                "Cannot start BluetoothService without a subscribed receiver"
-            ));
-        }
+        let mut command_rx = self
+            .command_rx
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("BluetoothService has already been started"))?;
+        let mut inhibited = self.inhibited.clone();
+        let mut airplane_mode = self.airplane_mode.clone();
+        let blackout = &Conf::instance().blackout;
 
-        let mut rx = self.rx.take().unwrap();
         loop {
-            let event = rx.recv().await?;
-            tracing::info!("BluetoothService received event: {:#?}", event);
-
-            match event {
-                BluetoothEvent::AdapterOn => {
+            let event = tokio::select! {
+                event = self.events.recv() => match event {
+                    // Ticks only inform observers of the countdown (e.g., the control interface)
+                    Some(BluetoothEvent::TimerTick { .. }) => continue,
+                    Some(event) => event,
+                    None => return Err(anyhow::anyhow!("The event bus of the adapter is gone")),
+                },
+                Some(command) = command_rx.recv() => {
+                    info!("BluetoothService received command: {:?}", command);
+                    self.history.record(format!("Command: {:?}", command));
+                    let _ = self
+                        .on_command(command)
+                        .await
+                        .inspect_err(|e| error!("Error on command: {:#?}", e.backtrace()));
+                    continue;
+                }
+                Some(inhibited) = watched_changed(&mut inhibited) => {
+                    self.on_inhibition_changed(inhibited);
+                    continue;
+                }
+                Some(on) = watched_changed(&mut airplane_mode) => {
+                    let _ = self
+                        .step(Input::AirplaneModeChanged(on))
+                        .await
+                        .inspect_err(|e| {
+                            error!("Error on airplane mode change: {:#?}", e.backtrace())
+                        });
+                    continue;
+                }
+                Some(active) = blackout::changed(blackout, self.fsm.blackout()) => {
                     let _ = self
-                        .on_adapter_on()
+                        .step(Input::BlackoutChanged(active))
                         .await
-                        .inspect_err(|e| error!("Error on AdapterOn event: {:#?}", e.backtrace()));
+                        .inspect_err(|e| error!("Error on blackout change: {:#?}", e.backtrace()));
+                    continue;
                 }
-                BluetoothEvent::AdapterOff => {
+                Some(()) = keep_on_expired(self.keep_on_until) => {
                     let _ = self
-                        .on_adapter_off()
+                        .on_keep_on_expired()
                         .await
-                        .inspect_err(|e| error!("Error on AdapterOff event: {:#?}", e.backtrace()));
+                        .inspect_err(|e| error!("Error on end of keep-on: {:#?}", e.backtrace()));
+                    continue;
                 }
-                BluetoothEvent::InterfaceAdded => {
-                    let _ = self.on_interface_added().await.inspect_err(|e| {
-                        error!("Error on InterfaceAdded event: {:#?}", e.backtrace())
+                Some(()) = self.wake_timer.expired() => {
+                    self.wake_timer.cancel();
+                    let _ = self
+                        .step(Input::WakeTimerExpired)
+                        .await
+                        .inspect_err(|e| error!("Error on wake timer: {:#?}", e.backtrace()));
+                    continue;
+                }
+            };
+            self.history.record(match event.device() {
+                Some(device) => format!("Event: {} ({})", event.kind(), device.display_name()),
+                None => format!("Event: {}", event.kind()),
+            });
+            // Handling the event continues the trace of the signal that caused it, if any
+            let kind = event.kind();
+            let stamp = self
+                .backend
+                .latency()
+                .and_then(|latency| latency.take(kind));
+            let span = info_span!(
+                parent: stamp.as_ref().and_then(|stamp| stamp.span.id()),
+                "bluetooth_event",
+                event = kind,
+                adapter = self.backend.adapter_name(),
+                device = event.device().map(BluetoothDevice::display_name),
+            );
+            self.on_event(event).instrument(span).await;
+            if let (Some(latency), Some(stamp)) = (self.backend.latency(), stamp) {
+                latency.handled(kind, &stamp);
+            }
+        }
+    }
+
+    /// Handles a single `BluetoothEvent`, logging errors of the handler.
+    async fn on_event(&mut self, event: BluetoothEvent) {
+        tracing::info!("BluetoothService received event: {:#?}", event);
+
+        match event {
+            BluetoothEvent::AdapterOn => {
+                let _ = self
+                    .on_adapter_on()
+                    .await
+                    .inspect_err(|e| error!("Error on AdapterOn event: {:#?}", e.backtrace()));
+            }
+            BluetoothEvent::AdapterOff => {
+                let _ = self
+                    .on_adapter_off()
+                    .await
+                    .inspect_err(|e| error!("Error on AdapterOff event: {:#?}", e.backtrace()));
+            }
+            BluetoothEvent::AdapterRemoved => {
+                let _ = self
+                    .on_adapter_removed()
+                    .await
+                    .inspect_err(|e| error!("Error on AdapterRemoved event: {:#?}", e.backtrace()));
+            }
+            BluetoothEvent::AdapterAdded { powered } => {
+                let _ = self
+                    .on_adapter_added(powered)
+                    .await
+                    .inspect_err(|e| error!("Error on AdapterAdded event: {:#?}", e.backtrace()));
+            }
+            BluetoothEvent::InterfaceAdded => {
+                let _ = self
+                    .on_interface_added()
+                    .await
+                    .inspect_err(|e| error!("Error on InterfaceAdded event: {:#?}", e.backtrace()));
+            }
+            BluetoothEvent::InterfaceRemoved => {
+                let _ = self.on_interface_removed().await.inspect_err(|e| {
+                    error!("Error on InterfaceRemoved event: {:#?}", e.backtrace())
+                });
+            }
+            BluetoothEvent::DeviceConnected(device) => {
+                let _ = self.on_device_connected(&device).await.inspect_err(|e| {
+                    error!("Error on DeviceConnected event: {:#?}", e.backtrace())
+                });
+            }
+            BluetoothEvent::DeviceDisconnected(device) => {
+                let _ = self.on_device_disconnected(&device).await.inspect_err(|e| {
+                    error!("Error on DeviceDisconnected event: {:#?}", e.backtrace())
+                });
+            }
+            BluetoothEvent::FlagChanged(flag, value) => self.on_flag_changed(flag, value),
+            BluetoothEvent::DiscoveringChanged(discovering) => {
+                debug!("Adapter discovering: {}", discovering);
+            }
+            BluetoothEvent::DeviceOutOfRange(device) => {
+                let _ = self.on_device_out_of_range(&device).await.inspect_err(|e| {
+                    error!("Error on DeviceOutOfRange event: {:#?}", e.backtrace())
+                });
+            }
+            BluetoothEvent::DeviceInRange(device) => {
+                let _ = self
+                    .on_device_in_range(&device)
+                    .await
+                    .inspect_err(|e| error!("Error on DeviceInRange event: {:#?}", e.backtrace()));
+            }
+            BluetoothEvent::TetheringChanged(device, tethering) => {
+                let _ = self
+                    .on_tethering_changed(&device, tethering)
+                    .await
+                    .inspect_err(|e| {
+                        error!("Error on TetheringChanged event: {:#?}", e.backtrace())
                     });
+            }
+            BluetoothEvent::DeviceDormant(device) => {
+                let _ = self
+                    .on_device_dormant(&device)
+                    .await
+                    .inspect_err(|e| error!("Error on DeviceDormant event: {:#?}", e.backtrace()));
+            }
+            BluetoothEvent::TimerTick { .. } | BluetoothEvent::BeforePowerOff { .. } => {}
+        }
+    }
+
+    /// Pauses the active timeout timer while the timeout is inhibited (an application inhibits
+    /// idling, a configured process runs or a file transfer is in progress), and resumes it once
+    /// the inhibition ends (unless airplane mode is on), so the countdown continues where it left
+    /// off.
+    fn on_inhibition_changed(&mut self, inhibited: bool) {
+        let Some(timer) = self.active_timer.as_ref().filter(|t| !t.is_finished()) else {
+            return;
+        };
+
+        if inhibited {
+            timer.pause();
+        } else if !self.fsm.airplane_mode() {
+            timer.resume();
+        }
+    }
+
+    /// Feeds `input` to the state machine and applies the resulting effects.
+    ///
+    /// A timer that finished on its own (e.g., after its last stage) is reported to the state
+    /// machine first, so it can start a new one.
+    #[instrument(name = "state_transition", skip(self), fields(from = ?self.fsm.state(), to))]
+    async fn step(&mut self, input: Input) -> Result<()> {
+        let automatically = input
+            == Input::AdapterOff {
+                automatically: true,
+            };
+        if self.active_timer.as_ref().is_some_and(|t| t.is_finished()) {
+            self.active_timer = None;
+            self.fsm.timer_finished();
+        }
+
+        let previous = self.fsm.state();
+        let effects = self.fsm.step(input);
+        Span::current().record("to", field::debug(self.fsm.state()));
+        if self.fsm.state() != previous {
+            info!(
+                "BluetoothService state: {:?} -> {:?}",
+                previous,
+                self.fsm.state()
+            );
+            self.history
+                .record(format!("State: {:?} -> {:?}", previous, self.fsm.state()));
+            self.state_tx.send_replace(self.fsm.state());
+            self.record_power(automatically);
+        }
+
+        for effect in effects {
+            self.apply(effect).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Records whether the adapter is powered in the statistics, `automatically` turned off by the
+    /// timeout, and in the saved power state, and sends the weekly summary if it is due and
+    /// enabled.
+    fn record_power(&self, automatically: bool) {
+        self.stats
+            .set_powered(self.fsm.state().is_powered(), automatically);
+        // A detached adapter was not turned off
+        if let Some(saved_power) = &self.saved_power
+            && self.fsm.state() != BluetoothServiceState::Detached
+        {
+            saved_power.save(self.fsm.state().is_powered());
+        }
+
+        #[cfg(feature = "notifications")]
+        {
+            let notifications = Conf::instance().notifications();
+            if !notifications.enabled || !notifications.weekly_summary {
+                return;
+            }
+            let Some(summary) = self.stats.weekly_summary() else {
+                return;
+            };
+
+            let adapter = self.backend.adapter_name().to_string();
+            tokio::spawn(async move {
+                if let Err(e) = Notification::weekly_summary(&adapter, &summary)
+                    .send()
+                    .await
+                {
+                    warn!("Could not send the weekly summary: {}", e);
                 }
-                BluetoothEvent::InterfaceRemoved => {
-                    let _ = self.on_interface_removed().await.inspect_err(|e| {
-                        error!("Error on InterfaceRemoved event: {:#?}", e.backtrace())
+            });
+        }
+    }
+
+    /// Applies a single `effect` requested by the state machine.
+    #[instrument(name = "action", skip(self))]
+    async fn apply(&mut self, effect: Effect) -> Result<()> {
+        debug!("Applying {:?}", effect);
+
+        match effect {
+            Effect::StartTimer {
+                timeout,
+                announce,
+                device,
+            } => {
+                self.active_timer = Some(
+                    self.spawn_timer(self.timeout_task(timeout).announce(announce).device(device)),
+                );
+            }
+            Effect::CancelTimer => self.cancel_timer(),
+            #[cfg(feature = "notifications")]
+            Effect::CancelTimerOnConnect { device } => self.cancel_timer_on_connect(device),
+            #[cfg(not(feature = "notifications"))]
+            Effect::CancelTimerOnConnect { .. } => self.cancel_timer(),
+            Effect::ReleaseTimer => {
+                if let Some(timer) = self.active_timer.take() {
+                    tokio::spawn(async move {
+                        // Give some time for the timer to abort gracefully
+                        tokio::time::sleep(Duration::from_secs(3)).await;
+                        if !timer.is_finished() {
+                            timer.abort();
+                            info!("Cancelled active timeout timer.");
+                            #[cfg(feature = "notifications")]
+                            if let Some(id) = timer.notification_id() {
+                                withdraw_notification(id).await;
+                            }
+                        }
                     });
                 }
             }
+            Effect::SnoozeTimer(duration) => {
+                let snoozed = self
+                    .active_timer
+                    .as_ref()
+                    .is_some_and(|timer| !timer.is_finished() && timer.snooze(duration));
+
+                if !snoozed {
+                    self.active_timer = Some(self.spawn_timer(self.timeout_task(duration)));
+                }
+            }
+            Effect::AccelerateTimer(duration) => {
+                let accelerated = self
+                    .active_timer
+                    .as_ref()
+                    .is_some_and(|timer| !timer.is_finished() && timer.accelerate(duration));
+
+                if !accelerated {
+                    self.active_timer = Some(self.spawn_timer(self.timeout_task(duration)));
+                }
+            }
+            Effect::PauseTimer => {
+                if let Some(timer) = self.active_timer.as_ref().filter(|t| !t.is_finished()) {
+                    timer.pause();
+                }
+            }
+            Effect::ResumeTimer => {
+                if let Some(timer) = self.active_timer.as_ref().filter(|t| !t.is_finished())
+                    && !self.is_inhibited()
+                {
+                    timer.resume();
+                }
+            }
+            Effect::PowerOff => self.backend.power_off().await?,
+            Effect::PowerOn => self.backend.power_on().await?,
+            // A window too long to have an end lasts until it is ended early
+            Effect::StartKeepOn(duration) => {
+                self.keep_on_until = Instant::now().checked_add(duration)
+            }
+            Effect::CancelKeepOn => self.keep_on_until = None,
+            Effect::AutoConnect => self.auto_connect(),
+            Effect::CancelFlagTimers => {
+                for (_, timer) in self.flag_timers.drain() {
+                    timer.abort();
+                }
+            }
+            #[cfg(feature = "notifications")]
+            Effect::NotifyBlackout => self.notify_blackout(),
+            #[cfg(not(feature = "notifications"))]
+            Effect::NotifyBlackout => {}
+            Effect::StartWakeTimer(after) => self.wake_timer.start(after),
+            Effect::CancelWakeTimer => self.wake_timer.cancel(),
+        }
+
+        Ok(())
+    }
+
+    /// Handles the end of the window the adapter was kept on for, returning to the regular
+    /// timeout.
+    pub async fn on_keep_on_expired(&mut self) -> Result<()> {
+        self.keep_on_until = None;
+        self.step(Input::KeepOnExpired).await
+    }
+
+    /// Handles a `BluetoothServiceCommand`.
+    ///
+    /// Commands only affect an idle adapter; they are ignored while devices are connected or the
+    /// adapter is off. A change of the settings and keeping the adapter on for a while apply in
+    /// every state.
+    pub async fn on_command(&mut self, command: BluetoothServiceCommand) -> Result<()> {
+        if command == BluetoothServiceCommand::SettingsChanged {
+            let conf = Conf::instance();
+            return self
+                .step(Input::SettingsChanged {
+                    timeouts: conf.timeouts(),
+                    announce: conf.notifications().on_start,
+                })
+                .await;
+        }
+
+        self.step(Input::Command(command)).await
+    }
+
+    /// Spawns a task that connects the devices listed in `auto_connect`, if any.
+    ///
+    /// Devices that are already connected or unknown to the adapter are skipped.
+    fn auto_connect(&self) {
+        let patterns = &Conf::instance().auto_connect;
+        if patterns.is_empty() {
+            return;
+        }
+
+        let backend = self.backend.clone();
+        tokio::spawn(async move {
+            let devices = backend
+                .devices()
+                .await
+                .inspect_err(|e| warn!("Could not list devices to auto-connect: {}", e))
+                .unwrap_or_default();
+
+            for pattern in patterns {
+                let Some(device) = devices.iter().find(|device| device.matches(pattern)) else {
+                    warn!(
+                        "Device '{}' to auto-connect is not known to the adapter.",
+                        pattern
+                    );
+                    continue;
+                };
+                if device.connected {
+                    continue;
+                }
+
+                info!("Auto-connecting device {}.", device.display_name());
+                let _ = backend.connect_device(device).await.inspect_err(|e| {
+                    warn!("Failed to connect device {}: {}", device.display_name(), e)
+                });
+            }
+        });
+    }
+
+    /// Aborts the active timeout timer, if any, and withdraws its outstanding notification (e.g.,
+    /// a warning), as it is outdated.
+    fn cancel_timer(&mut self) {
+        #[cfg(feature = "notifications")]
+        if let Some(id) = self
+            .active_timer
+            .as_ref()
+            .filter(|t| !t.is_finished())
+            .and_then(TimeoutHandle::notification_id)
+        {
+            tokio::spawn(withdraw_notification(id));
+        }
+        self.abort_timer();
+    }
+
+    /// Aborts the active timeout timer, if any, leaving its notifications alone.
+    fn abort_timer(&mut self) {
+        if let Some(timer) = self.active_timer.take()
+            && !timer.is_finished()
+        {
+            let remaining = timer.remaining();
+            timer.abort();
+            self.deadline.send_replace(None);
+            match remaining {
+                Some(remaining) => info!(
+                    "Cancelled active timeout timer, {} before its deadline.",
+                    humantime::format_duration(remaining)
+                ),
+                None => info!("Cancelled active timeout timer."),
+            }
+        }
+    }
+
+    /// Aborts the active timeout timer because a device connected.
+    ///
+    /// Outstanding warning notifications of the timer are withdrawn, or replaced by a
+    /// "timer cancelled" notification (naming `device_name`, if known) if
+    /// `notifications.on_cancel` is enabled.
+    #[cfg(feature = "notifications")]
+    fn cancel_timer_on_connect(&mut self, device_name: Option<String>) {
+        let Some(timer) = self.active_timer.as_ref().filter(|t| !t.is_finished()) else {
+            self.active_timer = None;
+            return;
+        };
+        let notification_id = timer.notification_id();
+        self.abort_timer();
+
+        let conf = Conf::instance();
+        let adapter = self.backend.adapter_name().to_string();
+        tokio::spawn(async move {
+            let result = if conf.notifications().enabled && conf.notifications().on_cancel {
+                Notification::countdown_cancelled(&adapter, device_name.as_deref())
+                    .replaces_id(notification_id.unwrap_or(0))
+                    .send()
+                    .await
+                    .map(|_| ())
+            } else if let Some(id) = notification_id {
+                Notification::close(id).await
+            } else {
+                Ok(())
+            };
+
+            if let Err(e) = result {
+                warn!("Could not withdraw timeout notification: {}", e);
+            }
+        });
+    }
+
+    /// Notifies the user that the adapter was turned off because of the active blackout period,
+    /// naming when it ends.
+    #[cfg(feature = "notifications")]
+    fn notify_blackout(&self) {
+        let conf = Conf::instance();
+        let Some(period) = blackout::active(&conf.blackout, chrono::Local::now().time()) else {
+            return;
+        };
+        if !conf.notifications().enabled {
+            return;
         }
+
+        let adapter = self.backend.adapter_name().to_string();
+        let until = period.to.format("%H:%M").to_string();
+        tokio::spawn(async move {
+            if let Err(e) = Notification::blackout(&adapter, &until).send().await {
+                warn!("Could not send the blackout notification: {}", e);
+            }
+        });
     }
 
     /// Handles the `AdapterOn` event.
@@ -161,31 +911,12 @@ impl BluetoothService {
     pub async fn on_adapter_on(&mut self) -> Result<()> {
         debug!("Handling AdapterOn event...");
 
-        match self.state {
-            BluetoothServiceState::Off | BluetoothServiceState::Idle
-                if self.active_timer.is_none()
-                    || self.active_timer.as_ref().unwrap().is_finished() =>
-            {
-                self.active_timer =
-                    Some(TimeoutTask::new(self.timeout, self.service_proxy.clone()).spawn());
-            }
-            BluetoothServiceState::Running
-                if self.active_timer.is_some()
-                    && !self.active_timer.as_ref().unwrap().is_finished() =>
-            {
-                self.active_timer.take().unwrap().abort();
-                info!("Cancelled active timeout timer.");
-            }
-            _ => {}
-        }
-
-        if self.get_connected_devices_count().await > 0 {
-            self.state = BluetoothServiceState::Running;
-        } else {
-            self.state = BluetoothServiceState::Idle;
-        }
-
-        Ok(())
+        let connected = self
+            .get_connected_devices_count()
+            .await
+            .inspect_err(|e| warn!("{}; assuming no devices are connected.", e))
+            .unwrap_or(0);
+        self.step(Input::AdapterOn { connected }).await
     }
 
     /// Handles the `AdapterOff` event.
@@ -194,69 +925,665 @@ impl BluetoothService {
     pub async fn on_adapter_off(&mut self) -> Result<()> {
         debug!("Handling AdapterOff event...");
 
-        if self.active_timer.is_some() {
-            tokio::spawn({
-                let timer = self.active_timer.take().unwrap();
-
-                async move {
-                    // Give some time for the timer to abort gracefully
-                    tokio::time::sleep(Duration::from_secs(3)).await;
-                    if !timer.is_finished() {
-                        timer.abort();
-                        info!("Cancelled active timeout timer.");
-                    }
-                }
-            });
-        }
+        // The adapter turning off once the deadline has passed was the timeout's doing
+        let automatically = self
+            .active_timer
+            .as_ref()
+            .is_some_and(|t| t.is_finished() || t.remaining() == Some(Duration::ZERO));
+        self.step(Input::AdapterOff { automatically }).await
+    }
 
-        self.state = BluetoothServiceState::Off;
-        Ok(())
+    /// Handles the `AdapterRemoved` event, detaching the service from the adapter until it
+    /// returns.
+    pub async fn on_adapter_removed(&mut self) -> Result<()> {
+        debug!("Handling AdapterRemoved event...");
+
+        self.step(Input::AdapterRemoved).await
+    }
+
+    /// Handles the `AdapterAdded` event, picking up the adapter again in its current state.
+    pub async fn on_adapter_added(&mut self, powered: bool) -> Result<()> {
+        debug!("Handling AdapterAdded event...");
+
+        let connected = if powered {
+            self.get_connected_devices_count()
+                .await
+                .inspect_err(|e| warn!("{}; assuming no devices are connected.", e))
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        self.step(Input::AdapterAdded { powered, connected }).await
     }
 
     /// Handles the `InterfaceAdded` event, which typically signifies a device connection.
     pub async fn on_interface_added(&mut self) -> Result<()> {
         debug!("Handling InterfaceAdded event...");
 
-        self.on_interface_changed().await
+        self.on_interface_changed(None).await
     }
 
     /// Handles the `InterfaceRemoved` event, which typically signifies a device disconnection.
     pub async fn on_interface_removed(&mut self) -> Result<()> {
         debug!("Handling InterfaceRemoved event...");
 
-        self.on_interface_changed().await
+        self.on_interface_changed(None).await
+    }
+
+    /// Handles the `DeviceConnected` event.
+    pub async fn on_device_connected(&mut self, device: &BluetoothDevice) -> Result<()> {
+        debug!("Handling DeviceConnected event...");
+        info!("{} connected.", device.display_name());
+
+        self.on_interface_changed(Some(device)).await
+    }
+
+    /// Handles the `DeviceDisconnected` event.
+    pub async fn on_device_disconnected(&mut self, device: &BluetoothDevice) -> Result<()> {
+        debug!("Handling DeviceDisconnected event...");
+        info!("{} disconnected.", device.display_name());
+        self.out_of_range.remove(&device.object_path);
+        self.dormant.remove(&device.object_path);
+
+        self.on_interface_changed(Some(device)).await
+    }
+
+    /// Handles the `FlagChanged` event.
+    ///
+    /// Setting a flag (re)starts its timer, clearing it cancels the timer.
+    pub fn on_flag_changed(&mut self, flag: AdapterFlag, value: bool) {
+        debug!(
+            "Handling FlagChanged event: {} = {}",
+            flag.property(),
+            value
+        );
+
+        if let Some(timer) = self.flag_timers.remove(&flag) {
+            timer.abort();
+        }
+        if value && let Some(timer) = flag::spawn_timer(flag, self.backend.clone()) {
+            self.flag_timers.insert(flag, timer);
+        }
+    }
+
+    /// Handles the `DeviceOutOfRange` event.
+    ///
+    /// The device is no longer counted as connected until it is back in range or reconnects.
+    pub async fn on_device_out_of_range(&mut self, device: &BluetoothDevice) -> Result<()> {
+        debug!("Handling DeviceOutOfRange event...");
+        info!(
+            "{} is out of range, treating it as disconnected.",
+            device.display_name()
+        );
+        self.out_of_range.insert(device.object_path.clone());
+
+        self.on_interface_changed(Some(device)).await
+    }
+
+    /// Handles the `DeviceInRange` event.
+    pub async fn on_device_in_range(&mut self, device: &BluetoothDevice) -> Result<()> {
+        debug!("Handling DeviceInRange event...");
+        info!("{} is back in range.", device.display_name());
+        self.out_of_range.remove(&device.object_path);
+
+        self.on_interface_changed(Some(device)).await
+    }
+
+    /// Handles the `TetheringChanged` event.
+    ///
+    /// A tethering device always counts as connected, as powering off the adapter would cut the
+    /// network connection.
+    pub async fn on_tethering_changed(
+        &mut self,
+        device: &BluetoothDevice,
+        tethering: bool,
+    ) -> Result<()> {
+        debug!("Handling TetheringChanged event...");
+        info!(
+            "{} {} tethering.",
+            device.display_name(),
+            if tethering { "started" } else { "stopped" }
+        );
+
+        self.on_interface_changed(Some(device)).await
+    }
+
+    /// Handles the `DeviceDormant` event.
+    ///
+    /// The device is no longer counted as connected until it reconnects.
+    pub async fn on_device_dormant(&mut self, device: &BluetoothDevice) -> Result<()> {
+        debug!("Handling DeviceDormant event...");
+        info!(
+            "{} has been connected for long, treating it as disconnected.",
+            device.display_name()
+        );
+        self.dormant.insert(device.object_path.clone());
+
+        self.on_interface_changed(Some(device)).await
     }
 
     /// Handles changes in device connections.
     ///
     /// This method checks the number of connected devices and updates the service state
-    /// and timeout timer accordingly.
-    async fn on_interface_changed(&mut self) -> Result<()> {
-        let connected_devices = self.get_connected_devices_count().await;
-        debug!("Connected devices count: {}", connected_devices);
-
-        if connected_devices > 0 {
-            if let Some(timer) = self.active_timer.take() {
-                if !timer.is_finished() {
-                    timer.abort();
-                    info!("Cancelled active timeout timer.");
-                }
-            }
-            self.state = BluetoothServiceState::Running;
-        } else {
-            if self.active_timer.is_none() {
-                debug!("No connected devices and no active timer. Starting timeout timer...");
-                self.active_timer =
-                    Some(TimeoutTask::new(self.timeout, self.service_proxy.clone()).spawn());
+    /// and timeout timer accordingly. If known, the `device` that caused the change is named in
+    /// the start and cancel notifications.
+    ///
+    /// If BlueZ does not answer in time, the change is ignored: the devices counted before are
+    /// more likely to be right than none at all, and the next change is counted again.
+    async fn on_interface_changed(&mut self, device: Option<&BluetoothDevice>) -> Result<()> {
+        if self.fsm.state() == BluetoothServiceState::Detached {
+            debug!("Adapter is detached; ignoring the change of its devices.");
+            return Ok(());
+        }
+
+        let connected = match self.get_connected_devices_count().await {
+            Ok(connected) => connected,
+            Err(e) => {
+                warn!("{}; keeping the current state.", e);
+                return Ok(());
             }
-            self.state = BluetoothServiceState::Idle;
+        };
+        let device = device.and_then(|d| d.common_name.clone());
+
+        self.step(Input::DevicesChanged { connected, device }).await
+    }
+
+    /// Gets the current number of connected devices, not counting devices that are out of range,
+    /// dormant or untrusted with `trusted_only` (unless they are tethering). If the devices cannot
+    /// be listed, none are counted.
+    ///
+    /// # Errors
+    ///
+    /// - [`DbusTimeout`] if BlueZ does not list the devices in time.
+    async fn get_connected_devices_count(&self) -> Result<usize> {
+        let trusted_only = Conf::instance().trusted_only;
+        let devices = match self.backend.devices().await {
+            Ok(devices) => devices,
+            Err(e) if e.is::<DbusTimeout>() => return Err(e),
+            Err(_) => vec![],
+        };
+        Ok(devices
+            .iter()
+            .filter(|dev| {
+                counts_as_connected(dev, trusted_only, |path| {
+                    self.out_of_range.contains(path) || self.dormant.contains(path)
+                })
+            })
+            .count())
+    }
+}
+
+impl Drop for BluetoothService {
+    /// Stops the timers of the service, which would otherwise keep running on the adapter (e.g.,
+    /// after it was removed).
+    fn drop(&mut self) {
+        if let Some(timer) = self.active_timer.take() {
+            timer.abort();
         }
+        for timer in self.flag_timers.values() {
+            timer.abort();
+        }
+    }
+}
 
-        Ok(())
+#[cfg(test)]
+mod tests {
+    // -- std imports
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    // -- module imports
+    use super::*;
+    use crate::bluetooth::backend::mock::MockBackend;
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Collects the output of a log subscriber.
+    struct LogWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for LogWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
     }
 
-    /// Gets the current number of connected devices.
-    async fn get_connected_devices_count(&self) -> usize {
-        get_connected_devices_count_from_proxy(&self.service_proxy).await
+    /// Creates a service managing `backend` with [`TIMEOUT`].
+    async fn service(backend: &Arc<MockBackend>) -> BluetoothService {
+        Conf::for_tests();
+        BluetoothService::new(backend.clone(), Some(TIMEOUT.into()))
+            .await
+            .expect("service should be created")
+    }
+
+    /// Returns `true` if the service has a running timeout timer.
+    fn timer_armed(service: &BluetoothService) -> bool {
+        service
+            .active_timer
+            .as_ref()
+            .is_some_and(|timer| !timer.is_finished())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn starts_off_without_timer_if_adapter_is_off() {
+        let backend = MockBackend::new(false);
+        let service = service(&backend).await;
+
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Off);
+        assert!(!timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn starts_idle_with_timer_if_no_device_is_connected() {
+        let backend = MockBackend::new(true);
+        let service = service(&backend).await;
+
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Idle);
+        assert!(timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn starts_running_without_timer_if_a_device_is_connected() {
+        let backend = MockBackend::new(true);
+        backend.add_device("headset", true);
+        let service = service(&backend).await;
+
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Running);
+        assert!(!timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn connecting_a_device_cancels_the_timer() {
+        let backend = MockBackend::new(true);
+        let headset = backend.add_device("headset", false);
+        let mut service = service(&backend).await;
+
+        backend.connect(&headset);
+        service.on_device_connected(&headset).await.unwrap();
+
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Running);
+        assert!(!timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn disconnecting_the_last_device_arms_the_timer() {
+        let backend = MockBackend::new(true);
+        let headset = backend.add_device("headset", true);
+        let keyboard = backend.add_device("keyboard", true);
+        let mut service = service(&backend).await;
+
+        backend.disconnect(&headset);
+        service.on_device_disconnected(&headset).await.unwrap();
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Running);
+        assert!(!timer_armed(&service));
+
+        backend.disconnect(&keyboard);
+        service.on_device_disconnected(&keyboard).await.unwrap();
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Idle);
+        assert!(timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn disconnects_are_ignored_while_bluez_hangs() {
+        let backend = MockBackend::new(true);
+        let headset = backend.add_device("headset", true);
+        let mut service = service(&backend).await;
+
+        backend.set_hanging(true);
+        backend.disconnect(&headset);
+        service.on_device_disconnected(&headset).await.unwrap();
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Running);
+        assert!(!timer_armed(&service));
+
+        backend.set_hanging(false);
+        service.on_device_disconnected(&headset).await.unwrap();
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Idle);
+        assert!(timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn adapter_is_powered_off_after_the_timeout() {
+        let backend = MockBackend::new(true);
+        let service = service(&backend).await;
+
+        tokio::time::sleep(TIMEOUT - Duration::from_secs(1)).await;
+        assert!(backend.powered());
+        assert!(timer_armed(&service));
+
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert!(!backend.powered());
+        assert_eq!(backend.power_offs(), 1);
+
+        // the timer ends once the adapter has been checked to stay off
+        tokio::time::sleep(Conf::instance().power_off_verify).await;
+        assert!(!timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_countdown_does_not_fall_behind_its_own_ticks() {
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer({
+                let logs = logs.clone();
+                move || LogWriter(logs.clone())
+            })
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let backend = MockBackend::new(true);
+        let _service = service(&backend).await;
+
+        tokio::time::sleep(TIMEOUT + Duration::from_secs(1)).await;
+
+        assert!(!backend.powered());
+        let logs = String::from_utf8(logs.lock().unwrap().clone()).unwrap();
+        assert!(!logs.contains("fell behind"), "{}", logs);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn commands_apply_while_an_active_session_holds_the_countdown() {
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer({
+                let logs = logs.clone();
+                move || LogWriter(logs.clone())
+            })
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let backend = MockBackend::new(true);
+        let (session_idle, idle) = watch::channel(true);
+        let mut service = service(&backend).await;
+        service.abort_timer();
+        service.idle = Some(idle);
+        let timer = service.timeout_task(TIMEOUT).spawn();
+
+        session_idle.send_replace(false);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(timer.accelerate(Duration::from_secs(2)));
+        tokio::time::sleep(TIMEOUT).await;
+
+        let logs_so_far = String::from_utf8(logs.lock().unwrap().clone()).unwrap();
+        assert!(
+            logs_so_far.contains("Timeout accelerated"),
+            "{}",
+            logs_so_far
+        );
+        assert!(timer.remaining().is_none());
+        session_idle.send_replace(true);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(timer.remaining(), Some(Duration::from_millis(1900)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_device_connecting_at_the_last_moment_keeps_the_adapter_on() {
+        let backend = MockBackend::new(true);
+        let headset = backend.add_device("headset", false);
+        let service = service(&backend).await;
+
+        tokio::time::sleep(TIMEOUT - Duration::from_millis(100)).await;
+        // not handled by the service yet
+        backend.connect(&headset);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        assert!(backend.powered());
+        assert_eq!(backend.power_offs(), 0);
+        assert!(!timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn adapter_off_moves_to_off_and_adapter_on_rearms_the_timer() {
+        let backend = MockBackend::new(true);
+        let mut service = service(&backend).await;
+
+        service.on_adapter_off().await.unwrap();
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Off);
+        assert!(service.active_timer.is_none());
+
+        service.on_adapter_on().await.unwrap();
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Idle);
+        assert!(timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keep_on_cancels_the_timer_until_the_next_state_change() {
+        let backend = MockBackend::new(true);
+        let headset = backend.add_device("headset", false);
+        let mut service = service(&backend).await;
+
+        service
+            .on_command(BluetoothServiceCommand::KeepOn)
+            .await
+            .unwrap();
+        assert!(!timer_armed(&service));
+
+        tokio::time::sleep(TIMEOUT * 2).await;
+        assert!(backend.powered());
+
+        backend.connect(&headset);
+        service.on_device_connected(&headset).await.unwrap();
+        backend.disconnect(&headset);
+        service.on_device_disconnected(&headset).await.unwrap();
+        assert!(timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keep_on_for_powers_on_the_adapter_until_it_expires() {
+        let backend = MockBackend::new(false);
+        let mut service = service(&backend).await;
+
+        service
+            .on_command(BluetoothServiceCommand::KeepOnFor(TIMEOUT))
+            .await
+            .unwrap();
+        assert!(backend.powered());
+        service.on_adapter_on().await.unwrap();
+        assert!(!timer_armed(&service));
+
+        service.on_keep_on_expired().await.unwrap();
+        assert!(timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn snooze_postpones_the_timeout() {
+        let backend = MockBackend::new(true);
+        let mut service = service(&backend).await;
+
+        service
+            .on_command(BluetoothServiceCommand::Snooze(TIMEOUT))
+            .await
+            .unwrap();
+
+        tokio::time::sleep(TIMEOUT + Duration::from_secs(1)).await;
+        assert!(backend.powered());
+
+        tokio::time::sleep(TIMEOUT).await;
+        assert!(!backend.powered());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timer_reports_the_remaining_time() {
+        let backend = MockBackend::new(true);
+        let mut service = service(&backend).await;
+        let remaining = |service: &BluetoothService| service.active_timer.as_ref()?.remaining();
+        assert_eq!(remaining(&service), Some(TIMEOUT));
+
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(remaining(&service), Some(TIMEOUT - Duration::from_secs(10)));
+
+        service
+            .on_command(BluetoothServiceCommand::Snooze(TIMEOUT))
+            .await
+            .unwrap();
+        tokio::task::yield_now().await;
+        assert_eq!(
+            remaining(&service),
+            Some(2 * TIMEOUT - Duration::from_secs(10))
+        );
+
+        tokio::time::sleep(2 * TIMEOUT).await;
+        assert_eq!(remaining(&service), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn paused_timer_keeps_the_remaining_time() {
+        let backend = MockBackend::new(true);
+        let service = service(&backend).await;
+        let timer = service.active_timer.as_ref().unwrap();
+
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert!(timer.pause());
+        tokio::task::yield_now().await;
+        assert_eq!(timer.remaining(), None);
+
+        tokio::time::sleep(2 * TIMEOUT).await;
+        assert!(backend.powered());
+
+        assert!(timer.resume());
+        tokio::task::yield_now().await;
+        assert_eq!(timer.remaining(), Some(TIMEOUT - Duration::from_secs(10)));
+
+        tokio::time::sleep(TIMEOUT).await;
+        assert!(!backend.powered());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timer_emits_ticks() {
+        let backend = MockBackend::new(true);
+        let mut events = backend.events().subscribe("test");
+        let _service = service(&backend).await;
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let ticks: Vec<_> = std::iter::from_fn(|| events.try_recv()).collect();
+        assert_eq!(
+            ticks,
+            [
+                BluetoothEvent::TimerTick { remaining: TIMEOUT },
+                BluetoothEvent::TimerTick {
+                    remaining: TIMEOUT - Duration::from_secs(1)
+                },
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timer_announces_the_power_off() {
+        let backend = MockBackend::new(true);
+        let _service = service(&backend).await;
+        let before = Conf::instance().before_power_off;
+
+        // Subscribed late, so the ticks until then do not overflow the channel
+        tokio::time::sleep(TIMEOUT - before - Duration::from_secs(1)).await;
+        let mut events = backend.events().subscribe("test");
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        let announcements: Vec<_> = std::iter::from_fn(|| events.try_recv())
+            .filter(|event| matches!(event, BluetoothEvent::BeforePowerOff { .. }))
+            .collect();
+        assert_eq!(
+            announcements,
+            [BluetoothEvent::BeforePowerOff { remaining: before }]
+        );
+        assert!(backend.powered());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn commands_are_ignored_while_devices_are_connected() {
+        let backend = MockBackend::new(true);
+        backend.add_device("headset", true);
+        let mut service = service(&backend).await;
+
+        service
+            .on_command(BluetoothServiceCommand::TurnOffNow)
+            .await
+            .unwrap();
+
+        assert!(backend.powered());
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Running);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn out_of_range_devices_do_not_count_as_connected() {
+        let backend = MockBackend::new(true);
+        let headset = backend.add_device("headset", true);
+        let mut service = service(&backend).await;
+
+        service.on_device_out_of_range(&headset).await.unwrap();
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Idle);
+        assert!(timer_armed(&service));
+
+        service.on_device_in_range(&headset).await.unwrap();
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Running);
+        assert!(!timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dormant_devices_do_not_count_until_they_reconnect() {
+        let backend = MockBackend::new(true);
+        let watch = backend.add_device("watch", true);
+        let mut service = service(&backend).await;
+
+        service.on_device_dormant(&watch).await.unwrap();
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Idle);
+        assert!(timer_armed(&service));
+
+        backend.disconnect(&watch);
+        service.on_device_disconnected(&watch).await.unwrap();
+        backend.connect(&watch);
+        service.on_device_connected(&watch).await.unwrap();
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Running);
+        assert!(!timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tethering_devices_always_count_as_connected() {
+        let backend = MockBackend::new(true);
+        let phone = backend.add_device("phone", true);
+        let mut service = service(&backend).await;
+
+        backend.set_tethering(&phone, true);
+        service.on_tethering_changed(&phone, true).await.unwrap();
+        service.on_device_out_of_range(&phone).await.unwrap();
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Running);
+
+        backend.set_tethering(&phone, false);
+        service.on_tethering_changed(&phone, false).await.unwrap();
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Idle);
+        assert!(timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tethering_devices_count_as_connected_at_startup() {
+        let backend = MockBackend::new(true);
+        let phone = backend.add_device("phone", false);
+        backend.set_tethering(&phone, true);
+        let service = service(&backend).await;
+
+        assert_eq!(service.fsm.state(), BluetoothServiceState::Running);
+        assert!(!timer_armed(&service));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn inhibited_while_any_source_is() {
+        let (inhibitors_tx, inhibitors) = watch::channel(false);
+        let (transfers_tx, transfers) = watch::channel(false);
+        let mut inhibited = any_inhibited(vec![inhibitors, transfers]).unwrap();
+
+        inhibitors_tx.send_replace(true);
+        inhibited.changed().await.unwrap();
+        assert!(*inhibited.borrow_and_update());
+
+        transfers_tx.send_replace(true);
+        inhibitors_tx.send_replace(false);
+        tokio::task::yield_now().await;
+        assert!(!inhibited.has_changed().unwrap());
+
+        transfers_tx.send_replace(false);
+        inhibited.changed().await.unwrap();
+        assert!(!*inhibited.borrow_and_update());
     }
 }
@@ -1,5 +1,13 @@
+// -- std imports
+use std::time::Duration;
+
+// -- crate imports (conditional)
+#[cfg(feature = "mgmt-fallback")]
+use tracing::warn;
+
 // -- crate imports
 use anyhow::Result;
+use tracing::debug;
 use zbus::{
     Connection,
     fdo::{ObjectManagerProxy, PropertiesProxy},
@@ -8,7 +16,27 @@ use zbus::{
 };
 
 // -- module imports
-use crate::{bluetooth::device::BluetoothDevice, configuration::Conf};
+use crate::{
+    bluetooth::{
+        cache::DeviceCache, capabilities::AdapterCapabilities, device::BluetoothDevice,
+        flag::AdapterFlag, mgmt, retry, rfkill,
+    },
+    bus,
+    configuration::{Conf, PowerOffMethod},
+};
+
+/// A D-Bus call to BlueZ that did not complete within `dbus.call_timeout` and was cancelled.
+///
+/// Returned within an [`anyhow::Error`] by the methods of [`BluetoothServiceProxy`], so callers
+/// can tell a hanging `bluetoothd` from a failing one via [`anyhow::Error::is`].
+#[derive(Debug, thiserror::Error)]
+#[error("D-Bus call '{call}' timed out after {}", humantime::format_duration(*.after))]
+pub struct DbusTimeout {
+    /// Method or property the call was for (e.g., "Powered").
+    pub call: String,
+    /// Timeout the call exceeded.
+    pub after: Duration,
+}
 
 /// A proxy for interacting with the Bluetooth service via D-Bus.
 ///
@@ -20,6 +48,8 @@ pub struct BluetoothServiceProxy {
     pub iface: String,
     /// The current connection to the D-Bus.
     conn: Connection,
+    /// Devices returned by the last [`BluetoothServiceProxy::get_devices`] call.
+    cache: DeviceCache,
 }
 
 impl BluetoothServiceProxy {
@@ -28,7 +58,9 @@ impl BluetoothServiceProxy {
     /// # Arguments
     ///
     /// - `iface` - A string slice that holds the D-Bus object path of the Bluetooth adapter (e.g.,
-    ///     "/org/bluez/hci0").
+    ///   "/org/bluez/hci0").
+    /// - `cache` - The cache of [`BluetoothServiceProxy::get_devices`], shared with the observer
+    ///   that invalidates it.
     ///
     /// # Returns
     ///
@@ -38,10 +70,11 @@ impl BluetoothServiceProxy {
     /// # Errors
     ///
     /// - [`anyhow::Error`] if the connection to the system D-Bus cannot be established.
-    pub async fn new(iface: String) -> Result<Self> {
+    pub async fn new(iface: String, cache: DeviceCache) -> Result<Self> {
         Ok(Self {
             iface,
-            conn: Connection::system().await?,
+            conn: bus::system().await?,
+            cache,
         })
     }
 
@@ -56,29 +89,114 @@ impl BluetoothServiceProxy {
     /// # Errors
     ///
     /// - [`anyhow::Error`] if the D-Bus call fails or the property cannot be retrieved.
+    /// - [`DbusTimeout`] if BlueZ does not answer within `dbus.call_timeout`, on every attempt.
     pub async fn is_powered(&self) -> Result<bool> {
-        let conf = Conf::instance();
-        let proxy = PropertiesProxy::builder(&self.conn)
-            .destination(conf.dbus.service.as_str())?
-            .path(self.iface.as_str())?
-            .build()
-            .await?;
+        call("Powered", move || async move {
+            let conf = Conf::instance();
+            let proxy = PropertiesProxy::builder(&self.conn)
+                .destination(conf.dbus.service.as_str())?
+                .path(self.iface.as_str())?
+                .build()
+                .await?;
+
+            let powered = proxy
+                .get(
+                    InterfaceName::from_static_str(conf.dbus.adapter_iface.as_str())?,
+                    "Powered",
+                )
+                .await?
+                .downcast_ref::<bool>()?;
+
+            Ok(powered)
+        })
+        .await
+    }
+
+    /// Retrieves the alias of the adapter (which defaults to its `Name`, usually the hostname).
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the D-Bus call fails or the property cannot be retrieved.
+    /// - [`DbusTimeout`] if BlueZ does not answer within `dbus.call_timeout`, on every attempt.
+    pub async fn alias(&self) -> Result<String> {
+        call("Alias", move || async move {
+            let conf = Conf::instance();
+            let proxy = PropertiesProxy::builder(&self.conn)
+                .destination(conf.dbus.service.as_str())?
+                .path(self.iface.as_str())?
+                .build()
+                .await?;
+            let alias = proxy
+                .get(
+                    InterfaceName::from_static_str(conf.dbus.adapter_iface.as_str())?,
+                    "Alias",
+                )
+                .await?;
 
-        let powered = proxy
-            .get(
-                InterfaceName::from_static_str(conf.dbus.adapter_iface.as_str())?,
-                "Powered",
-            )
-            .await?
-            .downcast_ref::<bool>()?;
+            Ok(String::try_from(alias)?)
+        })
+        .await
+    }
+
+    /// Retrieves what the controller of the adapter supports: BR/EDR from the settings the kernel
+    /// reports the controller to support (see [`mgmt::supports_bredr`]), LE from its `Roles`. BlueZ
+    /// versions without `Roles` (before 5.53) report no LE roles.
+    ///
+    /// If the kernel cannot be asked (e.g., without Bluetooth support in the kernel of a
+    /// container), BR/EDR support is guessed from the adapter's `Class`, which is 0 on controllers
+    /// without it.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the D-Bus call fails or the properties cannot be retrieved.
+    /// - [`DbusTimeout`] if BlueZ does not answer within `dbus.call_timeout`, on every attempt.
+    pub async fn capabilities(&self) -> Result<AdapterCapabilities> {
+        let (class, roles) = call("GetAll", move || async move {
+            let conf = Conf::instance();
+            let proxy = PropertiesProxy::builder(&self.conn)
+                .destination(conf.dbus.service.as_str())?
+                .path(self.iface.as_str())?
+                .build()
+                .await?;
+            let properties = proxy
+                .get_all(InterfaceName::from_static_str(
+                    conf.dbus.adapter_iface.as_str(),
+                )?)
+                .await?;
+
+            let class = properties
+                .get("Class")
+                .ok_or_else(|| anyhow::anyhow!("Adapter has no 'Class'"))?
+                .downcast_ref::<u32>()?;
+            let roles = match properties.get("Roles") {
+                Some(roles) => Vec::<String>::try_from(roles.try_clone()?)?,
+                None => Vec::new(),
+            };
+
+            Ok((class, roles))
+        })
+        .await?;
 
-        Ok(powered)
+        let bredr = mgmt::supports_bredr(self.hci())
+            .await
+            .inspect_err(|e| {
+                debug!(
+                    "Could not read the supported settings of {} ({:#}); guessing from its class.",
+                    self.hci(),
+                    e
+                )
+            })
+            .unwrap_or(class != 0);
+        Ok(AdapterCapabilities::new(bredr, roles))
     }
 
     /// Retrieves a list of Bluetooth devices associated with this adapter.
     ///
-    /// This method queries the ObjectManager for all managed objects and filters them
-    /// to find devices that belong to the current adapter interface.
+    /// This method queries the ObjectManager for all managed objects and filters them to find
+    /// devices that belong to the current adapter interface. The properties of each device,
+    /// including its battery level and tethering state, are taken from the same reply, so there are
+    /// no per-device calls to make (or to run concurrently). The result is cached until the
+    /// observer reports a change or `dbus.device_cache_ttl` passes.
     ///
     /// # Returns
     ///
@@ -88,48 +206,84 @@ impl BluetoothServiceProxy {
     /// # Errors
     ///
     /// - [`anyhow::Error`] if the D-Bus call fails or the objects cannot be retrieved.
+    /// - [`DbusTimeout`] if BlueZ does not answer within `dbus.call_timeout`, on every attempt.
     pub async fn get_devices(&self) -> Result<Vec<BluetoothDevice>> {
-        let conf = Conf::instance();
-        let proxy = ObjectManagerProxy::builder(&self.conn)
-            .destination(conf.dbus.service.as_str())?
-            .path("/")?
-            .build()
-            .await?;
-
-        let objects = proxy.get_managed_objects().await?;
-        let mut devices = vec![];
-
-        for (path, ifaces) in objects {
-            let props = match ifaces.get(conf.dbus.device_iface.as_str()) {
-                Some(p) => p,
-                None => continue,
-            };
-
-            let path_str = path.to_string();
-            if !path_str.starts_with(&format!("{}/dev_", self.iface)) {
-                continue;
+        call("GetManagedObjects", move || async move {
+            if let Some(devices) = self.cache.get() {
+                return Ok(devices);
             }
 
-            let name = props.get("Name").map(|v| v.to_string());
-            let connected = props
-                .get("Connected")
-                .map(|v| v.downcast_ref::<bool>().ok())
-                .flatten()
-                .unwrap_or(false);
-
-            devices.push(BluetoothDevice {
-                object_path: path_str,
-                common_name: name,
-                connected,
-            });
-        }
+            let conf = Conf::instance();
+            let generation = self.cache.generation();
+            let proxy = ObjectManagerProxy::builder(&self.conn)
+                .destination(conf.dbus.service.as_str())?
+                .path("/")?
+                .build()
+                .await?;
+
+            let objects = proxy.get_managed_objects().await?;
+            let mut devices = vec![];
+
+            for (path, ifaces) in objects {
+                let props = match ifaces.get(conf.dbus.device_iface.as_str()) {
+                    Some(p) => p,
+                    None => continue,
+                };
+
+                let path_str = path.to_string();
+                if !path_str.starts_with(&format!("{}/dev_", self.iface)) {
+                    continue;
+                }
+
+                let string = |name| {
+                    props
+                        .get(name)
+                        .and_then(|v| String::try_from(v.clone()).ok())
+                };
+                let flag = |name| {
+                    props
+                        .get(name)
+                        .and_then(|v| v.downcast_ref::<bool>().ok())
+                        .unwrap_or(false)
+                };
 
-        Ok(devices)
+                let battery = ifaces
+                    .get(conf.dbus.battery_iface.as_str())
+                    .and_then(|battery| battery.get("Percentage"))
+                    .and_then(|v| v.downcast_ref::<u8>().ok());
+                let tethering = ifaces
+                    .get(conf.dbus.network_iface.as_str())
+                    .and_then(|network| network.get("Connected"))
+                    .and_then(|v| v.downcast_ref::<bool>().ok())
+                    .unwrap_or(false);
+
+                let device = BluetoothDevice::new(path_str);
+                devices.push(BluetoothDevice {
+                    address: string("Address").or(device.address.clone()),
+                    common_name: string("Alias").or_else(|| string("Name")),
+                    connected: flag("Connected"),
+                    paired: flag("Paired"),
+                    trusted: flag("Trusted"),
+                    rssi: props.get("RSSI").and_then(|v| v.downcast_ref::<i16>().ok()),
+                    icon: string("Icon"),
+                    battery,
+                    tethering,
+                    ..device
+                });
+            }
+
+            self.cache.set(generation, devices.clone());
+            Ok(devices)
+        })
+        .await
     }
 
     /// Turns off the Bluetooth adapter.
     ///
-    /// This method sets the "Powered" property of the adapter interface to `false` via D-Bus.
+    /// Depending on `power_off`, this method either sets the "Powered" property of the adapter
+    /// interface to `false` via D-Bus, or soft-blocks the adapter via rfkill. If setting "Powered"
+    /// fails and `mgmt_fallback` is set, the adapter is powered off through the kernel's
+    /// management API instead (with the `mgmt-fallback` feature).
     ///
     /// # Returns
     ///
@@ -138,22 +292,226 @@ impl BluetoothServiceProxy {
     /// # Errors
     ///
     /// - [`anyhow::Error`] if the D-Bus call fails or the property cannot be set.
+    /// - [`anyhow::Error`] if the adapter cannot be soft-blocked.
+    /// - [`anyhow::Error`] if the management API fallback fails (e.g., without `CAP_NET_ADMIN`).
+    /// - [`DbusTimeout`] if BlueZ does not answer within `dbus.call_timeout`, on every attempt.
     pub async fn turn_off_adapter(&self) -> Result<()> {
-        let conf = Conf::instance();
-        let proxy = PropertiesProxy::builder(&self.conn)
-            .destination(conf.dbus.service.as_str())?
-            .path(self.iface.as_str())?
-            .build()
-            .await?;
-
-        proxy
-            .set(
-                InterfaceName::from_static_str(conf.dbus.adapter_iface.as_str())?,
-                "Powered",
-                Value::Bool(false),
-            )
-            .await?;
-
-        Ok(())
+        match Conf::instance().power_off {
+            PowerOffMethod::Powered => match self.set_powered(false).await {
+                #[cfg(feature = "mgmt-fallback")]
+                Err(e) if Conf::instance().mgmt_fallback => {
+                    warn!(
+                        "BlueZ did not power off {}: {:#}; falling back to the management API.",
+                        self.hci(),
+                        e
+                    );
+                    mgmt::power_off(self.hci()).await
+                }
+                result => result,
+            },
+            PowerOffMethod::Rfkill => rfkill::soft_block(self.hci(), true),
+        }
+    }
+
+    /// Turns on the Bluetooth adapter.
+    ///
+    /// This method sets the "Powered" property of the adapter interface to `true` via D-Bus. If
+    /// `power_off` is `rfkill`, the adapter is unblocked first.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the D-Bus call fails or the property cannot be set.
+    /// - [`anyhow::Error`] if the adapter cannot be unblocked.
+    /// - [`DbusTimeout`] if BlueZ does not answer within `dbus.call_timeout`, on every attempt.
+    pub async fn turn_on_adapter(&self) -> Result<()> {
+        if Conf::instance().power_off == PowerOffMethod::Rfkill
+            && rfkill::is_soft_blocked(self.hci())?
+        {
+            rfkill::soft_block(self.hci(), false)?;
+            // BlueZ restores the previous powered state once the adapter is unblocked
+            return Ok(());
+        }
+
+        self.set_powered(true).await
+    }
+
+    /// Returns the name of the adapter (e.g., "hci0"), the last segment of its object path.
+    pub fn hci(&self) -> &str {
+        self.iface.rsplit('/').next().unwrap_or(&self.iface)
+    }
+
+    /// Connects the given `device` by calling the `Connect` method of its device interface.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the D-Bus call fails (e.g., the device is out of range or not
+    ///   paired).
+    /// - [`DbusTimeout`] if BlueZ does not answer within `dbus.call_timeout`, on every attempt.
+    pub async fn connect_device(&self, device: &BluetoothDevice) -> Result<()> {
+        call("Connect", move || async move {
+            let conf = Conf::instance();
+            self.conn
+                .call_method(
+                    Some(conf.dbus.service.as_str()),
+                    device.object_path.as_str(),
+                    Some(conf.dbus.device_iface.as_str()),
+                    "Connect",
+                    &(),
+                )
+                .await?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Checks if the given `device` is paired with the adapter.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the D-Bus call fails or the property cannot be retrieved.
+    /// - [`DbusTimeout`] if BlueZ does not answer within `dbus.call_timeout`, on every attempt.
+    pub async fn is_paired(&self, device: &BluetoothDevice) -> Result<bool> {
+        call("Paired", move || async move {
+            let conf = Conf::instance();
+            let proxy = PropertiesProxy::builder(&self.conn)
+                .destination(conf.dbus.service.as_str())?
+                .path(device.object_path.as_str())?
+                .build()
+                .await?;
+            let paired = proxy
+                .get(
+                    InterfaceName::from_static_str(conf.dbus.device_iface.as_str())?,
+                    "Paired",
+                )
+                .await?;
+
+            Ok(bool::try_from(paired)?)
+        })
+        .await
     }
+
+    /// Disconnects the given `device` by calling the `Disconnect` method of its device interface.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the D-Bus call fails.
+    /// - [`DbusTimeout`] if BlueZ does not answer within `dbus.call_timeout`, on every attempt.
+    pub async fn disconnect_device(&self, device: &BluetoothDevice) -> Result<()> {
+        call("Disconnect", move || async move {
+            let conf = Conf::instance();
+            self.conn
+                .call_method(
+                    Some(conf.dbus.service.as_str()),
+                    device.object_path.as_str(),
+                    Some(conf.dbus.device_iface.as_str()),
+                    "Disconnect",
+                    &(),
+                )
+                .await?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Checks if the given adapter `flag` is currently set.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the D-Bus call fails or the property cannot be retrieved.
+    /// - [`DbusTimeout`] if BlueZ does not answer within `dbus.call_timeout`, on every attempt.
+    pub async fn get_flag(&self, flag: AdapterFlag) -> Result<bool> {
+        call(flag.property(), move || async move {
+            let conf = Conf::instance();
+            let proxy = PropertiesProxy::builder(&self.conn)
+                .destination(conf.dbus.service.as_str())?
+                .path(self.iface.as_str())?
+                .build()
+                .await?;
+            let value = proxy
+                .get(
+                    InterfaceName::from_static_str(conf.dbus.adapter_iface.as_str())?,
+                    flag.property(),
+                )
+                .await?;
+
+            Ok(bool::try_from(value)?)
+        })
+        .await
+    }
+
+    /// Sets the given adapter `flag` to `value`.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the D-Bus call fails or the property cannot be set.
+    /// - [`DbusTimeout`] if BlueZ does not answer within `dbus.call_timeout`, on every attempt.
+    pub async fn set_flag(&self, flag: AdapterFlag, value: bool) -> Result<()> {
+        self.set_adapter_property(flag.property(), value).await
+    }
+
+    /// Sets the "Powered" property of the adapter interface to `powered`.
+    async fn set_powered(&self, powered: bool) -> Result<()> {
+        self.set_adapter_property("Powered", powered).await
+    }
+
+    /// Sets the boolean property `name` of the adapter interface to `value`.
+    async fn set_adapter_property(&self, name: &str, value: bool) -> Result<()> {
+        call(name, move || async move {
+            let conf = Conf::instance();
+            let proxy = PropertiesProxy::builder(&self.conn)
+                .destination(conf.dbus.service.as_str())?
+                .path(self.iface.as_str())?
+                .build()
+                .await?;
+
+            proxy
+                .set(
+                    InterfaceName::from_static_str(conf.dbus.adapter_iface.as_str())?,
+                    name,
+                    Value::Bool(value),
+                )
+                .await?;
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Runs the D-Bus call `op`, named `call` after the method or property it is for, retrying
+/// transient failures (see [`retry::with_backoff`]) and cancelling attempts that exceed
+/// `dbus.call_timeout` (see [`timed`]).
+///
+/// # Errors
+///
+/// - [`DbusTimeout`] if the last attempt does not complete in time.
+/// - [`anyhow::Error`] if the last attempt fails.
+async fn call<T, F>(call: &str, mut op: impl FnMut() -> F) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    retry::with_backoff(call, || timed(call, op())).await
+}
+
+/// Runs the D-Bus `call` (named after the method or property it is for), cancelling it if it does
+/// not complete within `dbus.call_timeout`. A zero timeout waits indefinitely.
+///
+/// # Errors
+///
+/// - [`DbusTimeout`] if the call does not complete in time.
+/// - [`anyhow::Error`] if the call fails.
+async fn timed<T>(call: &str, future: impl Future<Output = Result<T>>) -> Result<T> {
+    let after = Conf::instance().dbus.call_timeout;
+    if after.is_zero() {
+        return future.await;
+    }
+
+    tokio::time::timeout(after, future)
+        .await
+        .map_err(|_| DbusTimeout {
+            call: call.to_string(),
+            after,
+        })?
 }
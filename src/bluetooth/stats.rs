@@ -0,0 +1,360 @@
+// -- std imports
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+// -- crate imports
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+// -- module imports
+use crate::configuration::state_path;
+
+/// Length of a day in seconds.
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// Number of days whose totals are kept.
+const KEPT_DAYS: u64 = 28;
+
+/// Powered-time totals of a single day (UTC).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DayStats {
+    /// Seconds the adapter was powered on.
+    pub powered_secs: u64,
+    /// Seconds the adapter was off.
+    pub off_secs: u64,
+    /// Seconds the adapter was off after the timeout turned it off, until it was powered on
+    /// again; the radio-on time saved, if it would have stayed on otherwise.
+    pub saved_secs: u64,
+    /// Number of times the timeout turned the adapter off.
+    pub auto_power_offs: u32,
+}
+
+impl DayStats {
+    /// Adds the totals of `other` to these.
+    fn add(&mut self, other: &DayStats) {
+        self.powered_secs += other.powered_secs;
+        self.off_secs += other.off_secs;
+        self.saved_secs += other.saved_secs;
+        self.auto_power_offs += other.auto_power_offs;
+    }
+}
+
+/// The persisted part of the [`PowerStats`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Record {
+    /// Totals by day since the Unix epoch.
+    days: BTreeMap<u64, DayStats>,
+    /// Week (see [`week`]) of the last weekly summary.
+    summarized_week: u64,
+}
+
+/// State of the accounting, shared by the clones of [`PowerStats`].
+#[derive(Debug)]
+struct Accounting {
+    /// Totals recorded so far.
+    record: Record,
+    /// Whether the adapter is powered, `None` until it is known.
+    powered: Option<bool>,
+    /// Whether the adapter is off because the timeout turned it off.
+    auto_off: bool,
+    /// Start of the period not yet added to the totals.
+    since: SystemTime,
+    /// File the record is kept in, if any.
+    path: Option<PathBuf>,
+}
+
+/// Per-day totals of how long the adapter was powered on and off, and how often the timeout
+/// turned it off.
+///
+/// The totals are kept for the last four weeks, in the file given to [`PowerStats::load`] so they
+/// survive restarts. Clones share the same totals, so the service can record while the control
+/// interface reads them.
+#[derive(Debug, Clone)]
+pub struct PowerStats {
+    /// The shared accounting.
+    inner: Arc<Mutex<Accounting>>,
+}
+
+/// Returns the day since the Unix epoch that `time` falls on.
+fn day(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / DAY_SECS
+}
+
+/// Returns the week since the Unix epoch, starting on Mondays, that `day` falls in.
+fn week(day: u64) -> u64 {
+    // The epoch was a Thursday
+    (day + 3) / 7
+}
+
+/// Returns the start of `day` since the Unix epoch.
+pub fn day_start(day: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(day * DAY_SECS)
+}
+
+impl PowerStats {
+    /// Creates empty statistics that are not persisted.
+    pub fn new() -> Self {
+        Self::with_record(Record::default(), None)
+    }
+
+    /// Loads the statistics kept in the file at `path`, starting empty if it does not exist or
+    /// cannot be parsed. Changes are written back to it.
+    pub fn load(path: PathBuf) -> Self {
+        let record = match fs::read_to_string(&path) {
+            Ok(contents) => serde_yaml::from_str(&contents)
+                .inspect_err(|e| warn!("Ignoring invalid statistics '{}': {}", path.display(), e))
+                .unwrap_or_default(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Record::default(),
+            Err(e) => {
+                warn!("Could not read statistics '{}': {}", path.display(), e);
+                Record::default()
+            }
+        };
+
+        Self::with_record(record, Some(path))
+    }
+
+    /// Creates statistics continuing `record`, kept in the file at `path`.
+    fn with_record(record: Record, path: Option<PathBuf>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Accounting {
+                record,
+                powered: None,
+                auto_off: false,
+                since: SystemTime::now(),
+                path,
+            })),
+        }
+    }
+
+    /// Records that the adapter is now `powered` (or off), `automatically` turned off by the
+    /// timeout.
+    pub fn set_powered(&self, powered: bool, automatically: bool) {
+        self.set_powered_at(powered, automatically, SystemTime::now());
+    }
+
+    /// Returns the totals of the last `count` days up to today (including the running period),
+    /// oldest first, as pairs of the day since the Unix epoch and its totals.
+    #[cfg_attr(not(feature = "cli-control"), allow(dead_code))]
+    pub fn days(&self, count: u64) -> Vec<(u64, DayStats)> {
+        self.days_at(count, SystemTime::now())
+    }
+
+    /// Returns the totals of the previous week, once per week, if the timeout turned the adapter
+    /// off in it. Weeks start on Mondays (UTC).
+    #[cfg_attr(not(feature = "notifications"), allow(dead_code))]
+    pub fn weekly_summary(&self) -> Option<DayStats> {
+        self.weekly_summary_at(SystemTime::now())
+    }
+
+    /// [`PowerStats::set_powered`] at `now`.
+    fn set_powered_at(&self, powered: bool, automatically: bool, now: SystemTime) {
+        let mut accounting = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        accounting.account(now);
+        if accounting.powered == Some(powered) {
+            return;
+        }
+
+        let auto_off = !powered && automatically;
+        if auto_off {
+            accounting.today(now).auto_power_offs += 1;
+        }
+        accounting.powered = Some(powered);
+        accounting.auto_off = auto_off;
+        accounting.save();
+    }
+
+    /// [`PowerStats::days`] at `now`.
+    fn days_at(&self, count: u64, now: SystemTime) -> Vec<(u64, DayStats)> {
+        let mut accounting = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        accounting.account(now);
+
+        let today = day(now);
+        (today.saturating_sub(count.saturating_sub(1))..=today)
+            .map(|day| {
+                let stats = accounting.record.days.get(&day).copied();
+                (day, stats.unwrap_or_default())
+            })
+            .collect()
+    }
+
+    /// [`PowerStats::weekly_summary`] at `now`.
+    fn weekly_summary_at(&self, now: SystemTime) -> Option<DayStats> {
+        let mut accounting = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        accounting.account(now);
+
+        let current = week(day(now));
+        let summarized = std::mem::replace(&mut accounting.record.summarized_week, current);
+        if summarized >= current {
+            return None;
+        }
+        accounting.save();
+        // Nothing to summarize on the first start
+        if summarized == 0 {
+            return None;
+        }
+
+        let mut total = DayStats::default();
+        for (_, stats) in accounting
+            .record
+            .days
+            .iter()
+            .filter(|(day, _)| week(**day) == current - 1)
+        {
+            total.add(stats);
+        }
+
+        Some(total).filter(|total| total.auto_power_offs > 0)
+    }
+}
+
+impl Default for PowerStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Accounting {
+    /// Returns the totals of the day `now` falls on.
+    fn today(&mut self, now: SystemTime) -> &mut DayStats {
+        self.record.days.entry(day(now)).or_default()
+    }
+
+    /// Adds the period since the last call to the totals of the days it spans, and drops the
+    /// totals of days no longer kept.
+    fn account(&mut self, now: SystemTime) {
+        let mut start = self.since;
+        self.since = now;
+        let Some(powered) = self.powered else {
+            return;
+        };
+        let auto_off = self.auto_off;
+
+        while start < now {
+            let end = day_start(day(start) + 1).min(now);
+            let secs = end.duration_since(start).unwrap_or_default().as_secs();
+            let stats = self.today(start);
+            if powered {
+                stats.powered_secs += secs;
+            } else {
+                stats.off_secs += secs;
+                if auto_off {
+                    stats.saved_secs += secs;
+                }
+            }
+            start = end;
+        }
+
+        let oldest = day(now).saturating_sub(KEPT_DAYS - 1);
+        self.record.days.retain(|day, _| *day >= oldest);
+    }
+
+    /// Writes the record to its file, if any. Failures are logged.
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let result = serde_yaml::to_string(&self.record)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| Ok(fs::write(path, contents)?));
+        match result {
+            Ok(()) => debug!("Saved statistics to '{}'.", path.display()),
+            Err(e) => warn!("Could not save statistics '{}': {}", path.display(), e),
+        }
+    }
+}
+
+/// Returns the path of the file the statistics of `adapter` (e.g., "/org/bluez/hci0") are kept
+/// in.
+///
+/// This is `hci0.stats.yml` among the state files (see [`state_path`]). Statistics kept in the
+/// XDG data directory by earlier versions are moved there.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the state directory cannot be determined or created.
+pub fn path(adapter: &str) -> Result<PathBuf> {
+    let name = adapter.rsplit('/').next().unwrap_or(adapter);
+    let file = format!("{}.stats.yml", name);
+    let path = state_path(&file)?;
+
+    #[cfg(not(debug_assertions))]
+    if let Some(previous) =
+        xdg::BaseDirectories::with_prefix(env!("CARGO_PKG_NAME")).find_data_file(&file)
+        && !path.exists()
+        && let Err(e) = fs::rename(&previous, &path)
+    {
+        warn!("Could not move statistics '{}': {}", previous.display(), e);
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    /// Returns the instant `hours` after the start of `day`.
+    fn at(day: u64, hours: u64) -> SystemTime {
+        day_start(day) + Duration::from_secs(hours * 60 * 60)
+    }
+
+    #[test]
+    fn splits_periods_at_midnight() {
+        let stats = PowerStats::new();
+        stats.set_powered_at(true, false, at(100, 20));
+        stats.set_powered_at(false, true, at(101, 2));
+
+        let days = stats.days_at(2, at(101, 5));
+
+        assert_eq!(days[0].0, 100);
+        assert_eq!(days[0].1.powered_secs, 4 * 3600);
+        assert_eq!(days[1].1.powered_secs, 2 * 3600);
+        assert_eq!(days[1].1.off_secs, 3 * 3600);
+        assert_eq!(days[1].1.saved_secs, 3 * 3600);
+        assert_eq!(days[1].1.auto_power_offs, 1);
+    }
+
+    #[test]
+    fn manual_power_offs_save_nothing() {
+        let stats = PowerStats::new();
+        stats.set_powered_at(true, false, at(100, 0));
+        stats.set_powered_at(false, false, at(100, 1));
+
+        let days = stats.days_at(1, at(100, 3));
+
+        assert_eq!(days[0].1.off_secs, 2 * 3600);
+        assert_eq!(days[0].1.saved_secs, 0);
+        assert_eq!(days[0].1.auto_power_offs, 0);
+    }
+
+    #[test]
+    fn summarizes_the_previous_week_once() {
+        // Day 4 is a Monday
+        let monday = 4 + 7 * 100;
+        let stats = PowerStats::new();
+        assert_eq!(stats.weekly_summary_at(at(monday - 7, 0)), None);
+
+        stats.set_powered_at(true, false, at(monday - 2, 0));
+        stats.set_powered_at(false, true, at(monday - 2, 1));
+        stats.set_powered_at(true, false, at(monday - 2, 3));
+
+        let summary = stats.weekly_summary_at(at(monday, 8)).unwrap();
+        assert_eq!(summary.auto_power_offs, 1);
+        assert_eq!(summary.saved_secs, 2 * 3600);
+        assert_eq!(stats.weekly_summary_at(at(monday, 9)), None);
+    }
+}
@@ -0,0 +1,77 @@
+// -- crate imports
+use zbus::{Connection, Result, connection};
+
+// -- module imports (conditional)
+#[cfg(feature = "cli-control")]
+use crate::configuration::ControlBus;
+
+// -- module imports
+use crate::configuration::Conf;
+
+/// Returns a builder of a connection to the system bus at `dbus.system_bus`, or to the default
+/// system bus (which honors `DBUS_SYSTEM_BUS_ADDRESS`) if it is not configured.
+///
+/// # Errors
+///
+/// - [`zbus::Error`] if the address is invalid.
+pub fn system_builder() -> Result<connection::Builder<'static>> {
+    match &Conf::instance().dbus.system_bus {
+        Some(address) => connection::Builder::address(address.as_str()),
+        None => connection::Builder::system(),
+    }
+}
+
+/// Returns a builder of a connection to the session bus at `dbus.session_bus`, or to the default
+/// session bus (which honors `DBUS_SESSION_BUS_ADDRESS`) if it is not configured.
+///
+/// # Errors
+///
+/// - [`zbus::Error`] if the address is invalid.
+pub fn session_builder() -> Result<connection::Builder<'static>> {
+    match &Conf::instance().dbus.session_bus {
+        Some(address) => connection::Builder::address(address.as_str()),
+        None => connection::Builder::session(),
+    }
+}
+
+/// Returns a builder of a connection to the bus the control interface is served on, as selected
+/// by `dbus.control_bus` (see [`session_builder`] and [`system_builder`]).
+///
+/// # Errors
+///
+/// - [`zbus::Error`] if the address is invalid.
+#[cfg(feature = "cli-control")]
+pub fn control_builder() -> Result<connection::Builder<'static>> {
+    match Conf::instance().dbus.control_bus {
+        ControlBus::Session => session_builder(),
+        ControlBus::System => system_builder(),
+    }
+}
+
+/// Connects to the system bus (see [`system_builder`]).
+///
+/// # Errors
+///
+/// - [`zbus::Error`] if the address is invalid or the bus cannot be reached.
+pub async fn system() -> Result<Connection> {
+    system_builder()?.build().await
+}
+
+/// Connects to the session bus (see [`session_builder`]).
+///
+/// # Errors
+///
+/// - [`zbus::Error`] if the address is invalid or the bus cannot be reached.
+pub async fn session() -> Result<Connection> {
+    session_builder()?.build().await
+}
+
+/// Connects to the bus the control interface is served on (see [`control_builder`]).
+///
+/// # Errors
+///
+/// - [`zbus::Error`] if the address is invalid or the bus cannot be reached.
+#[cfg(feature = "cli-control")]
+pub async fn control() -> Result<Connection> {
+    control_builder()?.build().await
+}
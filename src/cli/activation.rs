@@ -0,0 +1,87 @@
+// -- std imports
+use std::{fs, io, path::PathBuf};
+
+// -- crate imports
+use anyhow::{Context, Result};
+
+// -- module imports
+use crate::control::CONTROL_DBUS_NAME;
+
+/// Returns the path of the D-Bus service file activating [`CONTROL_DBUS_NAME`] on the session
+/// bus, creating its directory if necessary.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the directory cannot be determined or created.
+fn service_file_path() -> Result<PathBuf> {
+    xdg::BaseDirectories::new()
+        .place_data_file(format!("dbus-1/services/{}.service", CONTROL_DBUS_NAME))
+        .context("Could not create the D-Bus services directory")
+}
+
+/// Returns the D-Bus service file running `exec`, or starting the systemd unit `systemd_service`
+/// if the bus delegates activation to systemd.
+fn service_file(exec: &str, systemd_service: Option<&str>) -> String {
+    let mut file = format!(
+        "[D-BUS Service]\nName={}\nExec={}\n",
+        CONTROL_DBUS_NAME, exec
+    );
+    if let Some(unit) = systemd_service {
+        file.push_str(&format!("SystemdService={}\n", unit));
+    }
+
+    file
+}
+
+/// Writes a D-Bus service file, so the session bus starts the daemon once a client (e.g.,
+/// `bluetooth-timeout status`) addresses its control interface while it is not running.
+///
+/// # Arguments
+///
+/// - `exec` - The command line starting the daemon.
+/// - `systemd_service` - The systemd user unit started instead of `exec`, if any.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the file cannot be written.
+pub fn install(exec: &str, systemd_service: Option<&str>) -> Result<PathBuf> {
+    let path = service_file_path()?;
+    fs::write(&path, service_file(exec, systemd_service))
+        .with_context(|| format!("Could not write '{}'", path.display()))?;
+
+    Ok(path)
+}
+
+/// Removes the D-Bus service file written by [`install`], if any.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the file exists but cannot be removed.
+pub fn uninstall() -> Result<()> {
+    let path = service_file_path()?;
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).with_context(|| format!("Could not remove '{}'", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    #[test]
+    fn service_file_delegates_to_systemd() {
+        assert_eq!(
+            service_file(
+                "/usr/local/bin/bluetooth-timeout run",
+                Some("bluetooth-timeout.service")
+            ),
+            "[D-BUS Service]\n\
+             Name=io.github.hendrikboeck.BluetoothTimeout\n\
+             Exec=/usr/local/bin/bluetooth-timeout run\n\
+             SystemdService=bluetooth-timeout.service\n"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+// -- std imports
+use std::{env, fs, io, path::PathBuf};
+
+// -- crate imports
+use anyhow::{Context, Result};
+
+// -- module imports
+use crate::{
+    cli::activation,
+    configuration::{CONF_PATH_ENV, conf_filepath},
+    tr,
+};
+
+/// Name of the installed desktop entry.
+const ENTRY_NAME: &str = "bluetooth-timeout.desktop";
+
+/// Returns the path of the desktop entry in the user's autostart directory, creating the directory
+/// if necessary.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the autostart directory cannot be determined or created.
+fn entry_path() -> Result<PathBuf> {
+    xdg::BaseDirectories::new()
+        .place_config_file(format!("autostart/{}", ENTRY_NAME))
+        .context("Could not create the autostart directory")
+}
+
+/// Returns the command line running the daemon from the binary at `exe` with the config file at
+/// `conf`.
+fn command(exe: &str, conf: &str) -> String {
+    format!("env {}={} {} run", CONF_PATH_ENV, quote(conf), quote(exe))
+}
+
+/// Returns the desktop entry running `command`.
+fn entry(command: &str) -> String {
+    format!(
+        "[Desktop Entry]
+Type=Application
+Name=Bluetooth Timeout
+Comment=Turns off the Bluetooth adapter when no device is connected
+Exec={}
+Terminal=false
+NoDisplay=true
+X-GNOME-Autostart-enabled=true
+",
+        command.replace('%', "%%")
+    )
+}
+
+/// Quotes `arg` for a command line, if it contains reserved characters.
+fn quote(arg: &str) -> String {
+    if arg
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "/._-+=:@,".contains(c))
+    {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Writes an XDG autostart desktop entry that runs the daemon with the current config file when
+/// the user logs in, for session managers without systemd. The session bus is told to start the
+/// daemon the same way when its control interface is addressed (see [`activation::install`]).
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the paths of the binary or config file cannot be determined, or the
+///   files cannot be written.
+pub fn install() -> Result<()> {
+    let exe = env::current_exe().context("Could not determine the path of the binary")?;
+    let conf = env::current_dir()?.join(conf_filepath()?);
+    let command = command(&exe.to_string_lossy(), &conf.to_string_lossy());
+    let path = entry_path()?;
+    fs::write(&path, entry(&command))
+        .with_context(|| format!("Could not write '{}'", path.display()))?;
+    activation::install(&command, None)?;
+
+    println!(
+        "{}",
+        tr!(
+            "cli-autostart-installed",
+            path = path.to_string_lossy().into_owned()
+        )
+    );
+    Ok(())
+}
+
+/// Removes the desktop entry and D-Bus service file written by [`install`], if any.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the files exist but cannot be removed.
+pub fn uninstall() -> Result<()> {
+    activation::uninstall()?;
+
+    let path = entry_path()?;
+    match fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e).with_context(|| format!("Could not remove '{}'", path.display()));
+        }
+    }
+
+    println!(
+        "{}",
+        tr!(
+            "cli-autostart-uninstalled",
+            path = path.to_string_lossy().into_owned()
+        )
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    #[test]
+    fn entry_runs_the_daemon_with_the_config_file() {
+        let entry = entry(&command(
+            "/usr/local/bin/bluetooth-timeout",
+            "/home/me/.config/bluetooth-timeout/config.yml",
+        ));
+
+        assert!(entry.contains(
+            "\nExec=env BLUETOOTH_TIMEOUT_CONFIG=/home/me/.config/bluetooth-timeout/config.yml \
+             /usr/local/bin/bluetooth-timeout run\n"
+        ));
+    }
+
+    #[test]
+    fn reserved_characters_are_escaped() {
+        assert_eq!(quote("/home/me/my $HOME"), "\"/home/me/my \\$HOME\"");
+        assert!(entry("/opt/100%").contains("\nExec=/opt/100%%\n"));
+    }
+}
@@ -0,0 +1,60 @@
+// -- crate imports
+use anyhow::Result;
+use schemars::{Schema, generate::SchemaSettings, transform::RecursiveTransform};
+
+// -- module imports
+use crate::configuration::Conf;
+
+/// Returns the JSON Schema of the config file.
+///
+/// The schema follows draft 7, which YAML language servers (e.g., `yaml-language-server`) support
+/// for completion and validation. Descriptions are taken from the documentation of [`Conf`], which
+/// also names the defaults; the `default` keywords are left out, as durations would appear in
+/// their serialized form (`{ secs, nanos }`) instead of the `5m` of the config file.
+pub fn schema() -> Schema {
+    let mut schema = SchemaSettings::draft07()
+        .with_transform(RecursiveTransform(|schema: &mut Schema| {
+            schema.remove("default");
+        }))
+        .into_generator()
+        .into_root_schema_for::<Conf>();
+    schema.insert("title".into(), "bluetooth-timeout configuration".into());
+
+    schema
+}
+
+/// Prints the JSON Schema of the config file (see [`schema`]).
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the schema cannot be serialized.
+pub fn print_schema() -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&schema())?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    // -- crate imports
+    use serde_json::json;
+
+    // -- module imports
+    use super::*;
+
+    #[test]
+    fn schema_describes_durations_as_strings() {
+        let schema = schema();
+        let properties = &schema.as_value()["properties"];
+
+        assert_eq!(properties["power_debounce"]["type"], json!("string"));
+        assert!(properties["power_debounce"].get("default").is_none());
+        assert!(
+            schema.as_value()["required"]
+                .as_array()
+                .unwrap()
+                .contains(&json!("dbus"))
+        );
+        assert!(schema.as_value()["definitions"]["OneOrMany"]["anyOf"].is_array());
+    }
+}
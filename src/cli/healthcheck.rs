@@ -0,0 +1,22 @@
+// -- crate imports
+use anyhow::Result;
+
+// -- module imports
+use crate::{bus, control::proxy::ControlProxy, tr};
+
+/// Checks that the daemon is running and healthy (see the control interface's `CheckHealth`),
+/// e.g., for a container's `HEALTHCHECK` or a monitoring script.
+///
+/// The daemon is not started via D-Bus activation if it is not running.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the daemon cannot be reached on `dbus.control_bus` or reports the reason
+///   it is unhealthy.
+pub async fn run() -> Result<()> {
+    let conn = bus::control().await?;
+    ControlProxy::new(&conn).await?.check_health().await?;
+
+    println!("{}", tr!("cli-healthy"));
+    Ok(())
+}
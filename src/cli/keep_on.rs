@@ -0,0 +1,39 @@
+// -- std imports
+use std::time::Duration;
+
+// -- crate imports
+use anyhow::Result;
+
+// -- module imports
+use crate::{bus, control::proxy::ControlProxy, tr};
+
+/// Asks the running daemon to keep the adapter on for a while.
+///
+/// # Arguments
+///
+/// - `duration` - The duration to keep the adapter on for. Ends an active window if zero.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the daemon cannot be reached on `dbus.control_bus`.
+pub async fn run(duration: Duration) -> Result<()> {
+    let conn = bus::control().await?;
+    ControlProxy::new(&conn)
+        .await?
+        .keep_on(duration.as_secs())
+        .await?;
+
+    if duration.is_zero() {
+        println!("{}", tr!("cli-keep-on-ended"));
+    } else {
+        println!(
+            "{}",
+            tr!(
+                "cli-kept-on",
+                duration = humantime::format_duration(duration).to_string()
+            )
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,248 @@
+// -- std imports (conditional)
+#[cfg(feature = "cli-control")]
+use std::time::Duration;
+
+// -- crate imports (conditional)
+#[cfg(feature = "cli-control")]
+use clap::Args;
+
+// -- crate imports
+use clap::{ArgAction, Parser, Subcommand};
+use tracing::level_filters::LevelFilter;
+
+// -- module imports (conditional)
+#[cfg(feature = "cli-control")]
+use crate::{cli::service::ServiceScope, control::MAX_SECONDS};
+
+// -- module definitions
+#[cfg(feature = "cli-control")]
+pub mod activation;
+#[cfg(feature = "cli-control")]
+pub mod autostart;
+#[cfg(feature = "cli-control")]
+pub mod config;
+#[cfg(feature = "cli-control")]
+pub mod healthcheck;
+#[cfg(feature = "cli-control")]
+pub mod keep_on;
+#[cfg(feature = "cli-control")]
+pub mod monitor;
+#[cfg(feature = "notifications")]
+pub mod notify_test;
+#[cfg(feature = "cli-control")]
+pub mod power_off;
+#[cfg(feature = "cli-control")]
+pub mod profile;
+#[cfg(feature = "cli-control")]
+pub mod service;
+#[cfg(feature = "cli-control")]
+pub mod setup;
+#[cfg(feature = "cli-control")]
+pub mod snooze;
+#[cfg(feature = "cli-control")]
+pub mod status;
+#[cfg(feature = "cli-control")]
+pub mod timeout;
+
+/// Command line interface of the `bluetooth-timeout` binary.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// The command to execute. Runs the daemon if omitted.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Log more to stdout (`-v` for debug, `-vv` for trace). The log file is not affected.
+    #[arg(short, long, action = ArgAction::Count, global = true, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Log less to stdout (`-q` for warnings, `-qq` for errors, `-qqq` for nothing). The log file
+    /// is not affected.
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub quiet: u8,
+}
+
+impl Cli {
+    /// Returns the level of the stdout output selected by `--verbose` or `--quiet`, or `None` if
+    /// neither is given and `log.level` applies.
+    ///
+    /// Commands whose stdout is meant for other programs (`monitor`, `config`) only log errors by
+    /// default.
+    pub fn stdout_level(&self) -> Option<LevelFilter> {
+        match (self.verbose, self.quiet) {
+            #[cfg(feature = "cli-control")]
+            (0, 0) if matches!(self.command, Some(Command::Monitor | Command::Config(_))) => {
+                Some(LevelFilter::ERROR)
+            }
+            (0, 0) => None,
+            (1, _) => Some(LevelFilter::DEBUG),
+            (_, 0) => Some(LevelFilter::TRACE),
+            (_, 1) => Some(LevelFilter::WARN),
+            (_, 2) => Some(LevelFilter::ERROR),
+            (_, _) => Some(LevelFilter::OFF),
+        }
+    }
+}
+
+/// Commands supported by the `bluetooth-timeout` binary.
+#[derive(Debug, Default, Subcommand)]
+pub enum Command {
+    /// Run the Bluetooth timeout daemon (default).
+    #[default]
+    Run,
+
+    /// Turn off the adapter if it has been powered on without connected devices for the timeout,
+    /// as seen by earlier runs, and exit. Meant to be run periodically (e.g., from cron) instead
+    /// of the daemon.
+    Oneshot,
+
+    /// Send a sample warning and final notification to verify the notification setup.
+    #[cfg(feature = "notifications")]
+    NotifyTest,
+
+    /// Postpone the active timeout of the running daemon.
+    #[cfg(feature = "cli-control")]
+    Snooze {
+        /// Duration to postpone the timeout by (e.g. "15m"). Defaults to `notifications.snooze`.
+        #[arg(value_parser = parse_control_duration)]
+        duration: Option<Duration>,
+    },
+
+    /// Keep the adapter on for a while, powering it on if necessary.
+    #[cfg(feature = "cli-control")]
+    KeepOn {
+        /// Duration to keep the adapter on for (e.g. "3h"). "0s" ends an active window early.
+        #[arg(value_parser = parse_control_duration)]
+        duration: Duration,
+    },
+
+    /// Turn the adapter off right away, as the timeout of the running daemon would.
+    #[cfg(feature = "cli-control")]
+    PowerOff,
+
+    /// Switch the running daemon to a profile of the configuration.
+    #[cfg(feature = "cli-control")]
+    Profile {
+        /// Name of the profile. Switches back to the main configuration if omitted.
+        name: Option<String>,
+    },
+
+    /// Change the timeout of the running daemon until it exits.
+    #[cfg(feature = "cli-control")]
+    SetTimeout {
+        /// New timeout in every context (e.g. "10m"). Restores the configured timeout if omitted.
+        #[arg(value_parser = parse_control_duration)]
+        duration: Option<Duration>,
+
+        /// Also write the timeout to the config file, so it survives a restart.
+        #[arg(long, requires = "duration")]
+        persist: bool,
+    },
+
+    /// Show the state of the running daemon.
+    #[cfg(feature = "cli-control")]
+    Status {
+        /// Also list the recent state transitions, events and commands (see `log.history`).
+        #[arg(long)]
+        events: bool,
+
+        /// Also list how long the adapter was on and off on each of the last seven days, and how
+        /// often the timeout turned it off.
+        #[arg(long)]
+        stats: bool,
+
+        /// Also list how long the daemon took to handle each kind of adapter event, from the
+        /// D-Bus signal until its timeout was started, cancelled or left alone.
+        #[arg(long)]
+        latency: bool,
+    },
+
+    /// Print every state transition and event of the running daemon as one JSON object per line
+    /// until it stops.
+    #[cfg(feature = "cli-control")]
+    Monitor,
+
+    /// Exit successfully only if the running daemon can be reached and reports being healthy
+    /// (its service runs, adapter events are observed and the adapter can be queried).
+    #[cfg(feature = "cli-control")]
+    Healthcheck,
+
+    /// Inspect the configuration.
+    #[cfg(feature = "cli-control")]
+    #[command(subcommand)]
+    Config(ConfigCommand),
+
+    /// Interactively pick the adapter, timeout and notification preferences, write them to the
+    /// config file and optionally install the user service.
+    #[cfg(feature = "cli-control")]
+    Setup,
+
+    /// Install, enable and start a systemd unit running the daemon.
+    #[cfg(feature = "cli-control")]
+    InstallService(ServiceArgs),
+
+    /// Stop, disable and remove the systemd unit installed by `install-service`.
+    #[cfg(feature = "cli-control")]
+    UninstallService(ServiceArgs),
+
+    /// Start the daemon on login via an XDG autostart entry, for session managers without
+    /// systemd.
+    #[cfg(feature = "cli-control")]
+    InstallAutostart,
+
+    /// Remove the autostart entry written by `install-autostart`.
+    #[cfg(feature = "cli-control")]
+    UninstallAutostart,
+}
+
+/// Subcommands of the `config` command.
+#[cfg(feature = "cli-control")]
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print a JSON Schema of the config file, for completion and validation in editors with a
+    /// YAML language server.
+    Schema,
+}
+
+/// Selection of the service manager of the `install-service` and `uninstall-service` commands.
+#[cfg(feature = "cli-control")]
+#[derive(Debug, Args)]
+pub struct ServiceArgs {
+    /// Use the user's service manager (default).
+    #[arg(long, conflicts_with = "system")]
+    pub user: bool,
+
+    /// Use the system's service manager (requires root).
+    #[arg(long)]
+    pub system: bool,
+}
+
+#[cfg(feature = "cli-control")]
+impl ServiceArgs {
+    /// Returns the selected service manager.
+    pub fn scope(&self) -> ServiceScope {
+        if self.system {
+            ServiceScope::System
+        } else {
+            ServiceScope::User
+        }
+    }
+}
+
+/// Parses a duration passed to the control interface (e.g. "15m"), which takes whole seconds up to
+/// [`MAX_SECONDS`], rather than truncating it there.
+#[cfg(feature = "cli-control")]
+fn parse_control_duration(arg: &str) -> Result<Duration, String> {
+    let duration = humantime::parse_duration(arg).map_err(|e| e.to_string())?;
+    if duration.subsec_nanos() != 0 {
+        return Err("must be a whole number of seconds".into());
+    }
+    if duration.as_secs() > MAX_SECONDS {
+        return Err(format!(
+            "must not exceed {}",
+            humantime::format_duration(Duration::from_secs(MAX_SECONDS))
+        ));
+    }
+
+    Ok(duration)
+}
@@ -0,0 +1,71 @@
+// -- std imports
+use std::time::SystemTime;
+
+// -- crate imports
+use anyhow::Result;
+use futures_util::stream::StreamExt;
+use serde_json::{Value, json};
+
+// -- module imports
+use crate::{bus, control::proxy::ControlProxy};
+
+/// Prints every state transition and event of the running daemon to stdout as one JSON object per
+/// line (NDJSON), starting with its current state, until the daemon stops.
+///
+/// Each object has a `time` (RFC 3339, UTC) and a `type`:
+///
+/// - `state` - A state transition, `from` (`null` for the current state at startup) `to` a state.
+/// - `event` - An `event` of the adapter (e.g., `DeviceConnected`), with the `device` name and
+///   `address` it is about (`null` if none).
+/// - `before_power_off` - The timeout turns the adapter off in `seconds`.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the daemon cannot be reached on `dbus.control_bus` or stops.
+pub async fn run() -> Result<()> {
+    let conn = bus::control().await?;
+    let proxy = ControlProxy::new(&conn).await?;
+    let mut owners = proxy.inner().receive_owner_changed().await?;
+    let mut states = proxy.receive_state_changed().await;
+    let mut events = proxy.receive_bluetooth_event().await?;
+    let mut power_offs = proxy.receive_before_power_off().await?;
+
+    let mut state = proxy.state().await?;
+    print(json!({ "type": "state", "from": null, "to": state }));
+
+    loop {
+        tokio::select! {
+            Some(owner) = owners.next() => {
+                anyhow::ensure!(owner.is_some(), "The daemon stopped");
+            }
+            Some(change) = states.next() => {
+                let to = change.get().await?;
+                if to != state {
+                    print(json!({ "type": "state", "from": state, "to": to }));
+                    state = to;
+                }
+            }
+            Some(signal) = events.next() => {
+                let args = signal.args()?;
+                print(json!({
+                    "type": "event",
+                    "event": args.kind,
+                    "device": Some(args.device).filter(|device| !device.is_empty()),
+                    "address": Some(args.address).filter(|address| !address.is_empty()),
+                }));
+            }
+            Some(signal) = power_offs.next() => {
+                let args = signal.args()?;
+                print(json!({ "type": "before_power_off", "seconds": args.seconds }));
+            }
+            else => anyhow::bail!("The connection to the daemon was closed"),
+        }
+    }
+}
+
+/// Prints `object` as a line, with the current time as its `time`.
+fn print(mut object: Value) {
+    let time = humantime::format_rfc3339_millis(SystemTime::now()).to_string();
+    object["time"] = Value::String(time);
+    println!("{}", object);
+}
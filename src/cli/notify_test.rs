@@ -0,0 +1,94 @@
+// -- std imports
+use std::time::Duration;
+
+// -- crate imports
+use anyhow::Result;
+use tracing::warn;
+use zbus::fdo::ObjectManagerProxy;
+
+// -- module imports
+use crate::{
+    bluetooth::{cache::DeviceCache, service_proxy::BluetoothServiceProxy},
+    bus,
+    configuration::Conf,
+    daemon,
+    notification::Notification,
+    tr,
+};
+
+/// Sends a sample warning and the final notification through the regular notification code path.
+///
+/// The warning uses the first configured entry of `notifications.at` including its display
+/// timeout and icon overrides (or the timeout itself if none is configured), so the output
+/// matches what the daemon would send during a real timeout.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if either notification cannot be delivered to the notification daemon.
+pub async fn run(conf: &Conf) -> Result<()> {
+    if !conf.notifications().enabled {
+        warn!(
+            "Notifications are disabled in the configuration; sending test notifications anyway."
+        );
+    }
+
+    let adapter = adapter_name(conf).await;
+    let warning = match conf.notifications().at.first() {
+        Some(warning) => {
+            Notification::timeout_warning(&adapter, warning.at).with_overrides(warning)
+        }
+        None => Notification::timeout_warning(&adapter, conf.timeout.default),
+    };
+
+    let id = warning.send().await?;
+    println!("{}", tr!("cli-notify-test-warning-sent", id = id));
+
+    let actions = conf
+        .stages()
+        .pop()
+        .map(|stage| stage.action)
+        .unwrap_or_default();
+    let id = Notification::timeout_expired(&adapter, &actions)
+        .send()
+        .await?;
+    println!("{}", tr!("cli-notify-test-final-sent", id = id));
+
+    Ok(())
+}
+
+/// Returns the alias of the configured adapter, or the last segment of its path (e.g., "hci0") if
+/// BlueZ cannot be asked.
+async fn adapter_name(conf: &Conf) -> String {
+    let path = &conf.dbus.adapter_path;
+    let fallback = path.rsplit('/').next().unwrap_or(path).to_string();
+
+    fetch_alias(conf).await.unwrap_or(fallback)
+}
+
+/// Retrieves the alias of the configured adapter, or of the first adapter if all are managed.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the D-Bus calls fail or no adapter is present.
+async fn fetch_alias(conf: &Conf) -> Result<String> {
+    let path = if conf.dbus.manages_all_adapters() {
+        let conn = bus::system().await?;
+        let objects = ObjectManagerProxy::builder(&conn)
+            .destination(conf.dbus.service.as_str())?
+            .path("/")?
+            .build()
+            .await?;
+        daemon::list_adapters(&objects, conf)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("No Bluetooth adapter present"))?
+    } else {
+        conf.dbus.adapter_path.clone()
+    };
+
+    BluetoothServiceProxy::new(path, DeviceCache::new(Duration::ZERO))
+        .await?
+        .alias()
+        .await
+}
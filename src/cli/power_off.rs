@@ -0,0 +1,19 @@
+// -- crate imports
+use anyhow::Result;
+
+// -- module imports
+use crate::{bus, control::proxy::ControlProxy, tr};
+
+/// Asks the running daemon to turn the adapter off right away, as the timeout would.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the daemon cannot be reached on `dbus.control_bus` or polkit does not
+///   authorize the caller.
+pub async fn run() -> Result<()> {
+    let conn = bus::control().await?;
+    ControlProxy::new(&conn).await?.power_off().await?;
+
+    println!("{}", tr!("cli-powered-off"));
+    Ok(())
+}
@@ -0,0 +1,30 @@
+// -- crate imports
+use anyhow::Result;
+
+// -- module imports
+use crate::{bus, control::proxy::ControlProxy, tr};
+
+/// Asks the running daemon to switch to another profile.
+///
+/// # Arguments
+///
+/// - `name` - The profile to switch to. Switches back to the main configuration if `None`.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the daemon cannot be reached on `dbus.control_bus` or does not know the
+///   profile.
+pub async fn run(name: Option<String>) -> Result<()> {
+    let conn = bus::control().await?;
+    ControlProxy::new(&conn)
+        .await?
+        .set_profile(name.as_deref().unwrap_or_default())
+        .await?;
+
+    match name {
+        Some(name) => println!("{}", tr!("cli-profile-switched", profile = name)),
+        None => println!("{}", tr!("cli-profile-reset")),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,199 @@
+// -- std imports
+use std::{env, fs, io, path::PathBuf};
+
+// -- crate imports
+use anyhow::{Context, Result};
+use tokio::process::Command;
+
+// -- module imports
+use crate::{cli::activation, tr};
+
+/// Name of the installed systemd unit.
+const UNIT_NAME: &str = "bluetooth-timeout.service";
+
+/// Directory of the units of the system manager.
+const SYSTEM_UNIT_DIR: &str = "/etc/systemd/system";
+
+/// Interval of the systemd watchdog set in the installed unit (see [`crate::sd_notify`]).
+const WATCHDOG_SEC: u64 = 30;
+
+/// Service manager a unit is installed for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceScope {
+    /// The user's service manager (`systemctl --user`), which has access to the session bus.
+    User,
+    /// The system's service manager; see `notifications.bus: user_sessions`.
+    System,
+}
+
+impl ServiceScope {
+    /// Returns the path of the unit file, creating the user unit directory if necessary.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the user unit directory cannot be determined or created.
+    fn unit_path(self) -> Result<PathBuf> {
+        match self {
+            ServiceScope::User => xdg::BaseDirectories::new()
+                .place_config_file(format!("systemd/user/{}", UNIT_NAME))
+                .context("Could not create the systemd user unit directory"),
+            ServiceScope::System => Ok(PathBuf::from(SYSTEM_UNIT_DIR).join(UNIT_NAME)),
+        }
+    }
+
+    /// Returns the systemd unit running the binary at `exe`.
+    fn unit(self, exe: &str) -> String {
+        let (description, after, environment, wanted_by) = match self {
+            ServiceScope::User => (
+                "Bluetooth Timeout Daemon",
+                "bluetooth.service",
+                "WorkingDirectory=%h\n",
+                "default.target",
+            ),
+            ServiceScope::System => (
+                "Bluetooth Timeout Daemon (system service)",
+                "bluetooth.service systemd-logind.service\nRequires=bluetooth.service",
+                "# Reads the configuration from /etc/bluetooth-timeout/config.yml\n\
+                 Environment=XDG_CONFIG_HOME=/etc\n",
+                "multi-user.target",
+            ),
+        };
+
+        format!(
+            "[Unit]
+Description={description}
+After={after}
+
+[Service]
+Type=notify
+ExecStart={exe}
+{environment}Restart=on-failure
+RestartSec=5
+# Restarting does not help with an invalid configuration
+RestartPreventExitStatus=3
+WatchdogSec={WATCHDOG_SEC}
+
+[Install]
+WantedBy={wanted_by}
+"
+        )
+    }
+}
+
+/// Writes a systemd unit running this binary for `scope`, and enables and starts it.
+///
+/// The user unit is also started by the session bus when the control interface is addressed
+/// while the daemon is not running (see [`activation::install`]).
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the unit cannot be written (e.g., `--system` without root) or
+///   `systemctl` fails.
+pub async fn install(scope: ServiceScope) -> Result<()> {
+    let exe = env::current_exe().context("Could not determine the path of the binary")?;
+    let path = scope.unit_path()?;
+    fs::write(&path, scope.unit(&exe.to_string_lossy()))
+        .with_context(|| format!("Could not write '{}'", path.display()))?;
+
+    if scope == ServiceScope::User {
+        let exec = format!("{} run", exe.to_string_lossy());
+        activation::install(&exec, Some(UNIT_NAME))?;
+    }
+
+    systemctl(scope, &["daemon-reload"]).await?;
+    systemctl(scope, &["enable", "--now", UNIT_NAME]).await?;
+
+    println!(
+        "{}",
+        tr!(
+            "cli-service-installed",
+            path = path.to_string_lossy().into_owned()
+        )
+    );
+    Ok(())
+}
+
+/// Stops and disables the systemd unit of `scope` and removes its unit file (and the D-Bus
+/// service file of the user unit).
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the files cannot be removed or `systemctl` fails.
+pub async fn uninstall(scope: ServiceScope) -> Result<()> {
+    let path = scope.unit_path()?;
+    if scope == ServiceScope::User {
+        activation::uninstall()?;
+    }
+    if path.exists() {
+        systemctl(scope, &["disable", "--now", UNIT_NAME]).await?;
+    }
+    match fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e).with_context(|| format!("Could not remove '{}'", path.display()));
+        }
+    }
+    systemctl(scope, &["daemon-reload"]).await?;
+
+    println!(
+        "{}",
+        tr!(
+            "cli-service-uninstalled",
+            path = path.to_string_lossy().into_owned()
+        )
+    );
+    Ok(())
+}
+
+/// Runs `systemctl` with `args` for the service manager of `scope`.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if `systemctl` cannot be run or fails.
+async fn systemctl(scope: ServiceScope, args: &[&str]) -> Result<()> {
+    let mut command = Command::new("systemctl");
+    if scope == ServiceScope::User {
+        command.arg("--user");
+    }
+
+    let status = command
+        .args(args)
+        .status()
+        .await
+        .context("Could not run systemctl")?;
+    anyhow::ensure!(
+        status.success(),
+        "systemctl {} failed ({})",
+        args.join(" "),
+        status
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    #[test]
+    fn user_unit_notifies_and_is_wanted_by_the_session() {
+        let unit = ServiceScope::User.unit("/home/me/.local/bin/bluetooth-timeout");
+
+        assert!(unit.contains("After=bluetooth.service\n"));
+        assert!(unit.contains("Type=notify\n"));
+        assert!(unit.contains("ExecStart=/home/me/.local/bin/bluetooth-timeout\n"));
+        assert!(unit.contains("WatchdogSec=30\n"));
+        assert!(unit.contains("WantedBy=default.target\n"));
+    }
+
+    #[test]
+    fn system_unit_reads_the_system_configuration() {
+        let unit = ServiceScope::System.unit("/usr/local/bin/bluetooth-timeout");
+
+        assert!(unit.contains("Requires=bluetooth.service\n"));
+        assert!(unit.contains("Environment=XDG_CONFIG_HOME=/etc\n"));
+        assert!(unit.contains("WantedBy=multi-user.target\n"));
+    }
+}
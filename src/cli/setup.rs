@@ -0,0 +1,253 @@
+// -- std imports
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+    time::Duration,
+};
+
+// -- crate imports
+use anyhow::{Context, Result};
+use zbus::fdo::ObjectManagerProxy;
+
+// -- module imports
+use crate::{
+    bluetooth::{cache::DeviceCache, service_proxy::BluetoothServiceProxy},
+    bus,
+    cli::service::{self, ServiceScope},
+    configuration::{self, Conf},
+    daemon, tr,
+};
+
+/// Configuration written if no config file exists yet.
+const TEMPLATE: &str = include_str!("../../contrib/config.yml");
+
+/// Value of `dbus.adapter_path` selecting every adapter.
+const ALL_ADAPTERS: &str = "all";
+
+/// Settings chosen during the setup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Answers {
+    /// Object path of the managed adapter, or [`ALL_ADAPTERS`].
+    adapter: String,
+    /// Timeout of the idle adapter.
+    timeout: Duration,
+    /// Whether notifications are shown.
+    notifications: bool,
+    /// Whether a notification is shown when the countdown starts.
+    on_start: bool,
+}
+
+impl Answers {
+    /// Returns the YAML document `contents` with the chosen settings applied.
+    fn apply(&self, contents: &str) -> String {
+        let timeout = humantime::format_duration(self.timeout).to_string();
+        let values = [
+            (None, "timeout", timeout.as_str()),
+            (
+                Some("notifications"),
+                "enabled",
+                bool_str(self.notifications),
+            ),
+            (Some("notifications"), "on_start", bool_str(self.on_start)),
+            (Some("dbus"), "adapter_path", self.adapter.as_str()),
+        ];
+
+        values
+            .into_iter()
+            .fold(contents.to_string(), |contents, (section, key, value)| {
+                configuration::replace_value(&contents, section, key, value)
+            })
+    }
+}
+
+/// Interactively configures the daemon: asks for the adapter to manage, the timeout and the
+/// notification preferences, writes them to the config file and optionally installs the user
+/// service (see [`service::install`]).
+///
+/// Settings not asked for are kept from the existing config file, or taken from the default
+/// configuration if there is none.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the adapters cannot be listed, no adapter is present, the input cannot
+///   be read, or the config file cannot be written.
+pub async fn run(conf: &Conf) -> Result<()> {
+    let mut input = io::stdin().lock();
+
+    let adapter = choose_adapter(conf, &mut input).await?;
+    let timeout = loop {
+        let default = humantime::format_duration(conf.timeout.default).to_string();
+        let answer = prompt(&mut input, &tr!("cli-setup-timeout"), &default)?;
+        match humantime::parse_duration(&answer) {
+            Ok(timeout) => break timeout,
+            Err(e) => println!("{}", e),
+        }
+    };
+    let notifications = confirm(
+        &mut input,
+        &tr!("cli-setup-notifications"),
+        conf.notifications.enabled,
+    )?;
+    let on_start = notifications
+        && confirm(
+            &mut input,
+            &tr!("cli-setup-on-start"),
+            conf.notifications.on_start,
+        )?;
+    let answers = Answers {
+        adapter,
+        timeout,
+        notifications,
+        on_start,
+    };
+
+    let path = configuration::conf_filepath()?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => TEMPLATE.to_string(),
+        Err(e) => return Err(e).with_context(|| format!("Could not read '{}'", path)),
+    };
+    if let Some(dir) = Path::new(&path).parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Could not create '{}'", dir.display()))?;
+    }
+    fs::write(&path, answers.apply(&contents))
+        .with_context(|| format!("Could not write '{}'", path))?;
+    println!("{}", tr!("cli-setup-written", path = path));
+
+    if confirm(&mut input, &tr!("cli-setup-service"), false)? {
+        service::install(ServiceScope::User).await?;
+    }
+
+    Ok(())
+}
+
+/// Lists the adapters known to BlueZ and lets the user pick one, or all of them. A single adapter
+/// is picked without asking.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the D-Bus calls fail, no adapter is present or the input cannot be
+///   read.
+async fn choose_adapter(conf: &Conf, input: &mut impl BufRead) -> Result<String> {
+    let conn = bus::system().await?;
+    let objects = ObjectManagerProxy::builder(&conn)
+        .destination(conf.dbus.service.as_str())?
+        .path("/")?
+        .build()
+        .await?;
+    let adapters = daemon::list_adapters(&objects, conf).await?;
+
+    match adapters.as_slice() {
+        [] => anyhow::bail!("No Bluetooth adapter present"),
+        [adapter] => {
+            println!(
+                "{}",
+                tr!("cli-setup-adapter-found", adapter = adapter.as_str())
+            );
+            return Ok(adapter.clone());
+        }
+        _ => {}
+    }
+
+    println!("{}", tr!("cli-setup-adapters"));
+    for (number, path) in adapters.iter().enumerate() {
+        let alias = BluetoothServiceProxy::new(path.clone(), DeviceCache::new(Duration::ZERO))
+            .await?
+            .alias()
+            .await
+            .unwrap_or_default();
+        println!("  {}) {} {}", number + 1, path, alias);
+    }
+    println!("  0) {}", tr!("cli-setup-all-adapters"));
+
+    loop {
+        let answer = prompt(input, &tr!("cli-setup-adapter"), "1")?;
+        match answer.parse::<usize>() {
+            Ok(0) => return Ok(ALL_ADAPTERS.to_string()),
+            Ok(number) if number <= adapters.len() => return Ok(adapters[number - 1].clone()),
+            _ => println!("{}", tr!("cli-setup-invalid-choice")),
+        }
+    }
+}
+
+/// Asks `question`, returning the trimmed answer or `default` if it is empty.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the input cannot be read or has ended.
+fn prompt(input: &mut impl BufRead, question: &str, default: &str) -> Result<String> {
+    let answer = ask(input, question, default)?;
+
+    Ok(if answer.is_empty() {
+        default.to_string()
+    } else {
+        answer
+    })
+}
+
+/// Asks the yes/no `question`, returning `default` for an empty answer.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the input cannot be read or has ended.
+fn confirm(input: &mut impl BufRead, question: &str, default: bool) -> Result<bool> {
+    let hint = if default { "Y/n" } else { "y/N" };
+    loop {
+        match ask(input, question, hint)?.to_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("{}", tr!("cli-setup-invalid-choice")),
+        }
+    }
+}
+
+/// Prints `question` with `hint` and returns the trimmed line entered.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the input cannot be read or has ended.
+fn ask(input: &mut impl BufRead, question: &str, hint: &str) -> Result<String> {
+    print!("{} [{}] ", question, hint);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    anyhow::ensure!(input.read_line(&mut answer)? > 0, "Setup aborted");
+
+    Ok(answer.trim().to_string())
+}
+
+/// Returns `value` as YAML.
+fn bool_str(value: bool) -> &'static str {
+    if value { "true" } else { "false" }
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    #[test]
+    fn answers_are_applied_to_the_template() {
+        let answers = Answers {
+            adapter: ALL_ADAPTERS.to_string(),
+            timeout: Duration::from_secs(600),
+            notifications: true,
+            on_start: true,
+        };
+
+        let conf: Conf = serde_yaml::from_str(&answers.apply(TEMPLATE)).unwrap();
+
+        assert!(conf.dbus.manages_all_adapters());
+        assert_eq!(conf.timeout.default, Duration::from_secs(600));
+        assert!(conf.notifications.enabled);
+        assert!(conf.notifications.on_start);
+    }
+
+    #[test]
+    fn confirm_takes_the_default_for_an_empty_answer() {
+        assert!(confirm(&mut "\n".as_bytes(), "?", true).unwrap());
+        assert!(!confirm(&mut "maybe\nno\n".as_bytes(), "?", true).unwrap());
+    }
+}
@@ -0,0 +1,37 @@
+// -- std imports
+use std::time::Duration;
+
+// -- crate imports
+use anyhow::Result;
+
+// -- module imports
+use crate::{bus, configuration::Conf, control::proxy::ControlProxy, tr};
+
+/// Asks the running daemon to postpone the active timeout.
+///
+/// # Arguments
+///
+/// - `duration` - The duration to postpone the timeout by. Defaults to `notifications.snooze`.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the daemon cannot be reached on `dbus.control_bus`.
+pub async fn run(conf: &Conf, duration: Option<Duration>) -> Result<()> {
+    let duration = duration.unwrap_or(conf.notifications().snooze);
+
+    let conn = bus::control().await?;
+    ControlProxy::new(&conn)
+        .await?
+        .snooze(duration.as_secs())
+        .await?;
+
+    println!(
+        "{}",
+        tr!(
+            "cli-snoozed",
+            duration = humantime::format_duration(duration).to_string()
+        )
+    );
+
+    Ok(())
+}
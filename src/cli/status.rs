@@ -0,0 +1,145 @@
+// -- std imports
+use std::time::{Duration, UNIX_EPOCH};
+
+// -- crate imports
+use anyhow::Result;
+
+// -- module imports
+use crate::{
+    bluetooth::{capabilities::AdapterCapabilities, latency::BUCKETS_MS},
+    bus,
+    control::proxy::ControlProxy,
+    tr,
+};
+
+/// Number of days listed by `status --stats`.
+const STATS_DAYS: u32 = 7;
+
+/// Prints the state of the running daemon, its active profile, the time until its timeout expires,
+/// what the adapter's controller supports (if BlueZ tells), its connected devices (with their
+/// battery level, if reported), if `events` is set, its recent state transitions, events and
+/// commands, if `stats` is set, the powered time of the adapter on each of the last
+/// [`STATS_DAYS`] days and, if `latency` is set, how long its events took to be handled.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the daemon cannot be reached on `dbus.control_bus`.
+pub async fn run(events: bool, stats: bool, latency: bool) -> Result<()> {
+    let conn = bus::control().await?;
+    let proxy = ControlProxy::new(&conn).await?;
+
+    println!("{}", tr!("cli-status", state = proxy.state().await?));
+    let profile = proxy.profile().await?;
+    if !profile.is_empty() {
+        println!("{}", tr!("cli-status-profile", profile = profile));
+    }
+    if let Ok(seconds) = u64::try_from(proxy.remaining_seconds().await?) {
+        let remaining = humantime::format_duration(Duration::from_secs(seconds));
+        println!(
+            "{}",
+            tr!("cli-status-remaining", remaining = remaining.to_string())
+        );
+    }
+
+    if let Ok((bredr, roles)) = proxy.capabilities().await {
+        let capabilities = AdapterCapabilities { bredr, roles }.describe();
+        println!(
+            "{}",
+            tr!("cli-status-capabilities", capabilities = capabilities)
+        );
+    }
+
+    let devices = proxy.connected_devices().await?;
+    if !devices.is_empty() {
+        println!("{}", tr!("cli-status-devices"));
+    }
+    for (name, battery) in devices {
+        match battery {
+            0.. => println!(
+                "  {}",
+                tr!("cli-status-device-battery", name = name, percent = battery)
+            ),
+            _ => println!("  {}", name),
+        }
+    }
+
+    if events {
+        let entries = proxy.recent_events().await?;
+        if entries.is_empty() {
+            println!("{}", tr!("cli-status-no-events"));
+        } else {
+            println!("{}", tr!("cli-status-events"));
+        }
+
+        for (millis, message) in entries {
+            let time = UNIX_EPOCH + Duration::from_millis(millis);
+            println!("  {}  {}", humantime::format_rfc3339_seconds(time), message);
+        }
+    }
+
+    if stats {
+        println!("{}", tr!("cli-status-stats"));
+        for (start, powered, off, saved, count) in proxy.power_stats(STATS_DAYS).await? {
+            let date = humantime::format_rfc3339(UNIX_EPOCH + Duration::from_secs(start));
+            let line = tr!(
+                "cli-status-stats-day",
+                date = date.to_string()[..10].to_string(),
+                powered = format_secs(powered),
+                off = format_secs(off),
+                saved = format_secs(saved),
+                count = count
+            );
+            println!("  {}", line);
+        }
+    }
+
+    if latency {
+        let kinds = proxy.event_latencies().await?;
+        if kinds.is_empty() {
+            println!("{}", tr!("cli-status-no-latency"));
+        } else {
+            println!("{}", tr!("cli-status-latency"));
+        }
+
+        for (kind, count, sum, max, buckets) in kinds {
+            let line = tr!(
+                "cli-status-latency-kind",
+                kind = kind,
+                count = count,
+                mean = format_micros(sum / count.max(1)),
+                max = format_micros(max)
+            );
+            println!("  {}", line);
+            println!("    {}", format_buckets(&buckets));
+        }
+    }
+
+    Ok(())
+}
+
+/// Formats `secs` as a duration rounded to whole minutes (e.g., "2h 5m").
+fn format_secs(secs: u64) -> String {
+    match secs / 60 {
+        0 => "0m".to_string(),
+        minutes => humantime::format_duration(Duration::from_secs(minutes * 60)).to_string(),
+    }
+}
+
+/// Formats `micros` as a duration in milliseconds with up to three decimals (e.g., "1.25ms").
+fn format_micros(micros: u64) -> String {
+    let millis = format!("{:.3}", micros as f64 / 1000.0);
+    format!("{}ms", millis.trim_end_matches('0').trim_end_matches('.'))
+}
+
+/// Formats the event counts of the histogram `buckets` (e.g., "≤1ms: 3  ≤2ms: 0  …  >1000ms: 0").
+fn format_buckets(buckets: &[u64]) -> String {
+    let bounds = BUCKETS_MS.iter().map(|ms| format!("≤{}ms", ms));
+    let last = BUCKETS_MS.last().map(|ms| format!(">{}ms", ms));
+
+    bounds
+        .chain(last)
+        .zip(buckets)
+        .map(|(bound, count)| format!("{}: {}", bound, count))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
@@ -0,0 +1,40 @@
+// -- std imports
+use std::time::Duration;
+
+// -- crate imports
+use anyhow::Result;
+
+// -- module imports
+use crate::{bus, control::proxy::ControlProxy, tr};
+
+/// Asks the running daemon to change its timeout.
+///
+/// # Arguments
+///
+/// - `duration` - The new timeout. Restores the configured timeout if `None`.
+/// - `persist` - Whether the timeout is also written to the config file.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the daemon cannot be reached on `dbus.control_bus` or cannot write the
+///   config file.
+pub async fn run(duration: Option<Duration>, persist: bool) -> Result<()> {
+    let conn = bus::control().await?;
+    ControlProxy::new(&conn)
+        .await?
+        .set_timeout(duration.map_or(0, |duration| duration.as_secs()), persist)
+        .await?;
+
+    match duration {
+        Some(duration) => println!(
+            "{}",
+            tr!(
+                "cli-timeout-set",
+                duration = humantime::format_duration(duration).to_string()
+            )
+        ),
+        None => println!("{}", tr!("cli-timeout-reset")),
+    }
+
+    Ok(())
+}
@@ -1,6 +1,6 @@
 // -- std imports
-use std::sync::OnceLock;
-use std::{fs, time::Duration};
+use std::sync::{OnceLock, PoisonError, RwLock};
+use std::{collections::BTreeMap, fs, io, path::PathBuf, time::Duration};
 
 // -- crate imports (conditional)
 // for some reason, this is flagged as unused
@@ -13,75 +13,1438 @@ use anyhow::Result;
 use tracing::{info, warn};
 
 // -- module imports
-use crate::serde_ext::humantime_serde_duration;
+use crate::{
+    error::Error,
+    migration,
+    serde_ext::{
+        humantime_serde_duration, humantime_serde_option_duration, one_or_many, time_of_day,
+    },
+};
 
 /// Global singleton instance of [`Conf`].
 static CONF: OnceLock<Conf> = OnceLock::new();
 
+/// Name of the active profile of [`Conf::profiles`], if any.
+static PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Timeout set at runtime via [`Conf::set_timeout`], replacing the configured timeouts.
+static TIMEOUT_OVERRIDE: RwLock<Option<Duration>> = RwLock::new(None);
+
+/// Environment variable overriding the path to the configuration file (see [`conf_filepath`]).
+pub const CONF_PATH_ENV: &str = "BLUETOOTH_TIMEOUT_CONFIG";
+
 /// Returns the path to the configuration file.
 ///
-/// In debug builds this is `./contrib/config.yml` in the current working directory. In release
-/// builds this uses the XDG base directory and resolves to a path like
-/// `~/.config/bluetooth-timeout/config.yml`.
+/// If set, this is the value of [`CONF_PATH_ENV`]. Otherwise, in debug builds this is
+/// `./contrib/config.yml` in the current working directory. In release builds this uses the XDG
+/// base directory and resolves to a path like `~/.config/bluetooth-timeout/config.yml`.
+///
+/// # Errors
+/// - [`anyhow::Error`] if the config file path cannot be determined (release builds only).
+pub fn conf_filepath() -> Result<String> {
+    if let Some(path) = std::env::var_os(CONF_PATH_ENV) {
+        return Ok(path.to_string_lossy().into_owned());
+    }
+
+    #[cfg(debug_assertions)]
+    {
+        Ok("./contrib/config.yml".into())
+    }
+
+    #[cfg(not(debug_assertions))]
+    {
+        const APP_ID: &str = env!("CARGO_PKG_NAME");
+
+        xdg::BaseDirectories::with_prefix(APP_ID)
+            .get_config_file("config.yml")
+            .map(|path| path.to_string_lossy().to_string())
+            .context("Could not determine config file path")
+    }
+}
+
+/// Returns the path to the state file `file` (e.g., "hci0.idle.yml"), which the daemon keeps
+/// across restarts.
+///
+/// In debug builds this is `./<file>` in the current working directory. In release builds this
+/// uses the XDG base directory and resolves to a path like
+/// `~/.local/state/bluetooth-timeout/<file>`, creating the directory if needed.
+///
+/// # Errors
+/// - [`anyhow::Error`] if the state directory cannot be determined or created (release builds
+///   only).
+pub fn state_path(file: &str) -> Result<PathBuf> {
+    #[cfg(debug_assertions)]
+    {
+        Ok(PathBuf::from(".").join(file))
+    }
+
+    #[cfg(not(debug_assertions))]
+    {
+        const APP_ID: &str = env!("CARGO_PKG_NAME");
+
+        xdg::BaseDirectories::with_prefix(APP_ID)
+            .place_state_file(file)
+            .with_context(|| format!("Could not determine path of state file '{}'", file))
+    }
+}
+
+/// Application configuration.
+///
+/// This type is deserialized from a YAML config file and also provides built-in defaults.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+pub struct Conf {
+    /// Version of the configuration schema. Files of older versions are upgraded when loaded
+    /// (see [`migration::upgrade`]).
+    ///
+    /// Default: [`migration::CURRENT_VERSION`].
+    #[serde(default = "default_version")]
+    pub version: u32,
+
+    /// Duration before an idle adapter is turned off, optionally depending on what made it idle
+    /// (see [`TimeoutConf`]).
+    ///
+    /// Default: `5m`.
+    pub timeout: TimeoutConf,
+
+    /// Notification configuration. Use [`Conf::notifications`], which honors the active profile.
+    pub notifications: NotificationConf,
+
+    /// Coupling of the countdown to the idle state of the user session.
+    #[serde(default)]
+    pub idle: IdleConf,
+
+    /// Processes pausing the countdown while they run.
+    #[serde(default)]
+    pub inhibit: InhibitConf,
+
+    /// Reaction to the screen being locked.
+    #[serde(default)]
+    pub lock: LockConf,
+
+    /// Treating connected devices that are out of range as disconnected.
+    #[serde(default)]
+    pub proximity: ProximityConf,
+
+    /// Devices that no longer count as connected once they have been connected for a while, e.g.
+    /// smart watches or beacons that hold a connection around the clock.
+    ///
+    /// Default: `[]`.
+    #[serde(default)]
+    pub dormant: Vec<DormantConf>,
+
+    /// Only count trusted devices (`Trusted` in BlueZ) as connected, so a device that was paired
+    /// temporarily does not keep the adapter on. Tethering devices always count.
+    ///
+    /// Default: `false`.
+    #[serde(default)]
+    pub trusted_only: bool,
+
+    /// Periods of the day during which the adapter is kept off, e.g. overnight. The adapter is
+    /// turned off when a period starts, and right away again if it is powered on meanwhile.
+    ///
+    /// Default: `[]`.
+    #[serde(default)]
+    pub blackout: Vec<BlackoutConf>,
+
+    /// Automatic power-on of the adapter.
+    #[serde(default)]
+    pub power_on: PowerOnConf,
+
+    /// What happens when the timeout expires; either a single action or a list of actions that
+    /// are run in order.
+    ///
+    /// Default: `power_off`.
+    #[serde(
+        default = "default_action",
+        deserialize_with = "one_or_many::deserialize"
+    )]
+    #[schemars(with = "one_or_many::OneOrMany<TimeoutAction>")]
+    pub action: Vec<TimeoutAction>,
+
+    /// Shell command run by [`TimeoutAction::Command`] via `sh -c`.
+    ///
+    /// The adapter's object path is passed via the `BLUETOOTH_ADAPTER` environment variable.
+    ///
+    /// Default: none.
+    #[serde(default)]
+    pub action_command: Option<String>,
+
+    /// Stages run one after another once the timeout expires, each with its own action and
+    /// notification. If empty, [`Conf::action`] is run as a single stage.
+    ///
+    /// Default: `[]`.
+    #[serde(default)]
+    pub stages: Vec<StageConf>,
+
+    /// How the adapter is turned off.
+    ///
+    /// Default: `powered`.
+    #[serde(default)]
+    pub power_off: PowerOffMethod,
+
+    /// Whether to power the adapter off through the kernel's Bluetooth management API if BlueZ
+    /// rejects setting `Powered` (e.g., because of a polkit policy). Needs `CAP_NET_ADMIN` and the
+    /// `mgmt-fallback` feature; only applies to `power_off: powered`.
+    ///
+    /// Default: `false`.
+    #[serde(default)]
+    pub mgmt_fallback: bool,
+
+    /// Delay after turning the adapter off until it is checked to have stayed off. Some desktop
+    /// environments turn it back on right away; it is then turned off once more and, if that
+    /// does not stick either, soft-blocked via rfkill. `0s` disables the check.
+    ///
+    /// Default: `1s`.
+    #[serde(
+        default = "default_power_off_verify",
+        deserialize_with = "humantime_serde_duration::deserialize"
+    )]
+    #[schemars(with = "String")]
+    pub power_off_verify: Duration,
+
+    /// Duration the adapter's `Powered` state must be stable before it is acted upon. Some
+    /// firmwares briefly toggle it during profile switches, which would otherwise restart the
+    /// timeout. `0s` disables debouncing.
+    ///
+    /// Default: `500ms`.
+    #[serde(
+        default = "default_power_debounce",
+        deserialize_with = "humantime_serde_duration::deserialize"
+    )]
+    #[schemars(with = "String")]
+    pub power_debounce: Duration,
+
+    /// Interval of the `TimerTick` events a running timeout emits with its remaining time, which
+    /// keep observers such as the control interface's `RemainingSeconds` up to date. `0s`
+    /// disables the ticks.
+    ///
+    /// Default: `1s`.
+    #[serde(
+        default = "default_tick_interval",
+        deserialize_with = "humantime_serde_duration::deserialize"
+    )]
+    #[schemars(with = "String")]
+    pub tick_interval: Duration,
+
+    /// How long before a running timeout turns off the adapter (runs the first stage that powers
+    /// it off or soft-blocks it) the `BeforePowerOff` signal is emitted on the control interface.
+    /// `0s` disables the signal.
+    ///
+    /// Default: `10s`.
+    #[serde(
+        default = "default_before_power_off",
+        deserialize_with = "humantime_serde_duration::deserialize"
+    )]
+    #[schemars(with = "String")]
+    pub before_power_off: Duration,
+
+    /// Whether the countdown is paused while a file transfer of the BlueZ OBEX daemon (e.g., a
+    /// file sent from a phone) is in progress. Needs the session bus.
+    ///
+    /// Default: `true`.
+    #[serde(default = "default_pause_on_transfers")]
+    pub pause_on_transfers: bool,
+
+    /// Timeout of the adapter's discoverability, independent of the idle timeout.
+    ///
+    /// Default: `3m`, without notification.
+    #[serde(default = "default_discoverable")]
+    pub discoverable: FlagTimeoutConf,
+
+    /// Timeout of the adapter's pairability, independent of the idle timeout.
+    ///
+    /// Default: `0s` (disabled), without notification.
+    #[serde(default)]
+    pub pairable: FlagTimeoutConf,
+
+    /// Waking the adapter when a known device starts advertising.
+    #[serde(default)]
+    pub wake: WakeConf,
+
+    /// Devices (MAC addresses or names) that are connected whenever the adapter is powered on.
+    ///
+    /// Default: `[]`.
+    #[serde(default)]
+    pub auto_connect: Vec<String>,
+
+    /// Named profiles overriding the timeout and notification settings, switchable at runtime
+    /// (see [`Conf::set_profile`]).
+    ///
+    /// Default: `{}`.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConf>,
+
+    /// Logging configuration.
+    #[serde(default)]
+    pub log: LogConf,
+
+    /// Flavor of the async runtime the daemon runs on.
+    ///
+    /// Default: `multi_thread`.
+    #[serde(default)]
+    pub runtime: RuntimeFlavor,
+
+    /// Timeouts of radios other than Bluetooth by their type, turned off via rfkill once they
+    /// have had no active connection for their timeout (see [`RadioConf`]).
+    ///
+    /// Default: `{}`.
+    #[serde(default)]
+    pub radios: BTreeMap<RadioType, RadioConf>,
+
+    /// D-Bus related configuration.
+    pub dbus: DBusConf,
+}
+
+/// Notification configuration.
+///
+/// This struct is part of the main [`Conf`] struct.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+pub struct NotificationConf {
+    /// Whether notifications are enabled.
+    ///
+    /// Default: `true`.
+    #[serde(default = "default_notification_enabled")]
+    pub enabled: bool,
+
+    /// Notifications to be sent at specified durations before the timeout ends.
+    ///
+    /// Entries are either plain durations (e.g. `5m`) or [`WarningConf`] mappings that override
+    /// the display timeout and icon of that warning.
+    ///
+    /// Default: `[5m, 1m, 30s, 10s]`.
+    #[serde(default = "default_notification_at")]
+    pub at: Vec<WarningConf>,
+
+    /// Whether a notification is sent when the last device disconnects and the countdown starts.
+    ///
+    /// Default: `false`.
+    #[serde(default)]
+    pub on_start: bool,
+
+    /// Whether a notification is sent when the countdown is cancelled because a device connected.
+    /// It replaces the last warning; otherwise, outstanding warnings are withdrawn.
+    ///
+    /// Default: `false`.
+    #[serde(default)]
+    pub on_cancel: bool,
+
+    /// Whether a summary of the previous week is sent once a week (on the first state change
+    /// after Monday 00:00 UTC), telling how often the timeout turned the adapter off and how much
+    /// radio-on time that saved.
+    ///
+    /// Default: `false`.
+    #[serde(default)]
+    pub weekly_summary: bool,
+
+    /// Whether the start and cancel notifications name the device that disconnected or connected.
+    ///
+    /// Default: `true`.
+    #[serde(default = "default_notification_device_names")]
+    pub device_names: bool,
+
+    /// How the remaining time is presented.
+    ///
+    /// Default: `warnings`.
+    #[serde(default)]
+    pub mode: NotificationMode,
+
+    /// Update interval of the resident notification in [`NotificationMode::Countdown`].
+    ///
+    /// Default: `5s`.
+    #[serde(
+        default = "default_notification_countdown_interval",
+        deserialize_with = "humantime_serde_duration::deserialize"
+    )]
+    #[schemars(with = "String")]
+    pub countdown_interval: Duration,
+
+    /// Whether warning notifications carry "Keep on", "Snooze" and "Turn off now" buttons.
+    ///
+    /// Default: `true`.
+    #[serde(default = "default_notification_actions")]
+    pub actions: bool,
+
+    /// Duration the timeout is postponed by when the "Snooze" action is invoked.
+    ///
+    /// Default: `15m`.
+    #[serde(
+        default = "default_notification_snooze",
+        deserialize_with = "humantime_serde_duration::deserialize"
+    )]
+    #[schemars(with = "String")]
+    pub snooze: Duration,
+
+    /// Cool-down during which repeated notifications of the same kind (e.g., "countdown started"
+    /// for a flapping device) or identical notifications are dropped. `0s` disables throttling.
+    ///
+    /// Default: `30s`.
+    #[serde(
+        default = "default_notification_cooldown",
+        deserialize_with = "humantime_serde_duration::deserialize"
+    )]
+    #[schemars(with = "String")]
+    pub cooldown: Duration,
+
+    /// Where notifications are delivered.
+    ///
+    /// Default: `desktop`.
+    #[serde(default)]
+    pub sink: NotificationSinkType,
+
+    /// Which bus notifications are delivered on if [`NotificationConf::sink`] is `desktop`.
+    ///
+    /// Default: `session`.
+    #[serde(default)]
+    pub bus: NotificationBus,
+
+    /// Where notifications go if no notification server is available.
+    ///
+    /// Default: `log`.
+    #[serde(default)]
+    pub fallback: NotificationFallback,
+
+    /// Shell command run for every notification if [`NotificationConf::sink`] or
+    /// [`NotificationConf::fallback`] is `command`.
+    ///
+    /// The notification is passed via the `NOTIFICATION_TITLE` and `NOTIFICATION_BODY`
+    /// environment variables.
+    ///
+    /// Default: none.
+    #[serde(default)]
+    pub fallback_command: Option<String>,
+
+    /// How warnings are handled while Do-Not-Disturb is active. The final notification is always
+    /// sent.
+    ///
+    /// Default: `suppress`.
+    #[serde(default)]
+    pub dnd: DndPolicy,
+
+    /// Urgency levels of the notifications.
+    #[serde(default)]
+    pub urgency: UrgencyConf,
+}
+
+/// Urgency level of a notification, as defined by the Desktop Notifications Specification.
+///
+/// Servers use it to decide how prominently a notification is shown; `critical` notifications are
+/// usually not suppressed by do-not-disturb or focus-assist rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Urgency {
+    /// Informational notifications, may be shown less prominently.
+    Low = 0,
+    /// Regular notifications.
+    Normal = 1,
+    /// Notifications that require attention and should not expire automatically.
+    Critical = 2,
+}
+
+/// Notification urgency configuration.
+///
+/// This struct is part of the [`NotificationConf`] struct.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct UrgencyConf {
+    /// Urgency of warnings with more than [`UrgencyConf::late_from`] remaining.
+    ///
+    /// Default: `low`.
+    pub early: Urgency,
+
+    /// Urgency of warnings with at most [`UrgencyConf::late_from`] remaining.
+    ///
+    /// Default: `normal`.
+    pub late: Urgency,
+
+    /// Urgency of the final "adapter turned off" notification.
+    ///
+    /// Default: `critical`.
+    pub r#final: Urgency,
+
+    /// Remaining duration from which on warnings use the [`UrgencyConf::late`] urgency.
+    ///
+    /// Default: `1m`.
+    #[serde(deserialize_with = "humantime_serde_duration::deserialize")]
+    #[schemars(with = "String")]
+    pub late_from: Duration,
+}
+
+impl Default for UrgencyConf {
+    fn default() -> Self {
+        Self {
+            early: Urgency::Low,
+            late: Urgency::Normal,
+            r#final: Urgency::Critical,
+            late_from: Duration::from_mins(1),
+        }
+    }
+}
+
+impl UrgencyConf {
+    /// Returns the urgency of a warning sent with `remaining` time left.
+    #[cfg(feature = "notifications")]
+    pub fn for_remaining(&self, remaining: Duration) -> Urgency {
+        if remaining <= self.late_from {
+            self.late
+        } else {
+            self.early
+        }
+    }
+}
+
+/// Duration before an idle adapter is turned off, depending on what made it idle.
+///
+/// This struct is part of the [`Conf`] struct. In the config file, it is either a plain duration
+/// (`5m`), used in every context, or a mapping (`{ default: 5m, after_boot: 15m, ... }`) whose
+/// missing contexts fall back to `default`.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+#[serde(from = "TimeoutRepr")]
+pub struct TimeoutConf {
+    /// Timeout in every context that has no timeout of its own.
+    ///
+    /// Default: `5m`.
+    pub default: Duration,
+
+    /// Timeout of an adapter that is idle when the daemon starts (e.g., at boot or login).
+    ///
+    /// Default: `default`.
+    pub after_boot: Option<Duration>,
+
+    /// Timeout after the last connected device disconnected.
+    ///
+    /// Default: `default`.
+    pub after_disconnect: Option<Duration>,
+
+    /// Timeout after the adapter was powered on (e.g., manually, or via [`PowerOnConf`]).
+    ///
+    /// Default: `default`.
+    pub after_manual_on: Option<Duration>,
+}
+
+/// What made the adapter idle, selecting the timeout from [`TimeoutConf`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TimeoutContext {
+    /// The adapter was idle when the daemon started.
+    Boot,
+    /// The last connected device disconnected.
+    Disconnect,
+    /// The adapter was powered on.
+    ManualOn,
+}
+
+impl TimeoutConf {
+    /// Returns the timeout in `context`.
+    pub fn after(&self, context: TimeoutContext) -> Duration {
+        match context {
+            TimeoutContext::Boot => self.after_boot,
+            TimeoutContext::Disconnect => self.after_disconnect,
+            TimeoutContext::ManualOn => self.after_manual_on,
+        }
+        .unwrap_or(self.default)
+    }
+}
+
+impl From<Duration> for TimeoutConf {
+    fn from(default: Duration) -> Self {
+        Self {
+            default,
+            after_boot: None,
+            after_disconnect: None,
+            after_manual_on: None,
+        }
+    }
+}
+
+/// Accepted representations of a [`TimeoutConf`] in the config file.
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+#[serde(untagged)]
+enum TimeoutRepr {
+    Short(
+        #[serde(deserialize_with = "humantime_serde_duration::deserialize")]
+        #[schemars(with = "String")]
+        Duration,
+    ),
+    Full {
+        #[serde(deserialize_with = "humantime_serde_duration::deserialize")]
+        #[schemars(with = "String")]
+        default: Duration,
+        #[serde(
+            default,
+            deserialize_with = "humantime_serde_option_duration::deserialize"
+        )]
+        #[schemars(with = "Option<String>")]
+        after_boot: Option<Duration>,
+        #[serde(
+            default,
+            deserialize_with = "humantime_serde_option_duration::deserialize"
+        )]
+        #[schemars(with = "Option<String>")]
+        after_disconnect: Option<Duration>,
+        #[serde(
+            default,
+            deserialize_with = "humantime_serde_option_duration::deserialize"
+        )]
+        #[schemars(with = "Option<String>")]
+        after_manual_on: Option<Duration>,
+    },
+}
+
+impl From<TimeoutRepr> for TimeoutConf {
+    fn from(repr: TimeoutRepr) -> Self {
+        match repr {
+            TimeoutRepr::Short(default) => TimeoutConf::from(default),
+            TimeoutRepr::Full {
+                default,
+                after_boot,
+                after_disconnect,
+                after_manual_on,
+            } => TimeoutConf {
+                default,
+                after_boot,
+                after_disconnect,
+                after_manual_on,
+            },
+        }
+    }
+}
+
+/// A single warning notification, sent when the given duration remains until the timeout.
+///
+/// This struct is part of the [`NotificationConf`] struct. In the config file, it is either a
+/// plain duration (`5m`) or a mapping (`{ at: 5m, display_timeout: 3000, icon: ... }`).
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+#[serde(from = "WarningRepr")]
+pub struct WarningConf {
+    /// Remaining duration at which the warning is sent.
+    pub at: Duration,
+
+    /// How long the notification is displayed, in milliseconds (`0` = until dismissed).
+    ///
+    /// Default: the notification server's default.
+    pub display_timeout: Option<i32>,
+
+    /// Icon name overriding the default warning icon.
+    ///
+    /// Default: `bluetooth-symbolic`.
+    pub icon: Option<String>,
+}
+
+impl From<Duration> for WarningConf {
+    fn from(at: Duration) -> Self {
+        Self {
+            at,
+            display_timeout: None,
+            icon: None,
+        }
+    }
+}
+
+/// Accepted representations of a [`WarningConf`] in the config file.
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+#[serde(untagged)]
+enum WarningRepr {
+    Short(
+        #[serde(deserialize_with = "humantime_serde_duration::deserialize")]
+        #[schemars(with = "String")]
+        Duration,
+    ),
+    Full {
+        #[serde(deserialize_with = "humantime_serde_duration::deserialize")]
+        #[schemars(with = "String")]
+        at: Duration,
+        display_timeout: Option<i32>,
+        icon: Option<String>,
+    },
+}
+
+impl From<WarningRepr> for WarningConf {
+    fn from(repr: WarningRepr) -> Self {
+        match repr {
+            WarningRepr::Short(at) => WarningConf::from(at),
+            WarningRepr::Full {
+                at,
+                display_timeout,
+                icon,
+            } => WarningConf {
+                at,
+                display_timeout,
+                icon,
+            },
+        }
+    }
+}
+
+/// How the remaining time until the timeout is presented to the user.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationMode {
+    /// Discrete warning notifications at the durations configured in [`NotificationConf::at`].
+    #[default]
+    Warnings,
+
+    /// A single resident notification with a progress bar, updated every
+    /// [`NotificationConf::countdown_interval`] from the first configured warning on.
+    Countdown,
+}
+
+/// Where notifications are delivered.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationSinkType {
+    /// The desktop notification server (`org.freedesktop.Notifications`) on
+    /// [`NotificationConf::bus`], or [`NotificationConf::fallback`] if none is available.
+    #[default]
+    Desktop,
+
+    /// Print the notification to standard output (e.g., a terminal or the journal).
+    Console,
+
+    /// Run [`NotificationConf::fallback_command`].
+    Command,
+
+    /// Drop the notification.
+    None,
+}
+
+/// Bus notifications are delivered on.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationBus {
+    /// The daemon's own session bus (when running as a user service).
+    #[default]
+    Session,
+
+    /// The session bus of the user whose graphical login session is currently active, as reported
+    /// by systemd-logind (when running as a system service).
+    UserSessions,
+}
+
+/// Bus the control interface is served on.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlBus {
+    /// The daemon's own session bus (when running as a user service); every client on it belongs
+    /// to the same user.
+    #[default]
+    Session,
+
+    /// The system bus (when running as a system service), where privileged commands are
+    /// authorized by polkit.
+    System,
+}
+
+/// Fallback channel for notifications if no notification server is available.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationFallback {
+    /// Drop the notification.
+    None,
+
+    /// Write the notification to the log (and thereby the journal).
+    #[default]
+    Log,
+
+    /// Broadcast the notification to all terminals using `wall`.
+    Wall,
+
+    /// Run [`NotificationConf::fallback_command`].
+    Command,
+}
+
+/// Handling of warning notifications while Do-Not-Disturb is active.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DndPolicy {
+    /// Send warnings as usual.
+    Ignore,
+
+    /// Do not send warnings.
+    #[default]
+    Suppress,
+
+    /// Send warnings with `low` urgency, so they end up in the notification history only.
+    Downgrade,
+}
+
+/// Default for [`Conf::version`]: files without a version are of the current schema, unless
+/// [`migration::upgrade`] recognizes an older one.
+fn default_version() -> u32 {
+    migration::CURRENT_VERSION
+}
+
+/// Default for [`NotificationConf::enabled`], used if the field is missing in the config file.
+fn default_notification_enabled() -> bool {
+    true
+}
+
+/// Default for [`NotificationConf::at`], used if the field is missing in the config file.
+fn default_notification_at() -> Vec<WarningConf> {
+    vec![
+        Duration::from_mins(5).into(),
+        Duration::from_mins(1).into(),
+        Duration::from_secs(30).into(),
+        Duration::from_secs(10).into(),
+    ]
+}
+
+/// Default for [`NotificationConf::countdown_interval`], used if the field is missing in the
+/// config file.
+fn default_notification_countdown_interval() -> Duration {
+    Duration::from_secs(5)
+}
+
+/// Default for [`NotificationConf::device_names`], used if the field is missing in the config
+/// file.
+fn default_notification_device_names() -> bool {
+    true
+}
+
+/// Default for [`NotificationConf::cooldown`], used if the field is missing in the config file.
+fn default_notification_cooldown() -> Duration {
+    Duration::from_secs(30)
+}
+
+/// Default for [`NotificationConf::actions`], used if the field is missing in the config file.
+fn default_notification_actions() -> bool {
+    true
+}
+
+/// Default for [`NotificationConf::snooze`], used if the field is missing in the config file.
+fn default_notification_snooze() -> Duration {
+    Duration::from_mins(15)
+}
+
+/// Idle coupling configuration.
+///
+/// This struct is part of the main [`Conf`] struct.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct IdleConf {
+    /// Where the idle state of the user session is read from. Unless this is `none`, the countdown
+    /// only runs while the session is idle and restarts once it becomes idle again.
+    ///
+    /// Default: `none`.
+    pub source: IdleSource,
+
+    /// How often the idle state is queried.
+    ///
+    /// Default: `5s`.
+    #[serde(deserialize_with = "humantime_serde_duration::deserialize")]
+    #[schemars(with = "String")]
+    pub poll_interval: Duration,
+
+    /// Time without user input after which the session is considered idle ([`IdleSource::X11`]
+    /// only, logind decides on its own).
+    ///
+    /// Default: `1m`.
+    #[serde(deserialize_with = "humantime_serde_duration::deserialize")]
+    #[schemars(with = "String")]
+    pub after: Duration,
+
+    /// Whether the countdown is also paused while applications inhibit idling (e.g., video
+    /// playback or presentations). Works with any [`IdleSource`], including `none`. Unlike an
+    /// active session, an inhibitor only freezes the countdown, which continues where it left off
+    /// once the inhibitor is released.
+    ///
+    /// Default: `false`.
+    pub respect_inhibitors: bool,
+}
+
+impl Default for IdleConf {
+    fn default() -> Self {
+        Self {
+            source: IdleSource::default(),
+            poll_interval: Duration::from_secs(5),
+            after: Duration::from_mins(1),
+            respect_inhibitors: false,
+        }
+    }
+}
+
+/// Process inhibition configuration.
+///
+/// This struct is part of the main [`Conf`] struct.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct InhibitConf {
+    /// Names of processes (e.g., `steam` or `obs`) that pause the countdown while any of them
+    /// runs, like an application inhibiting idling (see [`IdleConf::respect_inhibitors`]). A name
+    /// matches the process name or the file name of the executable.
+    ///
+    /// Default: `[]`.
+    pub processes: Vec<String>,
+
+    /// How often the running processes are checked.
+    ///
+    /// Default: `5s`.
+    #[serde(deserialize_with = "humantime_serde_duration::deserialize")]
+    #[schemars(with = "String")]
+    pub poll_interval: Duration,
+}
+
+impl Default for InhibitConf {
+    fn default() -> Self {
+        Self {
+            processes: vec![],
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Source of the idle state of the user session.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleSource {
+    /// The countdown runs regardless of user activity.
+    #[default]
+    None,
+
+    /// The `IdleHint` of systemd-logind, which is set by the desktop environment.
+    Logind,
+
+    /// The time since the last user input, as reported by the X server (MIT-SCREEN-SAVER
+    /// extension).
+    X11,
+
+    /// [`IdleSource::X11`] in X11 sessions, [`IdleSource::Logind`] otherwise (based on
+    /// `XDG_SESSION_TYPE`).
+    Auto,
+}
+
+impl IdleSource {
+    /// Resolves [`IdleSource::Auto`] based on the type of the current session.
+    pub fn resolve(self) -> Self {
+        match self {
+            Self::Auto if std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t == "x11") => Self::X11,
+            Self::Auto => Self::Logind,
+            source => source,
+        }
+    }
+}
+
+/// Screen lock configuration.
+///
+/// This struct is part of the main [`Conf`] struct.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct LockConf {
+    /// What happens when the screen is locked while no devices are connected.
+    ///
+    /// Default: `ignore`.
+    pub action: LockAction,
+
+    /// Remaining duration the countdown is shortened to in [`LockAction::Accelerate`].
+    ///
+    /// Default: `30s`.
+    #[serde(deserialize_with = "humantime_serde_duration::deserialize")]
+    #[schemars(with = "String")]
+    pub timeout: Duration,
+}
+
+impl Default for LockConf {
+    fn default() -> Self {
+        Self {
+            action: LockAction::default(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Reaction to the screen being locked while the adapter is idle.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LockAction {
+    /// Keep counting down as usual.
+    #[default]
+    Ignore,
+
+    /// Turn off the adapter right away.
+    Immediate,
+
+    /// Shorten the remaining countdown to [`LockConf::timeout`].
+    Accelerate,
+}
+
+/// Action carried out when the timeout expires.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutAction {
+    /// Turn off the adapter according to [`Conf::power_off`].
+    PowerOff,
+
+    /// Soft-block the adapter via rfkill, regardless of [`Conf::power_off`].
+    Rfkill,
+
+    /// Disconnect all connected devices, but keep the adapter on.
+    Disconnect,
+
+    /// Make the adapter non-discoverable, but keep it on.
+    DiscoverableOff,
+
+    /// Run [`Conf::action_command`].
+    Command,
+}
+
+impl TimeoutAction {
+    /// Returns `true` if the action turns off the adapter.
+    pub fn turns_off_adapter(self) -> bool {
+        matches!(self, Self::PowerOff | Self::Rfkill)
+    }
+}
+
+/// A stage of the timeout pipeline.
+///
+/// This struct is part of the main [`Conf`] struct.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+pub struct StageConf {
+    /// Delay of the stage after the timeout expired.
+    ///
+    /// Default: `0s`.
+    #[serde(default, deserialize_with = "humantime_serde_duration::deserialize")]
+    #[schemars(with = "String")]
+    pub after: Duration,
+
+    /// Action (or list of actions) run by the stage.
+    #[serde(deserialize_with = "one_or_many::deserialize")]
+    #[schemars(with = "one_or_many::OneOrMany<TimeoutAction>")]
+    pub action: Vec<TimeoutAction>,
+
+    /// Whether a notification is sent once the stage has run.
+    ///
+    /// Default: `true`.
+    #[serde(default = "default_stage_notify")]
+    pub notify: bool,
+}
+
+/// Default for [`StageConf::notify`], used if the field is missing in the config file.
+fn default_stage_notify() -> bool {
+    true
+}
+
+/// Default for [`Conf::action`], used if the field is missing in the config file.
+fn default_action() -> Vec<TimeoutAction> {
+    vec![TimeoutAction::PowerOff]
+}
+
+/// Default for [`Conf::power_off_verify`], used if the field is missing in the config file.
+fn default_power_off_verify() -> Duration {
+    Duration::from_secs(1)
+}
+
+/// Default for [`Conf::power_debounce`], used if the field is missing in the config file.
+fn default_power_debounce() -> Duration {
+    Duration::from_millis(500)
+}
+
+/// Default for [`Conf::tick_interval`], used if the field is missing in the config file.
+fn default_tick_interval() -> Duration {
+    Duration::from_secs(1)
+}
+
+/// Default for [`Conf::before_power_off`], used if the field is missing in the config file.
+fn default_before_power_off() -> Duration {
+    Duration::from_secs(10)
+}
+
+/// Default for [`Conf::pause_on_transfers`], used if the field is missing in the config file.
+fn default_pause_on_transfers() -> bool {
+    true
+}
+
+/// Method used to turn off the adapter.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerOffMethod {
+    /// Set the adapter's `Powered` property to `false`.
+    #[default]
+    Powered,
+
+    /// Soft-block the adapter via rfkill, which survives desktop environments re-enabling it.
+    Rfkill,
+}
+
+/// Flavor of the async runtime.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RuntimeFlavor {
+    /// One worker thread per CPU core.
+    #[default]
+    MultiThread,
+
+    /// All tasks on the main thread, which reduces the memory and thread count of a daemon that
+    /// mostly sleeps.
+    CurrentThread,
+}
+
+/// Proximity configuration.
+///
+/// This struct is part of the main [`Conf`] struct.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct ProximityConf {
+    /// Whether the RSSI of connected devices is monitored.
+    ///
+    /// Default: `false`.
+    pub enabled: bool,
+
+    /// Signal strength (in dBm) below which a device is considered out of range. Devices that
+    /// report no RSSI at all are out of range as well.
+    ///
+    /// Default: `-80`.
+    pub rssi_threshold: i16,
+
+    /// How long a device has to be out of range before it no longer counts as connected.
+    ///
+    /// Default: `5m`.
+    #[serde(deserialize_with = "humantime_serde_duration::deserialize")]
+    #[schemars(with = "String")]
+    pub out_of_range_for: Duration,
+
+    /// How often the RSSI is polled.
+    ///
+    /// Default: `30s`.
+    #[serde(deserialize_with = "humantime_serde_duration::deserialize")]
+    #[schemars(with = "String")]
+    pub poll_interval: Duration,
+}
+
+impl Default for ProximityConf {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rssi_threshold: -80,
+            out_of_range_for: Duration::from_mins(5),
+            poll_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A rule marking a device as dormant after it has been connected for `after`.
+///
+/// This struct is part of the main [`Conf`] struct.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+pub struct DormantConf {
+    /// MAC address or name of the device (see `auto_connect`).
+    pub device: String,
+
+    /// How long the device has to be connected before it no longer counts as connected.
+    #[serde(deserialize_with = "humantime_serde_duration::deserialize")]
+    #[schemars(with = "String")]
+    pub after: Duration,
+}
+
+/// A period of the day during which the adapter is kept off, from `from` (inclusive) to `to`
+/// (exclusive), in local time. Periods ending before they start span midnight (e.g., `22:00` to
+/// `06:00`).
 ///
-/// # Errors
-/// - [`anyhow::Error`] if the config file path cannot be determined (release builds only).
-pub fn conf_filepath() -> Result<String> {
-    #[cfg(debug_assertions)]
-    {
-        Ok("./contrib/config.yml".into())
-    }
+/// This struct is part of the main [`Conf`] struct.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+pub struct BlackoutConf {
+    /// Start of the period, as `HH:MM`.
+    #[serde(deserialize_with = "time_of_day::deserialize")]
+    #[schemars(with = "String")]
+    pub from: chrono::NaiveTime,
 
-    #[cfg(not(debug_assertions))]
-    {
-        const APP_ID: &str = env!("CARGO_PKG_NAME");
+    /// End of the period, as `HH:MM`.
+    #[serde(deserialize_with = "time_of_day::deserialize")]
+    #[schemars(with = "String")]
+    pub to: chrono::NaiveTime,
+}
 
-        xdg::BaseDirectories::with_prefix(APP_ID)
-            .get_config_file("config.yml")
-            .map(|path| path.to_string_lossy().to_string())
-            .context("Could not determine config file path")
+impl BlackoutConf {
+    /// Returns `true` if `time` lies within the period.
+    pub fn contains(&self, time: chrono::NaiveTime) -> bool {
+        if self.from <= self.to {
+            self.from <= time && time < self.to
+        } else {
+            self.from <= time || time < self.to
+        }
     }
 }
 
-/// Application configuration.
+/// A named profile, overriding parts of the main [`Conf`] struct while it is active.
 ///
-/// This type is deserialized from a YAML config file and also provides built-in defaults.
-#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
-pub struct Conf {
-    /// Number of seconds before a timeout is triggered.
+/// This struct is part of the main [`Conf`] struct.
+#[derive(Debug, Default, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct ProfileConf {
+    /// Timeout replacing [`Conf::timeout`], or `off` to never turn off the adapter.
+    ///
+    /// Default: [`Conf::timeout`].
+    pub timeout: Option<ProfileTimeout>,
+
+    /// Notification configuration replacing [`Conf::notifications`] as a whole; fields missing
+    /// here take their defaults, not the values of the main section.
     ///
-    /// Default: `5m1s`.
+    /// Default: [`Conf::notifications`].
+    pub notifications: Option<NotificationConf>,
+    /// Wi-Fi SSIDs or NetworkManager connection names that activate the profile automatically
+    /// once one of them is connected.
+    ///
+    /// Default: `[]`.
+    pub networks: Vec<String>,
+}
+
+/// Timeout of a [`ProfileConf`].
+///
+/// In the config file, it is either `off` or a [`TimeoutConf`].
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+#[serde(from = "ProfileTimeoutRepr")]
+pub enum ProfileTimeout {
+    /// The adapter is never turned off while the profile is active.
+    Off,
+    /// The adapter is turned off after the given timeout.
+    After(TimeoutConf),
+}
+
+/// Accepted representations of a [`ProfileTimeout`] in the config file.
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+#[serde(untagged)]
+enum ProfileTimeoutRepr {
+    Off(OffRepr),
+    After(TimeoutConf),
+}
+
+/// The literal `off`.
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum OffRepr {
+    Off,
+}
+
+impl From<ProfileTimeoutRepr> for ProfileTimeout {
+    fn from(repr: ProfileTimeoutRepr) -> Self {
+        match repr {
+            ProfileTimeoutRepr::Off(OffRepr::Off) => ProfileTimeout::Off,
+            ProfileTimeoutRepr::After(timeouts) => ProfileTimeout::After(timeouts),
+        }
+    }
+}
+
+/// Timeout of an adapter flag (e.g., `Discoverable`), which is turned off once it has been set
+/// for `timeout`.
+///
+/// This struct is part of the main [`Conf`] struct.
+#[derive(Debug, Default, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct FlagTimeoutConf {
+    /// Duration after which the flag is turned off again; `0s` disables the timeout.
     #[serde(deserialize_with = "humantime_serde_duration::deserialize")]
+    #[schemars(with = "String")]
     pub timeout: Duration,
 
-    /// Notification configuration.
-    pub notifications: NotificationConf,
+    /// Whether a notification is sent when the flag is turned off.
+    pub notify: bool,
+}
 
-    /// D-Bus related configuration.
-    pub dbus: DBusConf,
+/// Default for [`Conf::discoverable`], used if the field is missing in the config file.
+fn default_discoverable() -> FlagTimeoutConf {
+    FlagTimeoutConf {
+        timeout: Duration::from_mins(3),
+        notify: false,
+    }
 }
 
-/// Notification configuration.
+/// Advertisement monitoring configuration.
 ///
 /// This struct is part of the main [`Conf`] struct.
-#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
-pub struct NotificationConf {
-    /// Whether notifications are enabled.
+#[derive(Debug, Default, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct WakeConf {
+    /// Whether an advertisement monitor is registered with BlueZ. Requires a powered adapter,
+    /// so it only has an effect while the adapter is kept on (e.g., after a `disconnect` stage).
     ///
-    /// Default: `true`.
+    /// Default: `false`.
     pub enabled: bool,
 
-    /// Notifications to be sent at specified durations before the timeout ends.
+    /// Advertisement patterns of the devices to wake for; an advertisement matching any of them
+    /// is reported by BlueZ.
     ///
-    /// Default: `[5m, 1m, 30s, 10s]`.
-    #[serde(deserialize_with = "humantime_serde_duration::deserialize_vec")]
-    pub at: Vec<Duration>,
+    /// Default: `[]`.
+    pub patterns: Vec<AdPatternConf>,
+}
+
+/// A pattern matched against advertisement data.
+///
+/// This struct is part of the [`WakeConf`] struct.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+pub struct AdPatternConf {
+    /// AD data type to match (e.g., `0xff` for manufacturer specific data).
+    pub ad_type: u8,
+
+    /// Start position of `content` within the AD data.
+    ///
+    /// Default: `0`.
+    #[serde(default)]
+    pub offset: u8,
+
+    /// Bytes to match, as a hex string (e.g., "4c00").
+    pub content: String,
+}
+
+/// Automatic power-on configuration.
+///
+/// This struct is part of the main [`Conf`] struct.
+#[derive(Debug, Default, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct PowerOnConf {
+    /// Whether the adapter is powered on when the daemon starts.
+    ///
+    /// Default: `false`.
+    pub at_startup: bool,
+
+    /// Whether the adapter is powered on when a graphical login session begins (mostly useful
+    /// when running as a system service).
+    ///
+    /// Default: `false`.
+    pub on_session_start: bool,
+
+    /// Whether the adapter is set to the powered state it was left in (on or off) when the daemon
+    /// starts, overriding BlueZ's `AutoEnable`. The state is remembered in a state file and not
+    /// updated once the system is shutting down. Takes precedence over `at_startup`, which only
+    /// applies if no state was remembered yet.
+    ///
+    /// Default: `false`.
+    pub restore: bool,
+
+    /// Duration after which an adapter turned off by the timeout is powered on again (e.g., `8h`
+    /// to have it back the next morning), unless it was powered on in the meantime. Turning it
+    /// off manually or during a blackout period does not start the timer.
+    ///
+    /// Default: none.
+    #[serde(deserialize_with = "humantime_serde_option_duration::deserialize")]
+    #[schemars(with = "Option<String>")]
+    pub after_timeout: Option<Duration>,
+}
+
+/// Logging configuration.
+///
+/// This struct is part of the main [`Conf`] struct.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct LogConf {
+    /// Whether logs are printed to stdout (captured by `systemd`).
+    ///
+    /// Default: `true`.
+    pub stdout: bool,
+
+    /// Whether logs are written to the log file.
+    ///
+    /// Default: `true`.
+    pub file: bool,
+
+    /// Path of the log file. Rotated files are placed next to it, keeping its stem and extension.
+    ///
+    /// Default: `~/.local/share/bluetooth-timeout/bluetooth-timeout.log`
+    /// (`./bluetooth-timeout.log` in debug builds).
+    pub path: Option<PathBuf>,
+
+    /// How often the log file is rotated. Rotated files carry the date in their name (e.g.,
+    /// `bluetooth-timeout.2025-01-31.log`).
+    ///
+    /// Default: `daily`.
+    pub rotation: LogRotation,
+
+    /// Number of log files kept when rotating; older files are deleted.
+    ///
+    /// Default: `7`.
+    pub max_files: usize,
+
+    /// Format of the log output on stdout and in the log file.
+    ///
+    /// Default: `text`.
+    pub format: LogFormat,
+
+    /// Filter directives selecting what is logged, in the syntax of `RUST_LOG` (e.g., `info` or
+    /// `info,bluetooth_timeout::bluetooth::observer=debug`). The `RUST_LOG` environment variable
+    /// takes precedence, if set.
+    ///
+    /// Default: `info` (`debug` in debug builds).
+    pub level: Option<String>,
+
+    /// Number of recent state transitions, events and commands kept in memory, listed by
+    /// `bluetooth-timeout status --events` and included in crash reports.
+    ///
+    /// Default: `50`.
+    pub history: usize,
+
+    /// Interval at which the battery level of the connected devices that report one is logged
+    /// (e.g., `30m`), to tell whether a device disconnected because its battery ran out.
+    ///
+    /// Default: none (not logged).
+    #[serde(deserialize_with = "humantime_serde_option_duration::deserialize")]
+    #[schemars(with = "Option<String>")]
+    pub battery_interval: Option<Duration>,
+
+    /// OTLP/HTTP endpoint of an OpenTelemetry collector (e.g., Jaeger) the tracing spans are
+    /// exported to, such as `http://localhost:4318/v1/traces`. Handling a Bluetooth event is
+    /// traced from its D-Bus signal over the state transition to the resulting actions, filtered
+    /// like the logs. Requires the `otlp` feature.
+    ///
+    /// Default: `None` (no export).
+    pub otlp_endpoint: Option<String>,
+
+    /// OTLP/HTTP endpoint of an OpenTelemetry collector the event latency histograms (see
+    /// `status --latency`) are exported to every minute, such as
+    /// `http://localhost:4318/v1/metrics`. Requires the `otlp` and `metrics` features.
+    ///
+    /// Default: `None` (no export).
+    pub otlp_metrics_endpoint: Option<String>,
+}
+
+impl Default for LogConf {
+    fn default() -> Self {
+        Self {
+            stdout: true,
+            file: true,
+            path: None,
+            rotation: LogRotation::default(),
+            max_files: 7,
+            format: LogFormat::default(),
+            level: None,
+            history: 50,
+            battery_interval: None,
+            otlp_endpoint: None,
+            otlp_metrics_endpoint: None,
+        }
+    }
+}
+
+/// Format of the log output.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+
+    /// One JSON object per line, with the fields of the event and its span as top-level keys
+    /// (e.g., for Loki or Vector).
+    Json,
+}
+
+/// Rotation interval of the log file.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LogRotation {
+    /// Never rotate; the log file grows indefinitely.
+    Never,
+
+    /// Start a new log file every hour.
+    Hourly,
+
+    /// Start a new log file every day.
+    #[default]
+    Daily,
+
+    /// Start a new log file every week.
+    Weekly,
 }
 
 /// D-Bus related configuration.
 ///
 /// This struct is part of the main [`Conf`] struct.
-#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
 pub struct DBusConf {
     /// D-Bus service name (usually "org.bluez").
     ///
@@ -93,7 +1456,9 @@ pub struct DBusConf {
     /// Default: "org.bluez.Adapter1".
     pub adapter_iface: String,
 
-    /// D-Bus object path for the Bluetooth adapter to manage.
+    /// D-Bus object path for the Bluetooth adapter to manage, or [`ALL_ADAPTERS`] to manage every
+    /// adapter present (including ones added later, e.g. by a docking station) with the same
+    /// policy.
     ///
     /// Default: "/org/bluez/hci0".
     pub adapter_path: String,
@@ -102,37 +1467,305 @@ pub struct DBusConf {
     ///
     /// Default: "org.bluez.Device1".
     pub device_iface: String,
+
+    /// D-Bus interface name for the battery level of Bluetooth devices.
+    ///
+    /// Default: "org.bluez.Battery1".
+    #[serde(default = "default_battery_iface")]
+    pub battery_iface: String,
+
+    /// D-Bus interface name for the network (PAN) connections of Bluetooth devices, used to detect
+    /// tethering.
+    ///
+    /// Default: "org.bluez.Network1".
+    #[serde(default = "default_network_iface")]
+    pub network_iface: String,
+
+    /// How long the adapter's devices and their properties are cached between queries. The cache
+    /// is also dropped whenever BlueZ signals a change, so this only bounds the staleness of
+    /// changes BlueZ does not signal. `0s` disables caching.
+    ///
+    /// Default: `2s`.
+    #[serde(
+        default = "default_device_cache_ttl",
+        deserialize_with = "humantime_serde_duration::deserialize"
+    )]
+    #[schemars(with = "String")]
+    pub device_cache_ttl: Duration,
+
+    /// How long a D-Bus call to BlueZ may take before it is cancelled, so that a hanging
+    /// `bluetoothd` cannot stall the daemon. `0s` waits indefinitely.
+    ///
+    /// Default: `5s`.
+    #[serde(
+        default = "default_call_timeout",
+        deserialize_with = "humantime_serde_duration::deserialize"
+    )]
+    #[schemars(with = "String")]
+    pub call_timeout: Duration,
+
+    /// How often a D-Bus call to BlueZ is attempted before giving up, if it fails in a way that
+    /// is likely to go away (e.g., it timed out or BlueZ is busy). `1` disables retrying.
+    ///
+    /// Default: 3.
+    #[serde(default = "default_call_attempts")]
+    pub call_attempts: u32,
+
+    /// Delay before the first retry of a failed D-Bus call. It doubles with every further retry,
+    /// and a random part of up to half of it is taken off, so that retries spread out.
+    ///
+    /// Default: `200ms`.
+    #[serde(
+        default = "default_retry_backoff",
+        deserialize_with = "humantime_serde_duration::deserialize"
+    )]
+    #[schemars(with = "String")]
+    pub retry_backoff: Duration,
+
+    /// Address of the system bus BlueZ and logind are reached on (e.g.,
+    /// "unix:path=/run/dbus/system_bus_socket"), e.g. when running in a container.
+    ///
+    /// Default: the standard system bus, or `DBUS_SYSTEM_BUS_ADDRESS` if set.
+    #[serde(default)]
+    pub system_bus: Option<String>,
+
+    /// Address of the session bus used for notifications, the control interface and the screen
+    /// lock.
+    ///
+    /// Default: the bus of the user session, or `DBUS_SESSION_BUS_ADDRESS` if set.
+    #[serde(default)]
+    pub session_bus: Option<String>,
+
+    /// Bus the control interface is served on, and CLI commands reach the daemon on. On the
+    /// system bus, commands that pause or change the timeout or power the adapter off must be
+    /// authorized by polkit.
+    ///
+    /// Default: `session`.
+    #[serde(default)]
+    pub control_bus: ControlBus,
+}
+
+/// Type of a radio other than Bluetooth, as named by rfkill.
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, serde::Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RadioType {
+    /// Wi-Fi.
+    Wlan,
+
+    /// Mobile broadband (e.g., LTE modems).
+    Wwan,
+}
+
+/// Timeout of the radios of a [`RadioType`].
+///
+/// This struct is part of the main [`Conf`] struct.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, schemars::JsonSchema)]
+pub struct RadioConf {
+    /// Duration the radios may be on without an active connection before they are soft-blocked.
+    #[serde(deserialize_with = "humantime_serde_duration::deserialize")]
+    #[schemars(with = "String")]
+    pub timeout: Duration,
+
+    /// How often the connections of the radios are checked.
+    ///
+    /// Default: `30s`.
+    #[serde(
+        default = "default_radio_poll_interval",
+        deserialize_with = "humantime_serde_duration::deserialize"
+    )]
+    #[schemars(with = "String")]
+    pub poll_interval: Duration,
+}
+
+/// Default for [`RadioConf::poll_interval`], used if the field is missing in the config file.
+fn default_radio_poll_interval() -> Duration {
+    Duration::from_secs(30)
+}
+
+/// Value of [`DBusConf::adapter_path`] that manages every adapter.
+pub const ALL_ADAPTERS: &str = "all";
+
+impl DBusConf {
+    /// Returns whether every adapter is managed, instead of the one at `adapter_path`.
+    pub fn manages_all_adapters(&self) -> bool {
+        self.adapter_path == ALL_ADAPTERS
+    }
+}
+
+/// Default for [`DBusConf::battery_iface`], used if the field is missing in the config file.
+fn default_battery_iface() -> String {
+    "org.bluez.Battery1".to_string()
+}
+
+/// Default for [`DBusConf::network_iface`], used if the field is missing in the config file.
+fn default_network_iface() -> String {
+    "org.bluez.Network1".to_string()
+}
+
+/// Default for [`DBusConf::device_cache_ttl`], used if the field is missing in the config file.
+fn default_device_cache_ttl() -> Duration {
+    Duration::from_secs(2)
+}
+
+/// Default for [`DBusConf::call_timeout`], used if the field is missing in the config file.
+fn default_call_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+/// Default for [`DBusConf::call_attempts`], used if the field is missing in the config file.
+fn default_call_attempts() -> u32 {
+    3
+}
+
+/// Default for [`DBusConf::retry_backoff`], used if the field is missing in the config file.
+fn default_retry_backoff() -> Duration {
+    Duration::from_millis(200)
 }
 
 impl Default for Conf {
     fn default() -> Self {
         Self {
-            timeout: Duration::from_mins(5),
+            version: migration::CURRENT_VERSION,
+            timeout: TimeoutConf::from(Duration::from_mins(5)),
             notifications: NotificationConf {
-                enabled: true,
-                at: vec![
-                    Duration::from_mins(5),
-                    Duration::from_mins(1),
-                    Duration::from_secs(30),
-                    Duration::from_secs(10),
-                ],
+                enabled: default_notification_enabled(),
+                at: default_notification_at(),
+                on_start: false,
+                on_cancel: false,
+                weekly_summary: false,
+                device_names: default_notification_device_names(),
+                mode: NotificationMode::default(),
+                countdown_interval: default_notification_countdown_interval(),
+                actions: default_notification_actions(),
+                snooze: default_notification_snooze(),
+                cooldown: default_notification_cooldown(),
+                sink: NotificationSinkType::default(),
+                bus: NotificationBus::default(),
+                fallback: NotificationFallback::default(),
+                fallback_command: None,
+                dnd: DndPolicy::default(),
+                urgency: UrgencyConf::default(),
             },
+            idle: IdleConf::default(),
+            inhibit: InhibitConf::default(),
+            lock: LockConf::default(),
+            proximity: ProximityConf::default(),
+            dormant: vec![],
+            trusted_only: false,
+            blackout: vec![],
+            power_on: PowerOnConf::default(),
+            action: default_action(),
+            action_command: None,
+            stages: vec![],
+            power_off: PowerOffMethod::default(),
+            mgmt_fallback: false,
+            power_off_verify: default_power_off_verify(),
+            power_debounce: default_power_debounce(),
+            tick_interval: default_tick_interval(),
+            before_power_off: default_before_power_off(),
+            pause_on_transfers: default_pause_on_transfers(),
+            discoverable: default_discoverable(),
+            pairable: FlagTimeoutConf::default(),
+            wake: WakeConf::default(),
+            auto_connect: vec![],
+            profiles: BTreeMap::new(),
+            log: LogConf::default(),
+            runtime: RuntimeFlavor::default(),
+            radios: BTreeMap::new(),
             dbus: DBusConf {
                 service: "org.bluez".to_string(),
                 adapter_iface: "org.bluez.Adapter1".to_string(),
                 device_iface: "org.bluez.Device1".to_string(),
+                battery_iface: default_battery_iface(),
+                network_iface: default_network_iface(),
+                device_cache_ttl: default_device_cache_ttl(),
+                call_timeout: default_call_timeout(),
+                call_attempts: default_call_attempts(),
+                retry_backoff: default_retry_backoff(),
                 adapter_path: "/org/bluez/hci0".to_string(),
+                system_bus: None,
+                session_bus: None,
+                control_bus: ControlBus::default(),
             },
         }
     }
 }
 
+/// Returns the YAML document `contents` with `key` set to `value`, either at the top level or in
+/// the top-level mapping `section` (indented by two spaces, as in `contrib/config.yml`).
+///
+/// The line of `key` and the lines of a mapping or list below it are replaced; a trailing comment
+/// on that line is kept. A missing top-level key is prepended, a missing key of a section is added
+/// at its top, and a missing section is appended.
+#[cfg(feature = "cli-control")]
+pub fn replace_value(contents: &str, section: Option<&str>, key: &str, value: &str) -> String {
+    let indent = if section.is_some() { "  " } else { "" };
+    let prefix = format!("{}{}:", indent, key);
+    let (nested, item) = (format!("{} ", indent), format!("{}-", indent));
+    let mut lines = Vec::new();
+    let mut in_section = section.is_none();
+    let mut section_line = None;
+    let mut replaced = false;
+    let mut in_value = false;
+
+    for line in contents.lines() {
+        if in_value {
+            if line.starts_with(&nested) || line.starts_with(&item) {
+                continue;
+            }
+            in_value = false;
+        }
+
+        if let Some(section) = section
+            && !line.starts_with([' ', '\t', '#'])
+            && !line.is_empty()
+        {
+            in_section = line
+                .strip_prefix(section)
+                .is_some_and(|rest| rest.starts_with(':'));
+            if in_section {
+                section_line = Some(lines.len());
+            }
+        }
+
+        match line.strip_prefix(&prefix) {
+            Some(rest) if in_section && !replaced => {
+                let (old, comment) = rest.split_once(" #").unwrap_or((rest, ""));
+                in_value = old.trim().is_empty();
+                lines.push(match comment {
+                    "" => format!("{} {}", prefix, value),
+                    comment => format!("{} {} #{}", prefix, value, comment),
+                });
+                replaced = true;
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    if !replaced {
+        let line = format!("{} {}", prefix, value);
+        match (section, section_line) {
+            (None, _) => lines.insert(0, line),
+            (Some(_), Some(index)) => lines.insert(index + 1, line),
+            (Some(section), None) => lines.extend([format!("{}:", section), line]),
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
 impl Conf {
     /// Loads the configuration from [`conf_filepath`] into the global instance.
     ///
-    /// If the path cannot be determined or the file cannot be read or parsed, falls back to
-    /// [`Conf::instance`], which uses the default configuration.
-    pub fn load() -> &'static Self {
+    /// If the path cannot be determined, falls back to [`Conf::instance`], which uses the default
+    /// configuration.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::ConfigInvalid`] if the config file exists but cannot be read or parsed.
+    pub fn load() -> Result<&'static Self, Error> {
         match conf_filepath() {
             Ok(p) => Self::from_file(&p),
             Err(e) => {
@@ -140,7 +1773,7 @@ impl Conf {
                     "Could not determine config file path: {}. Falling back to defaults.",
                     e
                 );
-                Self::instance()
+                Ok(Self::instance())
             }
         }
     }
@@ -148,39 +1781,202 @@ impl Conf {
     /// Initializes the global configuration from the YAML file at `path`.
     ///
     /// If the configuration is already initialized, the existing instance is returned and the file
-    /// is ignored. On any read or parse error, falls back to [`Conf::default`].
-    pub fn from_file(path: &str) -> &'static Self {
+    /// is ignored. If the file does not exist, falls back to [`Conf::default`]. A file of an older
+    /// schema version is upgraded first.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::ConfigInvalid`] if the file exists but cannot be read, upgraded (see
+    ///   [`migration::upgrade`]) or parsed.
+    pub fn from_file(path: &str) -> Result<&'static Self, Error> {
         if let Some(conf) = CONF.get() {
             warn!(
                 "Conf::from_file({}) called, but configuration is already initialized. Using \
                     existing configuration and ignoring the file.",
                 path
             );
-            return conf;
+            return Ok(conf);
         }
 
-        CONF.get_or_init(|| {
-            fs::read_to_string(path)
-                .map_err(|e| {
-                    warn!(
-                        "Could not read config file '{}': {}. Falling back to defaults.",
-                        path, e
-                    );
-                })
-                .and_then(|contents| {
-                    serde_yaml::from_str::<Conf>(&contents).map_err(|e| {
-                        warn!(
-                            "Could not parse config file '{}': {}. Falling back to defaults.",
-                            path, e
-                        );
-                    })
-                })
-                .map(|conf| {
-                    info!("Successfully loaded configuration from '{}'.", path);
-                    conf
-                })
-                .unwrap_or_else(|_| Conf::default())
-        })
+        let invalid = |reason: String| Error::ConfigInvalid {
+            path: path.to_string(),
+            reason,
+        };
+        let conf = match fs::read_to_string(path) {
+            Ok(contents) => {
+                let upgraded =
+                    migration::upgrade(path, &contents).map_err(|e| invalid(format!("{:#}", e)))?;
+                let conf = serde_yaml::from_str::<Conf>(upgraded.as_deref().unwrap_or(&contents))
+                    .map_err(|e| invalid(e.to_string()))?;
+                info!("Successfully loaded configuration from '{}'.", path);
+                conf
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                warn!(
+                    "Config file '{}' does not exist. Falling back to defaults.",
+                    path
+                );
+                Conf::default()
+            }
+            Err(e) => return Err(invalid(e.to_string())),
+        };
+
+        Ok(CONF.get_or_init(|| conf))
+    }
+
+    /// Returns what happens when the timeout expires, i.e. the most drastic action of the first
+    /// stage: `off`, `disconnect`, `discoverable` or `command`.
+    ///
+    /// This selects the wording of the start and warning notifications.
+    #[cfg(feature = "notifications")]
+    pub fn expiry_effect(&self) -> &'static str {
+        let actions = self
+            .stages()
+            .into_iter()
+            .next()
+            .map(|stage| stage.action)
+            .unwrap_or_default();
+
+        if actions.iter().any(|action| action.turns_off_adapter()) {
+            "off"
+        } else if actions.contains(&TimeoutAction::Disconnect) {
+            "disconnect"
+        } else if actions.contains(&TimeoutAction::DiscoverableOff) {
+            "discoverable"
+        } else {
+            "command"
+        }
+    }
+
+    /// Returns the stages of the timeout pipeline, sorted by their delay.
+    ///
+    /// Without configured stages, this is a single stage running [`Conf::action`] when the
+    /// timeout expires.
+    pub fn stages(&self) -> Vec<StageConf> {
+        if self.stages.is_empty() {
+            return vec![StageConf {
+                after: Duration::ZERO,
+                action: self.action.clone(),
+                notify: true,
+            }];
+        }
+
+        let mut stages = self.stages.clone();
+        stages.sort_by_key(|stage| stage.after);
+        stages
+    }
+
+    /// Returns the name of the active profile, or `None` if the main configuration applies.
+    pub fn profile(&self) -> Option<String> {
+        PROFILE
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Activates the profile `name` of [`Conf::profiles`], or the main configuration if `None`.
+    ///
+    /// Only later reads of the configuration (e.g., via [`Conf::notifications`] or
+    /// [`Conf::timeouts`]) are affected; running timers have to be restarted by the caller.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if no profile `name` is configured.
+    pub fn set_profile(&self, name: Option<&str>) -> Result<()> {
+        if let Some(name) = name
+            && !self.profiles.contains_key(name)
+        {
+            anyhow::bail!("Unknown profile '{}'", name);
+        }
+
+        *PROFILE.write().unwrap_or_else(PoisonError::into_inner) = name.map(str::to_string);
+        match name {
+            Some(name) => info!("Switched to profile '{}'.", name),
+            None => info!("Switched back to the main configuration."),
+        }
+
+        Ok(())
+    }
+
+    /// Sets the timeout to `timeout` in every context, regardless of the active profile, until the
+    /// daemon exits; `None` restores the configured timeouts.
+    ///
+    /// As with [`Conf::set_profile`], only later reads via [`Conf::timeouts`] are affected.
+    #[cfg(feature = "cli-control")]
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        *TIMEOUT_OVERRIDE
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = timeout;
+        match timeout {
+            Some(timeout) => info!(
+                "Timeout set to {} until restart.",
+                humantime::format_duration(timeout)
+            ),
+            None => info!("Timeout reset to the configuration."),
+        }
+    }
+
+    /// Writes `timeout` as the top-level `timeout` of the config file at `path`, so it survives
+    /// a restart. Other settings and comments are kept; a `timeout` mapping is replaced as a
+    /// whole.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the file cannot be read or written.
+    #[cfg(feature = "cli-control")]
+    pub fn persist_timeout(path: &str, timeout: Duration) -> Result<()> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let timeout = humantime::format_duration(timeout).to_string();
+        fs::write(path, replace_value(&contents, None, "timeout", &timeout))?;
+        info!("Wrote timeout to '{}'.", path);
+        Ok(())
+    }
+
+    /// Returns `true` if any profile is bound to networks, which have to be watched then.
+    pub fn has_network_profiles(&self) -> bool {
+        self.profiles
+            .values()
+            .any(|profile| !profile.networks.is_empty())
+    }
+
+    /// Returns the active profile, if any.
+    fn active_profile(&self) -> Option<&ProfileConf> {
+        self.profile().and_then(|name| self.profiles.get(&name))
+    }
+
+    /// Returns the notification configuration of the active profile, or [`Conf::notifications`]
+    /// if it has none.
+    pub fn notifications(&self) -> &NotificationConf {
+        self.active_profile()
+            .and_then(|profile| profile.notifications.as_ref())
+            .unwrap_or(&self.notifications)
+    }
+
+    /// Returns the timeouts of the active profile, or [`Conf::timeout`] if it has none. Returns
+    /// `None` if the active profile turned the timeout `off`.
+    ///
+    /// A timeout set via [`Conf::set_timeout`] takes precedence over both in every context.
+    pub fn timeouts(&self) -> Option<TimeoutConf> {
+        if let Some(timeout) = *TIMEOUT_OVERRIDE
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+        {
+            return Some(timeout.into());
+        }
+
+        match self
+            .active_profile()
+            .and_then(|profile| profile.timeout.as_ref())
+        {
+            Some(ProfileTimeout::Off) => None,
+            Some(ProfileTimeout::After(timeouts)) => Some(timeouts.clone()),
+            None => Some(self.timeout.clone()),
+        }
     }
 
     /// Returns the global configuration instance.
@@ -196,4 +1992,72 @@ impl Conf {
             Conf::default()
         })
     }
+
+    /// Returns the global configuration instance for unit tests.
+    ///
+    /// Initializes it with [`Conf::default`], but with notifications and the watching of file
+    /// transfers disabled, so tests do not depend on a session bus.
+    #[cfg(test)]
+    pub fn for_tests() -> &'static Self {
+        CONF.get_or_init(|| {
+            let mut conf = Conf::default();
+            conf.notifications.enabled = false;
+            conf.pause_on_transfers = false;
+            conf
+        })
+    }
+}
+
+#[cfg(all(test, feature = "cli-control"))]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    #[test]
+    fn replace_value_keeps_other_settings_and_comments() {
+        let contents = "timeout: 5m # idle timeout\nnotifications:\n  enabled: true\n";
+
+        assert_eq!(
+            replace_value(contents, None, "timeout", "10m"),
+            "timeout: 10m # idle timeout\nnotifications:\n  enabled: true\n"
+        );
+    }
+
+    #[test]
+    fn replace_value_replaces_a_mapping_as_a_whole() {
+        let contents = "timeout:\n  default: 5m\n  after_boot: 15m\nlog:\n  level: info\n";
+
+        assert_eq!(
+            replace_value(contents, None, "timeout", "1m 30s"),
+            "timeout: 1m 30s\nlog:\n  level: info\n"
+        );
+    }
+
+    #[test]
+    fn replace_value_adds_a_missing_timeout() {
+        assert_eq!(
+            replace_value("log:\n  level: info\n", None, "timeout", "2m"),
+            "timeout: 2m\nlog:\n  level: info\n"
+        );
+    }
+
+    #[test]
+    fn replace_value_sets_keys_of_sections() {
+        let contents =
+            "notifications:\n  enabled: true\n  at:\n  - 5m\n  - 1m\nlog:\n  file: true\n";
+
+        assert_eq!(
+            replace_value(contents, Some("notifications"), "at", "[]"),
+            "notifications:\n  enabled: true\n  at: []\nlog:\n  file: true\n"
+        );
+        assert_eq!(
+            replace_value(contents, Some("log"), "enabled", "false"),
+            "notifications:\n  enabled: true\n  at:\n  - 5m\n  - 1m\nlog:\n  enabled: false\n  \
+             file: true\n"
+        );
+        assert_eq!(
+            replace_value("timeout: 5m\n", Some("dbus"), "adapter_path", "all"),
+            "timeout: 5m\ndbus:\n  adapter_path: all\n"
+        );
+    }
 }
@@ -0,0 +1,333 @@
+// -- std imports
+use std::time::{Duration, UNIX_EPOCH};
+
+// -- crate imports
+use tokio::{
+    sync::{mpsc, watch},
+    time::Instant,
+};
+use tracing::info;
+use zbus::{Connection, fdo, interface, message::Header, object_server::SignalEmitter};
+
+// -- module imports
+use crate::{
+    bluetooth::{
+        backend::SharedBackend,
+        history::History,
+        service::{BluetoothServiceCommand, BluetoothServiceState},
+        stats::{self, PowerStats},
+    },
+    configuration::{Conf, conf_filepath},
+    control::{
+        DayTotals, KindLatencies, MAX_SECONDS,
+        polkit::{self, ACTION_CONTROL_TIMEOUT, ACTION_POWER_OFF},
+    },
+};
+
+/// The daemon's control interface, exported on the session bus (or the system bus, see
+/// `dbus.control_bus`).
+///
+/// Method calls are translated into [`BluetoothServiceCommand`]s and forwarded to the running
+/// Bluetooth service. The state, remaining timeout and history of the service, and the connected
+/// devices can be queried.
+#[derive(Debug)]
+pub struct ControlInterface {
+    /// Sender for commands to the Bluetooth service.
+    commands: mpsc::UnboundedSender<BluetoothServiceCommand>,
+    /// Current state of the Bluetooth service.
+    state: watch::Receiver<BluetoothServiceState>,
+    /// Recent state transitions, events and commands of the Bluetooth service.
+    history: History,
+    /// Totals of the powered time of the managed Bluetooth adapter.
+    stats: PowerStats,
+    /// Deadline of the active timeout of the Bluetooth service, if any.
+    deadline: watch::Receiver<Option<Instant>>,
+    /// Backend of the managed Bluetooth adapter.
+    backend: SharedBackend,
+}
+
+impl ControlInterface {
+    /// Creates a new control interface forwarding commands to `commands` and reporting `state`,
+    /// `deadline`, `history` and `stats` of the Bluetooth service and the devices connected to
+    /// `backend`.
+    pub fn new(
+        commands: mpsc::UnboundedSender<BluetoothServiceCommand>,
+        state: watch::Receiver<BluetoothServiceState>,
+        history: History,
+        stats: PowerStats,
+        deadline: watch::Receiver<Option<Instant>>,
+        backend: SharedBackend,
+    ) -> Self {
+        Self {
+            commands,
+            state,
+            history,
+            stats,
+            deadline,
+            backend,
+        }
+    }
+
+    /// Converts the `seconds` passed to a method into a [`Duration`].
+    ///
+    /// # Errors
+    ///
+    /// - [`fdo::Error::InvalidArgs`] if `seconds` exceeds [`MAX_SECONDS`].
+    fn duration(seconds: u64) -> fdo::Result<Duration> {
+        if seconds > MAX_SECONDS {
+            return Err(fdo::Error::InvalidArgs(format!(
+                "Duration of {}s exceeds the maximum of {}s",
+                seconds, MAX_SECONDS
+            )));
+        }
+
+        Ok(Duration::from_secs(seconds))
+    }
+
+    /// Forwards `command` to the Bluetooth service.
+    fn send(&self, command: BluetoothServiceCommand) -> fdo::Result<()> {
+        info!("Control interface received command: {:?}", command);
+        self.commands
+            .send(command)
+            .map_err(|_| fdo::Error::Failed("Bluetooth service is not running".into()))
+    }
+}
+
+#[interface(name = "io.github.hendrikboeck.BluetoothTimeout1")]
+impl ControlInterface {
+    /// Postpones the active timeout by `seconds`, or starts a timeout of `seconds` if none is
+    /// active. Fails with `InvalidArgs` if `seconds` exceeds a year.
+    async fn snooze(
+        &self,
+        seconds: u64,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        polkit::authorize(conn, &header, ACTION_CONTROL_TIMEOUT).await?;
+        self.send(BluetoothServiceCommand::Snooze(Self::duration(seconds)?))
+    }
+
+    /// Keeps the adapter on for `seconds`, powering it on if it is off, regardless of connected
+    /// devices and the timeout, or ends an active window if `seconds` is `0`. Afterwards, the
+    /// regular timeout applies again. Fails with `InvalidArgs` if `seconds` exceeds a year.
+    async fn keep_on(
+        &self,
+        seconds: u64,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        polkit::authorize(conn, &header, ACTION_CONTROL_TIMEOUT).await?;
+        self.send(BluetoothServiceCommand::KeepOnFor(Self::duration(seconds)?))
+    }
+
+    /// Activates the profile `name` of the configuration, or the main configuration if `name` is
+    /// empty. A running countdown restarts with the timeout of the new profile.
+    async fn set_profile(
+        &self,
+        name: &str,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        polkit::authorize(conn, &header, ACTION_CONTROL_TIMEOUT).await?;
+        let name = Some(name).filter(|name| !name.is_empty());
+        Conf::instance()
+            .set_profile(name)
+            .map_err(|e| fdo::Error::InvalidArgs(e.to_string()))?;
+        self.send(BluetoothServiceCommand::SettingsChanged)?;
+
+        let _ = self.profile_changed(&emitter).await;
+        Ok(())
+    }
+
+    /// Sets the timeout of the running daemon to `seconds` in every context, overriding the
+    /// configured and the profile timeouts until the daemon exits, or restores them if `seconds`
+    /// is `0`. With `persist`, the timeout is also written to the config file. A running
+    /// countdown restarts with the new timeout. Fails with `InvalidArgs` if `seconds` exceeds a
+    /// year, before anything is written.
+    async fn set_timeout(
+        &self,
+        seconds: u64,
+        persist: bool,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        polkit::authorize(conn, &header, ACTION_CONTROL_TIMEOUT).await?;
+        let timeout = Some(Self::duration(seconds)?).filter(|timeout| !timeout.is_zero());
+        if persist {
+            let timeout = timeout.ok_or_else(|| {
+                fdo::Error::InvalidArgs("Cannot persist a reset of the timeout".into())
+            })?;
+            conf_filepath()
+                .and_then(|path| Conf::persist_timeout(&path, timeout))
+                .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+        }
+
+        Conf::instance().set_timeout(timeout);
+        self.send(BluetoothServiceCommand::SettingsChanged)
+    }
+
+    /// Turns the adapter off right away, as the timeout would.
+    async fn power_off(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        polkit::authorize(conn, &header, ACTION_POWER_OFF).await?;
+        self.send(BluetoothServiceCommand::TurnOffNow)
+    }
+
+    /// Succeeds if the daemon is healthy: its Bluetooth service is running, the adapter's events
+    /// are still observed and the adapter can be queried. Fails with the reason otherwise.
+    async fn check_health(&self) -> fdo::Result<()> {
+        if self.commands.is_closed() {
+            return Err(fdo::Error::Failed(
+                "Bluetooth service is not running".into(),
+            ));
+        }
+        if !self.backend.is_observing() {
+            return Err(fdo::Error::Failed(
+                "Adapter events are no longer observed".into(),
+            ));
+        }
+        self.backend
+            .is_powered()
+            .await
+            .map_err(|e| fdo::Error::Failed(format!("Adapter cannot be queried: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Returns the recent state transitions, events and commands of the service, oldest first, as
+    /// pairs of milliseconds since the Unix epoch and a description.
+    async fn recent_events(&self) -> Vec<(u64, String)> {
+        self.history
+            .entries()
+            .into_iter()
+            .map(|entry| {
+                let millis = entry
+                    .time
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis();
+                (millis as u64, entry.message)
+            })
+            .collect()
+    }
+
+    /// Returns the powered-time totals of the last `days` days up to today, oldest first: the
+    /// start of the day (UTC) in seconds since the Unix epoch, the seconds the adapter was on and
+    /// off, the seconds of the latter after the timeout turned it off, and the number of times the
+    /// timeout turned it off.
+    async fn power_stats(&self, days: u32) -> Vec<DayTotals> {
+        self.stats
+            .days(u64::from(days))
+            .into_iter()
+            .map(|(day, totals)| {
+                let start = stats::day_start(day)
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                (
+                    start,
+                    totals.powered_secs,
+                    totals.off_secs,
+                    totals.saved_secs,
+                    totals.auto_power_offs,
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the connected devices as pairs of their name and battery level in percent (`-1` if
+    /// the device reports none).
+    async fn connected_devices(&self) -> fdo::Result<Vec<(String, i16)>> {
+        let devices = self
+            .backend
+            .connected_devices()
+            .await
+            .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+
+        Ok(devices
+            .iter()
+            .map(|device| {
+                let battery = device.battery.map_or(-1, i16::from);
+                (device.display_name().to_string(), battery)
+            })
+            .collect())
+    }
+
+    /// Returns what the controller of the adapter supports: whether it supports BR/EDR and its LE
+    /// roles (empty if it does not support LE).
+    async fn capabilities(&self) -> fdo::Result<(bool, Vec<String>)> {
+        let capabilities = self
+            .backend
+            .capabilities()
+            .await
+            .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+
+        Ok((capabilities.bredr, capabilities.roles))
+    }
+
+    /// Returns the end-to-end latencies of the adapter's events by kind, from the reception of
+    /// the D-Bus signal to the service having handled the event: the kind, the number of events,
+    /// the sum and the maximum of their latencies in microseconds, and the number of events per
+    /// histogram bucket (up to 1, 2, 5, 10, 50, 100, 500 and 1000 ms, and slower). Empty if the
+    /// daemon was built without the `metrics` feature.
+    async fn event_latencies(&self) -> Vec<KindLatencies> {
+        #[cfg(feature = "metrics")]
+        if let Some(latency) = self.backend.latency() {
+            return latency
+                .histograms()
+                .into_iter()
+                .map(|(kind, histogram)| {
+                    (
+                        kind.to_string(),
+                        histogram.count,
+                        histogram.sum.as_micros() as u64,
+                        histogram.max.as_micros() as u64,
+                        histogram.buckets.to_vec(),
+                    )
+                })
+                .collect();
+        }
+
+        vec![]
+    }
+
+    /// Emitted `before_power_off` before the adapter is turned off by the timeout, with the
+    /// `seconds` left until then.
+    #[zbus(signal)]
+    pub async fn before_power_off(emitter: &SignalEmitter<'_>, seconds: u64) -> zbus::Result<()>;
+
+    /// Emitted for every event of the adapter (e.g., `DeviceConnected`), with the `device` and
+    /// its `address` it is about (empty if none).
+    #[zbus(signal)]
+    pub async fn bluetooth_event(
+        emitter: &SignalEmitter<'_>,
+        kind: &str,
+        device: &str,
+        address: &str,
+    ) -> zbus::Result<()>;
+
+    /// Current state of the service (`Off`, `Idle`, `Running` or `Detached`).
+    #[zbus(property)]
+    async fn state(&self) -> String {
+        format!("{:?}", *self.state.borrow())
+    }
+
+    /// Name of the active profile (empty if the main configuration applies).
+    #[zbus(property)]
+    async fn profile(&self) -> String {
+        Conf::instance().profile().unwrap_or_default()
+    }
+
+    /// Seconds until the active timeout expires (`-1` if no timeout is counting down).
+    #[zbus(property)]
+    async fn remaining_seconds(&self) -> i64 {
+        match *self.deadline.borrow() {
+            Some(deadline) => deadline.saturating_duration_since(Instant::now()).as_secs() as i64,
+            None => -1,
+        }
+    }
+}
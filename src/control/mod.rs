@@ -0,0 +1,178 @@
+// -- crate imports
+use anyhow::Result;
+use tokio::{
+    sync::{mpsc, watch},
+    time::Instant,
+};
+use zbus::Connection;
+
+// -- module definitions
+pub mod interface;
+pub mod polkit;
+pub mod proxy;
+
+// -- module imports
+use crate::{
+    bluetooth::{
+        backend::SharedBackend,
+        history::History,
+        observer::BluetoothEvent,
+        service::{BluetoothServiceCommand, BluetoothServiceState},
+        stats::PowerStats,
+    },
+    bus,
+    control::interface::ControlInterface,
+};
+
+/// Well-known name the daemon's control interface is registered under on its bus.
+pub const CONTROL_DBUS_NAME: &str = "io.github.hendrikboeck.BluetoothTimeout";
+
+/// D-Bus object path of the daemon's control interface.
+pub const CONTROL_DBUS_PATH: &str = "/io/github/hendrikboeck/BluetoothTimeout";
+
+/// Longest duration in seconds the control interface accepts (a year), so the deadlines derived
+/// from it cannot overflow.
+pub const MAX_SECONDS: u64 = 365 * 24 * 60 * 60;
+
+/// Powered-time totals of a day as returned by the control interface's `PowerStats`: the start of
+/// the day in seconds since the Unix epoch, the seconds on, off and off by the timeout, and the
+/// number of automatic power-offs.
+pub type DayTotals = (u64, u64, u64, u64, u32);
+
+/// Latencies of an event kind as returned by the control interface's `EventLatencies`: the kind,
+/// the number of events, the sum and the maximum of their latencies in microseconds, and the
+/// number of events per bucket of [`BUCKETS_MS`](crate::bluetooth::latency::BUCKETS_MS) plus one
+/// for slower events.
+pub type KindLatencies = (String, u64, u64, u64, Vec<u64>);
+
+/// Serves the control interface on the bus selected by `dbus.control_bus` (the session bus by
+/// default). On the system bus, privileged methods are authorized via [`polkit`].
+///
+/// # Arguments
+///
+/// - `commands` - Sender for commands to the Bluetooth service.
+/// - `state` - Current state of the Bluetooth service.
+/// - `history` - Recent state transitions, events and commands of the Bluetooth service.
+/// - `stats` - Totals of the powered time of the managed Bluetooth adapter.
+/// - `deadline` - Deadline of the active timeout of the Bluetooth service, if any.
+/// - `backend` - Backend of the managed Bluetooth adapter, to list its connected devices.
+///
+/// Changes of `State` and `RemainingSeconds` are announced via the standard `PropertiesChanged`
+/// signal, the latter also on every [`BluetoothEvent::TimerTick`] of the backend, so clients
+/// watching them (e.g., generated proxies of desktop applets) follow the countdown without
+/// polling. Every [`BluetoothEvent::BeforePowerOff`] is emitted as the `BeforePowerOff` signal,
+/// every other event except the ticks as the `BluetoothEvent` signal.
+/// The interface is described by `contrib/io.github.hendrikboeck.BluetoothTimeout1.xml`.
+///
+/// The returned connection owns the well-known name [`CONTROL_DBUS_NAME`] and must be kept alive
+/// for as long as the interface should be reachable. The name is neither taken from nor given up
+/// to another instance of the daemon.
+///
+/// # Errors
+///
+/// - [`zbus::Error::NameTaken`] if another running instance of the daemon owns the name.
+/// - [`anyhow::Error`] if the bus is unavailable.
+pub async fn serve(
+    commands: mpsc::UnboundedSender<BluetoothServiceCommand>,
+    state: watch::Receiver<BluetoothServiceState>,
+    history: History,
+    stats: PowerStats,
+    deadline: watch::Receiver<Option<Instant>>,
+    backend: SharedBackend,
+) -> Result<Connection> {
+    let mut events = backend.events().subscribe("control interface");
+    let (mut state_changes, mut deadline_changes) = (state.clone(), deadline.clone());
+    // A running instance keeps the name; the session bus only activates one if none is running
+    let conn = bus::control_builder()?
+        .name(CONTROL_DBUS_NAME)?
+        .allow_name_replacements(false)
+        .replace_existing_names(false)
+        .serve_at(
+            CONTROL_DBUS_PATH,
+            ControlInterface::new(commands, state, history, stats, deadline, backend),
+        )?
+        .build()
+        .await?;
+
+    let iface = conn
+        .object_server()
+        .interface::<_, ControlInterface>(CONTROL_DBUS_PATH)
+        .await?;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Some(BluetoothEvent::TimerTick { .. }) => {
+                        let _ = iface
+                            .get()
+                            .await
+                            .remaining_seconds_changed(iface.signal_emitter())
+                            .await;
+                    }
+                    Some(BluetoothEvent::BeforePowerOff { remaining }) => {
+                        let _ = ControlInterface::before_power_off(
+                            iface.signal_emitter(),
+                            remaining.as_secs_f64().round() as u64,
+                        )
+                        .await;
+                    }
+                    Some(event) => {
+                        let device = event.device();
+                        let _ = ControlInterface::bluetooth_event(
+                            iface.signal_emitter(),
+                            event.kind(),
+                            device.map_or("", |device| device.display_name()),
+                            device.and_then(|device| device.address.as_deref()).unwrap_or(""),
+                        )
+                        .await;
+                    }
+                    None => break,
+                },
+                Ok(()) = state_changes.changed() => {
+                    let _ = iface.get().await.state_changed(iface.signal_emitter()).await;
+                }
+                Ok(()) = deadline_changes.changed() => {
+                    let _ = iface
+                        .get()
+                        .await
+                        .remaining_seconds_changed(iface.signal_emitter())
+                        .await;
+                }
+            }
+        }
+    });
+
+    Ok(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    // -- crate imports
+    use zbus::object_server::Interface;
+
+    // -- module imports
+    use super::*;
+    use crate::{bluetooth::backend::mock::MockBackend, configuration::Conf};
+
+    #[tokio::test]
+    async fn shipped_introspection_matches_the_interface() {
+        Conf::for_tests();
+        let iface = ControlInterface::new(
+            mpsc::unbounded_channel().0,
+            watch::channel(BluetoothServiceState::Idle).1,
+            History::new(1),
+            PowerStats::new(),
+            watch::channel(None).1,
+            MockBackend::new(true),
+        );
+
+        let mut introspection = String::new();
+        iface.introspect_to_writer(&mut introspection, 2);
+        assert!(
+            include_str!("../../contrib/io.github.hendrikboeck.BluetoothTimeout1.xml")
+                .contains(&introspection),
+            "contrib/io.github.hendrikboeck.BluetoothTimeout1.xml is outdated:\n{}",
+            introspection
+        );
+    }
+}
@@ -0,0 +1,96 @@
+// -- std imports
+use std::collections::HashMap;
+
+// -- crate imports
+use zbus::{
+    Connection, fdo,
+    message::{Flags, Header},
+    proxy,
+    zvariant::Value,
+};
+
+// -- module imports
+use crate::configuration::{Conf, ControlBus};
+
+/// Polkit action for postponing, pausing or changing the timeout (`Snooze`, `KeepOn`,
+/// `SetProfile` and `SetTimeout`), as defined in
+/// `contrib/io.github.hendrikboeck.BluetoothTimeout.policy`.
+pub const ACTION_CONTROL_TIMEOUT: &str = "io.github.hendrikboeck.BluetoothTimeout.control-timeout";
+
+/// Polkit action for turning the adapter off right away (`PowerOff`).
+pub const ACTION_POWER_OFF: &str = "io.github.hendrikboeck.BluetoothTimeout.power-off";
+
+/// `AllowUserInteraction` of polkit's `CheckAuthorizationFlags`.
+const ALLOW_USER_INTERACTION: u32 = 1;
+
+/// Proxy of polkit's authority on the system bus.
+#[proxy(
+    interface = "org.freedesktop.PolicyKit1.Authority",
+    default_service = "org.freedesktop.PolicyKit1",
+    default_path = "/org/freedesktop/PolicyKit1/Authority"
+)]
+trait Authority {
+    /// Checks whether `subject` is authorized for `action_id`.
+    fn check_authorization(
+        &self,
+        subject: &(&str, HashMap<&str, Value<'_>>),
+        action_id: &str,
+        details: &HashMap<&str, &str>,
+        flags: u32,
+        cancellation_id: &str,
+    ) -> zbus::Result<(bool, bool, HashMap<String, String>)>;
+}
+
+/// Checks that the sender of the method call with `header`, received on `conn`, is authorized by
+/// polkit for `action`. Every caller is authorized if the control interface is served on the
+/// session bus, which only the user's own processes can reach.
+///
+/// If the caller allows interactive authorization (e.g., with `busctl
+/// --allow-interactive-authorization`), polkit may ask for a password through the caller's
+/// authentication agent.
+///
+/// # Errors
+///
+/// - [`fdo::Error::AccessDenied`] if polkit denies the action.
+/// - [`fdo::Error::InteractiveAuthorizationRequired`] if the caller would have to authenticate
+///   but does not allow interaction.
+/// - [`fdo::Error::Failed`] if polkit cannot be asked.
+pub async fn authorize(conn: &Connection, header: &Header<'_>, action: &str) -> fdo::Result<()> {
+    if Conf::instance().dbus.control_bus == ControlBus::Session {
+        return Ok(());
+    }
+
+    let sender = header
+        .sender()
+        .ok_or_else(|| fdo::Error::AccessDenied("The caller is unknown".into()))?;
+    let subject = (
+        "system-bus-name",
+        HashMap::from([("name", Value::from(sender.as_str()))]),
+    );
+    let flags = match header
+        .primary()
+        .flags()
+        .contains(Flags::AllowInteractiveAuth)
+    {
+        true => ALLOW_USER_INTERACTION,
+        false => 0,
+    };
+
+    let (authorized, challenge, _) = AuthorityProxy::new(conn)
+        .await?
+        .check_authorization(&subject, action, &HashMap::new(), flags, "")
+        .await
+        .map_err(|e| fdo::Error::Failed(format!("Could not ask polkit: {}", e)))?;
+
+    match (authorized, challenge) {
+        (true, _) => Ok(()),
+        (false, true) => Err(fdo::Error::InteractiveAuthorizationRequired(format!(
+            "'{}' requires authentication",
+            action
+        ))),
+        (false, false) => Err(fdo::Error::AccessDenied(format!(
+            "Not authorized for '{}'",
+            action
+        ))),
+    }
+}
@@ -0,0 +1,84 @@
+// -- crate imports
+use zbus::proxy;
+
+// -- module imports
+use crate::control::{DayTotals, KindLatencies};
+
+/// Client-side proxy for the daemon's control interface, used by the CLI commands.
+///
+/// Privileged methods allow interactive authorization, so polkit can ask for a password if the
+/// interface is served on the system bus.
+#[proxy(
+    interface = "io.github.hendrikboeck.BluetoothTimeout1",
+    default_service = "io.github.hendrikboeck.BluetoothTimeout",
+    default_path = "/io/github/hendrikboeck/BluetoothTimeout"
+)]
+pub trait Control {
+    /// Postpones the active timeout by `seconds`.
+    #[zbus(allow_interactive_auth)]
+    fn snooze(&self, seconds: u64) -> zbus::Result<()>;
+
+    /// Keeps the adapter on for `seconds`, or ends an active window if `seconds` is `0`.
+    #[zbus(allow_interactive_auth)]
+    fn keep_on(&self, seconds: u64) -> zbus::Result<()>;
+
+    /// Activates the profile `name`, or the main configuration if `name` is empty.
+    #[zbus(allow_interactive_auth)]
+    fn set_profile(&self, name: &str) -> zbus::Result<()>;
+
+    /// Sets the timeout to `seconds` until the daemon exits, or restores the configured timeout if
+    /// `seconds` is `0`; with `persist`, also writes it to the config file.
+    #[zbus(allow_interactive_auth)]
+    fn set_timeout(&self, seconds: u64, persist: bool) -> zbus::Result<()>;
+
+    /// Turns the adapter off right away.
+    #[zbus(allow_interactive_auth)]
+    fn power_off(&self) -> zbus::Result<()>;
+
+    /// Succeeds if the daemon is healthy, or fails with the reason. Does not start the daemon via
+    /// D-Bus activation.
+    #[zbus(no_autostart)]
+    fn check_health(&self) -> zbus::Result<()>;
+
+    /// Returns the recent state transitions, events and commands, as pairs of milliseconds since
+    /// the Unix epoch and a description.
+    fn recent_events(&self) -> zbus::Result<Vec<(u64, String)>>;
+
+    /// Returns the powered-time totals of the last `days` days, oldest first, as the start of the
+    /// day in seconds since the Unix epoch, the seconds on, off and off by the timeout, and the
+    /// number of automatic power-offs.
+    fn power_stats(&self, days: u32) -> zbus::Result<Vec<DayTotals>>;
+
+    /// Returns the connected devices as pairs of their name and battery level in percent (`-1` if
+    /// unknown).
+    fn connected_devices(&self) -> zbus::Result<Vec<(String, i16)>>;
+
+    /// Returns whether the controller supports BR/EDR and its LE roles.
+    fn capabilities(&self) -> zbus::Result<(bool, Vec<String>)>;
+
+    /// Returns the end-to-end latencies of the adapter's events by kind, as the kind, the number
+    /// of events, the sum and maximum of their latencies in microseconds, and the events per
+    /// histogram bucket.
+    fn event_latencies(&self) -> zbus::Result<Vec<KindLatencies>>;
+
+    /// Emitted before the adapter is turned off by the timeout, with the `seconds` left until then.
+    #[zbus(signal)]
+    fn before_power_off(&self, seconds: u64) -> zbus::Result<()>;
+
+    /// Emitted for every event of the adapter, with the `device` and its `address` it is about
+    /// (empty if none).
+    #[zbus(signal)]
+    fn bluetooth_event(&self, kind: &str, device: &str, address: &str) -> zbus::Result<()>;
+
+    /// Current state of the Bluetooth service.
+    #[zbus(property)]
+    fn state(&self) -> zbus::Result<String>;
+
+    /// Name of the active profile (empty if the main configuration applies).
+    #[zbus(property)]
+    fn profile(&self) -> zbus::Result<String>;
+
+    /// Seconds until the active timeout expires (`-1` if no timeout is counting down).
+    #[zbus(property)]
+    fn remaining_seconds(&self) -> zbus::Result<i64>;
+}
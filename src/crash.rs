@@ -0,0 +1,166 @@
+// -- std imports
+use std::{
+    backtrace::Backtrace, fmt::Write as _, fs, panic, path::PathBuf, sync::Mutex, thread,
+    time::SystemTime,
+};
+
+// -- crate imports
+use anyhow::Result;
+use tokio::sync::watch;
+use tracing::error;
+
+// -- module imports
+use crate::{
+    bluetooth::{history::History, service::BluetoothServiceState},
+    configuration::state_path,
+};
+
+/// Adapters whose state and history are included in crash reports (see [`watch`]).
+static WATCHED: Mutex<Vec<Watched>> = Mutex::new(Vec::new());
+
+/// An adapter whose state and history are included in crash reports.
+#[derive(Debug)]
+struct Watched {
+    /// D-Bus object path of the adapter (e.g., "/org/bluez/hci0").
+    adapter: String,
+    /// State of the service managing the adapter.
+    state: watch::Receiver<BluetoothServiceState>,
+    /// Recent state transitions, events and commands of that service.
+    history: History,
+}
+
+/// Installs a panic hook that writes a crash report before the previous hook (which prints the
+/// panic to stderr) runs.
+///
+/// The report contains the panic message and location, a backtrace, and the state and recent
+/// events of every [watched](watch) adapter. It is written to a file of its own (see [`path`]),
+/// so a daemon that "just died" leaves something to attach to a bug report.
+///
+/// The hook does not end the process: a panicking background task is restarted by its
+/// [`Supervisor`](crate::supervisor::Supervisor), which only ends the daemon if it keeps failing,
+/// so a report is written for each panic.
+pub fn install() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Box<dyn Any>");
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+            .unwrap_or_else(|| "unknown".to_owned());
+        let time = SystemTime::now();
+        let report = report(message, &location, time, &Backtrace::force_capture());
+        match path(time).and_then(|path| {
+            fs::write(&path, report)?;
+            Ok(path)
+        }) {
+            Ok(path) => error!("Panicked; crash report written to '{}'.", path.display()),
+            Err(e) => error!("Panicked; could not write crash report: {}", e),
+        }
+        previous(info);
+    }));
+}
+
+/// Includes the `state` and `history` of the service managing `adapter` in crash reports, for as
+/// long as the service exists.
+pub fn watch(adapter: &str, state: watch::Receiver<BluetoothServiceState>, history: History) {
+    let mut watched = WATCHED.lock().unwrap_or_else(|e| e.into_inner());
+    watched.retain(|w| w.adapter != adapter && w.state.has_changed().is_ok());
+    watched.push(Watched {
+        adapter: adapter.to_owned(),
+        state,
+        history,
+    });
+}
+
+/// Renders the crash report of the panic with `message` at `location` (e.g., "src/main.rs:1:2")
+/// that happened at `time`.
+fn report(message: &str, location: &str, time: SystemTime, backtrace: &Backtrace) -> String {
+    let mut report = String::new();
+    let _ = writeln!(
+        report,
+        "{} {} crashed at {}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        humantime::format_rfc3339_seconds(time)
+    );
+    let _ = writeln!(
+        report,
+        "Thread '{}' panicked at {}:",
+        thread::current().name().unwrap_or("<unnamed>"),
+        location
+    );
+    let _ = writeln!(report, "{}\n", message);
+
+    // The locks are not waited for, as the panic may have happened while one was held
+    if let Ok(watched) = WATCHED.try_lock() {
+        for w in watched.iter().filter(|w| w.state.has_changed().is_ok()) {
+            let _ = writeln!(report, "Adapter {}: {:?}", w.adapter, *w.state.borrow());
+            let Some(entries) = w.history.try_entries() else {
+                let _ = writeln!(report, "  (history is being recorded)");
+                report.push('\n');
+                continue;
+            };
+            for entry in entries {
+                let _ = writeln!(
+                    report,
+                    "  {}  {}",
+                    humantime::format_rfc3339_seconds(entry.time),
+                    entry.message
+                );
+            }
+            report.push('\n');
+        }
+    }
+
+    let _ = writeln!(report, "Backtrace:\n{}", backtrace);
+    report
+}
+
+/// Returns the path of the crash report of a panic at `time`.
+///
+/// This is `crash-<time>.txt` among the state files (see [`state_path`]).
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the state directory cannot be determined or created.
+fn path(time: SystemTime) -> Result<PathBuf> {
+    state_path(&format!(
+        "crash-{}.txt",
+        humantime::format_rfc3339_seconds(time)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    // -- std imports
+    use std::time::UNIX_EPOCH;
+
+    // -- module imports
+    use super::*;
+
+    #[test]
+    fn reports_the_panic_and_the_watched_adapters() {
+        let (state_tx, state) = watch::channel(BluetoothServiceState::Idle);
+        let history = History::new(2);
+        history.record("State: Off -> Idle");
+        watch("/org/bluez/hci9", state, history);
+
+        let report = report(
+            "boom",
+            "src/main.rs:1:2",
+            UNIX_EPOCH,
+            &Backtrace::disabled(),
+        );
+        drop(state_tx);
+
+        assert!(report.contains("crashed at 1970-01-01T00:00:00Z"));
+        assert!(report.contains("panicked at src/main.rs:1:2:\nboom"));
+        assert!(report.contains("Adapter /org/bluez/hci9: Idle"));
+        assert!(report.contains("State: Off -> Idle"));
+    }
+}
@@ -0,0 +1,442 @@
+// -- std imports
+use std::{collections::HashMap, sync::Arc};
+
+// -- crate imports
+use anyhow::Result;
+use futures_util::stream::StreamExt;
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::{debug, error, info, warn};
+use zbus::{Connection, fdo::ObjectManagerProxy};
+
+// -- module imports (conditional)
+#[cfg(feature = "notifications")]
+use crate::{
+    configuration::{NotificationBus, NotificationSinkType},
+    notification::NotificationActionObserver,
+};
+
+// -- module imports
+use crate::{
+    bluetooth::{
+        backend::{SharedBackend, bluez::BlueZBackend},
+        battery, dormant, monitor, proximity,
+        saved_power::{self, SavedPower},
+        service::{BluetoothService, BluetoothServiceCommand},
+        stats::{self, PowerStats},
+    },
+    bus,
+    configuration::{Conf, LockAction},
+    crash,
+    error::Error,
+    lock, network, power_on, radio, sd_notify,
+    supervisor::{Policy, Supervisor},
+    timeout,
+};
+
+/// Runs the Bluetooth timeout daemon until the service fails.
+///
+/// Manages the adapter at `dbus.adapter_path`, or every adapter if it is `all`, and the other
+/// radios configured in `radios` (see [`radio::spawn_all`]). Once started, systemd is notified
+/// (see [`sd_notify::ready`]), and its watchdog is pinged while the services of the adapters run
+/// and observe their adapters' events. The background tasks of the daemon are supervised (see
+/// [`Supervisor`]); one that keeps failing ends the daemon.
+///
+/// The control interface's well-known name is requested without queueing, so the daemon exits if
+/// another instance owns it, rather than managing the adapter twice. Once the name is acquired,
+/// clients that started the daemon via D-Bus activation are served.
+///
+/// # Errors
+///
+/// - [`Error`] if the daemon cannot be started or the service fails.
+pub async fn run(conf: &'static Conf) -> Result<(), Error> {
+    let mut supervisor = Supervisor::new();
+    radio::spawn_all(conf, &mut supervisor);
+    if let Err(e) = saved_power::listen_shutdown(&mut supervisor).await {
+        warn!("Could not watch for system shutdown: {}", e);
+    }
+    if conf.dbus.manages_all_adapters() {
+        return run_all(conf, supervisor).await;
+    }
+
+    let adapter = ManagedAdapter::start(conf, conf.dbus.adapter_path.clone(), true).await?;
+
+    // Keep the connection alive, as it owns the control interface's well-known name
+    #[cfg(feature = "cli-control")]
+    let _control = match crate::control::serve(
+        adapter.service.command_sender(),
+        adapter.service.state_receiver(),
+        adapter.service.history(),
+        adapter.service.stats(),
+        adapter.service.deadline_receiver(),
+        adapter.backend.clone(),
+    )
+    .await
+    {
+        Ok(conn) => Some(conn),
+        // Another instance manages the adapter already (e.g., one started by D-Bus activation)
+        Err(e) if matches!(e.downcast_ref(), Some(zbus::Error::NameTaken)) => {
+            return Err(Error::Other(anyhow::anyhow!(
+                "Another instance of the daemon is already running"
+            )));
+        }
+        Err(e) => {
+            warn!("Control interface is unavailable: {}", e);
+            None
+        }
+    };
+
+    let commands = adapter.service.command_sender();
+    let (actions_tx, mut actions_rx) = mpsc::unbounded_channel();
+    listen_session(conf, commands.clone(), actions_tx, &mut supervisor).await;
+    let mut watchdog = sd_notify::ready();
+
+    let backend = adapter.backend.clone();
+    let running = adapter.run();
+    tokio::pin!(running);
+    loop {
+        tokio::select! {
+            result = &mut running => return result,
+            e = supervisor.supervise() => return Err(Error::Other(e)),
+            // Every notification with actions was sent for the only adapter
+            Some((_, command)) = actions_rx.recv() => {
+                let _ = commands.send(command);
+            }
+            _ = watchdog.tick() => {
+                if !commands.is_closed() && backend.is_observing() {
+                    watchdog.ping();
+                }
+            }
+        }
+    }
+}
+
+/// Runs the daemon for every adapter known to BlueZ, starting and stopping the management of
+/// adapters as they are added and removed.
+///
+/// Commands from the user session (e.g., screen lock) are forwarded to all adapters, the actions
+/// invoked on a notification only to the adapter it was sent for (see
+/// [`timeout::notified_adapter`]). The control interface is not served, as it describes a single
+/// adapter. The background tasks of the daemon are spawned in `supervisor`.
+///
+/// # Errors
+///
+/// - [`Error::DbusUnavailable`] if the adapters cannot be listed or watched.
+/// - [`Error::Other`] if a background task keeps failing.
+async fn run_all(conf: &'static Conf, mut supervisor: Supervisor) -> Result<(), Error> {
+    let conn = bus::system()
+        .await
+        .map_err(|e| Error::DbusUnavailable(e.into()))?;
+    let objects = ObjectManagerProxy::builder(&conn)
+        .destination(conf.dbus.service.as_str())
+        .and_then(|builder| builder.path("/"))
+        .map_err(|e| Error::DbusUnavailable(e.into()))?
+        .build()
+        .await
+        .map_err(|e| Error::DbusUnavailable(e.into()))?;
+    let (mut added, mut removed) = tokio::try_join!(
+        objects.receive_interfaces_added(),
+        objects.receive_interfaces_removed()
+    )
+    .map_err(|e| Error::DbusUnavailable(e.into()))?;
+
+    let (commands_tx, mut commands_rx) = mpsc::unbounded_channel();
+    let (actions_tx, mut actions_rx) = mpsc::unbounded_channel();
+    listen_session(conf, commands_tx, actions_tx, &mut supervisor).await;
+
+    // Object path -> (task running the adapter, its command sender, its backend)
+    let mut adapters: HashMap<String, (JoinHandle<()>, mpsc::UnboundedSender<_>, SharedBackend)> =
+        HashMap::new();
+    for path in list_adapters(&objects, conf)
+        .await
+        .map_err(Error::DbusUnavailable)?
+    {
+        if let Some(adapter) = spawn_adapter(conf, path.clone(), true).await {
+            adapters.insert(path, adapter);
+        }
+    }
+    if adapters.is_empty() {
+        info!("No Bluetooth adapter present yet, waiting for one to be added.");
+    }
+    let mut watchdog = sd_notify::ready();
+
+    loop {
+        tokio::select! {
+            Some(signal) = added.next() => {
+                let Ok(args) = signal.args() else { continue };
+                if !args
+                    .interfaces_and_properties
+                    .contains_key(conf.dbus.adapter_iface.as_str())
+                {
+                    continue;
+                }
+
+                let path = args.object_path.to_string();
+                info!("Bluetooth adapter {} was added.", path);
+                if let Some(adapter) = spawn_adapter(conf, path.clone(), false).await
+                    && let Some((previous, _, _)) = adapters.insert(path, adapter)
+                {
+                    previous.abort();
+                }
+            }
+            Some(signal) = removed.next() => {
+                let Ok(args) = signal.args() else { continue };
+                if !args.interfaces.iter().any(|iface| iface == conf.dbus.adapter_iface.as_str()) {
+                    continue;
+                }
+
+                let path = args.object_path.to_string();
+                info!("Bluetooth adapter {} was removed.", path);
+                if let Some((task, _, _)) = adapters.remove(&path) {
+                    task.abort();
+                }
+            }
+            Some(command) = commands_rx.recv() => {
+                adapters.retain(|_, (task, _, _)| !task.is_finished());
+                for (_, commands, _) in adapters.values() {
+                    let _ = commands.send(command.clone());
+                }
+            }
+            Some((id, command)) = actions_rx.recv() => {
+                match timeout::notified_adapter(id).and_then(|path| adapters.get(&path)) {
+                    Some((_, commands, _)) => {
+                        let _ = commands.send(command);
+                    }
+                    None => debug!("Ignoring action on notification {} of no managed adapter.", id),
+                }
+            }
+            _ = watchdog.tick() => {
+                if adapters
+                    .values()
+                    .all(|(task, commands, backend)| {
+                        !task.is_finished() && !commands.is_closed() && backend.is_observing()
+                    })
+                {
+                    watchdog.ping();
+                }
+            }
+            e = supervisor.supervise() => return Err(Error::Other(e)),
+            else => {
+                return Err(Error::Other(anyhow::anyhow!(
+                    "Stopped receiving Bluetooth adapter changes"
+                )));
+            }
+        }
+    }
+}
+
+/// Returns the object paths of all adapters known to BlueZ.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the D-Bus call fails.
+pub async fn list_adapters(objects: &ObjectManagerProxy<'_>, conf: &Conf) -> Result<Vec<String>> {
+    let mut adapters: Vec<String> = objects
+        .get_managed_objects()
+        .await?
+        .into_iter()
+        .filter(|(_, ifaces)| ifaces.contains_key(conf.dbus.adapter_iface.as_str()))
+        .map(|(path, _)| path.to_string())
+        .collect();
+    adapters.sort();
+
+    Ok(adapters)
+}
+
+/// Starts managing the adapter at `path` in a task of its own (see [`ManagedAdapter::start`]).
+///
+/// Returns the task, the adapter's command sender and its backend, or `None` if the adapter cannot
+/// be managed.
+async fn spawn_adapter(
+    conf: &'static Conf,
+    path: String,
+    at_startup: bool,
+) -> Option<(
+    JoinHandle<()>,
+    mpsc::UnboundedSender<BluetoothServiceCommand>,
+    SharedBackend,
+)> {
+    let adapter = ManagedAdapter::start(conf, path.clone(), at_startup)
+        .await
+        .inspect_err(|e| warn!("Could not manage Bluetooth adapter {}: {}", path, e))
+        .ok()?;
+    let commands = adapter.service.command_sender();
+    let backend = adapter.backend.clone();
+
+    let task = tokio::spawn(async move {
+        if let Err(e) = adapter.run().await {
+            error!("Management of Bluetooth adapter {} stopped: {}", path, e);
+        }
+    });
+
+    Some((task, commands, backend))
+}
+
+/// Forwards commands from the user session (screen lock) and profile switches by network to
+/// `commands`, and the actions invoked on notifications to `actions` along with the ID of the
+/// notification, as far as they are enabled, in tasks spawned in `supervisor`.
+#[cfg_attr(not(feature = "notifications"), allow(unused_variables))]
+async fn listen_session(
+    conf: &'static Conf,
+    commands: mpsc::UnboundedSender<BluetoothServiceCommand>,
+    actions: mpsc::UnboundedSender<(u32, BluetoothServiceCommand)>,
+    supervisor: &mut Supervisor,
+) {
+    if conf.lock.action != LockAction::Ignore {
+        lock::listen(commands.clone(), supervisor).await;
+    }
+    if conf.has_network_profiles() {
+        network::listen(conf, commands.clone(), supervisor).await;
+    }
+
+    #[cfg(feature = "notifications")]
+    if conf.notifications().enabled
+        && conf.notifications().actions
+        && conf.notifications().sink == NotificationSinkType::Desktop
+        && conf.notifications().bus == NotificationBus::Session
+    {
+        match NotificationActionObserver::new().await {
+            Ok(action_observer) => {
+                action_observer.listen(actions, supervisor);
+            }
+            Err(e) => warn!("Notification actions are unavailable: {}", e),
+        }
+    }
+}
+
+/// A managed adapter: its backend, the service applying the timeout policy to it, and the
+/// supervised tasks bound to it (its observer and helpers).
+///
+/// Dropping it stops all of them, so an adapter that is removed leaves nothing running behind.
+#[derive(Debug)]
+struct ManagedAdapter {
+    /// Backend of the adapter, for the control interface and the watchdog.
+    backend: SharedBackend,
+    /// Service applying the timeout policy.
+    service: BluetoothService,
+    /// Connection serving the advertisement monitor, if waking is enabled.
+    _monitor: Option<Connection>,
+    /// Observer of the adapter's events and helper tasks (e.g., proximity polling) operating on
+    /// the adapter.
+    supervisor: Supervisor,
+}
+
+impl ManagedAdapter {
+    /// Starts managing the adapter at `path`.
+    ///
+    /// # Arguments
+    ///
+    /// - `conf` - The configuration of the daemon.
+    /// - `path` - The D-Bus object path of the adapter (e.g., "/org/bluez/hci0").
+    /// - `at_startup` - Whether the daemon is starting (rather than the adapter having been
+    ///   added), which restores the state it was left in if `power_on.restore` is enabled, or
+    ///   powers it on if `power_on.at_startup` is.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::DbusUnavailable`] if the connection to the system D-Bus cannot be established.
+    /// - [`Error::AdapterNotFound`] if BlueZ does not know the adapter.
+    /// - [`Error::Other`] if the service cannot determine the state of the adapter.
+    async fn start(conf: &'static Conf, path: String, at_startup: bool) -> Result<Self, Error> {
+        let bluez = Arc::new(BlueZBackend::new(path).await?);
+        let mut supervisor = Supervisor::new();
+        supervisor.spawn("observer", Policy::Restart, {
+            let bluez = bluez.clone();
+            move || bluez.observe()
+        });
+        let backend: SharedBackend = bluez;
+
+        if conf.proximity.enabled {
+            let backend = backend.clone();
+            supervisor.spawn("proximity", Policy::Restart, move || {
+                proximity::run(backend.clone(), &conf.proximity)
+            });
+        }
+        if let Some(interval) = conf.log.battery_interval {
+            let backend = backend.clone();
+            supervisor.spawn("battery log", Policy::Restart, move || {
+                battery::log(backend.clone(), interval)
+            });
+        }
+        if !conf.dormant.is_empty() {
+            let backend = backend.clone();
+            supervisor.spawn("dormant", Policy::Restart, move || {
+                dormant::run(backend.clone(), &conf.dormant)
+            });
+        }
+
+        let mut service = BluetoothService::new(backend.clone(), conf.timeouts()).await?;
+        crash::watch(
+            backend.adapter(),
+            service.state_receiver(),
+            service.history(),
+        );
+        match stats::path(backend.adapter()) {
+            Ok(path) => {
+                service.keep_stats(PowerStats::load(path));
+            }
+            Err(e) => warn!(
+                "Powered-time statistics are not kept across restarts: {}",
+                e
+            ),
+        }
+
+        let monitor = if conf.wake.enabled {
+            monitor::register(backend.clone(), &conf.wake.patterns)
+                .await
+                .inspect_err(|e| warn!("Advertisement monitor is unavailable: {}", e))
+                .ok()
+        } else {
+            None
+        };
+
+        // Read before the service saves the current state, which BlueZ may have changed already
+        let saved_power = saved_power::path(backend.adapter())
+            .inspect_err(|e| warn!("The powered state is not remembered: {}", e))
+            .ok()
+            .map(SavedPower::new);
+        let restored = saved_power
+            .as_ref()
+            .filter(|_| at_startup && conf.power_on.restore)
+            .and_then(SavedPower::load);
+        if let Some(saved_power) = saved_power {
+            service.keep_power_state(saved_power);
+        }
+
+        // Powered on (or off) after the service is created, so it sees the adapter changing
+        if let Some(powered) = restored {
+            let _ = power_on::restore(backend.as_ref(), powered)
+                .await
+                .inspect_err(|e| warn!("Failed to restore the powered state: {}", e));
+        } else if at_startup && conf.power_on.at_startup {
+            let _ = power_on::power_on(backend.as_ref(), "daemon started")
+                .await
+                .inspect_err(|e| warn!("Failed to power on adapter: {}", e));
+        }
+        if conf.power_on.on_session_start
+            && let Err(e) = power_on::listen_sessions(backend.clone(), &mut supervisor).await
+        {
+            warn!("Could not watch login sessions: {}", e);
+        }
+
+        Ok(Self {
+            backend,
+            service,
+            _monitor: monitor,
+            supervisor,
+        })
+    }
+
+    /// Runs the service of the adapter and supervises its tasks until either fails.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::Other`] if the service or a task of the adapter keeps failing.
+    async fn run(mut self) -> Result<(), Error> {
+        tokio::select! {
+            result = self.service.start() => result?,
+            e = self.supervisor.supervise() => return Err(Error::Other(e)),
+        }
+
+        Ok(())
+    }
+}
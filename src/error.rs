@@ -0,0 +1,114 @@
+// -- std imports
+use std::process::ExitCode;
+
+// -- crate imports (conditional)
+#[cfg(feature = "cli-control")]
+use zbus::DBusError;
+
+/// Errors that end the process.
+///
+/// Each variant maps to a distinct exit code (see [`Error::exit_code`]), so systemd units and
+/// scripts can tell, e.g., an invalid configuration (not worth restarting) from a missing adapter
+/// (worth retrying). Errors within the daemon are reported as [`anyhow::Error`] and only
+/// classified here when they end the process.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The configuration file exists but cannot be read or parsed.
+    #[error("Invalid configuration file '{path}': {reason}")]
+    ConfigInvalid { path: String, reason: String },
+
+    /// No connection to the required D-Bus could be established.
+    #[error("D-Bus is unavailable: {0:#}")]
+    DbusUnavailable(anyhow::Error),
+
+    /// The configured Bluetooth adapter does not exist (or BlueZ is not running).
+    #[error("Bluetooth adapter '{path}' not found: {reason:#}")]
+    AdapterNotFound { path: String, reason: anyhow::Error },
+
+    /// A notification could not be delivered, not even via the fallback channel.
+    #[cfg(feature = "notifications")]
+    #[error("Could not deliver notification: {0:#}")]
+    NotificationFailed(anyhow::Error),
+
+    /// The running daemon could not be reached via its control interface.
+    #[cfg(feature = "cli-control")]
+    #[error("Could not reach the running daemon: {0:#}")]
+    DaemonUnreachable(anyhow::Error),
+
+    /// The running daemon refused a command, as polkit does not authorize the caller.
+    #[cfg(feature = "cli-control")]
+    #[error("The daemon refused the command: {0:#}")]
+    CommandDenied(anyhow::Error),
+
+    /// The running daemon refused a command, as polkit would first have to authenticate the
+    /// caller, which the command line does not allow.
+    #[cfg(feature = "cli-control")]
+    #[error("The daemon requires authentication for the command: {0:#}")]
+    AuthenticationRequired(anyhow::Error),
+
+    /// The running daemon rejected the arguments of a command (e.g., an unknown profile).
+    #[cfg(feature = "cli-control")]
+    #[error("The daemon rejected the command: {0:#}")]
+    CommandInvalid(anyhow::Error),
+
+    /// Any other failure.
+    #[error("{0:#}")]
+    Other(#[from] anyhow::Error),
+}
+
+impl Error {
+    /// Returns the process exit code for the error.
+    ///
+    /// | Code | Error                                         |
+    /// |------|-----------------------------------------------|
+    /// | 1    | [`Error::Other`]                              |
+    /// | 2    | Invalid command line (reported by clap)       |
+    /// | 3    | [`Error::ConfigInvalid`]                      |
+    /// | 4    | [`Error::DbusUnavailable`]                    |
+    /// | 5    | [`Error::AdapterNotFound`]                    |
+    /// | 6    | [`Error::NotificationFailed`]                 |
+    /// | 7    | [`Error::DaemonUnreachable`]                  |
+    /// | 8    | [`Error::CommandDenied`]                      |
+    /// | 9    | [`Error::AuthenticationRequired`]             |
+    /// | 10   | [`Error::CommandInvalid`]                     |
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(match self {
+            Error::Other(_) => 1,
+            Error::ConfigInvalid { .. } => 3,
+            Error::DbusUnavailable(_) => 4,
+            Error::AdapterNotFound { .. } => 5,
+            #[cfg(feature = "notifications")]
+            Error::NotificationFailed(_) => 6,
+            #[cfg(feature = "cli-control")]
+            Error::DaemonUnreachable(_) => 7,
+            #[cfg(feature = "cli-control")]
+            Error::CommandDenied(_) => 8,
+            #[cfg(feature = "cli-control")]
+            Error::AuthenticationRequired(_) => 9,
+            #[cfg(feature = "cli-control")]
+            Error::CommandInvalid(_) => 10,
+        })
+    }
+
+    /// Classifies the failure `e` of a command sent to the running daemon by the error the
+    /// daemon replied with, as [`Error::DaemonUnreachable`] unless it refused the command.
+    #[cfg(feature = "cli-control")]
+    pub fn from_control(e: anyhow::Error) -> Self {
+        let name = match e.downcast_ref::<zbus::Error>() {
+            Some(zbus::Error::MethodError(name, _, _)) => Some(name.to_string()),
+            Some(zbus::Error::FDO(error)) => Some(error.name().to_string()),
+            _ => e
+                .downcast_ref::<zbus::fdo::Error>()
+                .map(|error| error.name().to_string()),
+        };
+
+        match name.as_deref() {
+            Some("org.freedesktop.DBus.Error.AccessDenied") => Error::CommandDenied(e),
+            Some("org.freedesktop.DBus.Error.InteractiveAuthorizationRequired") => {
+                Error::AuthenticationRequired(e)
+            }
+            Some("org.freedesktop.DBus.Error.InvalidArgs") => Error::CommandInvalid(e),
+            _ => Error::DaemonUnreachable(e),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+// -- std imports
+use std::{env, sync::OnceLock};
+
+// -- crate imports
+use fluent_bundle::{FluentArgs, FluentResource, concurrent::FluentBundle};
+use tracing::{debug, warn};
+use unic_langid::LanguageIdentifier;
+
+/// Translations embedded into the binary, as `(language, FTL source)` pairs.
+///
+/// The first entry is used as fallback for unsupported locales and missing messages.
+const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+];
+
+/// Global singleton instance of [`I18n`].
+static I18N: OnceLock<I18n> = OnceLock::new();
+
+/// Translates a message of the active locale, optionally with named arguments.
+///
+/// ```ignore
+/// let title = tr!("warning-title");
+/// let body = tr!("warning-body", remaining = "5m");
+/// ```
+#[macro_export]
+macro_rules! tr {
+    ($id:expr) => {
+        $crate::i18n::I18n::instance().tr($id, None)
+    };
+    ($id:expr, $($key:ident = $value:expr),+ $(,)?) => {{
+        let mut args = fluent_bundle::FluentArgs::new();
+        $(args.set(stringify!($key), $value);)+
+        $crate::i18n::I18n::instance().tr($id, Some(&args))
+    }};
+}
+
+/// Localized user-facing strings.
+///
+/// Holds the bundle of the locale selected from the environment and the English fallback bundle.
+pub struct I18n {
+    /// Bundle of the selected locale.
+    bundle: FluentBundle<FluentResource>,
+    /// Bundle of the fallback locale (the first entry of [`LOCALES`]).
+    fallback: FluentBundle<FluentResource>,
+}
+
+impl I18n {
+    /// Returns the global instance, initializing it from the environment's locale on first use.
+    pub fn instance() -> &'static Self {
+        I18N.get_or_init(|| Self::new(&system_language()))
+    }
+
+    /// Creates the translations for `language` (e.g., "de"), falling back to English.
+    fn new(language: &str) -> Self {
+        let (fallback_lang, fallback_src) = LOCALES[0];
+        let (lang, src) = LOCALES
+            .iter()
+            .copied()
+            .find(|(lang, _)| *lang == language)
+            .unwrap_or_else(|| {
+                debug!(
+                    "No translations for language '{}', using English.",
+                    language
+                );
+                LOCALES[0]
+            });
+        debug!("Using translations for language '{}'.", lang);
+
+        Self {
+            bundle: build_bundle(lang, src),
+            fallback: build_bundle(fallback_lang, fallback_src),
+        }
+    }
+
+    /// Formats the message `id` with the given arguments.
+    ///
+    /// Falls back to the English message if the active locale lacks it, and to `id` itself if the
+    /// message does not exist at all.
+    pub fn tr(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        [&self.bundle, &self.fallback]
+            .into_iter()
+            .find_map(|bundle| format_message(bundle, id, args))
+            .unwrap_or_else(|| {
+                warn!("Missing translation for message '{}'.", id);
+                id.to_string()
+            })
+    }
+}
+
+/// Builds a bundle from the embedded FTL source of `lang`.
+///
+/// Invalid entries are skipped and logged, as the sources are embedded at compile time.
+fn build_bundle(lang: &str, src: &str) -> FluentBundle<FluentResource> {
+    let langid: LanguageIdentifier = lang.parse().unwrap_or_default();
+    let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+    // Isolation marks around placeables show up as garbage in most notification daemons
+    bundle.set_use_isolating(false);
+
+    let resource = FluentResource::try_new(src.to_string()).unwrap_or_else(|(res, errors)| {
+        warn!("Could not parse translations for '{}': {:?}", lang, errors);
+        res
+    });
+    if let Err(errors) = bundle.add_resource(resource) {
+        warn!("Could not load translations for '{}': {:?}", lang, errors);
+    }
+
+    bundle
+}
+
+/// Formats the message `id` of `bundle`, returning `None` if it does not exist.
+fn format_message(
+    bundle: &FluentBundle<FluentResource>,
+    id: &str,
+    args: Option<&FluentArgs>,
+) -> Option<String> {
+    let pattern = bundle.get_message(id)?.value()?;
+    let mut errors = vec![];
+    let message = bundle.format_pattern(pattern, args, &mut errors);
+
+    if !errors.is_empty() {
+        warn!("Could not format message '{}': {:?}", id, errors);
+    }
+
+    Some(message.into_owned())
+}
+
+/// Returns the language of the user's locale (e.g., "de" for `de_DE.UTF-8`).
+///
+/// The locale is taken from the first non-empty variable of `LC_ALL`, `LC_MESSAGES` and `LANG`.
+/// Returns "en" if none is set or the locale is `C`/`POSIX`.
+fn system_language() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|locale| {
+            let language = locale.split(['_', '.', '@', '-']).next()?.to_lowercase();
+            match language.as_str() {
+                "" | "c" | "posix" => None,
+                _ => Some(language),
+            }
+        })
+        .unwrap_or_else(|| "en".to_string())
+}
@@ -0,0 +1,78 @@
+// -- crate imports
+use anyhow::Result;
+use tracing::debug;
+use zbus::Connection;
+
+// -- module imports
+use crate::{bus, logind};
+
+/// Flag of `org.gnome.SessionManager.IsInhibited` for idle inhibitors.
+const GNOME_INHIBIT_IDLE: u32 = 8;
+
+/// Returns `true` if an application currently inhibits idling (e.g., video playback or a
+/// presentation).
+///
+/// Inhibitors registered via `org.freedesktop.ScreenSaver` or the inhibit portal end up at the
+/// desktop's session manager, so GNOME's session manager, KDE's `PowerManagement.Inhibit` and
+/// logind's `idle` inhibitors are asked. Sources that are unavailable are skipped.
+pub async fn is_inhibited() -> bool {
+    if let Ok(session) = bus::session().await {
+        match gnome_inhibited(&session).await {
+            Ok(true) => return true,
+            Ok(false) => {}
+            Err(e) => debug!("Could not query GNOME session inhibitors: {}", e),
+        }
+        match kde_inhibited(&session).await {
+            Ok(true) => return true,
+            Ok(false) => {}
+            Err(e) => debug!("Could not query KDE inhibitors: {}", e),
+        }
+    }
+
+    match logind_inhibited().await {
+        Ok(inhibited) => inhibited,
+        Err(e) => {
+            debug!("Could not query logind inhibitors: {}", e);
+            false
+        }
+    }
+}
+
+/// Asks the GNOME session manager for idle inhibitors.
+async fn gnome_inhibited(conn: &Connection) -> Result<bool> {
+    let reply = conn
+        .call_method(
+            Some("org.gnome.SessionManager"),
+            "/org/gnome/SessionManager",
+            Some("org.gnome.SessionManager"),
+            "IsInhibited",
+            &(GNOME_INHIBIT_IDLE),
+        )
+        .await?;
+
+    Ok(reply.body().deserialize()?)
+}
+
+/// Asks KDE's power management for inhibitors.
+async fn kde_inhibited(conn: &Connection) -> Result<bool> {
+    let reply = conn
+        .call_method(
+            Some("org.freedesktop.PowerManagement"),
+            "/org/freedesktop/PowerManagement/Inhibit",
+            Some("org.freedesktop.PowerManagement.Inhibit"),
+            "HasInhibit",
+            &(),
+        )
+        .await?;
+
+    Ok(reply.body().deserialize()?)
+}
+
+/// Asks systemd-logind for blocking `idle` inhibitors.
+async fn logind_inhibited() -> Result<bool> {
+    let inhibitors = logind::list_inhibitors(&bus::system().await?).await?;
+
+    Ok(inhibitors.iter().any(|inhibitor| {
+        inhibitor.mode == "block" && inhibitor.what.split(':').any(|w| w == "idle")
+    }))
+}
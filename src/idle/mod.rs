@@ -0,0 +1,157 @@
+// -- std imports
+use std::time::Duration;
+
+// -- crate imports
+use anyhow::Result;
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+
+// -- module definitions
+pub mod inhibitors;
+pub mod processes;
+pub mod x11;
+
+// -- module imports
+use crate::{
+    bus,
+    configuration::{IdleConf, IdleSource, InhibitConf},
+    logind,
+};
+
+/// Watches the idle state of the user session according to `conf`.
+///
+/// Spawns a task that polls the configured [`IdleSource`] every `poll_interval` and publishes
+/// whether the session is idle. Returns `None` if idle coupling is disabled.
+///
+/// If the idle state cannot be queried, the session is considered idle, so the timeout behaves as
+/// if idle coupling were disabled.
+pub fn watch(conf: &IdleConf) -> Option<watch::Receiver<bool>> {
+    if conf.source == IdleSource::None {
+        return None;
+    }
+
+    let (source, after) = (conf.source.resolve(), conf.after);
+    info!(
+        "Coupling the timeout to the idle state of the session ({:?}).",
+        source
+    );
+
+    Some(poll(conf.poll_interval, "idle", true, move || {
+        is_idle(source, after)
+    }))
+}
+
+/// Watches whether an application inhibits idling (see [`inhibitors::is_inhibited`]), polling
+/// every `poll_interval`. Returns `None` unless `respect_inhibitors` is enabled.
+pub fn watch_inhibitors(conf: &IdleConf) -> Option<watch::Receiver<bool>> {
+    if !conf.respect_inhibitors {
+        return None;
+    }
+
+    info!("Pausing the timeout while applications inhibit idling.");
+    Some(poll(conf.poll_interval, "inhibited", false, || async {
+        Ok(inhibitors::is_inhibited().await)
+    }))
+}
+
+/// Watches whether one of the processes of `conf` runs (see [`processes::find_running`]),
+/// checking every `poll_interval`. Returns `None` if no processes are configured.
+pub fn watch_processes(conf: &InhibitConf) -> Option<watch::Receiver<bool>> {
+    if conf.processes.is_empty() {
+        return None;
+    }
+
+    info!(
+        "Pausing the timeout while any of {} runs.",
+        conf.processes.join(", ")
+    );
+    let names = conf.processes.clone();
+    Some(poll(
+        conf.poll_interval,
+        "running an inhibiting process",
+        false,
+        move || {
+            let running = processes::find_running(&names);
+            async move {
+                let running = running?;
+                if let Some(name) = &running {
+                    debug!("Process '{}' is running.", name);
+                }
+                Ok(running.is_some())
+            }
+        },
+    ))
+}
+
+/// Spawns a task that evaluates `query` every `interval` and publishes whether the session is
+/// `what` (e.g., "idle"), starting with `initial`. The task stops once all receivers are dropped.
+///
+/// If `query` fails, `initial` is assumed.
+fn poll<F, Fut>(
+    interval: Duration,
+    what: &'static str,
+    initial: bool,
+    query: F,
+) -> watch::Receiver<bool>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<bool>> + Send,
+{
+    let (tx, rx) = watch::channel(initial);
+
+    tokio::spawn(async move {
+        let mut failing = false;
+        loop {
+            let value = match query().await {
+                Ok(value) => {
+                    failing = false;
+                    value
+                }
+                Err(e) => {
+                    if !failing {
+                        warn!(
+                            "Could not query whether the session is {}, assuming {}: {}",
+                            what, initial, e
+                        );
+                    }
+                    failing = true;
+                    initial
+                }
+            };
+
+            tx.send_if_modified(|current| {
+                let changed = *current != value;
+                if changed {
+                    debug!(
+                        "Session is {}{}.",
+                        if value { "" } else { "no longer " },
+                        what
+                    );
+                }
+                *current = value;
+                changed
+            });
+            if tx.is_closed() {
+                break;
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    rx
+}
+
+/// Queries whether the user session is idle according to `source`.
+///
+/// For [`IdleSource::X11`], the session is idle if there was no user input for at least `after`.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the idle state cannot be queried.
+async fn is_idle(source: IdleSource, after: Duration) -> Result<bool> {
+    match source {
+        IdleSource::None => Ok(true),
+        IdleSource::Logind | IdleSource::Auto => logind::idle_hint(&bus::system().await?).await,
+        IdleSource::X11 => Ok(x11::idle_time().await? >= after),
+    }
+}
@@ -0,0 +1,64 @@
+// -- std imports
+use std::{fs, path::Path};
+
+// -- crate imports
+use anyhow::Result;
+
+/// Returns the name of the first process in `/proc` matching one of `names`, if any.
+///
+/// A process matches by its name (`comm`, which the kernel truncates to 15 bytes) or by the file
+/// name of its executable (the first argument of its command line). Processes that exit while
+/// they are inspected are skipped.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if `/proc` cannot be read.
+pub fn find_running(names: &[String]) -> Result<Option<String>> {
+    for entry in fs::read_dir("/proc")? {
+        let Ok(entry) = entry else { continue };
+        let dir = entry.path();
+        if !entry
+            .file_name()
+            .to_str()
+            .is_some_and(|pid| pid.bytes().all(|b| b.is_ascii_digit()))
+        {
+            continue;
+        }
+
+        if let Some(name) = names.iter().find(|name| matches(&dir, name)) {
+            return Ok(Some(name.clone()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns `true` if the process at `dir` (e.g., `/proc/1234`) is called `name`.
+fn matches(dir: &Path, name: &str) -> bool {
+    let comm = fs::read_to_string(dir.join("comm")).unwrap_or_default();
+    if comm.trim_end() == name {
+        return true;
+    }
+
+    let cmdline = fs::read(dir.join("cmdline")).unwrap_or_default();
+    let exe = cmdline.split(|b| *b == 0).next().unwrap_or_default();
+    Path::new(&*String::from_utf8_lossy(exe))
+        .file_name()
+        .is_some_and(|file| file == name)
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    #[test]
+    fn finds_the_current_process_by_its_executable() {
+        let exe = std::env::current_exe().unwrap();
+        let name = exe.file_name().unwrap().to_string_lossy().into_owned();
+
+        let names = ["surely-not-running".to_string(), name.clone()];
+        assert_eq!(find_running(&names).unwrap(), Some(name));
+        assert_eq!(find_running(&names[..1]).unwrap(), None);
+    }
+}
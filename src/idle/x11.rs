@@ -0,0 +1,42 @@
+// -- std imports
+use std::{sync::Mutex, time::Duration};
+
+// -- crate imports
+use anyhow::{Context, Result};
+use x11rb::{
+    connection::Connection,
+    protocol::{screensaver::ConnectionExt, xproto::Window},
+    rust_connection::RustConnection,
+};
+
+/// Connection to the X server and its root window, kept across queries.
+static DISPLAY: Mutex<Option<(RustConnection, Window)>> = Mutex::new(None);
+
+/// Returns the time since the last user input on the X server given by `$DISPLAY`, as reported
+/// by the MIT-SCREEN-SAVER extension.
+///
+/// The connection is re-established on the next call if the query fails.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the X server cannot be reached or does not support the extension.
+pub async fn idle_time() -> Result<Duration> {
+    tokio::task::spawn_blocking(|| {
+        let mut display = DISPLAY.lock().unwrap_or_else(|e| e.into_inner());
+        if display.is_none() {
+            let (conn, screen) = x11rb::connect(None).context("Could not connect to X server")?;
+            let root = conn.setup().roots[screen].root;
+            *display = Some((conn, root));
+        }
+
+        let (conn, root) = display.as_ref().unwrap();
+        let info = conn
+            .screensaver_query_info(*root)
+            .map_err(anyhow::Error::from)
+            .and_then(|cookie| Ok(cookie.reply()?))
+            .inspect_err(|_| *display = None)?;
+
+        Ok(Duration::from_millis(info.ms_since_user_input.into()))
+    })
+    .await?
+}
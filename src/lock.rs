@@ -0,0 +1,105 @@
+// -- crate imports
+use anyhow::Result;
+use futures_util::stream::StreamExt;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use zbus::{MatchRule, MessageStream, message::Type};
+
+// -- module imports
+use crate::{bluetooth::service::BluetoothServiceCommand, bus, logind, supervisor::Supervisor};
+
+/// D-Bus interface of the freedesktop screensaver, implemented by most screen lockers.
+const SCREENSAVER_IFACE: &str = "org.freedesktop.ScreenSaver";
+
+/// Listens for the screen being locked and sends [`BluetoothServiceCommand::ScreenLocked`] to
+/// `commands`, in tasks spawned in `supervisor`.
+///
+/// Two sources are watched: the `ActiveChanged` signal of `org.freedesktop.ScreenSaver` on the
+/// session bus, and the `Lock` signal of the daemon's logind session on the system bus. Sources
+/// that are unavailable are skipped with a warning.
+pub async fn listen(
+    commands: mpsc::UnboundedSender<BluetoothServiceCommand>,
+    supervisor: &mut Supervisor,
+) {
+    if let Err(e) = listen_screensaver(commands.clone(), supervisor).await {
+        warn!("Could not watch screensaver: {}", e);
+    }
+    if let Err(e) = listen_logind(commands, supervisor).await {
+        warn!("Could not watch logind session lock: {}", e);
+    }
+}
+
+/// Spawns a task forwarding `ActiveChanged(true)` of the screensaver as lock commands.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the session bus is unavailable or the subscription fails.
+async fn listen_screensaver(
+    commands: mpsc::UnboundedSender<BluetoothServiceCommand>,
+    supervisor: &mut Supervisor,
+) -> Result<()> {
+    let conn = bus::session().await?;
+    let rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .interface(SCREENSAVER_IFACE)?
+        .member("ActiveChanged")?
+        .build();
+    let mut stream = MessageStream::for_match_rule(rule, &conn, None).await?;
+
+    supervisor.track("screensaver listener", async move {
+        info!("Listening for screensaver activation.");
+
+        while let Some(Ok(msg)) = stream.next().await {
+            if msg.body().deserialize::<bool>().unwrap_or(false) {
+                debug!("Screensaver activated.");
+                if commands
+                    .send(BluetoothServiceCommand::ScreenLocked)
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Spawns a task forwarding the `Lock` signal of the daemon's logind session as lock commands.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the system bus or logind is unavailable or the subscription fails.
+async fn listen_logind(
+    commands: mpsc::UnboundedSender<BluetoothServiceCommand>,
+    supervisor: &mut Supervisor,
+) -> Result<()> {
+    let conn = bus::system().await?;
+    let session = logind::own_session(&conn).await?;
+    let rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .interface(logind::LOGIND_SESSION_IFACE)?
+        .member("Lock")?
+        .path(session.clone())?
+        .build();
+    let mut stream = MessageStream::for_match_rule(rule, &conn, None).await?;
+
+    supervisor.track("session lock listener", async move {
+        info!(
+            "Listening for lock requests of session {}.",
+            session.as_str()
+        );
+
+        while let Some(Ok(_)) = stream.next().await {
+            debug!("Session lock requested.");
+            if commands
+                .send(BluetoothServiceCommand::ScreenLocked)
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    Ok(())
+}
@@ -1,48 +1,100 @@
 // -- std imports (conditional)
-#[cfg(debug_assertions)]
-use std::fs;
+#[cfg(any(feature = "file-logging", feature = "otlp"))]
+use std::sync::OnceLock;
+#[cfg(feature = "file-logging")]
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 // -- std imports
-use std::{path::PathBuf, sync::OnceLock};
+use std::env;
 
 // -- crate imports (conditional)
+#[cfg(feature = "file-logging")]
+use anyhow::{Context, bail};
 #[cfg(all(debug_assertions, feature = "tokio-console"))]
 use console_subscriber::ConsoleLayer;
+#[cfg(feature = "otlp")]
+use opentelemetry::trace::TracerProvider;
+#[cfg(all(feature = "otlp", feature = "metrics"))]
+use opentelemetry_otlp::MetricExporter;
+#[cfg(feature = "otlp")]
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+#[cfg(all(feature = "otlp", feature = "metrics"))]
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+#[cfg(feature = "otlp")]
+use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
+#[cfg(feature = "file-logging")]
+use tracing_appender::{
+    non_blocking::{NonBlocking, WorkerGuard},
+    rolling::{RollingFileAppender, Rotation},
+};
 
 // -- crate imports
-use anyhow::{Context, Result};
+use anyhow::Result;
 use tracing::warn;
-use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
-use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*, registry::Registry};
+use tracing_subscriber::{
+    EnvFilter, Layer, filter::LevelFilter, fmt, prelude::*, registry::Registry,
+};
+
+// -- module imports (conditional)
+#[cfg(feature = "file-logging")]
+use crate::configuration::LogRotation;
+
+// -- module imports
+use crate::configuration::{LogConf, LogFormat};
 
 /// Global guard that keeps the non-blocking file writer alive.
 ///
 /// The guard is stored in a [`OnceLock`] so the background worker thread used by the non-blocking
 /// logger is not dropped prematurely, which would otherwise cause log records to be lost.
+#[cfg(feature = "file-logging")]
 static LOG_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
 
+/// Provider of the tracer exporting spans via OTLP, kept to flush the pending spans on exit (see
+/// [`shutdown_tracing`]).
+#[cfg(feature = "otlp")]
+static TRACER_PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// Provider of the meter exporting metrics via OTLP, kept to export the last measurements on exit
+/// (see [`shutdown_tracing`]).
+#[cfg(all(feature = "otlp", feature = "metrics"))]
+static METER_PROVIDER: OnceLock<SdkMeterProvider> = OnceLock::new();
+
 /// Name of the log file created by the application.
+#[cfg(feature = "file-logging")]
 const LOG_FILE_NAME: &str = concat!(env!("CARGO_PKG_NAME"), ".log");
 
-/// Log level used in debug builds.
+/// Default log level used in debug builds.
 #[cfg(debug_assertions)]
 const LOG_LEVEL: LevelFilter = LevelFilter::DEBUG;
 
-/// Log level used in release builds.
+/// Default log level used in release builds.
 #[cfg(not(debug_assertions))]
 const LOG_LEVEL: LevelFilter = LevelFilter::INFO;
 
-/// Returns the path to the log file used by the application.
+/// Returns the path to the log file used by the application, if it is not rotated (see
+/// `log.rotation`). Rotated files are placed in the same directory.
+///
+/// This is `log.path` if set. Otherwise, in debug builds this is `./bluetooth-timeout.log`; in
+/// release builds this uses the XDG data directory.
 ///
-/// In debug builds this is `./bluetooth-timeout.log`. In release builds this uses the XDG data
-/// directory.
+/// # Arguments
+///
+/// - `conf` - The logging configuration.
 ///
 /// # Errors
 /// - [`anyhow::Error`] if the XDG data directory cannot be used or created. (!release builds only)
-pub fn log_filepath() -> Result<PathBuf> {
+#[cfg(feature = "file-logging")]
+pub fn log_filepath(conf: &LogConf) -> Result<PathBuf> {
+    if let Some(path) = &conf.path {
+        return Ok(path.clone());
+    }
+
     #[cfg(debug_assertions)]
     {
-        let path = PathBuf::from(LOG_FILE_NAME);
+        let path = PathBuf::from(".").join(LOG_FILE_NAME);
         let _ = fs::remove_file(&path);
         Ok(path)
     }
@@ -59,24 +111,43 @@ pub fn log_filepath() -> Result<PathBuf> {
 
 /// Builds a non-blocking file writer for tracing logs.
 ///
-/// The returned writer is backed by a file appender that writes to the path returned by
-/// [`log_filepath`]. The associated [`WorkerGuard`] is stored in [`LOG_GUARD`] to ensure the
-/// background worker thread lives for the entire lifetime of the process.
+/// The returned writer is backed by a file appender that writes next to the path returned by
+/// [`log_filepath`], rotated according to `conf`. Rotated files keep the stem and extension of
+/// that path (e.g., `bluetooth-timeout.2025-01-31.log`). The associated [`WorkerGuard`] is stored in
+/// [`LOG_GUARD`] to ensure the background worker thread lives for the entire lifetime of the
+/// process.
 ///
 /// # Errors
 /// - [`anyhow::Error`] if the log file path cannot be determined or the file appender cannot be
 ///   created.
-fn build_file_writer() -> Result<NonBlocking> {
-    let path = log_filepath()?;
-
-    let dir = path
-        .parent()
-        .context("Could not determine log file directory")?;
-    let file_name = path
-        .file_name()
+#[cfg(feature = "file-logging")]
+fn build_file_writer(conf: &LogConf) -> Result<NonBlocking> {
+    let path = log_filepath(conf)?;
+
+    let dir = match path.parent() {
+        Some(dir) if dir.as_os_str().is_empty() => Path::new("."),
+        Some(dir) => dir,
+        None => bail!("Could not determine log file directory"),
+    };
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Could not create log directory '{}'", dir.display()))?;
+    let prefix = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
         .context("Could not determine log file name")?;
+    let rotation = match conf.rotation {
+        LogRotation::Never => Rotation::NEVER,
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Weekly => Rotation::WEEKLY,
+    };
 
-    let file_appender = tracing_appender::rolling::never(dir, file_name);
+    let file_appender = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(prefix)
+        .filename_suffix(path.extension().and_then(|ext| ext.to_str()).unwrap_or(""))
+        .max_log_files(conf.max_files.max(1))
+        .build(dir)?;
     let (file_writer, guard) = tracing_appender::non_blocking(file_appender);
 
     // Keep guard alive for entire process
@@ -85,71 +156,260 @@ fn build_file_writer() -> Result<NonBlocking> {
     Ok(file_writer)
 }
 
-/// Initializes global tracing with stdout and file logging.
+/// Runs `f` with a temporary stdout-only subscriber, logging at `stdout_level` (or [`LOG_LEVEL`]).
 ///
-/// # Errors
-/// - [`anyhow::Error`] if the global tracing subscriber cannot be installed.
-pub fn init_tracing() -> Result<()> {
+/// Used for logging while the configuration, which configures the global subscriber, is loaded.
+pub fn bootstrap<T>(stdout_level: Option<LevelFilter>, f: impl FnOnce() -> T) -> T {
+    let subscriber = fmt()
+        .with_target(false)
+        .with_max_level(stdout_level.unwrap_or(LOG_LEVEL))
+        .finish();
+    tracing::subscriber::with_default(subscriber, f)
+}
+
+/// Returns the filter directives in effect: `RUST_LOG` if set, otherwise `log.level`, otherwise
+/// [`LOG_LEVEL`].
+fn filter_directives(conf: &LogConf) -> String {
+    env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .or_else(|| conf.level.clone())
+        .unwrap_or_else(|| LOG_LEVEL.to_string())
+}
+
+/// Builds the filter for a layer from `directives`, falling back to [`LOG_LEVEL`] if they are
+/// invalid.
+fn env_filter(directives: &str) -> EnvFilter {
+    EnvFilter::try_new(directives).unwrap_or_else(|_| EnvFilter::new(LOG_LEVEL.to_string()))
+}
+
+/// A layer of the global subscriber.
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Builds the layer logging to stdout in the configured `format`, filtered by `directives`.
+fn stdout_layer(format: LogFormat, directives: &str) -> BoxedLayer {
+    if format == LogFormat::Json {
+        return json_layer(std::io::stdout, directives);
+    }
+
     #[cfg(debug_assertions)]
-    let stdout_layer = fmt::layer()
+    let layer = fmt::layer()
         // .pretty()
         .with_thread_ids(true)
         // .with_thread_names(true)
         .with_file(true)
         .with_line_number(true)
         .with_target(false)
-        .with_filter(LOG_LEVEL);
+        .with_filter(env_filter(directives));
+
+    #[cfg(not(debug_assertions))]
+    let layer = fmt::layer()
+        .with_thread_ids(true)
+        // .with_thread_names(true)
+        .with_target(false)
+        .with_filter(env_filter(directives));
+
+    layer.boxed()
+}
+
+/// Builds the layer logging to the log file via `writer` in the configured `format`, filtered by
+/// `directives`.
+#[cfg(feature = "file-logging")]
+fn file_layer(format: LogFormat, writer: NonBlocking, directives: &str) -> BoxedLayer {
+    if format == LogFormat::Json {
+        return json_layer(writer, directives);
+    }
+
+    #[cfg(debug_assertions)]
+    let layer = fmt::layer()
+        .pretty()
+        .with_thread_ids(true)
+        // .with_thread_names(true)
+        .with_file(true)
+        .with_line_number(true)
+        .with_target(false)
+        .with_ansi(false)
+        .with_writer(writer)
+        .with_filter(env_filter(directives));
 
     #[cfg(not(debug_assertions))]
-    let stdout_layer = fmt::layer()
+    let layer = fmt::layer()
         .with_thread_ids(true)
         // .with_thread_names(true)
+        .with_ansi(false)
+        .with_writer(writer)
         .with_target(false)
-        .with_filter(LOG_LEVEL);
-
-    match build_file_writer() {
-        Ok(writer) => {
-            #[cfg(debug_assertions)]
-            let file_layer = fmt::layer()
-                .pretty()
-                .with_thread_ids(true)
-                // .with_thread_names(true)
-                .with_file(true)
-                .with_line_number(true)
-                .with_target(false)
-                .with_ansi(false)
-                .with_writer(writer)
-                .with_filter(LOG_LEVEL);
-
-            #[cfg(not(debug_assertions))]
-            let file_layer = fmt::layer()
-                .with_thread_ids(true)
-                // .with_thread_names(true)
-                .with_ansi(false)
-                .with_writer(writer)
-                .with_target(false)
-                .with_filter(LOG_LEVEL);
-
-            #[cfg(all(debug_assertions, feature = "tokio-console"))]
-            let subscriber = Registry::default()
-                .with(stdout_layer)
-                .with(file_layer)
-                .with(ConsoleLayer::builder().spawn());
-
-            #[cfg(not(all(debug_assertions, feature = "tokio-console")))]
-            let subscriber = Registry::default().with(stdout_layer).with(file_layer);
-
-            tracing::subscriber::set_global_default(subscriber)?;
+        .with_filter(env_filter(directives));
+
+    layer.boxed()
+}
+
+/// Builds a layer writing one JSON object per line to `writer`, filtered by `directives`.
+///
+/// The fields of the event and of the current span (e.g., `event`, `adapter`, `device` while a
+/// Bluetooth event is handled) are top-level keys, so log shippers can index them.
+fn json_layer<W>(writer: W, directives: &str) -> BoxedLayer
+where
+    W: for<'a> fmt::MakeWriter<'a> + Send + Sync + 'static,
+{
+    fmt::layer()
+        .json()
+        .flatten_event(true)
+        .with_current_span(true)
+        .with_span_list(false)
+        .with_writer(writer)
+        .with_filter(env_filter(directives))
+        .boxed()
+}
+
+/// Builds the layer exporting spans to the OTLP/HTTP `endpoint`, filtered by `directives`. The
+/// spans are sent in batches by a background thread.
+///
+/// # Errors
+/// - [`anyhow::Error`] if the exporter cannot be created (e.g., the HTTP client fails to build).
+#[cfg(feature = "otlp")]
+fn otlp_layer(endpoint: &str, directives: &str) -> Result<BoxedLayer> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(env!("CARGO_PKG_NAME"))
+                .build(),
+        )
+        .build();
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    let _ = TRACER_PROVIDER.set(provider);
+
+    Ok(tracing_opentelemetry::layer()
+        .with_tracer(tracer)
+        .with_filter(env_filter(directives))
+        .boxed())
+}
+
+/// Installs the global meter exporting metrics (e.g., the event latencies) to the OTLP/HTTP
+/// `endpoint`. The metrics are sent every minute by a background thread.
+///
+/// # Errors
+/// - [`anyhow::Error`] if the exporter cannot be created (e.g., the HTTP client fails to build).
+#[cfg(all(feature = "otlp", feature = "metrics"))]
+fn otlp_meter(endpoint: &str) -> Result<()> {
+    let exporter = MetricExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(env!("CARGO_PKG_NAME"))
+                .build(),
+        )
+        .build();
+    opentelemetry::global::set_meter_provider(provider.clone());
+    let _ = METER_PROVIDER.set(provider);
+
+    Ok(())
+}
+
+/// Exports the spans and metrics that are still pending, if they are exported via OTLP, and stops
+/// the exporters. Called before the process exits.
+#[cfg(feature = "otlp")]
+pub fn shutdown_tracing() {
+    if let Some(provider) = TRACER_PROVIDER.get()
+        && let Err(e) = provider.shutdown()
+    {
+        warn!("Could not export the remaining spans: {}", e);
+    }
+    #[cfg(feature = "metrics")]
+    if let Some(provider) = METER_PROVIDER.get()
+        && let Err(e) = provider.shutdown()
+    {
+        warn!("Could not export the remaining metrics: {}", e);
+    }
+}
+
+/// Initializes global tracing with stdout and file logging, as enabled and configured by `conf`.
+/// File logging requires the `file-logging` feature, exporting spans via OTLP the `otlp` feature,
+/// exporting metrics via OTLP both the `otlp` and `metrics` features.
+///
+/// # Arguments
+///
+/// - `conf` - The logging configuration.
+/// - `stdout_level` - Level overriding the filter of the stdout output (e.g., from `--verbose`),
+///   if any. The log file is not affected.
+///
+/// # Errors
+/// - [`anyhow::Error`] if the global tracing subscriber cannot be installed.
+pub fn init_tracing(conf: &LogConf, stdout_level: Option<LevelFilter>) -> Result<()> {
+    let directives = filter_directives(conf);
+    let mut layers = Vec::new();
+
+    if conf.stdout {
+        let stdout_directives = stdout_level.map_or_else(|| directives.clone(), |l| l.to_string());
+        layers.push(stdout_layer(conf.format, &stdout_directives));
+    }
+
+    #[cfg(feature = "file-logging")]
+    let file_error = match conf.file.then(|| build_file_writer(conf)) {
+        Some(Ok(writer)) => {
+            layers.push(file_layer(conf.format, writer, &directives));
+            None
         }
-        Err(e) => {
-            let subscriber = Registry::default().with(stdout_layer);
-            tracing::subscriber::set_global_default(subscriber)?;
-
-            warn!(
-                "File logging could not be initialized. Falling back to stdout only: {}",
-                e
-            );
+        Some(Err(e)) => Some(e),
+        None => None,
+    };
+
+    #[cfg(feature = "otlp")]
+    let otlp_error = match conf
+        .otlp_endpoint
+        .as_deref()
+        .map(|e| otlp_layer(e, &directives))
+    {
+        Some(Ok(layer)) => {
+            layers.push(layer);
+            None
         }
+        Some(Err(e)) => Some(e),
+        None => None,
+    };
+
+    #[cfg(all(feature = "otlp", feature = "metrics"))]
+    let metrics_error = conf
+        .otlp_metrics_endpoint
+        .as_deref()
+        .and_then(|e| otlp_meter(e).err());
+
+    #[cfg(all(debug_assertions, feature = "tokio-console"))]
+    layers.push(ConsoleLayer::builder().spawn().boxed());
+
+    tracing::subscriber::set_global_default(Registry::default().with(layers))?;
+
+    if let Err(e) = EnvFilter::try_new(&directives) {
+        warn!(
+            "Invalid log filter '{}', using '{}' instead: {}",
+            directives, LOG_LEVEL, e
+        );
+    }
+
+    #[cfg(feature = "file-logging")]
+    if let Some(e) = file_error {
+        warn!(
+            "File logging could not be initialized. Logging to stdout only (if enabled): {}",
+            e
+        );
+    }
+
+    #[cfg(feature = "otlp")]
+    if let Some(e) = otlp_error {
+        warn!("Spans cannot be exported via OTLP: {}", e);
+    }
+
+    #[cfg(all(feature = "otlp", feature = "metrics"))]
+    if let Some(e) = metrics_error {
+        warn!("Metrics cannot be exported via OTLP: {}", e);
     }
 
     Ok(())
@@ -0,0 +1,196 @@
+// -- crate imports
+use anyhow::Result;
+use zbus::{Connection, fdo::PropertiesProxy, names::InterfaceName, zvariant::OwnedObjectPath};
+
+/// D-Bus name (and manager interface) of systemd-logind.
+const LOGIND_DBUS_NAME: &str = "org.freedesktop.login1";
+
+/// D-Bus object path of the logind manager.
+const LOGIND_DBUS_PATH: &str = "/org/freedesktop/login1";
+
+/// D-Bus interface of the logind manager.
+pub const LOGIND_MANAGER_IFACE: &str = "org.freedesktop.login1.Manager";
+
+/// D-Bus interface of logind session objects.
+pub const LOGIND_SESSION_IFACE: &str = "org.freedesktop.login1.Session";
+
+/// A login session as reported by systemd-logind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginSession {
+    /// The session ID (e.g., "2").
+    pub id: String,
+    /// The UID of the session's user.
+    pub uid: u32,
+    /// The name of the session's user.
+    pub user: String,
+    /// The seat the session is attached to (empty for remote or seatless sessions).
+    pub seat: String,
+    /// The D-Bus object path of the session.
+    pub path: OwnedObjectPath,
+    /// The session type (e.g., "x11", "wayland", "tty").
+    pub kind: String,
+    /// The session class (e.g., "user", "greeter").
+    pub class: String,
+    /// The session state ("online", "active" or "closing").
+    pub state: String,
+    /// Whether the session is the active session of its seat (i.e., in the foreground).
+    pub active: bool,
+}
+
+impl LoginSession {
+    /// Returns `true` for graphical sessions of regular users that are not closing.
+    pub fn is_graphical(&self) -> bool {
+        matches!(self.kind.as_str(), "x11" | "wayland" | "mir")
+            && self.class == "user"
+            && self.state != "closing"
+    }
+
+    /// Returns the address of the user's session bus (`/run/user/<uid>/bus`).
+    #[cfg(feature = "notifications")]
+    pub fn user_bus_address(&self) -> String {
+        format!("unix:path=/run/user/{}/bus", self.uid)
+    }
+}
+
+/// An inhibitor lock as reported by systemd-logind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inhibitor {
+    /// Colon-separated list of what is inhibited (e.g., "idle:sleep").
+    pub what: String,
+    /// Name of the application holding the lock.
+    pub who: String,
+    /// Reason given by the application.
+    pub why: String,
+    /// Lock mode ("block" or "delay").
+    pub mode: String,
+}
+
+/// Lists all inhibitor locks held via systemd-logind.
+///
+/// # Arguments
+///
+/// - `conn` - A connection to the system D-Bus.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if logind is unavailable or the inhibitors cannot be queried.
+pub async fn list_inhibitors(conn: &Connection) -> Result<Vec<Inhibitor>> {
+    let reply = conn
+        .call_method(
+            Some(LOGIND_DBUS_NAME),
+            LOGIND_DBUS_PATH,
+            Some(LOGIND_MANAGER_IFACE),
+            "ListInhibitors",
+            &(),
+        )
+        .await?;
+    let entries: Vec<(String, String, String, String, u32, u32)> = reply.body().deserialize()?;
+
+    Ok(entries
+        .into_iter()
+        .map(|(what, who, why, mode, _uid, _pid)| Inhibitor {
+            what,
+            who,
+            why,
+            mode,
+        })
+        .collect())
+}
+
+/// Returns the idle hint of systemd-logind, which is `true` if all sessions are idle.
+///
+/// # Arguments
+///
+/// - `conn` - A connection to the system D-Bus.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if logind is unavailable or the property cannot be read.
+pub async fn idle_hint(conn: &Connection) -> Result<bool> {
+    let proxy = PropertiesProxy::builder(conn)
+        .destination(LOGIND_DBUS_NAME)?
+        .path(LOGIND_DBUS_PATH)?
+        .build()
+        .await?;
+    let iface = InterfaceName::from_static_str(LOGIND_MANAGER_IFACE)?;
+
+    Ok(bool::try_from(proxy.get(iface, "IdleHint").await?)?)
+}
+
+/// Returns the object path of the login session the daemon belongs to.
+///
+/// For user services, which are not part of a session themselves, logind resolves this to the
+/// user's graphical session.
+///
+/// # Arguments
+///
+/// - `conn` - A connection to the system D-Bus.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if logind is unavailable or no session can be determined.
+pub async fn own_session(conn: &Connection) -> Result<OwnedObjectPath> {
+    let reply = conn
+        .call_method(
+            Some(LOGIND_DBUS_NAME),
+            LOGIND_DBUS_PATH,
+            Some(LOGIND_MANAGER_IFACE),
+            "GetSession",
+            &("auto"),
+        )
+        .await?;
+
+    Ok(reply.body().deserialize()?)
+}
+
+/// Lists all login sessions known to systemd-logind.
+///
+/// # Arguments
+///
+/// - `conn` - A connection to the system D-Bus.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if logind is unavailable or the sessions cannot be queried.
+pub async fn list_sessions(conn: &Connection) -> Result<Vec<LoginSession>> {
+    let reply = conn
+        .call_method(
+            Some(LOGIND_DBUS_NAME),
+            LOGIND_DBUS_PATH,
+            Some(LOGIND_MANAGER_IFACE),
+            "ListSessions",
+            &(),
+        )
+        .await?;
+    let entries: Vec<(String, u32, String, String, OwnedObjectPath)> =
+        reply.body().deserialize()?;
+
+    let mut sessions = Vec::with_capacity(entries.len());
+    for (id, uid, user, seat, path) in entries {
+        let proxy = PropertiesProxy::builder(conn)
+            .destination(LOGIND_DBUS_NAME)?
+            .path(path.clone())?
+            .build()
+            .await?;
+        let iface = InterfaceName::from_static_str(LOGIND_SESSION_IFACE)?;
+
+        let kind = String::try_from(proxy.get(iface.clone(), "Type").await?)?;
+        let class = String::try_from(proxy.get(iface.clone(), "Class").await?)?;
+        let state = String::try_from(proxy.get(iface.clone(), "State").await?)?;
+        let active = bool::try_from(proxy.get(iface, "Active").await?)?;
+
+        sessions.push(LoginSession {
+            id,
+            uid,
+            user,
+            seat,
+            path,
+            kind,
+            class,
+            state,
+            active,
+        });
+    }
+
+    Ok(sessions)
+}
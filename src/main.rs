@@ -1,43 +1,167 @@
+// -- std imports
+use std::{io, process::ExitCode};
+
 // -- crate imports
-use tracing::debug;
+use clap::Parser;
+use tokio::runtime::{self, Runtime};
+use tracing::{debug, error};
 
 // -- module definitions
+mod action;
 mod bluetooth;
+mod bus;
+mod cli;
 mod configuration;
+#[cfg(feature = "cli-control")]
+mod control;
+mod crash;
+mod daemon;
+mod error;
+#[cfg(any(feature = "notifications", feature = "cli-control"))]
+mod i18n;
+mod idle;
+mod lock;
 mod log;
+mod logind;
+mod migration;
+mod network;
+#[cfg(feature = "notifications")]
 mod notification;
+mod obex;
+mod oneshot;
+mod power_on;
+mod radio;
+mod sd_notify;
 mod serde_ext;
+mod supervisor;
 mod timeout;
 
+// -- module imports (conditional)
+#[cfg(feature = "cli-control")]
+use crate::cli::ConfigCommand;
+
 // -- module imports
 use crate::{
-    bluetooth::{observer::BluetoothEventObserver, service::BluetoothService},
-    configuration::Conf,
+    cli::{Cli, Command},
+    configuration::{Conf, RuntimeFlavor},
+    error::Error,
 };
 
-#[tokio::main]
-async fn main() {
-    log::init_tracing().expect("Could not initialize tracing");
-    debug!("Tracing initialized");
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let stdout_level = cli.stdout_level();
+
+    let conf = match log::bootstrap(stdout_level, Conf::load) {
+        Ok(conf) => conf,
+        Err(e) => {
+            log::bootstrap(stdout_level, || error!("{}", e));
+            return e.exit_code();
+        }
+    };
 
-    let conf = Conf::load();
+    if let Err(e) = log::init_tracing(&conf.log, stdout_level) {
+        let e = Error::from(e);
+        eprintln!("Could not initialize tracing: {}", e);
+        return e.exit_code();
+    }
+    debug!("Tracing initialized");
+    crash::install();
     debug!("Configuration:\n{:#?}", conf);
 
-    let observer = BluetoothEventObserver::new(conf.dbus.adapter_path.clone())
-        .await
-        .expect("Could not create Bluetooth observer");
+    let runtime = match build_runtime(conf.runtime) {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            let e = Error::from(anyhow::Error::from(e).context("Could not build async runtime"));
+            error!("{}", e);
+            return e.exit_code();
+        }
+    };
 
-    let rx = observer.subscribe();
-    observer.listen();
+    let code = match runtime.block_on(run(cli, conf)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{}", e);
+            e.exit_code()
+        }
+    };
 
-    let mut bt_service =
-        BluetoothService::new(conf.dbus.adapter_path.clone(), conf.timeout.clone())
-            .await
-            .expect("Could not create Bluetooth service");
+    #[cfg(feature = "otlp")]
+    log::shutdown_tracing();
+
+    code
+}
+
+/// Builds the async runtime of the given `flavor`.
+///
+/// # Errors
+///
+/// - [`io::Error`] if the runtime cannot be created.
+fn build_runtime(flavor: RuntimeFlavor) -> io::Result<Runtime> {
+    let mut builder = match flavor {
+        RuntimeFlavor::MultiThread => runtime::Builder::new_multi_thread(),
+        RuntimeFlavor::CurrentThread => runtime::Builder::new_current_thread(),
+    };
 
-    bt_service
-        .subscribe_to(rx)
-        .start()
-        .await
-        .expect("Bluetooth service failed");
+    builder.enable_all().build()
+}
+
+/// Runs the command given on the command line.
+///
+/// # Errors
+///
+/// - [`Error`] classifying the failure that ends the process (see [`Error::exit_code`]).
+async fn run(cli: Cli, conf: &'static Conf) -> Result<(), Error> {
+    match cli.command.unwrap_or_default() {
+        Command::Run => daemon::run(conf).await,
+        Command::Oneshot => oneshot::run(conf).await,
+        #[cfg(feature = "notifications")]
+        Command::NotifyTest => cli::notify_test::run(conf)
+            .await
+            .map_err(Error::NotificationFailed),
+        #[cfg(feature = "cli-control")]
+        Command::Snooze { duration } => cli::snooze::run(conf, duration)
+            .await
+            .map_err(Error::from_control),
+        #[cfg(feature = "cli-control")]
+        Command::KeepOn { duration } => cli::keep_on::run(duration)
+            .await
+            .map_err(Error::from_control),
+        #[cfg(feature = "cli-control")]
+        Command::PowerOff => cli::power_off::run().await.map_err(Error::from_control),
+        #[cfg(feature = "cli-control")]
+        Command::Profile { name } => cli::profile::run(name).await.map_err(Error::from_control),
+        #[cfg(feature = "cli-control")]
+        Command::SetTimeout { duration, persist } => cli::timeout::run(duration, persist)
+            .await
+            .map_err(Error::from_control),
+        #[cfg(feature = "cli-control")]
+        Command::Status {
+            events,
+            stats,
+            latency,
+        } => cli::status::run(events, stats, latency)
+            .await
+            .map_err(Error::from_control),
+        #[cfg(feature = "cli-control")]
+        Command::Monitor => cli::monitor::run().await.map_err(Error::from_control),
+        #[cfg(feature = "cli-control")]
+        Command::Healthcheck => cli::healthcheck::run().await.map_err(Error::from_control),
+        #[cfg(feature = "cli-control")]
+        Command::Config(ConfigCommand::Schema) => cli::config::print_schema().map_err(Error::Other),
+        #[cfg(feature = "cli-control")]
+        Command::Setup => cli::setup::run(conf).await.map_err(Error::Other),
+        #[cfg(feature = "cli-control")]
+        Command::InstallService(args) => cli::service::install(args.scope())
+            .await
+            .map_err(Error::Other),
+        #[cfg(feature = "cli-control")]
+        Command::UninstallService(args) => cli::service::uninstall(args.scope())
+            .await
+            .map_err(Error::Other),
+        #[cfg(feature = "cli-control")]
+        Command::InstallAutostart => cli::autostart::install().map_err(Error::Other),
+        #[cfg(feature = "cli-control")]
+        Command::UninstallAutostart => cli::autostart::uninstall().map_err(Error::Other),
+    }
 }
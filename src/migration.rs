@@ -0,0 +1,176 @@
+// -- std imports
+use std::{fs, time::Duration};
+
+// -- crate imports
+use anyhow::{Context, Result};
+use serde_yaml::{Mapping, Value};
+use tracing::{info, warn};
+
+// -- module imports
+use crate::configuration::Conf;
+
+/// Version of the configuration schema of this release, set as `version` in the config file.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// Top-level keys of the flat schema of version 1, which had no `version` field.
+const LEGACY_KEYS: [&str; 2] = ["timeout_seconds", "adapter_path"];
+
+/// Upgrades the configuration file at `path` with the given `contents` to [`CURRENT_VERSION`].
+///
+/// Returns the upgraded contents, or `None` if the file is current (or no mapping, which is left
+/// for the parser to report). The upgraded contents are written to `path`, after backing the
+/// original up to `<path>.v<version>.bak`; if that fails, they are only used for this run.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the file was written for a newer release or cannot be upgraded.
+pub fn upgrade(path: &str, contents: &str) -> Result<Option<String>> {
+    let Ok(Value::Mapping(mut doc)) = serde_yaml::from_str(contents) else {
+        return Ok(None);
+    };
+
+    let version = version(&doc)?;
+    if version == CURRENT_VERSION {
+        return Ok(None);
+    }
+    if version == 1 {
+        v1_to_v2(&mut doc)?;
+    }
+    let upgraded = serde_yaml::to_string(&doc)?;
+
+    let backup = format!("{}.v{}.bak", path, version);
+    let written = fs::write(&backup, contents)
+        .with_context(|| format!("Could not write backup '{}'", backup))
+        .and_then(|()| {
+            fs::write(path, &upgraded).with_context(|| format!("Could not write '{}'", path))
+        });
+    match written {
+        Ok(()) => info!(
+            "Upgraded configuration '{}' from version {} to {}; the original is kept as '{}'.",
+            path, version, CURRENT_VERSION, backup
+        ),
+        Err(e) => warn!(
+            "Upgraded configuration '{}' from version {} to {} for this run only: {:#}",
+            path, version, CURRENT_VERSION, e
+        ),
+    }
+
+    Ok(Some(upgraded))
+}
+
+/// Returns the schema version of `doc`. Without a `version`, this is 1 if it uses keys of the flat
+/// schema, and [`CURRENT_VERSION`] otherwise.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if `version` is not a supported version.
+fn version(doc: &Mapping) -> Result<u32> {
+    let Some(version) = doc.get("version") else {
+        let legacy = LEGACY_KEYS.iter().any(|key| doc.contains_key(key));
+        return Ok(if legacy { 1 } else { CURRENT_VERSION });
+    };
+
+    match version
+        .as_u64()
+        .and_then(|version| u32::try_from(version).ok())
+    {
+        Some(version @ 1..=CURRENT_VERSION) => Ok(version),
+        Some(version) if version > CURRENT_VERSION => anyhow::bail!(
+            "The configuration is of version {}, which requires a newer release (this one \
+             supports up to version {})",
+            version,
+            CURRENT_VERSION
+        ),
+        _ => anyhow::bail!("Invalid configuration version: {:?}", version),
+    }
+}
+
+/// Upgrades `doc` from the flat schema of version 1 to version 2: `timeout_seconds` becomes
+/// `timeout` and `adapter_path` becomes `dbus.adapter_path`. The sections version 2 requires are
+/// completed with their defaults.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if a key of the flat schema has a value of the wrong type.
+fn v1_to_v2(doc: &mut Mapping) -> Result<()> {
+    if let Some(seconds) = doc.remove("timeout_seconds") {
+        let seconds = seconds
+            .as_u64()
+            .context("timeout_seconds must be a number of seconds")?;
+        let timeout = humantime::format_duration(Duration::from_secs(seconds)).to_string();
+        doc.insert("timeout".into(), timeout.into());
+    }
+
+    let adapter_path = doc.remove("adapter_path");
+    let defaults = Conf::default().dbus;
+    let dbus = doc
+        .entry("dbus".into())
+        .or_insert_with(|| Value::Mapping(Mapping::new()))
+        .as_mapping_mut()
+        .context("dbus must be a mapping")?;
+    if let Some(adapter_path) = adapter_path {
+        anyhow::ensure!(adapter_path.is_string(), "adapter_path must be a string");
+        dbus.insert("adapter_path".into(), adapter_path);
+    }
+    for (key, value) in [
+        ("service", defaults.service),
+        ("adapter_iface", defaults.adapter_iface),
+        ("adapter_path", defaults.adapter_path),
+        ("device_iface", defaults.device_iface),
+    ] {
+        dbus.entry(key.into()).or_insert(value.into());
+    }
+    doc.entry("notifications".into())
+        .or_insert_with(|| Value::Mapping(Mapping::new()));
+
+    doc.insert("version".into(), 2.into());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    /// Returns the path of a scratch config file containing `contents`.
+    fn config_file(name: &str, contents: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("bluetooth-timeout-migration-{}", name));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.yml").to_string_lossy().into_owned();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn upgrades_the_flat_schema_and_keeps_a_backup() {
+        let legacy = "timeout_seconds: 300\nadapter_path: /org/bluez/hci1\n";
+        let path = config_file("flat", legacy);
+
+        let upgraded = upgrade(&path, legacy).unwrap().unwrap();
+        let conf: Conf = serde_yaml::from_str(&upgraded).unwrap();
+
+        assert_eq!(conf.version, CURRENT_VERSION);
+        assert_eq!(conf.timeout.default, Duration::from_secs(300));
+        assert_eq!(conf.dbus.adapter_path, "/org/bluez/hci1");
+        assert_eq!(conf.dbus.service, "org.bluez");
+        assert_eq!(fs::read_to_string(&path).unwrap(), upgraded);
+        assert_eq!(
+            fs::read_to_string(format!("{}.v1.bak", path)).unwrap(),
+            legacy
+        );
+    }
+
+    #[test]
+    fn leaves_current_configurations_alone() {
+        let contents = "timeout: 5m\nnotifications:\n  enabled: true\n";
+
+        assert_eq!(upgrade("/nonexistent/config.yml", contents).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let contents = format!("version: {}\ntimeout: 5m\n", CURRENT_VERSION + 1);
+
+        assert!(upgrade("/nonexistent/config.yml", &contents).is_err());
+    }
+}
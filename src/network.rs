@@ -0,0 +1,225 @@
+// -- std imports
+use std::collections::HashSet;
+
+// -- crate imports
+use anyhow::Result;
+use futures_util::stream::StreamExt;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use zbus::{
+    Connection, MatchRule, MessageStream,
+    fdo::PropertiesProxy,
+    message::Type,
+    names::InterfaceName,
+    zvariant::{self, OwnedObjectPath, OwnedValue},
+};
+
+// -- module imports
+use crate::{
+    bluetooth::service::BluetoothServiceCommand, bus, configuration::Conf, supervisor::Supervisor,
+};
+
+/// D-Bus name of NetworkManager.
+const NM_DBUS_NAME: &str = "org.freedesktop.NetworkManager";
+
+/// D-Bus object path of the NetworkManager manager object.
+const NM_DBUS_PATH: &str = "/org/freedesktop/NetworkManager";
+
+/// D-Bus interface of the NetworkManager manager object.
+const NM_IFACE: &str = "org.freedesktop.NetworkManager";
+
+/// D-Bus interface of active NetworkManager connections.
+const NM_ACTIVE_CONNECTION_IFACE: &str = "org.freedesktop.NetworkManager.Connection.Active";
+
+/// D-Bus interface of Wi-Fi access points.
+const NM_ACCESS_POINT_IFACE: &str = "org.freedesktop.NetworkManager.AccessPoint";
+
+/// `State` of an active connection that is fully activated.
+const NM_ACTIVE_CONNECTION_STATE_ACTIVATED: u32 = 2;
+
+/// Connection type of Wi-Fi connections.
+const NM_WIFI_TYPE: &str = "802-11-wireless";
+
+/// Listens for network connections coming and going and activates the first profile (in the
+/// order of their names) bound to a connected network via `networks`, or the main configuration
+/// once none is connected anymore. The services learn about it via
+/// [`BluetoothServiceCommand::SettingsChanged`] sent to `commands`. The connections are watched
+/// in a task spawned in `supervisor`.
+///
+/// A profile selected manually stays active until the connected networks select another one.
+/// NetworkManager not running is logged as a warning.
+pub async fn listen(
+    conf: &'static Conf,
+    commands: mpsc::UnboundedSender<BluetoothServiceCommand>,
+    supervisor: &mut Supervisor,
+) {
+    if let Err(e) = listen_network_manager(conf, commands, supervisor).await {
+        warn!("Could not watch NetworkManager: {}", e);
+    }
+}
+
+/// Spawns a task re-evaluating the connected networks whenever an active connection of
+/// NetworkManager changes its state.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the system bus is unavailable or the subscription fails.
+async fn listen_network_manager(
+    conf: &'static Conf,
+    commands: mpsc::UnboundedSender<BluetoothServiceCommand>,
+    supervisor: &mut Supervisor,
+) -> Result<()> {
+    let conn = bus::system().await?;
+    let rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .sender(NM_DBUS_NAME)?
+        .interface(NM_ACTIVE_CONNECTION_IFACE)?
+        .member("StateChanged")?
+        .build();
+    let mut stream = MessageStream::for_match_rule(rule, &conn, None).await?;
+
+    // Profile selected by the connected networks, if any
+    let mut selected = None;
+    select_profile(conf, &conn, &mut selected, &commands).await;
+    supervisor.track("NetworkManager listener", async move {
+        info!("Watching NetworkManager connections.");
+
+        while let Some(Ok(_)) = stream.next().await {
+            if !select_profile(conf, &conn, &mut selected, &commands).await {
+                break;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Activates the profile selected by the connected networks, unless it is the `selected` one
+/// already.
+///
+/// Returns `false` if the services are gone.
+async fn select_profile(
+    conf: &Conf,
+    conn: &Connection,
+    selected: &mut Option<String>,
+    commands: &mpsc::UnboundedSender<BluetoothServiceCommand>,
+) -> bool {
+    let networks = connected_networks(conn)
+        .await
+        .inspect_err(|e| warn!("Could not list connected networks: {}", e))
+        .unwrap_or_default();
+    debug!("Connected networks: {:?}", networks);
+
+    let profile = profile_for(conf, &networks).map(str::to_string);
+    if profile == *selected {
+        return true;
+    }
+
+    match &profile {
+        Some(name) => info!("Connected networks select profile '{}'.", name),
+        None => info!("No network bound to a profile is connected anymore."),
+    }
+    // The profile is known, as it was looked up in the configuration
+    let _ = conf.set_profile(profile.as_deref());
+    *selected = profile;
+    commands
+        .send(BluetoothServiceCommand::SettingsChanged)
+        .is_ok()
+}
+
+/// Returns the first profile (in the order of their names) bound to one of the connected
+/// `networks`.
+fn profile_for<'a>(conf: &'a Conf, networks: &HashSet<String>) -> Option<&'a str> {
+    conf.profiles
+        .iter()
+        .find(|(_, profile)| profile.networks.iter().any(|n| networks.contains(n)))
+        .map(|(name, _)| name.as_str())
+}
+
+/// Returns the names of the fully activated NetworkManager connections, plus the SSIDs of the
+/// Wi-Fi networks among them.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if NetworkManager is unavailable or its properties cannot be read.
+async fn connected_networks(conn: &Connection) -> Result<HashSet<String>> {
+    let mut networks = HashSet::new();
+    let active: Vec<OwnedObjectPath> =
+        property(conn, NM_DBUS_PATH, NM_IFACE, "ActiveConnections").await?;
+
+    for path in active {
+        let state: u32 = property(conn, &path, NM_ACTIVE_CONNECTION_IFACE, "State").await?;
+        if state != NM_ACTIVE_CONNECTION_STATE_ACTIVATED {
+            continue;
+        }
+
+        networks.insert(property(conn, &path, NM_ACTIVE_CONNECTION_IFACE, "Id").await?);
+        let kind: String = property(conn, &path, NM_ACTIVE_CONNECTION_IFACE, "Type").await?;
+        if kind == NM_WIFI_TYPE {
+            let access_point: OwnedObjectPath =
+                property(conn, &path, NM_ACTIVE_CONNECTION_IFACE, "SpecificObject").await?;
+            let ssid: Vec<u8> =
+                property(conn, &access_point, NM_ACCESS_POINT_IFACE, "Ssid").await?;
+            networks.insert(String::from_utf8_lossy(&ssid).into_owned());
+        }
+    }
+
+    Ok(networks)
+}
+
+/// Reads the property `name` of the NetworkManager object at `path`.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the property cannot be read or has another type.
+async fn property<T>(conn: &Connection, path: &str, iface: &'static str, name: &str) -> Result<T>
+where
+    T: TryFrom<OwnedValue, Error = zvariant::Error>,
+{
+    let proxy = PropertiesProxy::builder(conn)
+        .destination(NM_DBUS_NAME)?
+        .path(path)?
+        .build()
+        .await?;
+
+    Ok(T::try_from(
+        proxy
+            .get(InterfaceName::from_static_str(iface)?, name)
+            .await?,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+    use crate::configuration::ProfileConf;
+
+    fn profile(networks: &[&str]) -> ProfileConf {
+        ProfileConf {
+            networks: networks.iter().map(|n| n.to_string()).collect(),
+            ..ProfileConf::default()
+        }
+    }
+
+    #[test]
+    fn first_profile_bound_to_a_connected_network_is_selected() {
+        let mut conf = Conf::default();
+        conf.profiles
+            .insert("home".to_string(), profile(&["HomeWifi"]));
+        conf.profiles
+            .insert("office".to_string(), profile(&["Corp", "Dock Ethernet"]));
+        conf.profiles.insert("travel".to_string(), profile(&[]));
+
+        let networks = |names: &[&str]| names.iter().map(|n| n.to_string()).collect();
+        assert_eq!(
+            profile_for(&conf, &networks(&["Dock Ethernet"])),
+            Some("office")
+        );
+        assert_eq!(
+            profile_for(&conf, &networks(&["Corp", "HomeWifi"])),
+            Some("home")
+        );
+        assert_eq!(profile_for(&conf, &networks(&["Cafe"])), None);
+    }
+}
@@ -0,0 +1,80 @@
+// -- crate imports
+use anyhow::Result;
+use zbus::Connection;
+
+// -- module imports
+use crate::notification::{NOTIFICATIONS_DBUS_NAME, NOTIFICATIONS_DBUS_PATH};
+
+/// Capability of servers that show action buttons.
+const ACTIONS: &str = "actions";
+
+/// Capabilities of servers that render the `value` hint as a progress bar. The hint is not part
+/// of the specification; servers supporting it advertise the synchronous (in-place) updates it
+/// was introduced with, or dunst's equivalent stack tags.
+const PROGRESS: [&str; 2] = ["x-canonical-private-synchronous", "x-dunst-stack-tag"];
+
+/// Optional features of a notification server, as reported by `GetCapabilities`.
+///
+/// Notifications only carry what the server supports (see [`Notification::show`]); everything
+/// else is left out, so they degrade to plain text on minimal servers.
+///
+/// [`Notification::show`]: crate::notification::Notification::show
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Whether action buttons are shown.
+    pub actions: bool,
+    /// Whether the `value` hint is rendered as a progress bar.
+    pub progress: bool,
+}
+
+impl Capabilities {
+    /// Returns the capabilities advertised by the capability `names`.
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Self {
+        let has = |capability: &str| names.iter().any(|name| name.as_ref() == capability);
+        Self {
+            actions: has(ACTIONS),
+            progress: PROGRESS.into_iter().any(has),
+        }
+    }
+
+    /// Queries the capabilities of the notification server on `conn`.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the call fails or its reply cannot be parsed.
+    pub async fn query(conn: &Connection) -> Result<Self> {
+        let reply = conn
+            .call_method(
+                Some(NOTIFICATIONS_DBUS_NAME),
+                NOTIFICATIONS_DBUS_PATH,
+                Some(NOTIFICATIONS_DBUS_NAME),
+                "GetCapabilities",
+                &(),
+            )
+            .await?;
+        let names: Vec<String> = reply.body().deserialize()?;
+
+        Ok(Self::from_names(&names))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    #[test]
+    fn detects_actions_and_progress_bars() {
+        assert_eq!(
+            Capabilities::from_names(&["body", "actions", "x-dunst-stack-tag"]),
+            Capabilities {
+                actions: true,
+                progress: true
+            }
+        );
+        assert_eq!(
+            Capabilities::from_names(&["body", "body-markup"]),
+            Capabilities::default()
+        );
+    }
+}
@@ -0,0 +1,68 @@
+// -- crate imports
+use anyhow::Result;
+use tokio::process::Command;
+use tracing::debug;
+use zbus::{Connection, fdo::PropertiesProxy, names::InterfaceName};
+
+// -- module imports
+use crate::notification::{NOTIFICATIONS_DBUS_NAME, NOTIFICATIONS_DBUS_PATH, session};
+
+/// D-Bus interface of dunst's control API, exported on the notification server's object path.
+const DUNST_IFACE: &str = "org.dunstproject.cmd0";
+
+/// Returns whether Do-Not-Disturb is currently active.
+///
+/// The state is queried from (in this order) the notification server's `Inhibited` property
+/// (KDE Plasma), dunst's `paused` property, and GNOME's `show-banners` setting. Returns `false`
+/// if none of them can be determined.
+pub async fn is_active() -> bool {
+    if let Ok(conn) = session::connection().await {
+        for (iface, property) in [
+            (NOTIFICATIONS_DBUS_NAME, "Inhibited"),
+            (DUNST_IFACE, "paused"),
+        ] {
+            match server_property(&conn, iface, property).await {
+                Ok(active) => return active,
+                Err(e) => debug!("Could not read {}.{}: {}", iface, property, e),
+            }
+        }
+    }
+
+    match gnome_banners_disabled().await {
+        Ok(active) => active,
+        Err(e) => {
+            debug!("Could not read GNOME notification settings: {}", e);
+            false
+        }
+    }
+}
+
+/// Reads a boolean property of the notification server's object.
+async fn server_property(conn: &Connection, iface: &str, property: &str) -> Result<bool> {
+    let proxy = PropertiesProxy::builder(conn)
+        .destination(NOTIFICATIONS_DBUS_NAME)?
+        .path(NOTIFICATIONS_DBUS_PATH)?
+        .build()
+        .await?;
+
+    let value = proxy
+        .get(InterfaceName::try_from(iface)?, property)
+        .await?
+        .downcast_ref::<bool>()?;
+
+    Ok(value)
+}
+
+/// Returns whether GNOME's notification banners are disabled (GNOME's Do-Not-Disturb).
+async fn gnome_banners_disabled() -> Result<bool> {
+    let output = Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.notifications", "show-banners"])
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("gsettings exited with {}", output.status));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim() == "false")
+}
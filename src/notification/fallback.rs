@@ -0,0 +1,81 @@
+// -- std imports
+use std::process::Stdio;
+
+// -- crate imports
+use anyhow::{Context, Result};
+use tokio::{io::AsyncWriteExt, process::Command};
+use tracing::warn;
+
+// -- module imports
+use crate::{
+    configuration::{Conf, NotificationFallback},
+    notification::Notification,
+};
+
+/// Delivers `notification` via the configured fallback channel.
+///
+/// Used when no notification server is available on the session bus (e.g., headless sessions or
+/// TTYs), so warnings are not silently dropped.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the fallback command cannot be run or exits unsuccessfully.
+pub async fn deliver(notification: &Notification) -> Result<()> {
+    let conf = Conf::instance();
+
+    match conf.notifications().fallback {
+        NotificationFallback::None => Ok(()),
+        NotificationFallback::Log => {
+            warn!("{}: {}", notification.title, notification.body);
+            Ok(())
+        }
+        NotificationFallback::Wall => wall(notification).await,
+        NotificationFallback::Command => match &conf.notifications().fallback_command {
+            Some(command) => run_command(command, notification).await,
+            None => Err(anyhow::anyhow!(
+                "Notification fallback 'command' requires 'notifications.fallback_command'"
+            )),
+        },
+    }
+}
+
+/// Broadcasts the notification to all terminals using `wall`.
+async fn wall(notification: &Notification) -> Result<()> {
+    let mut child = Command::new("wall")
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("Could not run 'wall'")?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let message = format!("{}\n{}\n", notification.title, notification.body);
+        stdin.write_all(message.as_bytes()).await?;
+    }
+
+    check_status(child.wait().await?, "wall")
+}
+
+/// Runs the user-configured `command` through `sh -c`.
+///
+/// The notification is passed via the `NOTIFICATION_TITLE` and `NOTIFICATION_BODY` environment
+/// variables.
+pub async fn run_command(command: &str, notification: &Notification) -> Result<()> {
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("NOTIFICATION_TITLE", &notification.title)
+        .env("NOTIFICATION_BODY", &notification.body)
+        .status()
+        .await
+        .with_context(|| format!("Could not run notification fallback command '{command}'"))?;
+
+    check_status(status, command)
+}
+
+/// Turns an unsuccessful exit status of `program` into an error.
+fn check_status(status: std::process::ExitStatus, program: &str) -> Result<()> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("'{}' exited with {}", program, status))
+    }
+}
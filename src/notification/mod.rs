@@ -0,0 +1,646 @@
+// -- std imports
+use std::{collections::HashMap, time::Duration};
+
+// -- crate imports
+use anyhow::Result;
+use futures_util::stream::StreamExt;
+use tokio::{sync::mpsc, time::Instant};
+use tracing::{debug, error, info, instrument, warn};
+use zbus::{Connection, Proxy, zvariant::OwnedValue};
+
+// -- module definitions
+pub mod capabilities;
+pub mod dnd;
+pub mod fallback;
+pub mod session;
+pub mod sink;
+pub mod throttle;
+pub mod user_bus;
+
+// -- module imports
+use crate::{
+    bluetooth::{flag::AdapterFlag, service::BluetoothServiceCommand, stats::DayStats},
+    configuration::{
+        Conf, NotificationBus, NotificationSinkType, TimeoutAction, Urgency, WarningConf,
+    },
+    notification::capabilities::Capabilities,
+    supervisor::Supervisor,
+    tr,
+};
+
+/// The application name used when sending notifications to the desktop environment.
+pub const NOTIFICATION_APP_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// Well-known D-Bus name (and interface) of the desktop notification server.
+const NOTIFICATIONS_DBUS_NAME: &str = "org.freedesktop.Notifications";
+
+/// D-Bus object path of the desktop notification server.
+const NOTIFICATIONS_DBUS_PATH: &str = "/org/freedesktop/Notifications";
+
+/// Actions that can be attached to a notification and invoked by the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationAction {
+    /// Keep the adapter powered on until the next state change.
+    KeepOn,
+    /// Postpone the timeout by the configured snooze duration.
+    Snooze,
+    /// Turn off the adapter immediately.
+    TurnOffNow,
+}
+
+impl NotificationAction {
+    /// All actions, in the order they are shown on a notification.
+    pub const ALL: [NotificationAction; 3] = [
+        NotificationAction::KeepOn,
+        NotificationAction::Snooze,
+        NotificationAction::TurnOffNow,
+    ];
+
+    /// Returns the action key sent to (and reported back by) the notification server.
+    ///
+    /// Keys are prefixed with the application name, since `ActionInvoked` signals are broadcast
+    /// for the notifications of every application.
+    pub fn key(&self) -> &'static str {
+        match self {
+            NotificationAction::KeepOn => concat!(env!("CARGO_PKG_NAME"), ".keep-on"),
+            NotificationAction::Snooze => concat!(env!("CARGO_PKG_NAME"), ".snooze"),
+            NotificationAction::TurnOffNow => concat!(env!("CARGO_PKG_NAME"), ".turn-off-now"),
+        }
+    }
+
+    /// Returns the human-readable label of the action button.
+    pub fn label(&self, conf: &Conf) -> String {
+        match self {
+            NotificationAction::KeepOn => tr!("action-keep-on"),
+            NotificationAction::Snooze => tr!(
+                "action-snooze",
+                duration = humantime::format_duration(conf.notifications().snooze).to_string()
+            ),
+            NotificationAction::TurnOffNow => tr!("action-turn-off-now"),
+        }
+    }
+
+    /// Parses an action key reported by the notification server.
+    ///
+    /// Returns `None` for keys that do not belong to this application.
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.key() == key)
+    }
+
+    /// Converts the action into the command that is sent to the Bluetooth service.
+    pub fn to_command(self, conf: &Conf) -> BluetoothServiceCommand {
+        match self {
+            NotificationAction::KeepOn => BluetoothServiceCommand::KeepOn,
+            NotificationAction::Snooze => {
+                BluetoothServiceCommand::Snooze(conf.notifications().snooze)
+            }
+            NotificationAction::TurnOffNow => BluetoothServiceCommand::TurnOffNow,
+        }
+    }
+}
+
+/// A builder-pattern struct for constructing and sending desktop notifications via D-Bus.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    app_name: String,
+    title: String,
+    body: String,
+    icon: String,
+    actions: Vec<(String, String)>,
+    hints: HashMap<String, OwnedValue>,
+    throttle_key: Option<String>,
+    replaces_id: u32,
+    timeout: i32, // milliseconds; -1 = server default
+    /// When the notification becomes outdated (e.g., a warning once its countdown ended), if ever.
+    outdated_at: Option<Instant>,
+}
+
+impl Notification {
+    /// Create a new builder with some sane defaults.
+    pub fn new() -> Self {
+        Self {
+            app_name: NOTIFICATION_APP_NAME.to_string(),
+            title: String::new(),
+            body: String::new(),
+            icon: String::new(),
+            actions: Vec::new(),
+            hints: HashMap::new(),
+            throttle_key: None,
+            replaces_id: 0,
+            timeout: -1,
+            outdated_at: None,
+        }
+    }
+
+    /// Creates the warning notification sent while the timeout is counting down.
+    ///
+    /// # Arguments
+    ///
+    /// - `adapter` - The name of the adapter (e.g., its alias "ThinkPad").
+    /// - `remaining` - The time left until the adapter is turned off.
+    ///
+    /// If `notifications.actions` is enabled, the notification carries the buttons of all
+    /// [`NotificationAction`]s.
+    pub fn timeout_warning(adapter: &str, remaining: Duration) -> Self {
+        Self::new()
+            .title(tr!("warning-title"))
+            .body(tr!(
+                "warning-body",
+                adapter = adapter,
+                remaining = humantime::format_duration(remaining).to_string(),
+                effect = Conf::instance().expiry_effect()
+            ))
+            .icon("bluetooth-symbolic")
+            .urgency(
+                Conf::instance()
+                    .notifications()
+                    .urgency
+                    .for_remaining(remaining),
+            )
+            .outdated_after(remaining)
+            .with_configured_actions()
+    }
+
+    /// Creates the notification sent when the last device disconnected and the countdown of
+    /// `adapter` starts.
+    ///
+    /// The disconnected `device` is named if known and `notifications.device_names` is enabled.
+    pub fn countdown_started(adapter: &str, timeout: Duration, device: Option<&str>) -> Self {
+        let remaining = humantime::format_duration(timeout).to_string();
+        let effect = Conf::instance().expiry_effect();
+        let body = match device_name(device) {
+            Some(device) => tr!(
+                "started-body-device",
+                adapter = adapter,
+                device = device,
+                remaining = remaining,
+                effect = effect
+            ),
+            None => tr!(
+                "started-body",
+                adapter = adapter,
+                remaining = remaining,
+                effect = effect
+            ),
+        };
+
+        Self::new()
+            .title(tr!("started-title"))
+            .body(body)
+            .throttle_key("countdown-started")
+            .icon("bluetooth-symbolic")
+            .urgency(Urgency::Low)
+            .outdated_after(timeout)
+            .with_configured_actions()
+    }
+
+    /// Creates the notification sent when the countdown of `adapter` was cancelled because a
+    /// device connected.
+    ///
+    /// The connected `device` is named if known and `notifications.device_names` is enabled.
+    pub fn countdown_cancelled(adapter: &str, device: Option<&str>) -> Self {
+        let body = match device_name(device) {
+            Some(device) => tr!("cancelled-body-device", adapter = adapter, device = device),
+            None => tr!("cancelled-body", adapter = adapter),
+        };
+
+        Self::new()
+            .title(tr!("cancelled-title"))
+            .body(body)
+            .throttle_key("countdown-cancelled")
+            .icon("bluetooth-active-symbolic")
+            .urgency(Urgency::Low)
+    }
+
+    /// Creates the weekly summary of the powered-time `stats` of `adapter` (see
+    /// `notifications.weekly_summary`).
+    pub fn weekly_summary(adapter: &str, stats: &DayStats) -> Self {
+        let saved = Duration::from_secs(stats.saved_secs / 60 * 60);
+        let body = tr!(
+            "weekly-summary-body",
+            adapter = adapter,
+            count = stats.auto_power_offs,
+            saved = humantime::format_duration(saved).to_string()
+        );
+
+        Self::new()
+            .title(tr!("weekly-summary-title"))
+            .body(body)
+            .icon("bluetooth-symbolic")
+            .urgency(Urgency::Low)
+    }
+
+    /// Creates the resident countdown notification used by [`NotificationMode::Countdown`].
+    ///
+    /// The notification shows the remaining time until `adapter` is turned off and a progress bar
+    /// (`value` hint) of the remaining fraction of `total`, and is meant to be updated in place
+    /// via `replaces_id`.
+    ///
+    /// [`NotificationMode::Countdown`]: crate::configuration::NotificationMode::Countdown
+    pub fn countdown(adapter: &str, remaining: Duration, total: Duration) -> Self {
+        let progress = match total.as_millis() {
+            0 => 0,
+            total => (remaining.as_millis() * 100 / total).min(100) as u8,
+        };
+
+        Self::new()
+            .title(tr!("countdown-title"))
+            .body(tr!(
+                "warning-body",
+                adapter = adapter,
+                remaining = humantime::format_duration(remaining).to_string(),
+                effect = Conf::instance().expiry_effect()
+            ))
+            .icon("bluetooth-symbolic")
+            .urgency(
+                Conf::instance()
+                    .notifications()
+                    .urgency
+                    .for_remaining(remaining),
+            )
+            .progress(progress)
+            .hint("resident", true)
+            .timeout(0)
+            .outdated_after(remaining)
+            .with_configured_actions()
+    }
+
+    /// Applies the display timeout and icon overrides of a configured warning.
+    pub fn with_overrides(self, warning: &WarningConf) -> Self {
+        let notification = match warning.display_timeout {
+            Some(timeout_ms) => self.timeout(timeout_ms),
+            None => self,
+        };
+
+        match &warning.icon {
+            Some(icon) => notification.icon(icon.as_str()),
+            None => notification,
+        }
+    }
+
+    /// Attaches the buttons of all [`NotificationAction`]s if `notifications.actions` is enabled.
+    fn with_configured_actions(self) -> Self {
+        let conf = Conf::instance();
+        // Actions are only observed on the daemon's own session bus
+        if !conf.notifications().actions
+            || conf.notifications().sink != NotificationSinkType::Desktop
+            || conf.notifications().bus != NotificationBus::Session
+        {
+            return self;
+        }
+
+        NotificationAction::ALL
+            .into_iter()
+            .fold(self, |n, action| n.action(action.key(), action.label(conf)))
+    }
+
+    /// Creates the final notification sent after `adapter` has been turned off.
+    pub fn adapter_turned_off(adapter: &str) -> Self {
+        Self::new()
+            .title(tr!("turned-off-title"))
+            .body(tr!("turned-off-body", adapter = adapter))
+            .icon("bluetooth-disabled-symbolic")
+            .urgency(Conf::instance().notifications().urgency.r#final)
+    }
+
+    /// Creates the notification sent after `adapter` has been turned off because of a blackout
+    /// period lasting `until` (e.g., "06:00").
+    pub fn blackout(adapter: &str, until: &str) -> Self {
+        Self::new()
+            .title(tr!("blackout-title"))
+            .body(tr!("blackout-body", adapter = adapter, until = until))
+            .icon("bluetooth-disabled-symbolic")
+            .urgency(Urgency::Low)
+            .throttle_key("blackout")
+    }
+
+    /// Creates the notification sent after `adapter` kept being turned back on after the timeout,
+    /// depending on whether it has been `blocked` via rfkill instead.
+    pub fn power_off_escalated(adapter: &str, blocked: bool) -> Self {
+        let body = if blocked {
+            tr!("power-off-blocked-body", adapter = adapter)
+        } else {
+            tr!("power-off-failed-body", adapter = adapter)
+        };
+
+        Self::new()
+            .title(tr!("power-off-blocked-title"))
+            .body(body)
+            .icon("bluetooth-disabled-symbolic")
+            .urgency(Urgency::Critical)
+    }
+
+    /// Creates the notification sent after `flag` of `adapter` has been turned off by its timer.
+    pub fn flag_turned_off(adapter: &str, flag: AdapterFlag) -> Self {
+        let (title, body) = match flag {
+            AdapterFlag::Discoverable => (
+                tr!("discoverable-off-title"),
+                tr!("discoverable-off-body", adapter = adapter),
+            ),
+            AdapterFlag::Pairable => (
+                tr!("pairable-off-title"),
+                tr!("pairable-off-body", adapter = adapter),
+            ),
+        };
+
+        Self::new()
+            .title(title)
+            .body(body)
+            .icon("bluetooth-symbolic")
+            .urgency(Urgency::Low)
+    }
+
+    /// Creates the final notification sent after the timeout `actions` have run on `adapter`.
+    ///
+    /// This is [`Notification::adapter_turned_off`] if any action turns off the adapter.
+    pub fn timeout_expired(adapter: &str, actions: &[TimeoutAction]) -> Self {
+        if actions.iter().any(|action| action.turns_off_adapter()) {
+            return Self::adapter_turned_off(adapter);
+        }
+
+        let body = if actions.contains(&TimeoutAction::Disconnect) {
+            tr!("disconnected-body", adapter = adapter)
+        } else if actions.contains(&TimeoutAction::DiscoverableOff) {
+            tr!("discoverable-off-body", adapter = adapter)
+        } else {
+            tr!("action-ran-body", adapter = adapter)
+        };
+        Self::new()
+            .title(tr!("expired-title"))
+            .body(body)
+            .icon("bluetooth-symbolic")
+            .urgency(Conf::instance().notifications().urgency.r#final)
+    }
+
+    /// (Optional) override the app name shown in the notification.
+    #[allow(dead_code)]
+    pub fn app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = app_name.into();
+        self
+    }
+
+    /// Set the summary (title) of the notification.
+    pub fn title(mut self, summary: impl Into<String>) -> Self {
+        self.title = summary.into();
+        self
+    }
+
+    /// Set the body text of the notification.
+    pub fn body(mut self, body: impl Into<String>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Icon name from your icon theme (e.g. "dialog-information"), or "" for none.
+    pub fn icon(mut self, icon: impl Into<String>) -> Self {
+        self.icon = icon.into();
+        self
+    }
+
+    /// Add an action button with the given key and label.
+    ///
+    /// When the button is clicked, the notification server emits an `ActionInvoked` signal
+    /// carrying `key`.
+    pub fn action(mut self, key: impl Into<String>, label: impl Into<String>) -> Self {
+        self.actions.push((key.into(), label.into()));
+        self
+    }
+
+    /// Set a hint, e.g. `("resident", true)`.
+    ///
+    /// See the Desktop Notifications Specification for the hints supported by most servers.
+    pub fn hint(mut self, key: impl Into<String>, value: impl Into<OwnedValue>) -> Self {
+        self.hints.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set the urgency level (`urgency` hint).
+    pub fn urgency(self, urgency: Urgency) -> Self {
+        self.hint("urgency", urgency as u8)
+    }
+
+    /// Set a progress bar value in percent (`value` hint), rendered by e.g. dunst and mako.
+    pub fn progress(self, percent: u8) -> Self {
+        self.hint("value", i32::from(percent.min(100)))
+    }
+
+    /// Key under which the notification is throttled (see `notifications.cooldown`).
+    ///
+    /// Defaults to the title and body, so only identical notifications are deduplicated. Setting a
+    /// key throttles all notifications of a kind (e.g., repeated "countdown started" messages of a
+    /// flapping device).
+    pub fn throttle_key(mut self, key: impl Into<String>) -> Self {
+        self.throttle_key = Some(key.into());
+        self
+    }
+
+    /// Notification ID to replace (0 = none).
+    ///
+    /// This allows updating an existing notification instead of creating a new one.
+    pub fn replaces_id(mut self, replaces_id: u32) -> Self {
+        self.replaces_id = replaces_id;
+        self
+    }
+
+    /// Timeout in milliseconds, -1 = server default, 0 = persistent (depends on daemon).
+    pub fn timeout(mut self, timeout_ms: i32) -> Self {
+        self.timeout = timeout_ms;
+        self
+    }
+
+    /// Mark the notification as outdated once `after` has elapsed, so it is not redelivered
+    /// afterwards (see [`session::deliver`]).
+    pub fn outdated_after(mut self, after: Duration) -> Self {
+        self.outdated_at = Instant::now().checked_add(after);
+        self
+    }
+
+    /// Returns `true` if the notification is outdated (see [`Notification::outdated_after`]).
+    pub fn is_outdated(&self) -> bool {
+        self.outdated_at
+            .is_some_and(|outdated_at| outdated_at <= Instant::now())
+    }
+
+    /// Send the notification to the configured [`NotificationSink`](sink::NotificationSink)
+    /// (by default the desktop notification server, falling back to the configured fallback
+    /// channel if none is available; notifications that cannot be shown due to the session bus are
+    /// redelivered instead, see [`session::deliver`]).
+    ///
+    /// Returns the ID of the sent notification, or `0` if it has none (e.g., the fallback was
+    /// used).
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the sink (or the fallback channel) fails.
+    ///
+    /// Notifications with the same throttle key as one sent within `notifications.cooldown` are
+    /// dropped; the ID of the earlier notification is returned instead. Only notifications shown
+    /// by a notification server count, those delivered without an ID (e.g., via the fallback
+    /// channel) are not throttled.
+    pub async fn send(self) -> Result<u32> {
+        let key = self
+            .throttle_key
+            .clone()
+            .unwrap_or_else(|| format!("{}\n{}", self.title, self.body));
+        if let Some(id) = throttle::check(&key, Conf::instance().notifications().cooldown) {
+            return Ok(id);
+        }
+
+        let id = sink::configured(Conf::instance().notifications())
+            .show(&self)
+            .await?;
+        if id != 0 {
+            throttle::record(key, id);
+        }
+        Ok(id)
+    }
+
+    /// Closes the notification with the given `id` (e.g., an outdated warning), if the configured
+    /// sink can take it back.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the session bus is unavailable or the call fails.
+    pub async fn close(id: u32) -> Result<()> {
+        sink::configured(Conf::instance().notifications())
+            .close(id)
+            .await
+    }
+
+    /// Send the notification via org.freedesktop.Notifications on `connection`, whose server has
+    /// the given `capabilities`.
+    ///
+    /// Action buttons and the progress bar are left out if the server does not support them; the
+    /// body still tells the remaining time.
+    ///
+    /// Returns the ID of the sent notification on success.
+    pub async fn show(
+        mut self,
+        connection: &Connection,
+        capabilities: Capabilities,
+    ) -> Result<u32> {
+        if !capabilities.actions {
+            self.actions.clear();
+        }
+        if !capabilities.progress {
+            self.hints.remove("value");
+        }
+
+        // Actions are sent as a flat list of alternating keys and labels
+        let actions: Vec<String> = self
+            .actions
+            .into_iter()
+            .flat_map(|(key, label)| [key, label])
+            .collect();
+
+        // Call Notify
+        let reply = connection
+            .call_method(
+                Some(NOTIFICATIONS_DBUS_NAME),
+                NOTIFICATIONS_DBUS_PATH,
+                Some(NOTIFICATIONS_DBUS_NAME),
+                "Notify",
+                &(
+                    self.app_name,
+                    self.replaces_id,
+                    self.icon,
+                    self.title,
+                    self.body,
+                    actions,
+                    self.hints,
+                    self.timeout,
+                ),
+            )
+            .await?;
+
+        Ok(reply.body().deserialize()?)
+    }
+}
+
+/// Returns the device name to show in notifications, or `None` if device names are disabled.
+fn device_name(device: Option<&str>) -> Option<String> {
+    device
+        .filter(|_| Conf::instance().notifications().device_names)
+        .map(str::to_string)
+}
+
+/// Observes `ActionInvoked` signals of the notification server and forwards the actions of this
+/// application's notifications to the Bluetooth service.
+#[derive(Debug, Clone)]
+pub struct NotificationActionObserver {
+    /// The current connection to the session D-Bus.
+    conn: Connection,
+}
+
+impl NotificationActionObserver {
+    /// Creates a new observer on the shared session D-Bus connection used for notifications.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the connection to the session D-Bus cannot be established.
+    pub async fn new() -> Result<Self> {
+        Ok(Self {
+            conn: session::connection().await?,
+        })
+    }
+
+    /// Spawns the observer to run in a background task of `supervisor`, sending a
+    /// [`BluetoothServiceCommand`] for every invoked action, along with the ID of the notification
+    /// it was invoked on.
+    #[instrument(skip_all)]
+    pub fn listen(
+        self,
+        tx: mpsc::UnboundedSender<(u32, BluetoothServiceCommand)>,
+        supervisor: &mut Supervisor,
+    ) {
+        info!("Spawning notification action observer task.");
+        supervisor.track("notification action observer", async move {
+            if let Err(e) = self.run(tx).await {
+                error!("Notification action observer failed: {}", e);
+            }
+        });
+    }
+
+    /// The private event loop. Listens for `ActionInvoked` signals and processes them.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if subscribing to the signal fails.
+    async fn run(&self, tx: mpsc::UnboundedSender<(u32, BluetoothServiceCommand)>) -> Result<()> {
+        let proxy = Proxy::new(
+            &self.conn,
+            NOTIFICATIONS_DBUS_NAME,
+            NOTIFICATIONS_DBUS_PATH,
+            NOTIFICATIONS_DBUS_NAME,
+        )
+        .await?;
+        let mut stream = proxy.receive_signal("ActionInvoked").await?;
+
+        info!("Listening for ActionInvoked signals.");
+        while let Some(signal) = stream.next().await {
+            let (id, key) = match signal.body().deserialize::<(u32, String)>() {
+                Ok(args) => args,
+                Err(e) => {
+                    warn!("Could not parse ActionInvoked signal: {}", e);
+                    continue;
+                }
+            };
+
+            let Some(action) = NotificationAction::from_key(&key) else {
+                debug!(
+                    "Ignoring foreign notification action '{}' (id: {}).",
+                    key, id
+                );
+                continue;
+            };
+
+            info!("Notification action invoked: {:?} (id: {}).", action, id);
+            if tx.send((id, action.to_command(Conf::instance()))).is_err() {
+                error!("Failed to forward notification action: service is not running.");
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,345 @@
+// -- std imports
+use std::{collections::VecDeque, time::Duration};
+
+// -- crate imports
+use anyhow::Result;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+use zbus::Connection;
+
+// -- module imports
+use crate::{
+    bus,
+    notification::{
+        NOTIFICATIONS_DBUS_NAME, NOTIFICATIONS_DBUS_PATH, Notification, capabilities::Capabilities,
+    },
+};
+
+/// Number of attempts to establish the session bus connection before giving up on a notification.
+const CONNECT_ATTEMPTS: u32 = 3;
+
+/// Delay before the first reconnection attempt; doubled after every failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Upper bound for the delay between redelivery attempts of queued notifications.
+const MAX_BACKOFF: Duration = Duration::from_secs(16);
+
+/// Number of redelivery rounds before queued notifications are dropped.
+const REDELIVERY_ATTEMPTS: u32 = 6;
+
+/// Maximum number of undelivered notifications kept for redelivery.
+const QUEUE_CAPACITY: usize = 8;
+
+/// Returned within an [`anyhow::Error`] by [`deliver`] for a notification that could not be
+/// delivered right away and was queued for redelivery instead, so callers can tell it from a
+/// failed delivery via [`anyhow::Error::is`] and do not deliver it via a fallback channel as well.
+#[derive(Debug, thiserror::Error)]
+#[error("Notification was queued for redelivery: {reason}")]
+pub struct Queued {
+    /// Why the notification could not be delivered right away.
+    pub reason: String,
+}
+
+/// Shared state of the session bus connection used for notifications.
+static SESSION: Mutex<SessionState> = Mutex::const_new(SessionState::new());
+
+/// The lazily (re)connected session bus connection and everything tied to it.
+struct SessionState {
+    /// The current connection, `None` until connected or after a delivery failure.
+    conn: Option<Connection>,
+    /// Whether a notification server was found on the connection. Until one is, it is probed
+    /// again on every delivery, as it may start after the daemon (e.g., right after login).
+    server_available: bool,
+    /// Capabilities of the notification server, queried along with its availability.
+    capabilities: Capabilities,
+    /// Notifications that could not be delivered due to bus errors, oldest first.
+    queue: VecDeque<Notification>,
+    /// Whether a redelivery task is currently running.
+    redelivering: bool,
+}
+
+impl SessionState {
+    /// Creates the initial, disconnected state.
+    const fn new() -> Self {
+        Self {
+            conn: None,
+            server_available: false,
+            capabilities: Capabilities {
+                actions: false,
+                progress: false,
+            },
+            queue: VecDeque::new(),
+            redelivering: false,
+        }
+    }
+
+    /// Returns the current connection, connecting with retry and exponential backoff if needed.
+    ///
+    /// # Errors
+    ///
+    /// - [`anyhow::Error`] if the session bus is still unavailable after [`CONNECT_ATTEMPTS`].
+    async fn connection(&mut self) -> Result<Connection> {
+        if let Some(conn) = &self.conn {
+            return Ok(conn.clone());
+        }
+
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 1;
+        let conn = loop {
+            match bus::session().await {
+                Ok(conn) => break conn,
+                Err(e) if attempt < CONNECT_ATTEMPTS => {
+                    debug!(
+                        "Could not connect to session bus (attempt {}/{}): {}",
+                        attempt, CONNECT_ATTEMPTS, e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        debug!("Connected to session bus for notifications.");
+        self.conn = Some(conn.clone());
+        self.server_available = false;
+
+        Ok(conn)
+    }
+
+    /// Returns whether a notification server is available on `conn`, probing it and querying its
+    /// capabilities until one is found.
+    async fn server_available(&mut self, conn: &Connection) -> bool {
+        if self.server_available {
+            return true;
+        }
+
+        let available = match probe_server(conn).await {
+            Ok((name, vendor, version)) => {
+                info!("Notification server: {} {} ({}).", name, version, vendor);
+                self.capabilities = Capabilities::query(conn)
+                    .await
+                    .inspect_err(|e| {
+                        warn!(
+                            "Could not query notification server capabilities, sending plain \
+                             notifications: {}",
+                            e
+                        )
+                    })
+                    .unwrap_or_default();
+                debug!("Notification server capabilities: {:?}", self.capabilities);
+                true
+            }
+            Err(e) => {
+                warn!("No notification server available: {}", e);
+                false
+            }
+        };
+        self.server_available = available;
+
+        available
+    }
+
+    /// Drops the current connection, so the next delivery reconnects.
+    fn invalidate(&mut self) {
+        self.conn = None;
+        self.server_available = false;
+    }
+
+    /// Queues `notification` for redelivery, dropping the oldest entry if the queue is full.
+    /// Outdated notifications are not queued.
+    fn enqueue(&mut self, notification: Notification) {
+        if notification.is_outdated() {
+            return;
+        }
+        if self.queue.len() == QUEUE_CAPACITY {
+            self.queue.pop_front();
+        }
+        self.queue.push_back(notification);
+    }
+}
+
+/// Delivers `notification` to the notification server over the shared session bus connection.
+///
+/// If the session bus is unavailable or the call fails, the connection is dropped and the
+/// notification is queued for redelivery in the background. Queued notifications are discarded
+/// once a newer notification has been delivered, as it supersedes them, or once they are
+/// outdated (e.g., a warning whose countdown ended).
+///
+/// Returns the ID of the delivered notification.
+///
+/// # Errors
+///
+/// - [`Queued`] if the notification was queued for redelivery; the caller should not use a
+///   fallback channel for it.
+/// - [`anyhow::Error`] if no notification server is available; the caller should use a fallback
+///   channel.
+pub async fn deliver(notification: &Notification) -> Result<u32> {
+    let mut session = SESSION.lock().await;
+
+    let conn = match session.connection().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            queue_for_redelivery(&mut session, notification.clone());
+            return Err(Queued {
+                reason: e.to_string(),
+            }
+            .into());
+        }
+    };
+
+    if !session.server_available(&conn).await {
+        return Err(anyhow::anyhow!("No notification server available"));
+    }
+
+    match notification.clone().show(&conn, session.capabilities).await {
+        Ok(id) => {
+            if !session.queue.is_empty() {
+                debug!(
+                    "Discarding {} queued notification(s) superseded by a newer one.",
+                    session.queue.len()
+                );
+                session.queue.clear();
+            }
+            Ok(id)
+        }
+        Err(e) => {
+            session.invalidate();
+            queue_for_redelivery(&mut session, notification.clone());
+            Err(Queued {
+                reason: e.to_string(),
+            }
+            .into())
+        }
+    }
+}
+
+/// Closes the notification with the given `id`, removing it from the screen and history.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the session bus is unavailable or the call fails.
+pub async fn close(id: u32) -> Result<()> {
+    close_on(&connection().await?, id).await
+}
+
+/// Closes the notification with the given `id` on the notification server on `conn`.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the call fails.
+pub async fn close_on(conn: &Connection, id: u32) -> Result<()> {
+    conn.call_method(
+        Some(NOTIFICATIONS_DBUS_NAME),
+        NOTIFICATIONS_DBUS_PATH,
+        Some(NOTIFICATIONS_DBUS_NAME),
+        "CloseNotification",
+        &(id,),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the shared session bus connection, connecting if needed.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the session bus is unavailable.
+pub async fn connection() -> Result<Connection> {
+    SESSION.lock().await.connection().await
+}
+
+/// Queues `notification` and spawns the redelivery task if it is not running yet.
+fn queue_for_redelivery(session: &mut SessionState, notification: Notification) {
+    session.enqueue(notification);
+
+    if !session.redelivering {
+        session.redelivering = true;
+        tokio::spawn(redeliver());
+    }
+}
+
+/// Retries delivering queued notifications with exponential backoff.
+///
+/// Gives up and drops the queue after [`REDELIVERY_ATTEMPTS`] rounds.
+async fn redeliver() {
+    let mut backoff = INITIAL_BACKOFF * 4;
+
+    for attempt in 1..=REDELIVERY_ATTEMPTS {
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+
+        let mut session = SESSION.lock().await;
+        let Ok(conn) = session.connection().await else {
+            debug!(
+                "Session bus still unavailable (redelivery attempt {}/{}).",
+                attempt, REDELIVERY_ATTEMPTS
+            );
+            continue;
+        };
+
+        if !session.server_available(&conn).await {
+            debug!(
+                "Notification server still unavailable (redelivery attempt {}/{}).",
+                attempt, REDELIVERY_ATTEMPTS
+            );
+            continue;
+        }
+
+        session
+            .queue
+            .retain(|notification| !notification.is_outdated());
+        let mut delivered = 0;
+        while let Some(notification) = session.queue.front().cloned() {
+            match notification.show(&conn, session.capabilities).await {
+                Ok(_) => {
+                    session.queue.pop_front();
+                    delivered += 1;
+                }
+                Err(e) => {
+                    debug!("Redelivery of queued notification failed: {}", e);
+                    session.invalidate();
+                    break;
+                }
+            }
+        }
+
+        if session.queue.is_empty() {
+            if delivered > 0 {
+                info!("Delivered queued notifications.");
+            }
+            session.redelivering = false;
+            return;
+        }
+    }
+
+    let mut session = SESSION.lock().await;
+    if !session.queue.is_empty() {
+        warn!(
+            "Dropping {} undeliverable notification(s).",
+            session.queue.len()
+        );
+        session.queue.clear();
+    }
+    session.redelivering = false;
+}
+
+/// Queries the name, vendor and version of the notification server.
+async fn probe_server(conn: &Connection) -> Result<(String, String, String)> {
+    let reply = conn
+        .call_method(
+            Some(NOTIFICATIONS_DBUS_NAME),
+            NOTIFICATIONS_DBUS_PATH,
+            Some(NOTIFICATIONS_DBUS_NAME),
+            "GetServerInformation",
+            &(),
+        )
+        .await?;
+
+    let (name, vendor, version, _spec_version): (String, String, String, String) =
+        reply.body().deserialize()?;
+
+    Ok((name, vendor, version))
+}
@@ -0,0 +1,156 @@
+// -- std imports
+use std::fmt::Debug;
+
+// -- crate imports
+use anyhow::Result;
+use async_trait::async_trait;
+use tracing::{debug, warn};
+
+// -- module imports
+use crate::{
+    configuration::{Conf, NotificationBus, NotificationConf, NotificationSinkType},
+    notification::{Notification, fallback, session, user_bus},
+};
+
+/// Destination of [`Notification`]s, selected by `notifications.sink` (see [`configured`]).
+///
+/// Only [`DesktopSink`] talks to a notification server, so headless setups and tests can deliver
+/// notifications without `org.freedesktop.Notifications`.
+#[async_trait]
+pub trait NotificationSink: Debug + Send + Sync {
+    /// Delivers `notification`.
+    ///
+    /// Returns the ID the notification can be replaced or closed by, or `0` if it has none.
+    async fn show(&self, notification: &Notification) -> Result<u32>;
+
+    /// Closes the notification with the given `id`. Defaults to doing nothing, for sinks whose
+    /// notifications cannot be taken back.
+    async fn close(&self, _id: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the sink selected by `conf`.
+pub fn configured(conf: &NotificationConf) -> Box<dyn NotificationSink> {
+    match conf.sink {
+        NotificationSinkType::Desktop => Box::new(DesktopSink { bus: conf.bus }),
+        NotificationSinkType::Console => Box::new(ConsoleSink),
+        NotificationSinkType::Command => Box::new(CommandSink {
+            command: conf.fallback_command.clone(),
+        }),
+        NotificationSinkType::None => Box::new(NullSink),
+    }
+}
+
+/// Delivers notifications to the desktop notification server on `bus`, or via the configured
+/// fallback channel if none is available.
+#[derive(Debug)]
+pub struct DesktopSink {
+    /// Bus the notification server is looked up on.
+    bus: NotificationBus,
+}
+
+#[async_trait]
+impl NotificationSink for DesktopSink {
+    async fn show(&self, notification: &Notification) -> Result<u32> {
+        let delivered = match self.bus {
+            NotificationBus::Session => session::deliver(notification).await,
+            NotificationBus::UserSessions => user_bus::deliver(notification).await,
+        };
+
+        match delivered {
+            Ok(id) => Ok(id),
+            // Shown once it is redelivered, so the fallback would show it twice
+            Err(e) if e.is::<session::Queued>() => {
+                debug!("{}", e);
+                Ok(0)
+            }
+            Err(e) => {
+                warn!(
+                    "Could not show notification, using fallback '{:?}': {}",
+                    Conf::instance().notifications().fallback,
+                    e
+                );
+                fallback::deliver(notification).await?;
+                Ok(0)
+            }
+        }
+    }
+
+    async fn close(&self, id: u32) -> Result<()> {
+        match self.bus {
+            NotificationBus::Session => session::close(id).await,
+            NotificationBus::UserSessions => user_bus::close(id).await,
+        }
+    }
+}
+
+/// Prints notifications to standard output.
+#[derive(Debug)]
+pub struct ConsoleSink;
+
+#[async_trait]
+impl NotificationSink for ConsoleSink {
+    async fn show(&self, notification: &Notification) -> Result<u32> {
+        println!("{}: {}", notification.title, notification.body);
+        Ok(0)
+    }
+}
+
+/// Runs a shell command for every notification (see [`fallback::run_command`]).
+#[derive(Debug)]
+pub struct CommandSink {
+    /// The command, or `None` if `notifications.fallback_command` is not set.
+    command: Option<String>,
+}
+
+#[async_trait]
+impl NotificationSink for CommandSink {
+    async fn show(&self, notification: &Notification) -> Result<u32> {
+        let Some(command) = &self.command else {
+            return Err(anyhow::anyhow!(
+                "Notification sink 'command' requires 'notifications.fallback_command'"
+            ));
+        };
+
+        fallback::run_command(command, notification).await?;
+        Ok(0)
+    }
+}
+
+/// Drops notifications.
+#[derive(Debug)]
+pub struct NullSink;
+
+#[async_trait]
+impl NotificationSink for NullSink {
+    async fn show(&self, _notification: &Notification) -> Result<u32> {
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+
+    #[tokio::test]
+    async fn command_sink_passes_the_notification_to_the_command() {
+        let notification = Notification::new().title("Title").body("Body");
+        let sink = |command: &str| CommandSink {
+            command: Some(command.to_owned()),
+        };
+
+        sink(r#"test "$NOTIFICATION_TITLE: $NOTIFICATION_BODY" = "Title: Body""#)
+            .show(&notification)
+            .await
+            .unwrap();
+        assert!(sink("false").show(&notification).await.is_err());
+        assert!(
+            CommandSink { command: None }
+                .show(&notification)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,39 @@
+// -- std imports
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// -- crate imports
+use tracing::debug;
+
+/// Notifications sent recently, as `(throttle key, sent at, notification ID)` entries.
+static RECENT: Mutex<Vec<(String, Instant, u32)>> = Mutex::new(Vec::new());
+
+/// Checks whether a notification with `key` was already sent within `cooldown`.
+///
+/// Returns the ID of the recently sent notification if the new one should be dropped, or `None`
+/// if it should be sent. Returning the previous ID keeps `replaces_id` chains intact.
+pub fn check(key: &str, cooldown: Duration) -> Option<u32> {
+    if cooldown.is_zero() {
+        return None;
+    }
+
+    let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    recent.retain(|(_, sent_at, _)| sent_at.elapsed() < cooldown);
+
+    let (_, _, id) = recent.iter().find(|(k, _, _)| k == key)?;
+    debug!(
+        "Throttling notification '{}' (cool-down {:?}).",
+        key, cooldown
+    );
+
+    Some(*id)
+}
+
+/// Records that a notification with `key` has been sent with the given `id`.
+pub fn record(key: String, id: u32) {
+    let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    recent.retain(|(k, _, _)| *k != key);
+    recent.push((key, Instant::now(), id));
+}
@@ -0,0 +1,162 @@
+// -- std imports
+use std::collections::HashMap;
+
+// -- crate imports
+use anyhow::Result;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+use zbus::{Connection, connection};
+
+// -- module imports
+use crate::{
+    bus,
+    logind::{self, LoginSession},
+    notification::{Notification, capabilities::Capabilities, session},
+};
+
+/// Connections to the session buses of logged-in users and the capabilities of their
+/// notification servers, keyed by UID.
+static USER_BUSES: Mutex<Option<HashMap<u32, (Connection, Capabilities)>>> = Mutex::const_new(None);
+
+/// Number of delivered notifications whose IDs on the users' buses are kept, to close them again
+/// (see [`close`]).
+const TRACKED: usize = 16;
+
+/// Recently delivered notifications, oldest first: the ID returned by [`deliver_to`] and the ID
+/// the notification got on the bus of each user it was delivered to, keyed by UID.
+static DELIVERED: Mutex<Vec<(u32, HashMap<u32, u32>)>> = Mutex::const_new(Vec::new());
+
+/// Delivers `notification` to the session bus of the user of the active graphical login session.
+///
+/// This is used when the daemon runs as a system service, where it has no session bus of its own.
+/// The sessions are enumerated via systemd-logind on every call, so the notification follows
+/// user switches. Only the session in the foreground of its seat is notified, users with a
+/// session in the background are not disturbed. Connections to the users' buses
+/// (`/run/user/<uid>/bus`) are cached, along with the capabilities of their notification servers.
+///
+/// Returns the ID of the notification on the first user bus it was delivered to.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if logind cannot be queried or the notification could not be delivered to
+///   any session.
+pub async fn deliver(notification: &Notification) -> Result<u32> {
+    let system = bus::system().await?;
+    let sessions: Vec<LoginSession> = logind::list_sessions(&system)
+        .await?
+        .into_iter()
+        .filter(|session| session.is_graphical() && session.active)
+        .collect();
+
+    deliver_to(notification, &sessions).await
+}
+
+/// Delivers `notification` to the session buses of the users of `sessions`.
+///
+/// Each user is notified once, even if they have multiple sessions.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the notification could not be delivered to any session.
+pub async fn deliver_to(notification: &Notification, sessions: &[LoginSession]) -> Result<u32> {
+    let mut buses = USER_BUSES.lock().await;
+    let buses = buses.get_or_insert_with(HashMap::new);
+
+    let mut delivered = HashMap::new();
+    let mut first = None;
+    let mut notified_uids = vec![];
+    for session in sessions {
+        if notified_uids.contains(&session.uid) {
+            continue;
+        }
+        notified_uids.push(session.uid);
+
+        let (conn, capabilities) = match buses.get(&session.uid) {
+            Some(bus) => bus.clone(),
+            None => match connect(session).await {
+                Ok(conn) => {
+                    let capabilities = Capabilities::query(&conn)
+                        .await
+                        .inspect_err(|e| {
+                            debug!(
+                                "Could not query notification server capabilities of '{}': {}",
+                                session.user, e
+                            )
+                        })
+                        .unwrap_or_default();
+                    buses.insert(session.uid, (conn.clone(), capabilities));
+                    (conn, capabilities)
+                }
+                Err(e) => {
+                    warn!(
+                        "Could not connect to session bus of '{}': {}",
+                        session.user, e
+                    );
+                    continue;
+                }
+            },
+        };
+
+        match notification.clone().show(&conn, capabilities).await {
+            Ok(id) => {
+                debug!(
+                    "Delivered notification to session {} of '{}'.",
+                    session.id, session.user
+                );
+                first.get_or_insert(id);
+                delivered.insert(session.uid, id);
+            }
+            Err(e) => {
+                warn!("Could not notify '{}': {}", session.user, e);
+                // Reconnect on the next notification, the bus may have been restarted
+                buses.remove(&session.uid);
+            }
+        }
+    }
+
+    let id =
+        first.ok_or_else(|| anyhow::anyhow!("No active graphical session could be notified"))?;
+    let mut tracked = DELIVERED.lock().await;
+    tracked.retain(|(tracked_id, _)| *tracked_id != id);
+    if tracked.len() >= TRACKED {
+        tracked.remove(0);
+    }
+    tracked.push((id, delivered));
+
+    Ok(id)
+}
+
+/// Closes the notification with the given `id` (as returned by [`deliver_to`]) on the buses of
+/// all users it was delivered to. Notifications that are no longer tracked are left alone.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the notification could not be closed on one of the buses.
+pub async fn close(id: u32) -> Result<()> {
+    let delivered = {
+        let mut tracked = DELIVERED.lock().await;
+        match tracked.iter().position(|(tracked_id, _)| *tracked_id == id) {
+            Some(index) => tracked.remove(index).1,
+            None => return Ok(()),
+        }
+    };
+
+    let buses = USER_BUSES.lock().await;
+    let mut result = Ok(());
+    for (uid, id) in delivered {
+        if let Some((conn, _)) = buses.as_ref().and_then(|buses| buses.get(&uid)) {
+            result = result.and(session::close_on(conn, id).await);
+        }
+    }
+
+    result
+}
+
+/// Connects to the session bus of the user of `session`.
+async fn connect(session: &LoginSession) -> Result<Connection> {
+    Ok(
+        connection::Builder::address(session.user_bus_address().as_str())?
+            .build()
+            .await?,
+    )
+}
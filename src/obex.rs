@@ -0,0 +1,152 @@
+// -- std imports
+use std::collections::{HashMap, HashSet};
+
+// -- crate imports
+use anyhow::Result;
+use futures_util::stream::StreamExt;
+use tokio::sync::watch;
+use tracing::{debug, info};
+use zbus::{
+    Connection, MatchRule, MessageStream,
+    fdo::{DBusProxy, ObjectManagerProxy},
+    message::Type,
+    names::BusName,
+    zvariant::OwnedValue,
+};
+
+// -- module imports
+use crate::bus;
+
+/// Well-known name of the BlueZ OBEX daemon (`obexd`) on the session bus.
+const OBEX_DBUS_NAME: &str = "org.bluez.obex";
+
+/// D-Bus interface of the file transfers of the OBEX daemon.
+const TRANSFER_IFACE: &str = "org.bluez.obex.Transfer1";
+
+/// Watches the file transfers of the BlueZ OBEX daemon on the session bus and publishes whether
+/// one is in progress (queued, active or suspended).
+///
+/// The OBEX daemon is usually started on demand, so it not running is no error: its transfers are
+/// picked up as soon as it starts.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the session bus is unavailable or the subscription fails.
+pub async fn watch_transfers() -> Result<watch::Receiver<bool>> {
+    let conn = bus::session().await?;
+    let rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .arg(0, TRANSFER_IFACE)?
+        .build();
+    let mut changes = MessageStream::for_match_rule(rule, &conn, None).await?;
+    let dbus = DBusProxy::new(&conn).await?;
+    let mut restarts = dbus
+        .receive_name_owner_changed_with_args(&[(0, OBEX_DBUS_NAME)])
+        .await?;
+
+    // Object paths of the transfers in progress
+    let mut transfers = transfers_in_progress(&conn, &dbus)
+        .await
+        .inspect_err(|e| debug!("Could not list OBEX transfers: {}", e))
+        .unwrap_or_default();
+    let (tx, rx) = watch::channel(!transfers.is_empty());
+
+    tokio::spawn(async move {
+        info!("Watching OBEX file transfers.");
+
+        loop {
+            tokio::select! {
+                Some(Ok(msg)) = changes.next() => {
+                    let Some(path) = msg.header().path().map(|p| p.to_string()) else {
+                        continue;
+                    };
+                    let Ok((_, changed, _)) = msg
+                        .body()
+                        .deserialize::<(String, HashMap<String, OwnedValue>, Vec<String>)>()
+                    else {
+                        continue;
+                    };
+                    let Some(status) = changed
+                        .get("Status")
+                        .and_then(|v| String::try_from(v.clone()).ok())
+                    else {
+                        continue;
+                    };
+
+                    debug!("OBEX transfer {} is {}.", path, status);
+                    if in_progress(&status) {
+                        transfers.insert(path);
+                    } else {
+                        transfers.remove(&path);
+                    }
+                }
+                Some(_) = restarts.next() => {
+                    // The transfers of a stopped (or restarted) OBEX daemon are gone
+                    transfers.clear();
+                }
+                else => break,
+            }
+
+            let busy = !transfers.is_empty();
+            tx.send_if_modified(|current| {
+                let changed = *current != busy;
+                if changed {
+                    info!(
+                        "OBEX file transfer {}.",
+                        if busy { "started" } else { "finished" }
+                    );
+                }
+                *current = busy;
+                changed
+            });
+            if tx.is_closed() {
+                break;
+            }
+        }
+    });
+
+    Ok(rx)
+}
+
+/// Returns the object paths of the transfers in progress, asking the OBEX daemon only if it is
+/// running (so it is not started just for this).
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the D-Bus calls fail.
+async fn transfers_in_progress(conn: &Connection, dbus: &DBusProxy<'_>) -> Result<HashSet<String>> {
+    if !dbus
+        .name_has_owner(BusName::try_from(OBEX_DBUS_NAME)?)
+        .await?
+    {
+        return Ok(HashSet::new());
+    }
+
+    let objects = ObjectManagerProxy::builder(conn)
+        .destination(OBEX_DBUS_NAME)?
+        .path("/")?
+        .build()
+        .await?
+        .get_managed_objects()
+        .await?;
+
+    Ok(objects
+        .into_iter()
+        .filter(|(_, ifaces)| {
+            ifaces
+                .get(TRANSFER_IFACE)
+                .and_then(|props| props.get("Status"))
+                .and_then(|status| String::try_from(status.clone()).ok())
+                .is_some_and(|status| in_progress(&status))
+        })
+        .map(|(path, _)| path.to_string())
+        .collect())
+}
+
+/// Returns `true` if a transfer with `status` is still in progress (rather than `complete` or
+/// failed with `error`).
+fn in_progress(status: &str) -> bool {
+    matches!(status, "queued" | "active" | "suspended")
+}
@@ -0,0 +1,257 @@
+// -- std imports
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+// -- crate imports
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use zbus::fdo::ObjectManagerProxy;
+
+// -- module imports
+use crate::{
+    bluetooth::backend::{BluetoothBackend, bluez::BlueZBackend},
+    bus,
+    configuration::{Conf, state_path},
+    daemon,
+    error::Error,
+};
+
+/// State kept between two runs, in the file returned by [`path`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct IdleState {
+    /// Seconds since the Unix epoch at which the adapter was first seen powered on without
+    /// connected devices.
+    idle_since: u64,
+}
+
+/// Result of checking an adapter once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    /// The adapter is off.
+    Off,
+    /// A device is connected.
+    Busy,
+    /// The adapter has been idle for the given duration, which is shorter than the timeout.
+    Idle(Duration),
+    /// The adapter had been idle for the given duration and was turned off.
+    TurnedOff(Duration),
+}
+
+/// Checks the adapter at `dbus.adapter_path` (or every adapter, if it is `all`) once and turns it
+/// off if it has been powered on without connected devices for the timeout, then returns.
+///
+/// When the adapter was idle is remembered between runs, so this is meant to be run periodically
+/// (e.g., from cron) instead of the daemon. The idle time starts at the first run that sees the
+/// adapter idle; connections between two runs are not noticed. The timeout is `timeout.default` of
+/// the configuration, as the context that made the adapter idle is unknown.
+///
+/// # Errors
+///
+/// - [`Error::DbusUnavailable`] if the system D-Bus or the adapters cannot be reached.
+/// - [`Error::AdapterNotFound`] if BlueZ does not know the adapter.
+/// - [`Error::Other`] if the adapter cannot be queried or turned off, or the state cannot be kept.
+pub async fn run(conf: &'static Conf) -> Result<(), Error> {
+    let Some(timeouts) = conf.timeouts() else {
+        info!("The timeout is off; leaving the adapter alone.");
+        return Ok(());
+    };
+
+    let adapters = if conf.dbus.manages_all_adapters() {
+        list_adapters(conf).await.map_err(Error::DbusUnavailable)?
+    } else {
+        vec![conf.dbus.adapter_path.clone()]
+    };
+
+    for adapter in adapters {
+        let backend = BlueZBackend::new(adapter).await?;
+        let path = path(backend.adapter())?;
+        let outcome = check(
+            &backend,
+            timeouts.default,
+            conf.trusted_only,
+            &path,
+            SystemTime::now(),
+        )
+        .await?;
+
+        let name = backend.adapter_name();
+        match outcome {
+            Outcome::Off => info!("{} is off.", name),
+            Outcome::Busy => info!("{} has connected devices.", name),
+            Outcome::Idle(idle) => info!(
+                "{} has been idle for {} of {}.",
+                name,
+                humantime::format_duration(idle),
+                humantime::format_duration(timeouts.default)
+            ),
+            Outcome::TurnedOff(idle) => info!(
+                "{} was idle for {}; turned it off.",
+                name,
+                humantime::format_duration(idle)
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the object paths of all adapters known to BlueZ.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the system D-Bus cannot be reached or the D-Bus call fails.
+async fn list_adapters(conf: &Conf) -> Result<Vec<String>> {
+    let conn = bus::system().await?;
+    let objects = ObjectManagerProxy::builder(&conn)
+        .destination(conf.dbus.service.as_str())?
+        .path("/")?
+        .build()
+        .await?;
+
+    daemon::list_adapters(&objects, conf).await
+}
+
+/// Checks the adapter managed by `backend` at `now`, turning it off if it has been idle for
+/// `timeout` according to the state kept at `path`. With `trusted_only`, untrusted devices do not
+/// count as connected.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the adapter cannot be queried or turned off, or the state cannot be
+///   written.
+async fn check(
+    backend: &dyn BluetoothBackend,
+    timeout: Duration,
+    trusted_only: bool,
+    path: &Path,
+    now: SystemTime,
+) -> Result<Outcome> {
+    if !backend.is_powered().await? {
+        forget(path)?;
+        return Ok(Outcome::Off);
+    }
+    let connected = backend.connected_devices().await?;
+    if connected
+        .iter()
+        .any(|device| device.keeps_adapter_on(trusted_only))
+    {
+        forget(path)?;
+        return Ok(Outcome::Busy);
+    }
+
+    let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let Some(state) = load(path)? else {
+        let contents = serde_yaml::to_string(&IdleState { idle_since: now })?;
+        fs::write(path, contents)?;
+        return Ok(Outcome::Idle(Duration::ZERO));
+    };
+
+    let idle = Duration::from_secs(now.saturating_sub(state.idle_since));
+    if idle < timeout {
+        return Ok(Outcome::Idle(idle));
+    }
+
+    backend.power_off().await?;
+    forget(path)?;
+    Ok(Outcome::TurnedOff(idle))
+}
+
+/// Returns the state kept at `path`, or `None` if there is none (or it cannot be parsed).
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the file exists but cannot be read.
+fn load(path: &Path) -> Result<Option<IdleState>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(serde_yaml::from_str(&contents)
+            .inspect_err(|e| warn!("Ignoring invalid idle state '{}': {}", path.display(), e))
+            .ok()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Removes the state kept at `path`, if any.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the file exists but cannot be removed.
+fn forget(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the path of the file the idle state of `adapter` (e.g., "/org/bluez/hci0") is kept in.
+///
+/// This is `hci0.idle.yml` among the state files (see [`state_path`]).
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the state directory cannot be determined or created.
+fn path(adapter: &str) -> Result<PathBuf> {
+    let name = adapter.rsplit('/').next().unwrap_or(adapter);
+
+    state_path(&format!("{}.idle.yml", name))
+}
+
+#[cfg(test)]
+mod tests {
+    // -- module imports
+    use super::*;
+    use crate::bluetooth::backend::mock::MockBackend;
+
+    /// Returns the path of a scratch state file that does not exist yet.
+    fn state_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("bluetooth-timeout-oneshot-{}.yml", name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    /// Returns the instant `secs` after the Unix epoch.
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[tokio::test]
+    async fn turns_off_the_adapter_once_idle_for_the_timeout() {
+        let backend = MockBackend::new(true);
+        let path = state_file("idle");
+        let timeout = Duration::from_secs(300);
+
+        let first = check(backend.as_ref(), timeout, false, &path, at(1000)).await;
+        let second = check(backend.as_ref(), timeout, false, &path, at(1200)).await;
+        let third = check(backend.as_ref(), timeout, false, &path, at(1300)).await;
+
+        assert_eq!(first.unwrap(), Outcome::Idle(Duration::ZERO));
+        assert_eq!(second.unwrap(), Outcome::Idle(Duration::from_secs(200)));
+        assert_eq!(third.unwrap(), Outcome::TurnedOff(timeout));
+        assert_eq!(backend.power_offs(), 1);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn connected_devices_restart_the_idle_time() {
+        let backend = MockBackend::new(true);
+        let path = state_file("busy");
+        let timeout = Duration::from_secs(300);
+        let device = backend.add_device("headset", false);
+
+        check(backend.as_ref(), timeout, false, &path, at(1000))
+            .await
+            .unwrap();
+        backend.connect(&device);
+        let busy = check(backend.as_ref(), timeout, false, &path, at(1200)).await;
+        backend.disconnect(&device);
+        let idle = check(backend.as_ref(), timeout, false, &path, at(1400)).await;
+
+        assert_eq!(busy.unwrap(), Outcome::Busy);
+        assert_eq!(idle.unwrap(), Outcome::Idle(Duration::ZERO));
+        assert_eq!(backend.power_offs(), 0);
+    }
+}
@@ -0,0 +1,104 @@
+// -- crate imports
+use anyhow::Result;
+use futures_util::stream::StreamExt;
+use tracing::{debug, info, warn};
+use zbus::{MatchRule, MessageStream, message::Type, zvariant::OwnedObjectPath};
+
+// -- module imports
+use crate::{
+    bluetooth::{
+        backend::{BluetoothBackend, SharedBackend},
+        rfkill,
+    },
+    bus, logind,
+    supervisor::Supervisor,
+};
+
+/// Powers on the adapter managed by `backend`, unless it is already on or airplane mode is on
+/// (see [`rfkill::is_airplane_mode`]).
+///
+/// `reason` is only used for logging.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the powered state cannot be read or set.
+pub async fn power_on(backend: &dyn BluetoothBackend, reason: &str) -> Result<()> {
+    if backend.is_powered().await? {
+        debug!("Adapter is already powered on ({}).", reason);
+        return Ok(());
+    }
+    let hci = backend.adapter().rsplit('/').next().unwrap_or_default();
+    if rfkill::is_airplane_mode(hci).unwrap_or(false) {
+        info!("Not powering on the adapter in airplane mode ({}).", reason);
+        return Ok(());
+    }
+
+    backend.power_on().await?;
+    info!("Adapter powered on ({}).", reason);
+
+    Ok(())
+}
+
+/// Sets the adapter managed by `backend` to the `powered` state it was left in (see
+/// `power_on.restore`). Powering on is subject to the same conditions as [`power_on`].
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the powered state cannot be read or set.
+pub async fn restore(backend: &dyn BluetoothBackend, powered: bool) -> Result<()> {
+    const REASON: &str = "restoring the state it was left in";
+
+    if powered {
+        return power_on(backend, REASON).await;
+    }
+    if backend.is_powered().await? {
+        backend.power_off().await?;
+        info!("Adapter powered off ({}).", REASON);
+    }
+
+    Ok(())
+}
+
+/// Spawns a task in `supervisor` that powers on the adapter managed by `backend` whenever a
+/// graphical login session begins.
+///
+/// Sessions are reported by the `SessionNew` signal of systemd-logind. The regular idle timeout
+/// applies afterwards, so the adapter is turned off again if no device connects.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the system bus is unavailable or the subscription fails.
+pub async fn listen_sessions(backend: SharedBackend, supervisor: &mut Supervisor) -> Result<()> {
+    let conn = bus::system().await?;
+    let rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .interface("org.freedesktop.login1.Manager")?
+        .member("SessionNew")?
+        .build();
+    let mut stream = MessageStream::for_match_rule(rule, &conn, None).await?;
+
+    supervisor.track("login session listener", async move {
+        info!("Listening for new login sessions.");
+
+        while let Some(Ok(msg)) = stream.next().await {
+            let Ok((id, _path)) = msg.body().deserialize::<(String, OwnedObjectPath)>() else {
+                continue;
+            };
+
+            let graphical = logind::list_sessions(&conn)
+                .await
+                .map(|sessions| sessions.iter().any(|s| s.id == id && s.is_graphical()))
+                .unwrap_or(false);
+            if !graphical {
+                debug!("Ignoring new non-graphical session {}.", id);
+                continue;
+            }
+
+            let _ = power_on(backend.as_ref(), "graphical session started")
+                .await
+                .inspect_err(|e| warn!("Failed to power on adapter: {}", e));
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,226 @@
+// -- std imports
+use std::{fs, path::Path};
+
+// -- crate imports
+use anyhow::Result;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+// -- module imports
+use crate::{
+    bluetooth::rfkill,
+    configuration::{Conf, RadioConf, RadioType},
+    supervisor::{Policy, Supervisor},
+};
+
+/// Directory listing the network interfaces of the system.
+const NET_SYSFS: &str = "/sys/class/net";
+
+/// State of the radios of a [`RadioType`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RadioState {
+    /// All radios are blocked (or there are none).
+    Off,
+    /// A radio is on, but none of its network interfaces is connected.
+    Idle,
+    /// A network interface of the radios is connected.
+    Connected,
+}
+
+/// Spawns a task per radio type configured in `radios` (see [`run`]) in `supervisor`, which
+/// restarts them should they stop.
+pub fn spawn_all(conf: &'static Conf, supervisor: &mut Supervisor) {
+    for (&kind, radio) in &conf.radios {
+        supervisor.spawn(name(kind), Policy::Restart, move || run(kind, radio));
+    }
+}
+
+/// Polls the radios of `kind` every `poll_interval` and soft-blocks them via rfkill once they
+/// have been on without an active connection for `timeout`.
+///
+/// Unlike the Bluetooth adapter, the radios are not powered on again; unblock them with `rfkill
+/// unblock` or the desktop's network settings.
+pub async fn run(kind: RadioType, conf: &'static RadioConf) {
+    info!(
+        "Turning off {} after {} without an active connection.",
+        name(kind),
+        humantime::format_duration(conf.timeout)
+    );
+    let mut idle_since = None;
+
+    loop {
+        tokio::time::sleep(conf.poll_interval).await;
+
+        let state = match state(kind) {
+            Ok(state) => state,
+            Err(e) => {
+                warn!("Could not determine the state of {}: {}", name(kind), e);
+                continue;
+            }
+        };
+        debug!("{} is {:?}.", name(kind), state);
+        if !expired(&mut idle_since, state, conf, Instant::now()) {
+            continue;
+        }
+
+        match rfkill::soft_block_all(rfkill_type(kind), true) {
+            Ok(()) => info!(
+                "Turned off {} after {} without an active connection.",
+                name(kind),
+                humantime::format_duration(conf.timeout)
+            ),
+            Err(e) => warn!("Could not turn off {}: {}", name(kind), e),
+        }
+    }
+}
+
+/// Tracks since when the radios have been idle in `idle_since`, given their `state` at `now`, and
+/// returns `true` once they have been idle for the timeout of `conf`.
+fn expired(
+    idle_since: &mut Option<Instant>,
+    state: RadioState,
+    conf: &RadioConf,
+    now: Instant,
+) -> bool {
+    if state != RadioState::Idle {
+        *idle_since = None;
+        return false;
+    }
+
+    let since = *idle_since.get_or_insert(now);
+    if now.duration_since(since) < conf.timeout {
+        return false;
+    }
+
+    *idle_since = None;
+    true
+}
+
+/// Returns the state of the radios of `kind`, from their rfkill switches and the operational
+/// state of their network interfaces.
+///
+/// # Errors
+///
+/// - [`anyhow::Error`] if the rfkill switches or network interfaces cannot be listed.
+fn state(kind: RadioType) -> Result<RadioState> {
+    if !rfkill::any_unblocked(rfkill_name(kind))? {
+        return Ok(RadioState::Off);
+    }
+
+    for entry in fs::read_dir(NET_SYSFS)?.flatten() {
+        let iface = entry.path();
+        if is_of_type(&iface, kind) && is_connected(&iface) {
+            return Ok(RadioState::Connected);
+        }
+    }
+
+    Ok(RadioState::Idle)
+}
+
+/// Returns `true` if the network interface at `iface` (e.g., `/sys/class/net/wlan0`) belongs to
+/// a radio of `kind`.
+fn is_of_type(iface: &Path, kind: RadioType) -> bool {
+    match kind {
+        RadioType::Wlan => iface.join("wireless").exists() || iface.join("phy80211").exists(),
+        RadioType::Wwan => fs::read_to_string(iface.join("uevent"))
+            .unwrap_or_default()
+            .lines()
+            .any(|line| line == "DEVTYPE=wwan"),
+    }
+}
+
+/// Returns `true` if the network interface at `iface` is connected, i.e. operational (or, for
+/// drivers not reporting an operational state, has a carrier).
+fn is_connected(iface: &Path) -> bool {
+    let read = |attr: &str| fs::read_to_string(iface.join(attr)).unwrap_or_default();
+
+    match read("operstate").trim() {
+        "up" => true,
+        "unknown" => read("carrier").trim() == "1",
+        _ => false,
+    }
+}
+
+/// Returns the human-readable name of the radios of `kind`, used for logging.
+fn name(kind: RadioType) -> &'static str {
+    match kind {
+        RadioType::Wlan => "Wi-Fi",
+        RadioType::Wwan => "mobile broadband",
+    }
+}
+
+/// Returns the name of the rfkill switches of `kind` in sysfs.
+fn rfkill_name(kind: RadioType) -> &'static str {
+    match kind {
+        RadioType::Wlan => "wlan",
+        RadioType::Wwan => "wwan",
+    }
+}
+
+/// Returns the rfkill type of `kind`.
+fn rfkill_type(kind: RadioType) -> u8 {
+    match kind {
+        RadioType::Wlan => rfkill::RFKILL_TYPE_WLAN,
+        RadioType::Wwan => rfkill::RFKILL_TYPE_WWAN,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // -- std imports
+    use std::time::Duration;
+
+    // -- module imports
+    use super::*;
+
+    /// Returns a configuration with a timeout of a minute.
+    fn conf() -> RadioConf {
+        RadioConf {
+            timeout: Duration::from_mins(1),
+            poll_interval: Duration::from_secs(30),
+        }
+    }
+
+    #[test]
+    fn expires_once_idle_for_the_timeout() {
+        let (conf, start) = (conf(), Instant::now());
+        let mut idle_since = None;
+
+        assert!(!expired(&mut idle_since, RadioState::Idle, &conf, start));
+        assert!(!expired(
+            &mut idle_since,
+            RadioState::Idle,
+            &conf,
+            start + Duration::from_secs(30)
+        ));
+        assert!(expired(
+            &mut idle_since,
+            RadioState::Idle,
+            &conf,
+            start + Duration::from_secs(60)
+        ));
+        assert_eq!(idle_since, None);
+    }
+
+    #[test]
+    fn connections_restart_the_idle_time() {
+        let (conf, start) = (conf(), Instant::now());
+        let mut idle_since = None;
+
+        expired(&mut idle_since, RadioState::Idle, &conf, start);
+        expired(
+            &mut idle_since,
+            RadioState::Connected,
+            &conf,
+            start + Duration::from_secs(30),
+        );
+
+        assert!(!expired(
+            &mut idle_since,
+            RadioState::Idle,
+            &conf,
+            start + Duration::from_secs(60)
+        ));
+        assert_eq!(idle_since, Some(start + Duration::from_secs(60)));
+    }
+}
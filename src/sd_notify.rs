@@ -0,0 +1,111 @@
+// -- std imports
+use std::{
+    env, io,
+    os::{
+        linux::net::SocketAddrExt,
+        unix::net::{SocketAddr, UnixDatagram},
+    },
+    process,
+    time::Duration,
+};
+
+// -- crate imports
+use tokio::time::Interval;
+use tracing::{debug, info, warn};
+
+/// The systemd watchdog of the unit, if it sets `WatchdogSec`.
+///
+/// It is pinged by the loop of the daemon once it has checked that the daemon is healthy (see
+/// [`Watchdog::tick`]), so systemd restarts a daemon that stopped working rather than one that
+/// merely stopped pinging.
+#[derive(Debug)]
+pub struct Watchdog {
+    /// Notification socket and the ticks at half the interval of the watchdog, or `None` if it is
+    /// disabled.
+    pings: Option<(String, Interval)>,
+}
+
+impl Watchdog {
+    /// Resolves once the watchdog is due to be pinged. Never resolves if it is disabled.
+    pub async fn tick(&mut self) {
+        match &mut self.pings {
+            Some((_, ticks)) => {
+                ticks.tick().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Pings the watchdog. Failures are logged as warnings.
+    pub fn ping(&self) {
+        if let Some((socket, _)) = &self.pings
+            && let Err(e) = notify(socket, "WATCHDOG=1")
+        {
+            warn!("Could not ping the systemd watchdog: {}", e);
+        }
+    }
+}
+
+/// Tells systemd that the daemon has started and returns the watchdog of the unit, which is
+/// disabled unless the unit sets `WatchdogSec`.
+///
+/// The notification socket is taken from `NOTIFY_SOCKET`, so this does nothing unless the daemon
+/// runs as a `Type=notify` unit. Failures are logged as warnings.
+pub fn ready() -> Watchdog {
+    let disabled = Watchdog { pings: None };
+    let Some(socket) = env::var_os("NOTIFY_SOCKET") else {
+        debug!("NOTIFY_SOCKET is not set; not notifying systemd.");
+        return disabled;
+    };
+    let socket = socket.to_string_lossy().into_owned();
+
+    if let Err(e) = notify(&socket, "READY=1") {
+        warn!("Could not notify systemd of the startup: {}", e);
+        return disabled;
+    }
+
+    let Some(interval) = watchdog_interval() else {
+        return disabled;
+    };
+    info!(
+        "Pinging the systemd watchdog every {} while the daemon is healthy.",
+        humantime::format_duration(interval / 2)
+    );
+
+    Watchdog {
+        pings: Some((socket, tokio::time::interval(interval / 2))),
+    }
+}
+
+/// Returns the interval of the watchdog from `WATCHDOG_USEC`, or `None` if it is disabled or
+/// meant for another process (`WATCHDOG_PID`).
+fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = env::var("WATCHDOG_PID")
+        && pid.parse() != Ok(process::id())
+    {
+        return None;
+    }
+
+    env::var("WATCHDOG_USEC")
+        .ok()?
+        .parse()
+        .ok()
+        .filter(|usec| *usec > 0)
+        .map(Duration::from_micros)
+}
+
+/// Sends `state` to the notification `socket`, which is a path or, starting with `@`, an abstract
+/// socket name.
+///
+/// # Errors
+///
+/// - [`io::Error`] if the socket cannot be reached.
+fn notify(socket: &str, state: &str) -> io::Result<()> {
+    let addr = match socket.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(socket)?,
+    };
+
+    UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &addr)?;
+    Ok(())
+}
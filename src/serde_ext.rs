@@ -14,14 +14,59 @@ pub mod humantime_serde_duration {
         let s = String::deserialize(deserializer)?;
         humantime::parse_duration(&s).map_err(serde::de::Error::custom)
     }
+}
+
+pub mod humantime_serde_option_duration {
+    use super::*;
+
+    /// Deserializes an optional human-readable duration (e.g., `5m`), for use with
+    /// `#[serde(default)]`.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        super::humantime_serde_duration::deserialize(deserializer).map(Some)
+    }
+}
+
+pub mod time_of_day {
+    use super::*;
+
+    /// Deserializes a time of day in the format `HH:MM` (e.g., `06:30`).
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<chrono::NaiveTime, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        chrono::NaiveTime::parse_from_str(&s, "%H:%M").map_err(|e| {
+            serde::de::Error::custom(format!(
+                "invalid time of day '{}' ({}), expected HH:MM",
+                s, e
+            ))
+        })
+    }
+}
+
+pub mod one_or_many {
+    use super::*;
+
+    /// Either a single value or a list of values.
+    #[derive(Deserialize, schemars::JsonSchema)]
+    #[serde(untagged)]
+    pub enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
 
-    pub fn deserialize_vec<'de, D>(deserializer: D) -> Result<Vec<Duration>, D::Error>
+    /// Deserializes either a single value or a sequence of values into a `Vec`.
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
     where
         D: Deserializer<'de>,
+        T: Deserialize<'de>,
     {
-        let v = Vec::<String>::deserialize(deserializer)?;
-        v.into_iter()
-            .map(|s| humantime::parse_duration(&s).map_err(serde::de::Error::custom))
-            .collect()
+        Ok(match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        })
     }
 }
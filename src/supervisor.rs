@@ -0,0 +1,224 @@
+// -- std imports
+use std::{collections::HashMap, fmt, future, pin::Pin, time::Duration};
+
+// -- crate imports
+use anyhow::anyhow;
+use tokio::{
+    task::{self, JoinSet},
+    time::Instant,
+};
+use tracing::{error, warn};
+
+/// Delay before the first restart of a task; it doubles with every further restart in a row.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// Number of restarts in a row after which a task with [`Policy::Restart`] is escalated.
+const MAX_RESTARTS: u32 = 5;
+
+/// Time a task has to run to count as stable again, resetting its restarts in a row.
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+
+/// A boxed future running a supervised task.
+type TaskFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// What the [`Supervisor`] does once a task exits (returns or panics). The tasks it supervises
+/// are loops, so any exit is unexpected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    /// The exit is logged; the task is not run again.
+    Log,
+    /// The task is run again after a delay that doubles with every restart in a row. After
+    /// [`MAX_RESTARTS`] of them, the exit is escalated: it ends the supervision (see
+    /// [`Supervisor::supervise`]).
+    Restart,
+}
+
+/// A task of the [`Supervisor`].
+struct Task {
+    /// Name of the task for the logs (e.g., "observer").
+    name: &'static str,
+    /// What to do once the task exits.
+    policy: Policy,
+    /// Creates the future running the task, or `None` if it cannot be run again.
+    start: Option<Box<dyn Fn() -> TaskFuture + Send + Sync>>,
+    /// When the task was (re)started.
+    started: Instant,
+    /// Number of restarts in a row.
+    restarts: u32,
+}
+
+/// Background tasks whose exits are noticed, e.g. the observer of an adapter and its helpers.
+///
+/// The tasks run in a [`JoinSet`], so dropping the supervisor aborts all of them. Their exits are
+/// only handled while [`Supervisor::supervise`] is awaited.
+#[derive(Default)]
+pub struct Supervisor {
+    /// The running tasks.
+    tasks: JoinSet<()>,
+    /// The running tasks by their ID.
+    specs: HashMap<task::Id, Task>,
+}
+
+impl fmt::Debug for Supervisor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<_> = self.specs.values().map(|task| task.name).collect();
+        names.sort_unstable();
+        f.debug_struct("Supervisor").field("tasks", &names).finish()
+    }
+}
+
+impl Supervisor {
+    /// Creates a supervisor without tasks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawns the task `name`, run by the futures created by `start`, and handles its exits
+    /// according to `policy`.
+    pub fn spawn<F, Fut>(&mut self, name: &'static str, policy: Policy, start: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.run(
+            Task {
+                name,
+                policy,
+                start: Some(Box::new(move || Box::pin(start()))),
+                started: Instant::now(),
+                restarts: 0,
+            },
+            None,
+        );
+    }
+
+    /// Spawns the task `name` run by `future`, which cannot be run again (e.g., as it owns a
+    /// signal stream), so its exit is only logged.
+    pub fn track<Fut>(&mut self, name: &'static str, future: Fut)
+    where
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let id = self.tasks.spawn(future).id();
+        self.specs.insert(
+            id,
+            Task {
+                name,
+                policy: Policy::Log,
+                start: None,
+                started: Instant::now(),
+                restarts: 0,
+            },
+        );
+    }
+
+    /// Runs `task` after `delay`, if any.
+    fn run(&mut self, mut task: Task, delay: Option<Duration>) {
+        let Some(start) = &task.start else {
+            return;
+        };
+        let future = start();
+        task.started = Instant::now() + delay.unwrap_or_default();
+
+        let id = match delay {
+            Some(delay) => self.tasks.spawn(async move {
+                tokio::time::sleep(delay).await;
+                future.await;
+            }),
+            None => self.tasks.spawn(future),
+        }
+        .id();
+        self.specs.insert(id, task);
+    }
+
+    /// Handles the exits of the tasks, logging, restarting or escalating them according to their
+    /// [`Policy`], until one is escalated. Never returns if no task is escalated.
+    ///
+    /// Returns the error describing the escalated exit.
+    pub async fn supervise(&mut self) -> anyhow::Error {
+        loop {
+            let Some(result) = self.tasks.join_next_with_id().await else {
+                return future::pending().await;
+            };
+            let (id, reason) = match result {
+                Ok((id, ())) => (id, "stopped".to_string()),
+                Err(e) if e.is_cancelled() => {
+                    self.specs.remove(&e.id());
+                    continue;
+                }
+                Err(e) => (e.id(), format!("panicked: {}", e)),
+            };
+            let Some(mut task) = self.specs.remove(&id) else {
+                continue;
+            };
+
+            if task.started.elapsed() >= STABLE_AFTER {
+                task.restarts = 0;
+            }
+            match task.policy {
+                Policy::Log => warn!("Background task '{}' {}.", task.name, reason),
+                Policy::Restart if task.restarts < MAX_RESTARTS => {
+                    let delay = RESTART_DELAY * 2u32.pow(task.restarts);
+                    warn!(
+                        "Background task '{}' {}; restarting it in {}.",
+                        task.name,
+                        reason,
+                        humantime::format_duration(delay)
+                    );
+                    task.restarts += 1;
+                    self.run(task, Some(delay));
+                }
+                Policy::Restart => {
+                    error!("Background task '{}' {}; giving up.", task.name, reason);
+                    return anyhow!("Background task '{}' {}", task.name, reason);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // -- std imports
+    use std::sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    };
+
+    // -- module imports
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn restarts_a_task_until_it_is_escalated() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let mut supervisor = Supervisor::new();
+        supervisor.spawn("flaky", Policy::Restart, {
+            let runs = runs.clone();
+            move || {
+                runs.fetch_add(1, Ordering::SeqCst);
+                async {}
+            }
+        });
+
+        let e = supervisor.supervise().await;
+
+        assert_eq!(runs.load(Ordering::SeqCst), MAX_RESTARTS + 1);
+        assert_eq!(e.to_string(), "Background task 'flaky' stopped");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn escalates_a_panicking_task_but_not_a_logged_one() {
+        let mut supervisor = Supervisor::new();
+        supervisor.track("listener", async {});
+        supervisor.spawn("observer", Policy::Restart, || async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            panic!("boom");
+        });
+
+        let e = supervisor.supervise().await;
+
+        assert!(
+            e.to_string()
+                .starts_with("Background task 'observer' panicked")
+        );
+    }
+}
@@ -1,25 +1,182 @@
+// -- std imports (conditional)
+#[cfg(feature = "notifications")]
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+};
+
 // -- std imports
-use std::time::Duration;
+use std::{cmp::Reverse, collections::HashSet, sync::Mutex, time::Duration};
+
+// -- crate imports (conditional)
+#[cfg(feature = "notifications")]
+use tracing::error;
 
 // -- crate imports
-use tokio::task::JoinHandle;
-use tracing::{error, info, warn};
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinHandle,
+    time::Instant,
+};
+use tracing::{debug, info, warn};
+
+// -- module imports (conditional)
+#[cfg(feature = "notifications")]
+use crate::{
+    configuration::{DndPolicy, Urgency},
+    notification::{Notification, dnd},
+};
 
 // -- module imports
 use crate::{
-    bluetooth::service_proxy::BluetoothServiceProxy, configuration::Conf,
-    notification::Notification,
+    action,
+    bluetooth::{backend::SharedBackend, event_bus::Subscription, observer::BluetoothEvent},
+    configuration::{Conf, NotificationMode, StageConf},
 };
 
+/// Number of sent notifications whose adapter is kept, to route the actions invoked on them (see
+/// [`notified_adapter`]).
+#[cfg_attr(not(feature = "notifications"), allow(dead_code))]
+const TRACKED_NOTIFICATIONS: usize = 16;
+
+/// Recently sent notifications of timeout tasks, oldest first: their ID and the object path of the
+/// adapter they were sent for.
+static NOTIFIED_ADAPTERS: Mutex<Vec<(u32, String)>> = Mutex::new(Vec::new());
+
+/// A scheduled step of a [`TimeoutTask`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    /// A warning (or countdown update) with the given remaining duration.
+    Warning(Duration),
+    /// The stage with the given index, run after the deadline.
+    Stage(usize),
+    /// The `BeforePowerOff` announcement of the stage with the given index, which turns off the
+    /// adapter.
+    BeforePowerOff(usize),
+}
+
+/// Commands that can be sent to a running [`TimeoutTask`] via its [`TimeoutHandle`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeoutCommand {
+    /// Postpone the deadline by the given duration.
+    Snooze(Duration),
+    /// Bring the deadline forward to at most the given duration from now.
+    Accelerate(Duration),
+    /// Freeze the countdown, keeping the remaining duration and the pending warnings.
+    Pause,
+    /// Continue a paused countdown where it left off.
+    Resume,
+}
+
 /// A task that monitors inactivity and turns off the Bluetooth adapter after a specified duration.
 ///
-/// It sends warning notifications at specific intervals (5m, 1m, 30s, 10s) before the timeout occurs.
-#[derive(Debug, Clone)]
-
+/// It sends warning notifications at the configured remaining durations (e.g. 5m, 1m, 30s, 10s)
+/// before the timeout occurs.
+#[derive(Debug)]
 pub struct TimeoutTask {
     pub timeout: Duration,
-    pub service_proxy: BluetoothServiceProxy,
-    last_notification_id: u32,
+    pub backend: SharedBackend,
+    /// ID of the last notification sent by the task, shared with the [`TimeoutHandle`].
+    #[cfg(feature = "notifications")]
+    last_notification_id: Arc<AtomicU32>,
+    /// Remaining duration at which the countdown notification started (countdown mode only).
+    #[cfg(feature = "notifications")]
+    countdown_total: Duration,
+    /// Whether a notification is sent when the task starts.
+    announce: bool,
+    /// Name of the device whose disconnection started the task, if known.
+    device: Option<String>,
+    /// Idle state of the user session; the countdown is paused while the session is active.
+    idle: Option<watch::Receiver<bool>>,
+    /// Stages run after the deadline (see [`Conf::stages`]).
+    stages: Vec<StageConf>,
+    /// Index of the next stage to run.
+    next_stage: usize,
+    /// Deadline of the countdown (`None` while paused or once the task has finished), shared with
+    /// the [`TimeoutHandle`].
+    deadline: watch::Sender<Option<Instant>>,
+    /// Receiver for commands sent via the [`TimeoutHandle`].
+    commands: mpsc::UnboundedReceiver<TimeoutCommand>,
+    /// Sender handed to the [`TimeoutHandle`] on `spawn`.
+    command_tx: mpsc::UnboundedSender<TimeoutCommand>,
+}
+
+/// Handle to a spawned [`TimeoutTask`].
+///
+/// Allows sending commands to the running task, and awaiting or aborting it.
+#[derive(Debug)]
+pub struct TimeoutHandle {
+    /// Handle of the underlying Tokio task.
+    handle: JoinHandle<()>,
+    /// Sender for commands to the running task.
+    tx: mpsc::UnboundedSender<TimeoutCommand>,
+    /// Deadline of the countdown, published by the task.
+    deadline: watch::Receiver<Option<Instant>>,
+    /// ID of the last notification sent by the task (`0` if none).
+    #[cfg(feature = "notifications")]
+    notification_id: Arc<AtomicU32>,
+}
+
+impl TimeoutHandle {
+    /// Returns `true` if the timeout task has finished.
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Aborts the timeout task.
+    pub fn abort(&self) {
+        self.handle.abort();
+    }
+
+    /// Returns the time left until the deadline, or `None` if the countdown is paused (the session
+    /// is active) or the task has finished. While the stages run after the deadline, this is zero.
+    pub fn remaining(&self) -> Option<Duration> {
+        if self.is_finished() {
+            return None;
+        }
+
+        self.deadline
+            .borrow()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Returns the ID of the last notification sent by the task, or `None` if it sent none.
+    #[cfg(feature = "notifications")]
+    pub fn notification_id(&self) -> Option<u32> {
+        match self.notification_id.load(Ordering::Relaxed) {
+            0 => None,
+            id => Some(id),
+        }
+    }
+
+    /// Postpones the deadline of the running timeout task by `duration`.
+    ///
+    /// Returns `false` if the task has already finished and the command could not be delivered.
+    pub fn snooze(&self, duration: Duration) -> bool {
+        self.tx.send(TimeoutCommand::Snooze(duration)).is_ok()
+    }
+
+    /// Shortens the remaining duration of the running timeout task to at most `duration`.
+    ///
+    /// Returns `false` if the task has already finished and the command could not be delivered.
+    pub fn accelerate(&self, duration: Duration) -> bool {
+        self.tx.send(TimeoutCommand::Accelerate(duration)).is_ok()
+    }
+
+    /// Pauses the countdown of the running timeout task until [`TimeoutHandle::resume`].
+    ///
+    /// Unlike aborting the task and spawning a new one, the remaining duration and the warnings
+    /// that are still due are kept. Returns `false` if the task has already finished.
+    pub fn pause(&self) -> bool {
+        self.tx.send(TimeoutCommand::Pause).is_ok()
+    }
+
+    /// Resumes the countdown of the running timeout task paused by [`TimeoutHandle::pause`].
+    ///
+    /// Returns `false` if the task has already finished.
+    pub fn resume(&self) -> bool {
+        self.tx.send(TimeoutCommand::Resume).is_ok()
+    }
 }
 
 impl TimeoutTask {
@@ -28,92 +185,630 @@ impl TimeoutTask {
     /// # Arguments
     ///
     /// * `timeout` - The total duration to wait before turning off the adapter.
-    /// * `service_proxy` - The proxy to communicate with the Bluetooth service.
-    pub fn new(timeout: Duration, service_proxy: BluetoothServiceProxy) -> Self {
+    /// * `backend` - The backend to operate on the Bluetooth adapter.
+    pub fn new(timeout: Duration, backend: SharedBackend) -> Self {
+        let (command_tx, commands) = mpsc::unbounded_channel();
+
         Self {
             timeout,
-            service_proxy,
-            last_notification_id: 0,
+            backend,
+            #[cfg(feature = "notifications")]
+            last_notification_id: Arc::new(AtomicU32::new(0)),
+            #[cfg(feature = "notifications")]
+            countdown_total: Duration::ZERO,
+            announce: false,
+            device: None,
+            idle: None,
+            stages: Conf::instance().stages(),
+            next_stage: 0,
+            deadline: watch::Sender::new(None),
+            commands,
+            command_tx,
         }
     }
 
+    /// Sends a notification announcing the countdown when the task starts (if notifications are
+    /// enabled).
+    pub fn announce(mut self, announce: bool) -> Self {
+        self.announce = announce;
+        self
+    }
+
+    /// Sets the name of the device whose disconnection started the task, which is mentioned in the
+    /// start notification.
+    pub fn device(mut self, device: Option<String>) -> Self {
+        self.device = device;
+        self
+    }
+
+    /// Couples the countdown to the idle state of the user session: while the session is active,
+    /// the countdown is paused, and it restarts from the full timeout once the session is idle
+    /// again. `None` disables the coupling.
+    pub fn idle(mut self, idle: Option<watch::Receiver<bool>>) -> Self {
+        self.idle = idle;
+        self
+    }
+
+    /// Publishes the deadline to `deadline` instead of a channel of its own, so observers (e.g.,
+    /// the control interface) can follow consecutive tasks.
+    pub fn deadline(mut self, deadline: watch::Sender<Option<Instant>>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
     /// Runs the timeout logic.
     ///
-    /// This method sleeps for calculated intervals to send notifications at
-    /// 5 minutes, 60 seconds, 30 seconds, and 10 seconds remaining.
-    /// Finally, it turns off the adapter and sends a final notification.
+    /// This method sleeps until the next configured warning is due and sends a notification, until
+    /// the deadline is reached. Snooze commands move the deadline and re-schedule all warnings
+    /// that fit into the new remaining duration. While paused, nothing is due; resuming moves the
+    /// deadline by the time spent paused. Moving the deadline or pausing withdraws the last
+    /// warning, as the time it tells is outdated. After the deadline, the stages run one after
+    /// another, each followed by its own notification.
+    ///
+    /// Right before a stage that turns off the adapter, the task ends instead if a device
+    /// connected since the previous step, or is connected now but was not when the task started:
+    /// the service may not have handled the connection yet, and would otherwise see it cut off.
     async fn run(mut self) {
         info!(
-            "Starting timeout task: will turn off adapter after {} of inactivity.",
+            "Starting timeout task: will turn off {} after {} of inactivity.",
+            self.backend.adapter_name(),
             humantime::format_duration(self.timeout)
         );
         let conf = Conf::instance();
+        let mut events = self.backend.events().subscribe("timeout task");
+        let connected: HashSet<String> = self
+            .backend
+            .connected_devices()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|device| device.object_path)
+            .collect();
 
-        if conf.notifications.enabled {
-            for &time in &conf.notifications.at {
-                self.notification_at(time.clone()).await;
+        // Published on `spawn`, so it is known before the task first runs
+        let mut deadline = self
+            .deadline
+            .borrow()
+            .unwrap_or_else(|| Instant::now() + self.timeout);
+        let mut pending = self.schedule(conf, self.timeout);
+
+        #[cfg(feature = "notifications")]
+        if self.announce && conf.notifications().enabled {
+            // A warning due right away would only repeat the announcement
+            if pending.last() == Some(&Step::Warning(self.timeout)) {
+                pending.pop();
             }
+            let id = Notification::countdown_started(
+                self.backend.adapter_name(),
+                self.timeout,
+                self.device.as_deref(),
+            )
+            .send()
+            .await
+            .inspect_err(|e| error!("Failed to show notification: {}", e))
+            .unwrap_or(0);
+            self.notification_sent(id);
         }
 
-        tokio::time::sleep(self.timeout).await;
-        match self.service_proxy.turn_off_adapter().await {
-            Ok(_) => info!("Adapter turned off."),
-            Err(e) => warn!("Failed to turn off adapter: {}", e),
-        }
+        let ticking = !conf.tick_interval.is_zero();
+        let mut next_tick = Instant::now();
+        // Last device drained on a tick that keeps the adapter on, until the next step
+        let mut just_connected: Option<String> = None;
+        // When the countdown was paused via `TimeoutCommand::Pause`, if it is
+        let mut paused: Option<Instant> = None;
+        // When the countdown was held as the user session became active, if it is; `deadline` is
+        // then the one it restarts with, were the session idle again right away
+        let mut held: Option<Instant> = None;
 
-        if conf.notifications.enabled {
-            let _ = Notification::new()
-                .title("Bluetooth Adapter Turned Off")
-                .body("Bluetooth adapter has been turned off due to inactivity.")
-                .icon("bluetooth-disabled-symbolic")
-                // .replaces_id(self.last_notification_id)
-                .show()
-                .await
-                .inspect_err(|e| error!("Failed to show notification: {}", e));
+        let mut idle = self.idle.take();
+        while let Some(&next) = pending.last() {
+            let wake_at = match next {
+                Step::Warning(at) => deadline - at,
+                Step::Stage(index) => deadline + self.stages[index].after,
+                Step::BeforePowerOff(index) => (deadline + self.stages[index].after)
+                    .checked_sub(conf.before_power_off)
+                    .unwrap_or_else(Instant::now),
+            };
+
+            tokio::select! {
+                _ = tokio::time::sleep_until(wake_at), if paused.is_none() && held.is_none() => {
+                    pending.pop();
+                    let just_connected =
+                        drain_connected(&mut events, conf.trusted_only).or(just_connected.take());
+                    match next {
+                        #[cfg(feature = "notifications")]
+                        Step::Warning(at) => self.send_notification(&at).await,
+                        #[cfg(not(feature = "notifications"))]
+                        Step::Warning(_) => unreachable!("warnings are only scheduled with notifications"),
+                        Step::Stage(index) => {
+                            let turns_off =
+                                self.stages[index].action.iter().any(|a| a.turns_off_adapter());
+                            if turns_off
+                                && let Some(device) =
+                                    self.last_moment_connection(just_connected, &connected).await
+                            {
+                                info!(
+                                    "{} connected at the last moment; not turning off {}.",
+                                    device,
+                                    self.backend.adapter_name()
+                                );
+                                break;
+                            }
+                            self.run_stage(index).await
+                        }
+                        Step::BeforePowerOff(index) => {
+                            self.before_power_off(deadline + self.stages[index].after);
+                        }
+                    }
+                },
+                _ = tokio::time::sleep_until(next_tick), if paused.is_none() && held.is_none() && ticking && next_tick < deadline => {
+                    self.tick(deadline);
+                    next_tick += conf.tick_interval;
+                    // Drained on every tick, so the ticks do not pile up between the steps
+                    if let Some(device) = drain_connected(&mut events, conf.trusted_only) {
+                        just_connected = Some(device);
+                    }
+                },
+                Some(command) = self.commands.recv() => match command {
+                    TimeoutCommand::Snooze(duration) => {
+                        let Some(snoozed) = deadline.checked_add(duration) else {
+                            warn!(
+                                "Ignoring snooze by {}, as it is too long.",
+                                humantime::format_duration(duration)
+                            );
+                            continue;
+                        };
+                        #[cfg(feature = "notifications")]
+                        self.withdraw_notification().await;
+                        deadline = snoozed;
+                        let remaining = self.publish(deadline, paused.or(held));
+                        pending = self.schedule(conf, remaining);
+                        next_tick = Instant::now();
+                        info!(
+                            "Timeout snoozed by {}: adapter will turn off in {}.",
+                            humantime::format_duration(duration),
+                            humantime::format_duration(remaining)
+                        );
+                    }
+                    TimeoutCommand::Accelerate(duration) => {
+                        #[cfg(feature = "notifications")]
+                        self.withdraw_notification().await;
+                        deadline = deadline
+                            .min(paused.or(held).unwrap_or_else(Instant::now) + duration);
+                        let remaining = self.publish(deadline, paused.or(held));
+                        pending = self.schedule(conf, remaining);
+                        next_tick = Instant::now();
+                        info!(
+                            "Timeout accelerated: adapter will turn off in {}.",
+                            humantime::format_duration(remaining)
+                        );
+                    }
+                    TimeoutCommand::Pause => {
+                        if paused.is_none() {
+                            let now = Instant::now();
+                            paused = Some(now);
+                            self.deadline.send_replace(None);
+                            #[cfg(feature = "notifications")]
+                            self.withdraw_notification().await;
+                            info!(
+                                "Timeout paused with {} remaining.",
+                                humantime::format_duration(deadline.saturating_duration_since(now))
+                            );
+                        }
+                    }
+                    TimeoutCommand::Resume => {
+                        if let Some(since) = paused.take() {
+                            // While held, the countdown stood still anyway
+                            if held.is_none() {
+                                deadline += since.elapsed();
+                            }
+                            let remaining = self.publish(deadline, held);
+                            next_tick = Instant::now();
+                            info!(
+                                "Timeout resumed: adapter will turn off in {}.",
+                                humantime::format_duration(remaining)
+                            );
+                        }
+                    }
+                },
+                Some(active) = session_changed(&mut idle, held.is_some()), if paused.is_none() => {
+                    if active {
+                        info!("Session is active, pausing timeout until it is idle again.");
+                        let now = Instant::now();
+                        held = Some(now);
+                        deadline = now + self.timeout;
+                        self.deadline.send_replace(None);
+                        #[cfg(feature = "notifications")]
+                        self.withdraw_notification().await;
+                        continue;
+                    }
+
+                    if let Some(since) = held.take() {
+                        deadline += since.elapsed();
+                    }
+                    let remaining = self.publish(deadline, paused);
+                    pending = self.schedule(conf, remaining);
+                    next_tick = Instant::now();
+                    info!(
+                        "Session is idle, restarting timeout: adapter will turn off in {}.",
+                        humantime::format_duration(remaining)
+                    );
+                }
+            }
         }
+
+        // Unless a newer task (sharing the channel) has published its deadline meanwhile
+        self.deadline.send_if_modified(|published| {
+            let own = *published == Some(deadline);
+            if own {
+                *published = None;
+            }
+            own
+        });
         info!("Timeout task completed.");
     }
 
-    /// Waits until the remaining time matches the specified `time`, then sends a warning n
-    /// otification.
+    /// Publishes `deadline`, unless the countdown is `paused` (since the given instant), and returns
+    /// the duration that remains until it.
+    fn publish(&self, deadline: Instant, paused: Option<Instant>) -> Duration {
+        if paused.is_none() {
+            self.deadline.send_replace(Some(deadline));
+        }
+
+        deadline.saturating_duration_since(paused.unwrap_or_else(Instant::now))
+    }
+
+    /// Publishes a [`BluetoothEvent::TimerTick`] with the time left until `deadline`.
+    fn tick(&self, deadline: Instant) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        // Nobody listening is not an error
+        self.backend
+            .events()
+            .publish(BluetoothEvent::TimerTick { remaining });
+    }
+
+    /// Publishes a [`BluetoothEvent::BeforePowerOff`] with the time left until `power_off`.
+    fn before_power_off(&self, power_off: Instant) {
+        let remaining = power_off.saturating_duration_since(Instant::now());
+        info!(
+            "Announcing power-off of {} in {}.",
+            self.backend.adapter_name(),
+            humantime::format_duration(remaining)
+        );
+        // Nobody listening is not an error
+        self.backend
+            .events()
+            .publish(BluetoothEvent::BeforePowerOff { remaining });
+    }
+
+    /// Computes the steps that are still due: the warnings within `remaining` (see
+    /// [`pending_warnings`]), the stages that have not run yet and the announcement of the first
+    /// of them that turns off the adapter (see [`Conf::before_power_off`]), sorted so that the
+    /// next due step is the last element.
     ///
-    /// If the current timeout duration is already less than or equal to `time`, this method does
-    /// nothing immediately.
-    async fn notification_at(&mut self, time: Duration) {
-        if self.timeout < time {
-            return;
+    /// In countdown mode, this also records the start of the countdown, which is used as the
+    /// 100% mark of the progress bar.
+    fn schedule(&mut self, conf: &Conf, remaining: Duration) -> Vec<Step> {
+        let warnings = pending_warnings(conf, remaining);
+        #[cfg(feature = "notifications")]
+        {
+            self.countdown_total = warnings.last().copied().unwrap_or_default();
         }
 
-        if self.timeout != time {
-            tokio::time::sleep(self.timeout - time).await;
-            self.timeout = time;
+        let power_off = (self.next_stage..self.stages.len())
+            .find(|&index| {
+                self.stages[index]
+                    .action
+                    .iter()
+                    .any(|action| action.turns_off_adapter())
+            })
+            .filter(|_| !conf.before_power_off.is_zero());
+
+        let mut steps: Vec<Step> = (self.next_stage..self.stages.len())
+            .map(Step::Stage)
+            .chain(warnings.into_iter().map(Step::Warning))
+            .chain(power_off.map(Step::BeforePowerOff))
+            .collect();
+        // Announcements go before a stage due at the same time, as the sort is stable
+        steps.sort_by_key(|&step| Reverse(self.offset(conf, step)));
+
+        steps
+    }
+
+    /// Returns when `step` is due relative to the deadline, in nanoseconds.
+    fn offset(&self, conf: &Conf, step: Step) -> i128 {
+        match step {
+            Step::Warning(at) => -(at.as_nanos() as i128),
+            Step::Stage(index) => self.stages[index].after.as_nanos() as i128,
+            Step::BeforePowerOff(index) => {
+                self.stages[index].after.as_nanos() as i128
+                    - conf.before_power_off.as_nanos() as i128
+            }
+        }
+    }
+
+    /// Returns the name of a device that connected at the last moment: `just_connected` (see
+    /// [`drain_connected`]), or else a device that keeps the adapter on and is connected now, but
+    /// is not in `known` (the object paths of the devices connected when the task started).
+    async fn last_moment_connection(
+        &self,
+        just_connected: Option<String>,
+        known: &HashSet<String>,
+    ) -> Option<String> {
+        if just_connected.is_some() {
+            return just_connected;
         }
 
-        self.send_notification(&time).await;
+        let trusted_only = Conf::instance().trusted_only;
+        let devices = self.backend.connected_devices().await.ok()?;
+        devices
+            .into_iter()
+            .find(|device| {
+                device.keeps_adapter_on(trusted_only) && !known.contains(&device.object_path)
+            })
+            .map(|device| device.display_name().to_string())
     }
 
-    /// Helper method to construct and send a warning notification.
+    /// Runs the stage at `index` and sends its notification, replacing the previous one.
+    async fn run_stage(&mut self, index: usize) {
+        let stage = &self.stages[index];
+        self.next_stage = index + 1;
+        debug!("Running timeout stage {}: {:?}.", index, stage.action);
+
+        action::run(&stage.action, self.backend.as_ref()).await;
+
+        #[cfg(feature = "notifications")]
+        if Conf::instance().notifications().enabled && stage.notify {
+            let id = Notification::timeout_expired(self.backend.adapter_name(), &stage.action)
+                .replaces_id(self.last_notification_id.load(Ordering::Relaxed))
+                .send()
+                .await
+                .inspect_err(|e| error!("Failed to show notification: {}", e))
+                .unwrap_or(0);
+            self.notification_sent(id);
+        }
+    }
+
+    /// Helper method to construct and send a warning (or countdown) notification.
+    ///
+    /// The notification replaces the previously sent one (if any), so the countdown is updated in
+    /// place. Updates `last_notification_id` so the next notification replaces this one.
     ///
-    /// Updates `last_notification_id` to allow future notifications to replace this one (if implemented).
+    /// While Do-Not-Disturb is active, the notification is suppressed or downgraded according to
+    /// `notifications.dnd`.
+    #[cfg(feature = "notifications")]
     async fn send_notification(&mut self, duration: &Duration) {
-        self.last_notification_id = Notification::new()
-            .title("Bluetooth Timeout Warning")
-            .body(&format!(
-                "Bluetooth adapter will turn off in {} due to inactivity.",
-                humantime::format_duration(*duration)
-            ))
-            .icon("bluetooth-symbolic")
-            // .replaces_id(self.last_notification_id)
-            .show()
+        debug!(
+            "Sending warning notification: {} remaining.",
+            humantime::format_duration(*duration)
+        );
+        let conf = Conf::instance();
+        let notification = match conf.notifications().mode {
+            NotificationMode::Warnings => {
+                let notification =
+                    Notification::timeout_warning(self.backend.adapter_name(), *duration);
+                match conf.notifications().at.iter().find(|w| w.at == *duration) {
+                    Some(warning) => notification.with_overrides(warning),
+                    None => notification,
+                }
+            }
+            NotificationMode::Countdown => Notification::countdown(
+                self.backend.adapter_name(),
+                *duration,
+                self.countdown_total,
+            ),
+        };
+
+        let policy = conf.notifications().dnd;
+        let notification = if policy != DndPolicy::Ignore && dnd::is_active().await {
+            match policy {
+                DndPolicy::Suppress => {
+                    info!("Do-Not-Disturb is active, suppressing warning notification.");
+                    return;
+                }
+                DndPolicy::Downgrade => {
+                    info!("Do-Not-Disturb is active, downgrading warning notification.");
+                    notification.urgency(Urgency::Low)
+                }
+                DndPolicy::Ignore => notification,
+            }
+        } else {
+            notification
+        };
+
+        let id = notification
+            .replaces_id(self.last_notification_id.load(Ordering::Relaxed))
+            .send()
             .await
             .inspect_err(|e| error!("Failed to show notification: {}", e))
             .unwrap_or(0);
+        self.notification_sent(id);
+    }
+
+    /// Keeps `id` as the ID of the last notification sent by the task, recording the adapter it
+    /// was sent for (see [`notified_adapter`]).
+    #[cfg(feature = "notifications")]
+    fn notification_sent(&self, id: u32) {
+        self.last_notification_id.store(id, Ordering::Relaxed);
+        if id == 0 {
+            return;
+        }
+
+        let mut sent = NOTIFIED_ADAPTERS.lock().unwrap();
+        sent.retain(|(sent_id, _)| *sent_id != id);
+        if sent.len() >= TRACKED_NOTIFICATIONS {
+            sent.remove(0);
+        }
+        sent.push((id, self.backend.adapter().to_string()));
+    }
+
+    /// Closes the last notification sent by the task (if any), as it is outdated.
+    #[cfg(feature = "notifications")]
+    async fn withdraw_notification(&self) {
+        let id = self.last_notification_id.swap(0, Ordering::Relaxed);
+        if id != 0 {
+            let _ = Notification::close(id)
+                .await
+                .inspect_err(|e| debug!("Failed to close notification: {}", e));
+        }
     }
 
     /// Spawns the `TimeoutTask` onto the Tokio runtime.
     ///
-    /// Returns a `JoinHandle` that can be used to await the task's completion or abort it.
-    pub fn spawn(self) -> JoinHandle<()> {
-        tokio::spawn(async move { self.run().await })
+    /// Returns a [`TimeoutHandle`] that can be used to send commands to the task or abort it.
+    pub fn spawn(self) -> TimeoutHandle {
+        let tx = self.command_tx.clone();
+        self.deadline
+            .send_replace(Some(Instant::now() + self.timeout));
+        let deadline = self.deadline.subscribe();
+        #[cfg(feature = "notifications")]
+        let notification_id = self.last_notification_id.clone();
+        let handle = tokio::spawn(async move { self.run().await });
+
+        TimeoutHandle {
+            handle,
+            tx,
+            deadline,
+            #[cfg(feature = "notifications")]
+            notification_id,
+        }
     }
 }
+
+/// The second timer of an adapter, which powers it back on a while after the [`TimeoutTask`]
+/// turned it off (see `power_on.after_timeout`).
+///
+/// It only keeps its deadline: the service awaits [`WakeTimer::expired`] next to its other
+/// events, so the state machine can decide whether the adapter is still to be powered on.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WakeTimer {
+    /// When the adapter is powered on again, or `None` if the timer is not running.
+    deadline: Option<Instant>,
+}
+
+impl WakeTimer {
+    /// (Re)starts the timer, expiring after `after`.
+    pub fn start(&mut self, after: Duration) {
+        info!(
+            "Powering the adapter back on in {}.",
+            humantime::format_duration(after)
+        );
+        self.deadline = Some(Instant::now() + after);
+    }
+
+    /// Stops the timer, if it is running.
+    pub fn cancel(&mut self) {
+        if self.deadline.take().is_some() {
+            debug!("Cancelled wake timer.");
+        }
+    }
+
+    /// Resolves once the timer has expired. Never resolves if it is not running.
+    pub async fn expired(self) -> Option<()> {
+        tokio::time::sleep_until(self.deadline?).await;
+        Some(())
+    }
+}
+
+/// Resolves once the user session changes: with `true` once it is active, or, while the countdown
+/// is `held`, with `false` once it is idle again. Never resolves if idle coupling is disabled and
+/// the countdown is not held.
+///
+/// If the idle watcher has stopped, the coupling is disabled by resetting `idle` to `None`; a held
+/// countdown is then released as if the session was idle.
+async fn session_changed(idle: &mut Option<watch::Receiver<bool>>, held: bool) -> Option<bool> {
+    let Some(rx) = idle.as_mut() else {
+        return held.then_some(false);
+    };
+    // Waits for the session to turn active, or idle again while held
+    if rx.wait_for(|idle| *idle == held).await.is_err() {
+        *idle = None;
+        return held.then_some(false);
+    }
+
+    Some(!held)
+}
+
+/// Returns the object path of the adapter whose timeout task sent the notification with the given
+/// `id`, or `None` if it is not among the recently sent notifications.
+pub fn notified_adapter(id: u32) -> Option<String> {
+    NOTIFIED_ADAPTERS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(sent_id, _)| *sent_id == id)
+        .map(|(_, adapter)| adapter.clone())
+}
+
+/// Drains `events` and returns the name of the last device that connected since they were last
+/// drained and keeps the adapter on (see [`BluetoothDevice::keeps_adapter_on`]), if any.
+///
+/// [`BluetoothDevice::keeps_adapter_on`]: crate::bluetooth::device::BluetoothDevice::keeps_adapter_on
+fn drain_connected(
+    events: &mut Subscription<BluetoothEvent>,
+    trusted_only: bool,
+) -> Option<String> {
+    let mut connected = None;
+    while let Some(event) = events.try_recv() {
+        if let BluetoothEvent::DeviceConnected(device) = event
+            && device.keeps_adapter_on(trusted_only)
+        {
+            connected = Some(device.display_name().to_string());
+        }
+    }
+
+    connected
+}
+
+/// Returns the warnings that are still due within `remaining`, sorted so that the next due
+/// warning is the last element.
+///
+/// In countdown mode, these are the update points of the resident notification: every
+/// `countdown_interval`, starting at the first configured warning (or `remaining`, whichever is
+/// shorter). Returns an empty list if notifications are disabled (or not compiled in).
+fn pending_warnings(conf: &Conf, remaining: Duration) -> Vec<Duration> {
+    if !cfg!(feature = "notifications") || !conf.notifications().enabled {
+        return vec![];
+    }
+
+    if conf.notifications().mode == NotificationMode::Countdown {
+        return countdown_steps(conf, remaining);
+    }
+
+    let mut pending: Vec<Duration> = conf
+        .notifications()
+        .at
+        .iter()
+        .map(|warning| warning.at)
+        .filter(|&at| at <= remaining)
+        .collect();
+    pending.sort_unstable();
+    pending.dedup();
+
+    pending
+}
+
+/// Returns the update points of the countdown notification within `remaining`, sorted so that
+/// the next due update is the last element.
+fn countdown_steps(conf: &Conf, remaining: Duration) -> Vec<Duration> {
+    let interval = conf.notifications().countdown_interval;
+    let start = conf
+        .notifications()
+        .at
+        .iter()
+        .map(|warning| warning.at)
+        .max()
+        .map_or(remaining, |first| first.min(remaining));
+
+    if interval.is_zero() {
+        return vec![start];
+    }
+
+    let mut steps: Vec<Duration> =
+        std::iter::successors(Some(start), |step| step.checked_sub(interval))
+            .take_while(|step| !step.is_zero())
+            .collect();
+    steps.reverse();
+
+    steps
+}
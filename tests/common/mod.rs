@@ -0,0 +1,589 @@
+//! Harness for integration tests: a private D-Bus daemon, a fake `org.bluez` service on it, and
+//! the `bluetooth-timeout` daemon running against both.
+
+// -- std imports
+use std::{
+    fs,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+// -- std imports (conditional)
+#[cfg(feature = "cli-control")]
+use std::collections::HashMap;
+
+// -- crate imports (conditional)
+#[cfg(feature = "cli-control")]
+use zbus::zvariant::OwnedValue;
+
+// -- crate imports
+use anyhow::{Context, Result};
+use zbus::{Connection, connection, fdo::ObjectManager, interface, object_server::SignalEmitter};
+
+/// Object path of the adapter served by [`FakeBluez`].
+pub const ADAPTER_PATH: &str = "/org/bluez/hci0";
+
+/// Counter making the directories of concurrently running tests unique.
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// A private `dbus-daemon`, isolated from the system and session buses of the host.
+///
+/// The daemon and its directory are removed on drop.
+pub struct TestBus {
+    daemon: Child,
+    /// Address clients connect to.
+    pub address: String,
+    /// Scratch directory of the test (bus socket, config and log files).
+    pub dir: PathBuf,
+}
+
+impl TestBus {
+    /// Starts a private bus, or returns `None` if `dbus-daemon` is not installed, so the tests can
+    /// be skipped.
+    pub fn start() -> Option<Self> {
+        let dir = std::env::temp_dir().join(format!(
+            "bluetooth-timeout-test-{}-{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir).expect("test directory should be created");
+
+        let mut daemon = match Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .arg(format!("--address=unix:path={}", dir.join("bus").display()))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(daemon) => daemon,
+            Err(e) => {
+                eprintln!("Skipping test, dbus-daemon is unavailable: {}", e);
+                return None;
+            }
+        };
+
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .expect("dbus-daemon should print its address");
+
+        Some(Self {
+            daemon,
+            address: address.trim().to_string(),
+            dir,
+        })
+    }
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// `org.bluez.Adapter1` of the fake adapter.
+struct Adapter {
+    powered: bool,
+    discoverable: bool,
+    pairable: bool,
+}
+
+#[interface(name = "org.bluez.Adapter1")]
+impl Adapter {
+    #[zbus(property)]
+    fn powered(&self) -> bool {
+        self.powered
+    }
+
+    #[zbus(property)]
+    fn set_powered(&mut self, powered: bool) {
+        self.powered = powered;
+    }
+
+    #[zbus(property)]
+    fn discoverable(&self) -> bool {
+        self.discoverable
+    }
+
+    #[zbus(property)]
+    fn set_discoverable(&mut self, discoverable: bool) {
+        self.discoverable = discoverable;
+    }
+
+    #[zbus(property)]
+    fn pairable(&self) -> bool {
+        self.pairable
+    }
+
+    #[zbus(property)]
+    fn set_pairable(&mut self, pairable: bool) {
+        self.pairable = pairable;
+    }
+}
+
+/// `org.bluez.Device1` of a fake device.
+struct Device {
+    alias: String,
+    connected: bool,
+}
+
+#[interface(name = "org.bluez.Device1")]
+impl Device {
+    async fn connect(&mut self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) {
+        self.connected = true;
+        let _ = self.connected_changed(&emitter).await;
+    }
+
+    async fn disconnect(&mut self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) {
+        self.connected = false;
+        let _ = self.connected_changed(&emitter).await;
+    }
+
+    #[zbus(property)]
+    fn alias(&self) -> &str {
+        &self.alias
+    }
+
+    #[zbus(property)]
+    fn connected(&self) -> bool {
+        self.connected
+    }
+
+    #[zbus(property)]
+    fn paired(&self) -> bool {
+        true
+    }
+}
+
+/// `org.bluez.Battery1` of a fake device.
+#[cfg(feature = "cli-control")]
+struct Battery {
+    percentage: u8,
+}
+
+#[cfg(feature = "cli-control")]
+#[interface(name = "org.bluez.Battery1")]
+impl Battery {
+    #[zbus(property)]
+    fn percentage(&self) -> u8 {
+        self.percentage
+    }
+}
+
+/// A fake `org.bluez` service exposing one adapter (see [`ADAPTER_PATH`]) and its devices via
+/// the object manager and properties interfaces.
+pub struct FakeBluez {
+    conn: Connection,
+}
+
+impl FakeBluez {
+    /// Serves a fake adapter that is `powered` on `bus`.
+    pub async fn start(bus: &TestBus, powered: bool) -> Result<Self> {
+        let conn = connection::Builder::address(bus.address.as_str())?
+            .name("org.bluez")?
+            .serve_at("/", ObjectManager)?
+            .serve_at(
+                ADAPTER_PATH,
+                Adapter {
+                    powered,
+                    discoverable: false,
+                    pairable: false,
+                },
+            )?
+            .build()
+            .await?;
+
+        Ok(Self { conn })
+    }
+
+    /// Adds another adapter with the given `name` (e.g., "hci1"), as if it was plugged in, and
+    /// returns its object path.
+    pub async fn add_adapter(&self, name: &str, powered: bool) -> Result<String> {
+        let path = format!("/org/bluez/{}", name);
+        self.conn
+            .object_server()
+            .at(
+                path.as_str(),
+                Adapter {
+                    powered,
+                    discoverable: false,
+                    pairable: false,
+                },
+            )
+            .await?;
+
+        Ok(path)
+    }
+
+    /// Removes the adapter with the given `name` (e.g., "hci0"), as if it was unplugged.
+    #[cfg(feature = "cli-control")]
+    pub async fn remove_adapter(&self, name: &str) -> Result<()> {
+        let path = format!("/org/bluez/{}", name);
+        self.conn
+            .object_server()
+            .remove::<Adapter, _>(path.as_str())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Adds a device with the given `name` to the adapter and returns its object path.
+    pub async fn add_device(&self, name: &str, connected: bool) -> Result<String> {
+        let path = format!("{}/dev_{}", ADAPTER_PATH, name);
+        self.conn
+            .object_server()
+            .at(
+                path.as_str(),
+                Device {
+                    alias: name.to_string(),
+                    connected,
+                },
+            )
+            .await?;
+
+        Ok(path)
+    }
+
+    /// Reports a battery level of `percentage` for the device at `path`.
+    #[cfg(feature = "cli-control")]
+    pub async fn set_battery(&self, path: &str, percentage: u8) -> Result<()> {
+        self.conn
+            .object_server()
+            .at(path, Battery { percentage })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Changes the `Connected` state of the device at `path`, as if it (dis)connected on its own.
+    pub async fn set_connected(&self, path: &str, connected: bool) -> Result<()> {
+        let device = self
+            .conn
+            .object_server()
+            .interface::<_, Device>(path)
+            .await?;
+        device.get_mut().await.connected = connected;
+        device
+            .get()
+            .await
+            .connected_changed(device.signal_emitter())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Makes the adapter discoverable, as if a settings dialog did so.
+    pub async fn set_discoverable(&self) -> Result<()> {
+        let adapter = self.adapter().await?;
+        adapter.get_mut().await.discoverable = true;
+        adapter
+            .get()
+            .await
+            .discoverable_changed(adapter.signal_emitter())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Changes the `Powered` state of the adapter, as if its firmware did so.
+    pub async fn set_powered(&self, powered: bool) -> Result<()> {
+        let adapter = self.adapter().await?;
+        adapter.get_mut().await.powered = powered;
+        adapter
+            .get()
+            .await
+            .powered_changed(adapter.signal_emitter())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns whether the adapter is powered on.
+    pub async fn powered(&self) -> bool {
+        self.powered_at(ADAPTER_PATH).await
+    }
+
+    /// Returns whether the adapter at `path` is powered on.
+    pub async fn powered_at(&self, path: &str) -> bool {
+        self.conn
+            .object_server()
+            .interface::<_, Adapter>(path)
+            .await
+            .unwrap()
+            .get()
+            .await
+            .powered
+    }
+
+    /// Returns whether the adapter is discoverable.
+    pub async fn discoverable(&self) -> bool {
+        self.adapter().await.unwrap().get().await.discoverable
+    }
+
+    async fn adapter(&self) -> Result<zbus::object_server::InterfaceRef<Adapter>> {
+        Ok(self
+            .conn
+            .object_server()
+            .interface::<_, Adapter>(ADAPTER_PATH)
+            .await?)
+    }
+}
+
+/// `org.freedesktop.PolicyKit1.Authority` of [`FakePolkit`].
+#[cfg(feature = "cli-control")]
+struct Authority {
+    allowed: Vec<String>,
+}
+
+#[cfg(feature = "cli-control")]
+#[interface(name = "org.freedesktop.PolicyKit1.Authority")]
+impl Authority {
+    fn check_authorization(
+        &self,
+        _subject: (String, HashMap<String, OwnedValue>),
+        action_id: String,
+        _details: HashMap<String, String>,
+        _flags: u32,
+        _cancellation_id: String,
+    ) -> (bool, bool, HashMap<String, String>) {
+        (self.allowed.contains(&action_id), false, HashMap::new())
+    }
+}
+
+/// A fake polkit authority that authorizes every caller for some actions and nobody for the
+/// others.
+#[cfg(feature = "cli-control")]
+pub struct FakePolkit {
+    _conn: Connection,
+}
+
+#[cfg(feature = "cli-control")]
+impl FakePolkit {
+    /// Serves an authority on `bus` that only authorizes the `allowed` actions.
+    pub async fn start(bus: &TestBus, allowed: &[&str]) -> Result<Self> {
+        let conn = connection::Builder::address(bus.address.as_str())?
+            .name("org.freedesktop.PolicyKit1")?
+            .serve_at(
+                "/org/freedesktop/PolicyKit1/Authority",
+                Authority {
+                    allowed: allowed.iter().map(|action| action.to_string()).collect(),
+                },
+            )?
+            .build()
+            .await?;
+
+        Ok(Self { _conn: conn })
+    }
+}
+
+/// The `bluetooth-timeout` daemon, running against a [`TestBus`] as both its system and session
+/// bus. It is killed on drop.
+pub struct Daemon {
+    process: Child,
+}
+
+impl Daemon {
+    /// Starts the daemon on `bus` with the given YAML configuration.
+    pub fn start(bus: &TestBus, config: &str) -> Result<Self> {
+        write_config(bus, config)?;
+
+        let process = command(bus)
+            .arg("run")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Could not start bluetooth-timeout")?;
+
+        Ok(Self { process })
+    }
+
+    /// Starts the daemon like [`Daemon::start`], with `envs` added to its environment.
+    pub fn start_with_env(bus: &TestBus, config: &str, envs: &[(&str, &str)]) -> Result<Self> {
+        write_config(bus, config)?;
+
+        let process = command(bus)
+            .arg("run")
+            .envs(envs.iter().copied())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Could not start bluetooth-timeout")?;
+
+        Ok(Self { process })
+    }
+
+    /// Starts the daemon like [`Daemon::start`], but with the bus addresses in its environment
+    /// pointing nowhere, so it only reaches `bus` if the configuration names it.
+    pub fn start_without_bus_env(bus: &TestBus, config: &str) -> Result<Self> {
+        write_config(bus, config)?;
+
+        let process = command(bus)
+            .arg("run")
+            .env("DBUS_SYSTEM_BUS_ADDRESS", "unix:path=/nonexistent")
+            .env("DBUS_SESSION_BUS_ADDRESS", "unix:path=/nonexistent")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Could not start bluetooth-timeout")?;
+
+        Ok(Self { process })
+    }
+
+    /// Returns `true` if the daemon exited with a failure within `timeout`.
+    #[cfg(feature = "cli-control")]
+    pub async fn fails_within(&mut self, timeout: Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + timeout;
+        while tokio::time::Instant::now() < deadline {
+            if let Ok(Some(status)) = self.process.try_wait() {
+                return !status.success();
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        false
+    }
+}
+
+/// Writes the given YAML configuration to where the binary reads it when run on `bus`.
+pub fn write_config(bus: &TestBus, config: &str) -> Result<()> {
+    // Debug builds read `./contrib/config.yml`, release builds the XDG config directory
+    for dir in ["contrib", "bluetooth-timeout"] {
+        fs::create_dir_all(bus.dir.join(dir))?;
+    }
+    fs::write(bus.dir.join("contrib/config.yml"), config)?;
+    fs::write(bus.dir.join("bluetooth-timeout/config.yml"), config)?;
+
+    Ok(())
+}
+
+/// Runs `bluetooth-timeout` with the given `args` on `bus` (e.g., a CLI command talking to a
+/// running [`Daemon`]) and returns its stdout.
+///
+/// The command is awaited without blocking the runtime, which serves [`FakeBluez`] meanwhile.
+pub async fn run_cli(bus: &TestBus, args: &[&str]) -> Result<String> {
+    let output = tokio::process::Command::from(command(bus))
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .context("Could not run bluetooth-timeout")?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Runs the CLI like [`run_cli`], but returns its exit code.
+#[cfg(feature = "cli-control")]
+pub async fn cli_exit_code(bus: &TestBus, args: &[&str]) -> Result<Option<i32>> {
+    let status = tokio::process::Command::from(command(bus))
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .context("Could not run bluetooth-timeout")?;
+
+    Ok(status.code())
+}
+
+/// Calls `method` of the control interface of the [`Daemon`] on `bus` with `args` directly, as a
+/// client other than the CLI would.
+#[cfg(feature = "cli-control")]
+pub async fn call_control<A>(bus: &TestBus, method: &str, args: &A) -> zbus::Result<()>
+where
+    A: serde::Serialize + zbus::zvariant::DynamicType,
+{
+    let conn = connection::Builder::address(bus.address.as_str())?
+        .build()
+        .await?;
+    conn.call_method(
+        Some("io.github.hendrikboeck.BluetoothTimeout"),
+        "/io/github/hendrikboeck/BluetoothTimeout",
+        Some("io.github.hendrikboeck.BluetoothTimeout1"),
+        method,
+        args,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Starts `bluetooth-timeout` with the given `args` on `bus` (e.g., a CLI command that keeps
+/// running), with its stdout piped. The process is killed when dropped.
+#[cfg(feature = "cli-control")]
+pub fn spawn_cli(bus: &TestBus, args: &[&str]) -> Result<tokio::process::Child> {
+    tokio::process::Command::from(command(bus))
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Could not start bluetooth-timeout")
+}
+
+/// Returns a command for the `bluetooth-timeout` binary, running in the directory of `bus` with
+/// `bus` as both its system and session bus.
+fn command(bus: &TestBus) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_bluetooth-timeout"));
+    command
+        .current_dir(&bus.dir)
+        .env("DBUS_SYSTEM_BUS_ADDRESS", &bus.address)
+        .env("DBUS_SESSION_BUS_ADDRESS", &bus.address)
+        .env("XDG_CONFIG_HOME", &bus.dir)
+        .env("XDG_DATA_HOME", &bus.dir)
+        .env("XDG_STATE_HOME", &bus.dir)
+        .env("XDG_RUNTIME_DIR", &bus.dir)
+        .env("LANG", "C")
+        .env_remove("LC_ALL")
+        .env_remove("LC_MESSAGES");
+    command
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+/// Returns a configuration with the given `timeout` and notifications disabled, followed by the
+/// `extra` YAML.
+pub fn config(timeout: &str, extra: &str) -> String {
+    format!(
+        "timeout: {timeout}
+notifications:
+  enabled: false
+  at: []
+  countdown_interval: 5s
+  snooze: 15m
+  cooldown: 30s
+dbus:
+  service: org.bluez
+  adapter_iface: org.bluez.Adapter1
+  adapter_path: {ADAPTER_PATH}
+  device_iface: org.bluez.Device1
+{extra}"
+    )
+}
+
+/// Polls `condition` until it holds or `timeout` elapses, and returns its last result.
+pub async fn eventually<F, Fut>(timeout: Duration, mut condition: F) -> bool
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        if condition().await {
+            return true;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
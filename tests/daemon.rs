@@ -0,0 +1,521 @@
+//! End-to-end tests of the daemon against a fake BlueZ on a private D-Bus daemon.
+
+// -- std imports
+use std::{os::unix::net::UnixDatagram, time::Duration};
+
+// -- module definitions
+mod common;
+
+// -- module imports
+use common::{ADAPTER_PATH, Daemon, FakeBluez, TestBus, config, eventually, run_cli, write_config};
+#[cfg(feature = "cli-control")]
+use common::{FakePolkit, call_control, cli_exit_code, spawn_cli};
+
+/// Upper bound for the daemon to react; generous, as it starts up in the meantime.
+const WITHIN: Duration = Duration::from_secs(10);
+
+#[tokio::test]
+async fn turns_off_idle_adapter_after_timeout() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1s", "")).unwrap();
+
+    assert!(eventually(WITHIN, || async { !bluez.powered().await }).await);
+}
+
+#[tokio::test]
+async fn notifies_systemd_and_pings_its_watchdog_while_healthy() {
+    let Some(bus) = TestBus::start() else { return };
+    let _bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let socket_path = bus.dir.join("notify");
+    let socket = UnixDatagram::bind(&socket_path).unwrap();
+    socket.set_nonblocking(true).unwrap();
+    let _daemon = Daemon::start_with_env(
+        &bus,
+        &config("1h", ""),
+        &[
+            ("NOTIFY_SOCKET", socket_path.to_str().unwrap()),
+            ("WATCHDOG_USEC", "200000"),
+        ],
+    )
+    .unwrap();
+
+    let mut states = Vec::new();
+    let pinged = eventually(WITHIN, || {
+        let mut buf = [0; 64];
+        while let Ok(len) = socket.recv(&mut buf) {
+            states.push(String::from_utf8_lossy(&buf[..len]).into_owned());
+        }
+        let pinged = states.iter().any(|state| state == "WATCHDOG=1");
+        async move { pinged }
+    })
+    .await;
+
+    assert!(pinged, "{:?}", states);
+    assert_eq!(states.first().map(String::as_str), Some("READY=1"));
+}
+
+#[tokio::test]
+async fn turns_off_idle_adapter_on_current_thread_runtime() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1s", "runtime: current_thread\n")).unwrap();
+
+    assert!(eventually(WITHIN, || async { !bluez.powered().await }).await);
+}
+
+#[tokio::test]
+async fn turns_off_adapter_after_last_device_disconnects() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let headset = bluez.add_device("AA_BB_CC_DD_EE_FF", true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1s", "")).unwrap();
+
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert!(bluez.powered().await);
+
+    bluez.set_connected(&headset, false).await.unwrap();
+    assert!(eventually(WITHIN, || async { !bluez.powered().await }).await);
+}
+
+#[tokio::test]
+async fn reconnecting_device_cancels_timeout() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let headset = bluez.add_device("AA_BB_CC_DD_EE_FF", true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("3s", "")).unwrap();
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    bluez.set_connected(&headset, false).await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    bluez.set_connected(&headset, true).await.unwrap();
+
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    assert!(bluez.powered().await);
+}
+
+#[tokio::test]
+async fn running_inhibiting_process_pauses_the_countdown() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    // The private bus of the test runs as `dbus-daemon`
+    let extra = "inhibit:\n  processes: [dbus-daemon]\n  poll_interval: 100ms\n";
+    let _daemon = Daemon::start(&bus, &config("1s", extra)).unwrap();
+
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert!(bluez.powered().await);
+}
+
+#[tokio::test]
+async fn connects_to_the_buses_named_in_the_configuration() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    // Continues the `dbus` section
+    let extra = format!(
+        "  system_bus: {}\n  session_bus: {}\n",
+        bus.address, bus.address
+    );
+    let _daemon = Daemon::start_without_bus_env(&bus, &config("1s", &extra)).unwrap();
+
+    assert!(eventually(WITHIN, || async { !bluez.powered().await }).await);
+}
+
+#[tokio::test]
+async fn untrusted_devices_do_not_keep_the_adapter_on_in_trusted_only_mode() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    // Devices of the fake BlueZ are not trusted
+    bluez.add_device("AA_BB_CC_DD_EE_FF", true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1s", "trusted_only: true\n")).unwrap();
+
+    assert!(eventually(WITHIN, || async { !bluez.powered().await }).await);
+}
+
+#[tokio::test]
+async fn keeps_the_adapter_off_during_a_blackout_period() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let now = chrono::Local::now().time();
+    let (from, to) = (
+        now - chrono::Duration::hours(1),
+        now + chrono::Duration::hours(1),
+    );
+    let extra = format!(
+        "blackout:\n  - from: \"{}\"\n    to: \"{}\"\n",
+        from.format("%H:%M"),
+        to.format("%H:%M")
+    );
+    let _daemon = Daemon::start(&bus, &config("1h", &extra)).unwrap();
+
+    assert!(eventually(WITHIN, || async { !bluez.powered().await }).await);
+
+    // Powering back on within `power_debounce` would be ignored as flapping
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    bluez.set_powered(true).await.unwrap();
+    assert!(eventually(WITHIN, || async { !bluez.powered().await }).await);
+}
+
+#[tokio::test]
+async fn turns_off_discoverability_after_its_timeout() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    bluez.add_device("AA_BB_CC_DD_EE_FF", true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1h", "discoverable:\n  timeout: 1s\n")).unwrap();
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    bluez.set_discoverable().await.unwrap();
+
+    assert!(eventually(WITHIN, || async { !bluez.discoverable().await }).await);
+    assert!(bluez.powered().await);
+}
+
+#[tokio::test]
+async fn manages_every_adapter_including_added_ones() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let config = config("1s", "").replace(ADAPTER_PATH, "all");
+    let _daemon = Daemon::start(&bus, &config).unwrap();
+
+    assert!(eventually(WITHIN, || async { !bluez.powered().await }).await);
+
+    let dock = bluez.add_adapter("hci1", true).await.unwrap();
+    assert!(eventually(WITHIN, || async { !bluez.powered_at(&dock).await }).await);
+}
+
+#[cfg(feature = "cli-control")]
+#[tokio::test]
+async fn picks_the_adapter_up_again_after_it_was_unplugged() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1h", "")).unwrap();
+    let state = async |expected: &str| {
+        eventually(WITHIN, || async {
+            run_cli(&bus, &["status"])
+                .await
+                .is_ok_and(|status| status.contains(expected))
+        })
+        .await
+    };
+    assert!(state("Idle").await);
+    // the observer subscribes to the signals of BlueZ in the background
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    bluez.remove_adapter("hci0").await.unwrap();
+    assert!(state("Detached").await);
+
+    bluez.add_adapter("hci0", true).await.unwrap();
+    assert!(state("Idle").await);
+}
+
+#[tokio::test]
+async fn oneshot_turns_off_the_adapter_once_idle_across_runs() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let headset = bluez.add_device("AA_BB_CC_DD_EE_FF", true).await.unwrap();
+    write_config(&bus, &config("1s", "")).unwrap();
+
+    run_cli(&bus, &["oneshot"]).await.unwrap();
+    bluez.set_connected(&headset, false).await.unwrap();
+    run_cli(&bus, &["oneshot"]).await.unwrap();
+    assert!(bluez.powered().await);
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    run_cli(&bus, &["oneshot"]).await.unwrap();
+    assert!(!bluez.powered().await);
+}
+
+#[cfg(feature = "cli-control")]
+#[tokio::test]
+async fn status_lists_recent_events() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let headset = bluez.add_device("AA_BB_CC_DD_EE_FF", true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1h", "")).unwrap();
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    bluez.set_connected(&headset, false).await.unwrap();
+
+    assert!(
+        eventually(WITHIN, || async {
+            let status = run_cli(&bus, &["status", "--events"]).await.unwrap();
+            status.contains("Idle")
+                && status.contains("DeviceDisconnected")
+                && status.contains("State: Running -> Idle")
+        })
+        .await
+    );
+}
+
+#[cfg(all(feature = "cli-control", feature = "metrics"))]
+#[tokio::test]
+async fn status_lists_event_latencies() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let headset = bluez.add_device("AA_BB_CC_DD_EE_FF", true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1h", "")).unwrap();
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    bluez.set_connected(&headset, false).await.unwrap();
+
+    assert!(
+        eventually(WITHIN, || async {
+            let status = run_cli(&bus, &["status", "--latency"]).await.unwrap();
+            status.contains("DeviceDisconnected: one event") && status.contains("≤1000ms")
+        })
+        .await
+    );
+}
+
+#[cfg(feature = "cli-control")]
+#[tokio::test]
+async fn status_shows_battery_of_connected_devices() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let headset = bluez.add_device("Headset", true).await.unwrap();
+    bluez.set_battery(&headset, 8).await.unwrap();
+    bluez.add_device("Mouse", true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1h", "")).unwrap();
+
+    assert!(
+        eventually(WITHIN, || async {
+            let status = run_cli(&bus, &["status"]).await.unwrap();
+            status.contains("Headset (battery: 8%)") && status.contains("  Mouse")
+        })
+        .await
+    );
+}
+
+#[cfg(feature = "cli-control")]
+#[tokio::test]
+async fn status_shows_remaining_time_of_idle_adapter() {
+    let Some(bus) = TestBus::start() else { return };
+    let _bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1h", "")).unwrap();
+
+    assert!(
+        eventually(WITHIN, || async {
+            let status = run_cli(&bus, &["status"]).await.unwrap();
+            status.contains("Timeout expires in 59m")
+        })
+        .await
+    );
+}
+
+#[cfg(feature = "cli-control")]
+#[tokio::test]
+async fn status_counts_automatic_power_offs() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1s", "")).unwrap();
+    assert!(eventually(WITHIN, || async { !bluez.powered().await }).await);
+
+    assert!(
+        eventually(WITHIN, || async {
+            let status = run_cli(&bus, &["status", "--stats"]).await.unwrap();
+            status.contains("one automatic power-off")
+        })
+        .await
+    );
+    assert!(bus.dir.join("hci0.stats.yml").exists());
+}
+
+#[cfg(feature = "cli-control")]
+#[tokio::test]
+async fn ignores_adapter_power_flapping() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    bluez.add_device("AA_BB_CC_DD_EE_FF", true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1h", "power_debounce: 1s\n")).unwrap();
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    bluez.set_powered(false).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    bluez.set_powered(true).await.unwrap();
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let status = run_cli(&bus, &["status", "--events"]).await.unwrap();
+    assert!(status.contains("Running"), "{}", status);
+    assert!(!status.contains("AdapterOff"), "{}", status);
+}
+
+#[cfg(feature = "cli-control")]
+#[tokio::test]
+async fn set_timeout_restarts_the_countdown_and_persists() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1h", "")).unwrap();
+
+    assert!(
+        eventually(WITHIN, || async {
+            run_cli(&bus, &["set-timeout", "2s", "--persist"])
+                .await
+                .unwrap()
+                .contains("Timeout set to 2s")
+        })
+        .await
+    );
+    assert!(eventually(WITHIN, || async { !bluez.powered().await }).await);
+
+    let config = std::fs::read_to_string(bus.dir.join("contrib/config.yml")).unwrap();
+    assert!(config.starts_with("timeout: 2s\n"), "{}", config);
+}
+
+#[cfg(feature = "cli-control")]
+#[tokio::test]
+async fn rejects_invalid_arguments() {
+    let Some(bus) = TestBus::start() else { return };
+    let _bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1h", "")).unwrap();
+    assert!(
+        eventually(WITHIN, || async {
+            run_cli(&bus, &["status"])
+                .await
+                .unwrap()
+                .contains("service state: Idle")
+        })
+        .await
+    );
+
+    let error = call_control(&bus, "Snooze", &(u64::MAX,))
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("InvalidArgs"), "{}", error);
+    let error = call_control(&bus, "KeepOn", &(u64::MAX,))
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("InvalidArgs"), "{}", error);
+    assert!(
+        run_cli(&bus, &["keep-on", "2years"])
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    let error = call_control(&bus, "SetTimeout", &(u64::MAX, true))
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("InvalidArgs"), "{}", error);
+    let config = std::fs::read_to_string(bus.dir.join("contrib/config.yml")).unwrap();
+    assert!(config.starts_with("timeout: 1h\n"), "{}", config);
+    assert!(
+        run_cli(&bus, &["snooze", "500ms"])
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    assert_eq!(
+        cli_exit_code(&bus, &["profile", "unknown"]).await.unwrap(),
+        Some(10)
+    );
+
+    let status = run_cli(&bus, &["status"]).await.unwrap();
+    assert!(status.contains("service state: Idle"), "{}", status);
+}
+
+#[cfg(feature = "cli-control")]
+#[tokio::test]
+async fn polkit_authorizes_commands_on_the_system_bus() {
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let _polkit = FakePolkit::start(
+        &bus,
+        &["io.github.hendrikboeck.BluetoothTimeout.control-timeout"],
+    )
+    .await
+    .unwrap();
+    let _daemon = Daemon::start(&bus, &config("1h", "  control_bus: system\n")).unwrap();
+
+    assert!(
+        eventually(WITHIN, || async {
+            run_cli(&bus, &["snooze", "10m"])
+                .await
+                .unwrap()
+                .contains("Snoozed timeout by 10m")
+        })
+        .await
+    );
+    let output = run_cli(&bus, &["power-off"]).await.unwrap();
+    assert!(!output.contains("Turned the adapter off"), "{}", output);
+    assert_eq!(cli_exit_code(&bus, &["power-off"]).await.unwrap(), Some(8));
+    assert!(!eventually(Duration::from_secs(2), || async { !bluez.powered().await }).await);
+}
+
+#[cfg(feature = "cli-control")]
+#[tokio::test]
+async fn second_instance_exits_if_the_control_name_is_taken() {
+    let Some(bus) = TestBus::start() else { return };
+    let _bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1h", "")).unwrap();
+    assert!(
+        eventually(WITHIN, || async {
+            run_cli(&bus, &["status"])
+                .await
+                .unwrap()
+                .contains("service state: Idle")
+        })
+        .await
+    );
+
+    let mut second = Daemon::start(&bus, &config("1h", "")).unwrap();
+    assert!(second.fails_within(WITHIN).await);
+}
+
+#[cfg(feature = "cli-control")]
+#[tokio::test]
+async fn healthcheck_succeeds_only_while_the_daemon_runs() {
+    let Some(bus) = TestBus::start() else { return };
+    let _bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let daemon = Daemon::start(&bus, &config("1h", "")).unwrap();
+    assert!(
+        eventually(WITHIN, || async {
+            run_cli(&bus, &["healthcheck"])
+                .await
+                .unwrap()
+                .contains("The daemon is healthy")
+        })
+        .await
+    );
+
+    drop(daemon);
+    let output = run_cli(&bus, &["healthcheck"]).await.unwrap();
+    assert!(!output.contains("The daemon is healthy"));
+}
+
+#[cfg(feature = "cli-control")]
+#[tokio::test]
+async fn monitor_streams_transitions_and_events_as_json_lines() {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    let Some(bus) = TestBus::start() else { return };
+    let bluez = FakeBluez::start(&bus, true).await.unwrap();
+    let headset = bluez.add_device("AA_BB_CC_DD_EE_FF", false).await.unwrap();
+    let _daemon = Daemon::start(&bus, &config("1h", "")).unwrap();
+    assert!(
+        eventually(WITHIN, || async {
+            run_cli(&bus, &["status"])
+                .await
+                .unwrap()
+                .contains("service state: Idle")
+        })
+        .await
+    );
+
+    let mut monitor = spawn_cli(&bus, &["monitor"]).unwrap();
+    let mut lines = BufReader::new(monitor.stdout.take().unwrap()).lines();
+    let first = lines.next_line().await.unwrap().unwrap();
+    assert!(first.contains(r#""to":"Idle""#), "{}", first);
+
+    bluez.set_connected(&headset, true).await.unwrap();
+    let mut seen = vec![];
+    let connected = tokio::time::timeout(WITHIN, async {
+        while let Some(line) = lines.next_line().await.unwrap() {
+            seen.push(line);
+            let printed = |needle: &str| seen.iter().any(|line| line.contains(needle));
+            if printed(r#""event":"DeviceConnected""#) && printed(r#""to":"Running""#) {
+                return;
+            }
+        }
+    })
+    .await;
+    assert!(connected.is_ok(), "{:?}", seen);
+}